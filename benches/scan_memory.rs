@@ -0,0 +1,79 @@
+//! Peak memory and allocations of a full scan, of the default search paths
+//! and of generated desktop files on Linux:
+//!
+//! ```text
+//! cargo bench --bench scan_memory
+//! ```
+
+use applications::AppFinder;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Print what scanning with `finder` allocates, and return the apps.
+fn measure(label: &str, finder: &AppFinder) -> usize {
+    let before = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+
+    let apps = finder.find().unwrap_or_default();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    let kept = CURRENT.load(Ordering::Relaxed) - before;
+    println!(
+        "{label}: {} apps, peak {} KiB, kept {} KiB, {} allocations",
+        apps.len(),
+        peak / 1024,
+        kept / 1024,
+        ALLOCATIONS.load(Ordering::Relaxed),
+    );
+
+    apps.len()
+}
+
+fn main() {
+    measure("default search paths", &AppFinder::new());
+
+    #[cfg(target_os = "linux")]
+    {
+        let dir =
+            std::env::temp_dir().join(format!("applications-rs-bench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("icon.png"), b"").unwrap();
+        for i in 0..2000 {
+            std::fs::write(
+                dir.join(format!("org.example.App{i}.desktop")),
+                format!(
+                    "[Desktop Entry]\nType=Application\nName=App {i}\nName[de]=Anwendung {i}\n\
+                     Name[zh_CN]=应用 {i}\nExec=/opt/example/app{i}/bin/app{i} %U\n\
+                     Icon={}\nCategories=Utility;Development;\n",
+                    dir.join("icon.png").display()
+                ),
+            )
+            .unwrap();
+        }
+        measure("2000 desktop files", &AppFinder::with_paths([&dir]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    // The permissions of the commands of the Tauri plugin
+    #[cfg(feature = "tauri-plugin")]
+    tauri_plugin::Builder::new(&["list_apps", "launch_app", "app_icon"]).build();
+}
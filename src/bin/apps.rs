@@ -0,0 +1,379 @@
+//! `apps`, lists the installed apps, renders their icons and watches for
+//! changes. Built with the `cli` feature.
+
+use applications::watcher::Watcher;
+use applications::{
+    App, AppFinder, AppKind, AppSearch, AppSnapshot, Error, IconCache, Result,
+    get_default_search_paths,
+};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const KINDS: &[&str] = &["gui", "web", "android", "wsl", "cli", "game", "system"];
+
+const USAGE: &str = "\
+Usage: apps <command> [options]
+
+Commands:
+  list                 List the installed apps
+      --json           Print a JSON array instead of a table
+      --kind <kind>    Only apps of a kind: gui, web, android, wsl, cli, game
+                       or system, may be repeated
+      --name <query>   Only apps whose name matches <query>
+      --verbose        Also print what the scan had to skip
+  icon <id>            Render the icon of the app whose path is <id>
+      --size <pixels>  Icon size, 64 by default
+      -o <file>        Where to write the PNG file, printed otherwise
+  watch                Print changes to the apps as JSON lines
+      --initial        Start with the apps that are installed
+  snapshot <file>      Save what a scan finds and skips to <file>, to attach
+                       to a report of a missing app
+
+Options of all commands:
+  --path <dir>         Search <dir> instead of the default search paths, may
+                       be repeated
+";
+
+/// Why `apps` failed: the command line is wrong, the usage is printed
+/// then, or the command failed.
+enum Failure {
+    Usage(String),
+    Failed(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        Self::Failed(e)
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::Usage(message)
+    }
+}
+
+/// The options of a command, in the order given.
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// Options that take a value, all others are flags.
+    const WITH_VALUE: &[&str] = &["--kind", "--name", "--size", "-o", "--path"];
+
+    fn parse(mut args: impl Iterator<Item = String>) -> std::result::Result<Self, String> {
+        let command = args.next().ok_or("no command given")?;
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                positional.push(arg);
+            } else if Self::WITH_VALUE.contains(&arg.as_str()) {
+                let value = args.next().ok_or(format!("{arg} needs a value"))?;
+                options.push((arg, Some(value)));
+            } else {
+                options.push((arg, None));
+            }
+        }
+
+        Ok(Self {
+            command,
+            positional,
+            options,
+        })
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    fn values(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.options
+            .iter()
+            .filter(move |(option, _)| option == name)
+            .filter_map(|(_, value)| value.as_deref())
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).last()
+    }
+
+    /// Fail on options `command` does not know.
+    fn check(&self, known: &[&str]) -> std::result::Result<(), String> {
+        match self
+            .options
+            .iter()
+            .find(|(option, _)| option != "--path" && !known.contains(&option.as_str()))
+        {
+            Some((option, _)) => Err(format!("unknown option {option} for {}", self.command)),
+            None => Ok(()),
+        }
+    }
+
+    fn search_paths(&self) -> Vec<PathBuf> {
+        let paths: Vec<PathBuf> = self.values("--path").map(PathBuf::from).collect();
+        if paths.is_empty() {
+            get_default_search_paths()
+        } else {
+            paths
+        }
+    }
+}
+
+fn kind_name(kind: &AppKind) -> &'static str {
+    match kind {
+        AppKind::Gui => "gui",
+        AppKind::WebApp { .. } => "web",
+        AppKind::AndroidApp { .. } => "android",
+        AppKind::WslApp { .. } => "wsl",
+        AppKind::Cli => "cli",
+        AppKind::Game { .. } => "game",
+        AppKind::System => "system",
+    }
+}
+
+fn list(args: &Args) -> Result<()> {
+    let report = AppFinder::with_paths(args.search_paths()).find_with_report();
+    if args.flag("--verbose") {
+        for skipped in &report.errors {
+            match &skipped.path {
+                Some(path) => eprintln!("skipped {}: {}", path.display(), skipped.error),
+                None => eprintln!("skipped: {}", skipped.error),
+            }
+        }
+    }
+
+    let kinds: Vec<&str> = args.values("--kind").collect();
+    let mut apps: Vec<&App> = match args.value("--name") {
+        Some(query) => AppSearch::new().search(&report.apps, query),
+        None => report.apps.iter().collect(),
+    };
+    apps.retain(|app| kinds.is_empty() || kinds.contains(&kind_name(&app.kind)));
+
+    if args.flag("--json") {
+        let apps = apps
+            .iter()
+            .map(|app| app.to_json())
+            .collect::<Result<Vec<_>>>()?;
+        println!("[{}]", apps.join(","));
+        return Ok(());
+    }
+
+    let name_width = apps
+        .iter()
+        .map(|app| app.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(4, 40);
+    println!("{:name_width$}  {:7}  PATH", "NAME", "KIND");
+    for app in apps {
+        println!(
+            "{:name_width$}  {:7}  {}",
+            app.name,
+            kind_name(&app.kind),
+            app.app_desktop_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn icon(args: &Args, id: &str, size: u32) -> Result<()> {
+    let apps = AppFinder::with_paths(args.search_paths()).find()?;
+    let Some(app) = apps
+        .iter()
+        .find(|app| app.app_desktop_path == Path::new(id))
+    else {
+        return Err(Error::Platform(format!("no app at {id}").into()));
+    };
+
+    let dir = IconCache::default_dir().unwrap_or_else(|| std::env::temp_dir().join("apps-icons"));
+    let path = IconCache::new(dir).get(app, size)?;
+    match args.value("-o") {
+        Some(out) => {
+            std::fs::copy(&path, out)?;
+        }
+        None => println!("{}", path.display()),
+    }
+
+    Ok(())
+}
+
+/// Watch the `--path`s, or the default search paths, of which the missing
+/// ones are watched once they are created.
+fn watcher(args: &Args) -> Result<Watcher> {
+    let paths: Vec<PathBuf> = args.values("--path").map(PathBuf::from).collect();
+    let mut watcher = Watcher::new(&paths)?;
+    if paths.is_empty() {
+        watcher.watch_defaults()?;
+    }
+    if args.flag("--initial") {
+        watcher = watcher.with_initial_state();
+    }
+
+    Ok(watcher)
+}
+
+fn watch(args: &Args) -> Result<()> {
+    let mut watcher = watcher(args)?;
+    loop {
+        for change in watcher.recv()? {
+            println!("{}", serde_json::to_string(&change)?);
+        }
+    }
+}
+
+fn run(args: &Args) -> std::result::Result<(), Failure> {
+    match args.command.as_str() {
+        "list" => {
+            args.check(&["--json", "--kind", "--name", "--verbose"])?;
+            if let Some(kind) = args.values("--kind").find(|kind| !KINDS.contains(kind)) {
+                return Err(format!("unknown kind {kind}").into());
+            }
+            list(args)?;
+        }
+        "icon" => {
+            args.check(&["--size", "-o"])?;
+            let [id] = args.positional.as_slice() else {
+                return Err("icon takes the path of an app".to_string().into());
+            };
+            let size = match args.value("--size") {
+                Some(size) => size.parse().map_err(|_| format!("invalid size {size}"))?,
+                None => 64,
+            };
+            icon(args, id, size)?;
+        }
+        "watch" => {
+            args.check(&["--initial"])?;
+            watch(args)?;
+        }
+        "snapshot" => {
+            args.check(&[])?;
+            let [file] = args.positional.as_slice() else {
+                return Err("snapshot takes the file to save it to".to_string().into());
+            };
+            AppSnapshot::capture(&AppFinder::with_paths(args.search_paths())).save(file)?;
+        }
+        command => return Err(format!("unknown command {command}").into()),
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(std::env::args().skip(1))
+        .map_err(Failure::Usage)
+        .and_then(|args| {
+            if args.command == "help" || args.flag("--help") || args.flag("-h") {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            run(&args)
+        });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("apps: {message}\n\n{USAGE}");
+            ExitCode::FAILURE
+        }
+        Err(Failure::Failed(e)) => {
+            eprintln!("apps: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> std::result::Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["list", "--kind", "gui", "--json", "--kind", "cli"]).unwrap();
+        assert_eq!(args.command, "list");
+        assert!(args.positional.is_empty());
+        assert!(args.flag("--json"));
+        assert!(!args.flag("--verbose"));
+        assert_eq!(args.values("--kind").collect::<Vec<_>>(), ["gui", "cli"]);
+        assert_eq!(args.value("--kind"), Some("cli"));
+        assert_eq!(args.value("--name"), None);
+
+        let args = parse(&[
+            "icon",
+            "/usr/share/applications/foo.desktop",
+            "-o",
+            "foo.png",
+        ])
+        .unwrap();
+        assert_eq!(args.positional, ["/usr/share/applications/foo.desktop"]);
+        assert_eq!(args.value("-o"), Some("foo.png"));
+
+        let args = parse(&["list", "--path", "/a", "--path", "/b"]).unwrap();
+        assert_eq!(
+            args.search_paths(),
+            [PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+
+        // A value may look like an option
+        let args = parse(&["list", "--name", "-x"]).unwrap();
+        assert_eq!(args.value("--name"), Some("-x"));
+    }
+
+    #[test]
+    fn test_parse_args_missing_value() {
+        assert_eq!(parse(&[]).err().as_deref(), Some("no command given"));
+        assert_eq!(
+            parse(&["list", "--name"]).err().as_deref(),
+            Some("--name needs a value")
+        );
+        assert_eq!(
+            parse(&["icon", "foo", "--size"]).err().as_deref(),
+            Some("--size needs a value")
+        );
+    }
+
+    #[test]
+    fn test_watcher() {
+        // Some of the default search paths are missing on any machine
+        let defaults = watcher(&parse(&["watch"]).unwrap()).unwrap();
+        assert!(!defaults.watch_list_is_empty());
+
+        let args = parse(&["watch", "--path", "/nonexistent/applications-rs"]).unwrap();
+        assert!(matches!(watcher(&args), Err(Error::NotADirectory(_))));
+    }
+
+    #[test]
+    fn test_parse_args_unknown() {
+        let args = parse(&["list", "--json", "--path", "/a"]).unwrap();
+        assert_eq!(args.check(&["--json"]), Ok(()));
+
+        let args = parse(&["watch", "--json"]).unwrap();
+        assert_eq!(
+            args.check(&["--initial"]),
+            Err("unknown option --json for watch".to_string())
+        );
+
+        let args = parse(&["list", "--kind", "toaster"]).unwrap();
+        assert!(matches!(
+            run(&args),
+            Err(Failure::Usage(message)) if message == "unknown kind toaster"
+        ));
+
+        let args = parse(&["frobnicate"]).unwrap();
+        assert!(matches!(
+            run(&args),
+            Err(Failure::Usage(message)) if message == "unknown command frobnicate"
+        ));
+
+        let args = parse(&["icon"]).unwrap();
+        assert!(matches!(run(&args), Err(Failure::Usage(_))));
+    }
+}
@@ -0,0 +1,228 @@
+//! The discovered apps saved to a file, so that the next start does not
+//! have to wait for a scan.
+
+use crate::platforms::cache_sources;
+use crate::{App, Error, Result, get_all_apps};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// Bumped whenever [`App`] or the file layout changes, older files are
+/// ignored.
+const CACHE_VERSION: u32 = 4;
+
+/// A search path, or another place apps are found in, e.g., a registry
+/// key on Windows, as it was when it was scanned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Source {
+    path: PathBuf,
+    /// See [`modified`].
+    modified: Option<SystemTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    sources: Vec<Source>,
+    /// Where apps are found besides the search paths: the `Uninstall` keys
+    /// and the packages key of the registry on Windows.
+    system_sources: Vec<Source>,
+    apps: Vec<App>,
+}
+
+/// A cache of the apps in some search paths, saved as JSON to a file.
+///
+/// [`AppCache::load`] is instant, it is up to the caller to revalidate,
+/// e.g., on a background thread:
+///
+/// ```no_run
+/// use applications::{AppCache, get_default_search_paths};
+///
+/// let cache = AppCache::new("/tmp/apps.json", &get_default_search_paths());
+/// let apps = cache.load().unwrap_or_default();
+/// std::thread::spawn(move || {
+///     if cache.is_stale() {
+///         let _apps = cache.refresh();
+///     }
+/// });
+/// ```
+///
+/// An [`AppIndex`](crate::AppIndex) made with
+/// [`AppIndex::with_cache`](crate::AppIndex::with_cache) keeps it up to
+/// date with a watcher.
+#[derive(Debug, Clone)]
+pub struct AppCache {
+    path: PathBuf,
+    search_paths: Vec<PathBuf>,
+}
+
+impl AppCache {
+    /// A cache of the apps in `search_paths`, saved at `path`.
+    pub fn new(path: impl Into<PathBuf>, search_paths: &[PathBuf]) -> Self {
+        Self {
+            path: path.into(),
+            search_paths: search_paths.to_vec(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn search_paths(&self) -> &[PathBuf] {
+        &self.search_paths
+    }
+
+    /// The apps saved last time, up to date or not. `None` if there are
+    /// none, or they were saved for other search paths or by another
+    /// version of the crate.
+    pub fn load(&self) -> Option<Vec<App>> {
+        let file = self.read().ok()?;
+        Some(file.apps)
+    }
+
+    /// Whether a search path has changed since the apps were saved, which
+    /// is when one of its directories, or their subdirectories, has
+    /// gained or lost an entry, or, on Windows, an app was added to or
+    /// removed from the `Uninstall` keys of the registry or the packages
+    /// of the user. Apps that are updated in place are only noticed by a
+    /// watcher.
+    pub fn is_stale(&self) -> bool {
+        match self.read() {
+            Ok(file) => !self.is_current(&file),
+            Err(_) => true,
+        }
+    }
+
+    /// Scan the search paths and save what is found.
+    pub fn refresh(&self) -> Result<Vec<App>> {
+        let (sources, system_sources) = (self.sources(), system_sources());
+        let apps = get_all_apps(&self.search_paths)?;
+        self.write(sources, system_sources, &apps)?;
+
+        Ok(apps)
+    }
+
+    /// The saved apps if they are up to date, the ones found by
+    /// [`AppCache::refresh`] otherwise.
+    pub fn get(&self) -> Result<Vec<App>> {
+        match self.read() {
+            Ok(file) if self.is_current(&file) => Ok(file.apps),
+            _ => self.refresh(),
+        }
+    }
+
+    /// Save `apps` as the apps in the search paths as they are now.
+    pub fn save(&self, apps: &[App]) -> Result<()> {
+        self.write(self.sources(), system_sources(), apps)
+    }
+
+    fn is_current(&self, file: &CacheFile) -> bool {
+        file.sources == self.sources() && file.system_sources == system_sources()
+    }
+
+    fn sources(&self) -> Vec<Source> {
+        self.search_paths
+            .iter()
+            .map(|path| Source {
+                path: path.clone(),
+                modified: modified(path),
+            })
+            .collect()
+    }
+
+    fn read(&self) -> Result<CacheFile> {
+        let content = std::fs::read(&self.path)?;
+        let file: CacheFile = serde_json::from_slice(&content)
+            .map_err(|e| Error::parse(&self.path, e.to_string()))?;
+        let search_paths: Vec<&PathBuf> = file.sources.iter().map(|source| &source.path).collect();
+        if file.version != CACHE_VERSION
+            || search_paths != self.search_paths.iter().collect::<Vec<_>>()
+        {
+            return Err(Error::parse(
+                &self.path,
+                "saved by another version or for other search paths",
+            ));
+        }
+
+        Ok(file)
+    }
+
+    /// Written next to the cache first, so that a reader never sees half
+    /// of it.
+    fn write(&self, sources: Vec<Source>, system_sources: Vec<Source>, apps: &[App]) -> Result<()> {
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            sources,
+            system_sources,
+            apps: apps.to_vec(),
+        };
+        let content =
+            serde_json::to_vec(&file).map_err(|e| Error::parse(&self.path, e.to_string()))?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+fn system_sources() -> Vec<Source> {
+    cache_sources()
+        .into_iter()
+        .map(|(path, modified)| Source { path, modified })
+        .collect()
+}
+
+/// The newest modification time of `path` and the directories in it, two
+/// levels deep, which changes when an app is installed or uninstalled.
+/// `None` if `path` does not exist.
+fn modified(path: &Path) -> Option<SystemTime> {
+    WalkDir::new(path)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_cache() {
+        let temp_dir = TempDir::new("cache");
+        let dir = temp_dir.path().to_path_buf();
+        let apps_dir = dir.join("apps");
+        std::fs::create_dir_all(&apps_dir).unwrap();
+        let cache = AppCache::new(dir.join("cache.json"), std::slice::from_ref(&apps_dir));
+        assert!(cache.load().is_none());
+        assert!(cache.is_stale());
+
+        let app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: apps_dir.join("foo.desktop"),
+            ..Default::default()
+        };
+        cache.save(std::slice::from_ref(&app)).unwrap();
+        assert_eq!(cache.load(), Some(vec![app.clone()]));
+        assert!(!cache.is_stale());
+        assert_eq!(cache.get().unwrap(), vec![app]);
+
+        // Not what the cache was made for
+        let other = AppCache::new(cache.path(), &[dir.join("other")]);
+        assert!(other.load().is_none());
+
+        // Modification times do not necessarily change within a second
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        std::fs::create_dir(apps_dir.join("new")).unwrap();
+        assert!(cache.is_stale());
+    }
+}
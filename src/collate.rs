@@ -0,0 +1,268 @@
+//! [`Collator`], sorting apps by the name users of a locale see.
+
+use crate::{App, normalize_locale};
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Latin letters with diacritics, and what they sort as in most
+/// languages.
+const FOLDED: &[(&str, &str)] = &[
+    ("àáâãäåāăą", "a"),
+    ("æ", "ae"),
+    ("çćĉċč", "c"),
+    ("ďđð", "d"),
+    ("èéêëēĕėęě", "e"),
+    ("ĝğġģ", "g"),
+    ("ĥħ", "h"),
+    ("ìíîïĩīĭįı", "i"),
+    ("ĳ", "ij"),
+    ("ĵ", "j"),
+    ("ķ", "k"),
+    ("ĺļľŀł", "l"),
+    ("ñńņňŉ", "n"),
+    ("òóôõöøōŏő", "o"),
+    ("œ", "oe"),
+    ("ŕŗř", "r"),
+    ("śŝşš", "s"),
+    ("ß", "ss"),
+    ("ţťŧ", "t"),
+    ("þ", "th"),
+    ("ùúûüũūŭůűų", "u"),
+    ("ŵ", "w"),
+    ("ýÿŷ", "y"),
+    ("źżž", "z"),
+];
+
+/// Letters that languages sort as letters of their own, the language and
+/// what they sort as. `\u{10FFFF}` sorts after any other character.
+const TAILORED: &[(&str, char, &str)] = &[
+    ("sv", 'å', "z\u{10FFFF}1"),
+    ("sv", 'ä', "z\u{10FFFF}2"),
+    ("sv", 'ö', "z\u{10FFFF}3"),
+    ("fi", 'å', "z\u{10FFFF}1"),
+    ("fi", 'ä', "z\u{10FFFF}2"),
+    ("fi", 'ö', "z\u{10FFFF}3"),
+    ("da", 'æ', "z\u{10FFFF}1"),
+    ("da", 'ø', "z\u{10FFFF}2"),
+    ("da", 'å', "z\u{10FFFF}3"),
+    ("nb", 'æ', "z\u{10FFFF}1"),
+    ("nb", 'ø', "z\u{10FFFF}2"),
+    ("nb", 'å', "z\u{10FFFF}3"),
+    ("nn", 'æ', "z\u{10FFFF}1"),
+    ("nn", 'ø', "z\u{10FFFF}2"),
+    ("nn", 'å', "z\u{10FFFF}3"),
+    ("es", 'ñ', "n\u{10FFFF}"),
+    ("de", 'ä', "ae"),
+    ("de", 'ö', "oe"),
+    ("de", 'ü', "ue"),
+];
+
+type Transliterate = Arc<dyn Fn(char) -> Option<String> + Send + Sync>;
+
+/// Compares apps by their [display name](App::display_name) in a locale
+/// the way users of the locale expect, rather than by their bytes:
+/// `Éditeur` sorts with the other names starting with E, case and accents
+/// only break ties, and letters some languages treat as their own, e.g.,
+/// `ä` in Swedish, sort where they do.
+///
+/// ```no_run
+/// use applications::{Collator, get_all_apps, get_default_search_paths};
+///
+/// let mut apps = get_all_apps(&get_default_search_paths()).unwrap();
+/// Collator::new("fr-FR").sort(&mut apps);
+/// ```
+///
+/// It is no full Unicode collation: scripts other than Latin sort by code
+/// point, unless [transliterated](Collator::transliterate), e.g., Chinese
+/// names by their Pinyin, as with [`AppSearch`](crate::AppSearch).
+#[derive(Clone)]
+pub struct Collator {
+    locale: String,
+    transliterate: Option<Transliterate>,
+}
+
+impl fmt::Debug for Collator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collator")
+            .field("locale", &self.locale)
+            .field("transliterate", &self.transliterate.is_some())
+            .finish()
+    }
+}
+
+impl Collator {
+    /// A collator for `locale`, in any form [`normalize_locale`] takes.
+    pub fn new(locale: &str) -> Self {
+        Self {
+            locale: normalize_locale(locale).unwrap_or_default(),
+            transliterate: None,
+        }
+    }
+
+    /// Sort the characters `transliterate` returns `Some` for by their
+    /// romanization, after the Latin letters.
+    pub fn transliterate(
+        mut self,
+        transliterate: impl Fn(char) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.transliterate = Some(Arc::new(transliterate));
+        self
+    }
+
+    /// Sort `apps` by their display names.
+    pub fn sort(&self, apps: &mut [App]) {
+        let mut keyed: Vec<(Key, App)> = apps
+            .iter_mut()
+            .map(|app| {
+                (
+                    self.key(app.display_name(&self.locale)),
+                    std::mem::take(app),
+                )
+            })
+            .collect();
+        keyed.sort_by(|(key, _), (other, _)| key.cmp(other));
+        for (app, (_, sorted)) in apps.iter_mut().zip(keyed) {
+            *app = sorted;
+        }
+    }
+
+    pub fn compare(&self, app: &App, other: &App) -> Ordering {
+        self.compare_names(
+            app.display_name(&self.locale),
+            other.display_name(&self.locale),
+        )
+    }
+
+    pub fn compare_names(&self, name: &str, other: &str) -> Ordering {
+        self.key(name).cmp(&self.key(other))
+    }
+
+    fn key(&self, name: &str) -> Key {
+        let language = self.locale.split('-').next().unwrap_or_default();
+        let mut key = Key {
+            primary: Vec::new(),
+            secondary: Vec::new(),
+            tertiary: Vec::new(),
+            name: name.to_string(),
+        };
+        for c in name.chars() {
+            if let Some(romanized) = self.transliterate.as_ref().and_then(|f| f(c)) {
+                key.primary
+                    .push((Group::Transliterated, romanized.to_lowercase()));
+                continue;
+            }
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            key.tertiary.push(c != lower);
+            let tailored = TAILORED
+                .iter()
+                .find(|(tailored_language, letter, _)| {
+                    *tailored_language == language && *letter == lower
+                })
+                .map(|(_, _, primary)| *primary);
+            let folded = FOLDED
+                .iter()
+                .find(|(letters, _)| letters.contains(lower))
+                .map(|(_, primary)| *primary);
+            let (group, primary) = match (tailored, folded) {
+                (Some(primary), _) | (None, Some(primary)) => (Group::Letter, primary.to_string()),
+                _ if lower.is_ascii_alphabetic() => (Group::Letter, lower.to_string()),
+                _ if lower.is_numeric() => (Group::Digit, lower.to_string()),
+                _ if lower.is_alphanumeric() => (Group::Other, lower.to_string()),
+                _ => (Group::Space, lower.to_string()),
+            };
+            key.secondary
+                .push(if tailored.is_none() && folded.is_some() {
+                    lower
+                } else {
+                    '\0'
+                });
+            key.primary.push((group, primary));
+        }
+
+        key
+    }
+}
+
+/// Where characters sort, relative to the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Group {
+    Space,
+    Digit,
+    Letter,
+    Transliterated,
+    Other,
+}
+
+/// What names are compared by, one level after the other: the letters,
+/// the accents, the case, in the end the name itself.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    primary: Vec<(Group, String)>,
+    secondary: Vec<char>,
+    /// Uppercase after lowercase.
+    tertiary: Vec<bool>,
+    name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted<'a>(collator: &Collator, names: &[&'a str]) -> Vec<&'a str> {
+        let mut names = names.to_vec();
+        names.sort_by(|name, other| collator.compare_names(name, other));
+        names
+    }
+
+    #[test]
+    fn test_collator() {
+        let french = Collator::new("fr_FR");
+        assert_eq!(
+            sorted(&french, &["Zed", "Éditeur", "editor", "Edge", "Files"]),
+            ["Edge", "Éditeur", "editor", "Files", "Zed"]
+        );
+        assert_eq!(sorted(&french, &["b", "B", "a"]), ["a", "b", "B"]);
+        assert_eq!(
+            sorted(&french, &["Foo 2", "Foo", "Foo Bar"]),
+            ["Foo", "Foo 2", "Foo Bar"]
+        );
+        assert_eq!(
+            sorted(&Collator::new("sv"), &["Örn", "Zoom", "Orca"]),
+            ["Orca", "Zoom", "Örn"]
+        );
+        assert_eq!(
+            sorted(&Collator::new("en"), &["Örn", "Zoom", "Orca"]),
+            ["Orca", "Örn", "Zoom"]
+        );
+
+        let chinese = Collator::new("zh-CN").transliterate(|c| {
+            let romanized = match c {
+                '访' => "fang",
+                '达' => "da",
+                '备' => "bei",
+                '忘' => "wang",
+                '录' => "lu",
+                '音' => "yin",
+                '乐' => "yue",
+                _ => return None,
+            };
+            Some(romanized.to_string())
+        });
+        let mut apps: Vec<App> = [("Finder", "访达"), ("Notes", "备忘录"), ("Music", "音乐")]
+            .into_iter()
+            .map(|(name, localized)| App {
+                name: name.to_string(),
+                localized_app_names: [("zh-CN".to_string(), localized.to_string())].into(),
+                ..Default::default()
+            })
+            .collect();
+        apps.push(App {
+            name: "Code".to_string(),
+            ..Default::default()
+        });
+        chinese.sort(&mut apps);
+        let names: Vec<&str> = apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, ["Code", "Notes", "Finder", "Music"]);
+    }
+}
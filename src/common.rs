@@ -1,13 +1,21 @@
 //! Common Data Structures
 
-use anyhow::Result;
+use crate::{Error, Result};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, Eq, Hash)]
+/// An installed application.
+///
+/// Its JSON form is described by a [schema](App::JSON_SCHEMA); fields
+/// missing from the JSON, e.g., ones added after it was written, take their
+/// default values.
+#[derive(Debug, Clone, PartialEq, Default, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct App {
     /// Base name. Should only be used when the localized app name needed is
     /// not found.
@@ -16,21 +24,461 @@ pub struct App {
     /// defined in "Info.plist", or the stem part of the app package's file
     /// name (Finder.app => Finder).
     pub name: String,
-    /// Localized app names, for example:
+    /// Localized app names, keyed by BCP 47 language tags as returned by
+    /// [`normalize_locale`], for example:
     ///
     /// ```text
     /// en: Finder
-    /// zh_CN: 访达
-    /// zh_HK: Finder
-    /// zh_TW: Finder
+    /// zh-CN: 访达
+    /// zh-HK: Finder
+    /// zh-Hans: 访达
     /// ```
     pub localized_app_names: BTreeMap<String, String>,
     /// Path to the icon file.
     pub icon_path: Option<PathBuf>,
+    /// Index of the icon resource when `icon_path` is an EXE or DLL on
+    /// Windows, a negative value is a resource ID.
+    pub icon_index: Option<i32>,
     /// Path to the executable file.
     pub app_path_exe: Option<PathBuf>,
     // Path to the .desktop file for Linux, .app for Mac
     pub app_desktop_path: PathBuf,
+    pub kind: AppKind,
+    /// Version string as shown to users, e.g., "CFBundleShortVersionString"
+    /// on macOS or "DisplayVersion" of the uninstall registry key on Windows.
+    pub version: Option<String>,
+    /// Who publishes the app, as provided by the app, it is not verified.
+    pub publisher: Option<String>,
+    /// Application User Model ID of a packaged (UWP/MSIX) Windows app, e.g.,
+    /// `Microsoft.WindowsCalculator_8wekyb3d8bbwe!App`, which is how such apps
+    /// are launched.
+    pub aumid: Option<String>,
+    /// The package manager that installed the app, if it was found through
+    /// one.
+    pub install_source: Option<InstallSource>,
+    /// The winget package ID, e.g., `Microsoft.VisualStudioCode`, for
+    /// driving `winget upgrade`. Only set by `apply_winget_ids()` on
+    /// Windows.
+    pub winget_id: Option<String>,
+    /// Arguments to start the app with, e.g., the ones a Windows shortcut
+    /// passes to its target.
+    pub launch_args: Vec<String>,
+    /// Whether starting the app brings up a UAC prompt, because its
+    /// manifest or its shortcut asks to run as administrator. Windows only.
+    pub requires_elevation: bool,
+    /// The MSI product code, e.g., `{23170F69-40C1-2702-2409-000001000000}`,
+    /// of apps installed by Windows Installer.
+    pub product_code: Option<String>,
+    /// What the installer registered to uninstall the app. Windows only.
+    pub uninstaller: Option<UninstallCommand>,
+    /// Whether the app is installed for the current user or for everyone,
+    /// modifying it takes admin rights in the latter case.
+    pub scope: InstallScope,
+    /// Categories to group apps by: the `Categories` of a desktop entry
+    /// on Linux, e.g., `["Utility", "TextEditor"]`, the Start Menu folders
+    /// of the shortcut on Windows, e.g., `["Accessories"]`.
+    pub categories: Vec<String>,
+    /// What found the app, more than one source if a scan found it more
+    /// than once, e.g., a Start Menu shortcut that Scoop installed.
+    pub sources: Vec<DiscoverySource>,
+    /// The `.app` bundle the app is embedded in on macOS, e.g., for the
+    /// apps of a suite in its `Contents/Applications`, to show them under
+    /// it.
+    pub parent: Option<PathBuf>,
+    /// The mount point of the external drive or network share the app was
+    /// found on, e.g., `/Volumes/SSD`, by
+    /// [`Provider::Volumes`](crate::Provider::Volumes) on macOS.
+    pub volume: Option<PathBuf>,
+    /// The user in whose home folder the app is, if it is not the current
+    /// user, e.g., `bob` for `/Users/bob/Applications/Foo.app`, which
+    /// Spotlight lists to all users on macOS, see
+    /// [`AppFinder::other_users`](crate::AppFinder::other_users).
+    pub other_user: Option<String>,
+}
+
+/// What [`App::to_json`] writes, the version goes first.
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct VersionedApp<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    app: &'a App,
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+struct VersionedAppOwned {
+    /// Missing from JSON written by `serde` directly, which is version 1.
+    #[serde(default = "first_schema_version")]
+    schema_version: u32,
+    #[serde(flatten)]
+    app: App,
+}
+
+#[cfg(feature = "json")]
+fn first_schema_version() -> u32 {
+    1
+}
+
+impl App {
+    /// The version of the schema of the JSON form of an [`App`]. Within a
+    /// version, fields and variants are only added, never renamed or
+    /// removed, and their meaning does not change.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// The [JSON Schema](https://json-schema.org/) of the JSON form of an
+    /// [`App`], for consumers written in other languages.
+    pub const JSON_SCHEMA: &str = include_str!("../docs/schema/app.v1.schema.json");
+
+    /// The app as a JSON object, with a `schema_version` field set to
+    /// [`App::SCHEMA_VERSION`]. Fails if a path is not valid Unicode.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&VersionedApp {
+            schema_version: Self::SCHEMA_VERSION,
+            app: self,
+        })?)
+    }
+
+    /// An app read from a JSON object written by [`App::to_json`]. JSON of
+    /// a newer schema version is rejected, as its fields may mean
+    /// something else.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<App> {
+        let versioned: VersionedAppOwned = serde_json::from_str(json)?;
+        if versioned.schema_version > Self::SCHEMA_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(versioned.schema_version));
+        }
+
+        Ok(versioned.app)
+    }
+
+    /// The name to show to users of `locale`, e.g., `zh-CN` or `zh_CN`:
+    /// the localized name of the locale, or else of a less specific one,
+    /// e.g., `zh` for `zh-CN`, or of the same language, and [`App::name`]
+    /// if there is none.
+    pub fn display_name(&self, locale: &str) -> &str {
+        let Some(locale) = normalize_locale(locale) else {
+            return &self.name;
+        };
+        // `zh-CN` names are `zh-Hans` ones, and the other way around
+        let alias = match locale.as_str() {
+            "zh-CN" | "zh-SG" => Some("zh-Hans"),
+            "zh-TW" | "zh-HK" | "zh-MO" => Some("zh-Hant"),
+            "zh-Hans" => Some("zh-CN"),
+            "zh-Hant" => Some("zh-TW"),
+            _ => None,
+        };
+        let mut tag = locale.as_str();
+        loop {
+            if let Some(name) = self.localized_app_names.get(tag) {
+                return name;
+            }
+            if let Some(name) = alias.and_then(|alias| self.localized_app_names.get(alias)) {
+                return name;
+            }
+            match tag.rsplit_once('-') {
+                Some((parent, _)) => tag = parent,
+                None => break,
+            }
+        }
+        self.localized_app_names
+            .iter()
+            .find(|(other, _)| other.split('-').next() == Some(tag))
+            .map_or(&self.name, |(_, name)| name)
+    }
+}
+
+/// The parsed `QuietUninstallString` or `UninstallString` of an [`App`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UninstallCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Whether it runs without asking the user anything, it does when it
+    /// comes from `QuietUninstallString` or is an MSI uninstall.
+    pub quiet: bool,
+}
+
+/// Legacy `.lproj` directory names of macOS, e.g., `English.lproj`.
+const LEGACY_LOCALE_NAMES: &[(&str, &str)] = &[
+    ("English", "en"),
+    ("French", "fr"),
+    ("German", "de"),
+    ("Italian", "it"),
+    ("Japanese", "ja"),
+    ("Spanish", "es"),
+    ("Dutch", "nl"),
+];
+
+/// The BCP 47 language tag of `locale`, which may be in any form the
+/// platforms use: `zh_CN` and `sr_RS@latin` of desktop entries and POSIX
+/// locales, `zh-Hans` or `English` of `.lproj` directories, `zh-CN` of
+/// Windows. `zh_CN` becomes `zh-CN`, and `sr_RS@latin` becomes `sr-Latn-RS`.
+/// `None` for what is not a locale, e.g., `Base.lproj`.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let locale = locale.trim();
+    if let Some((_, language)) = LEGACY_LOCALE_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(locale))
+    {
+        return Some(language.to_string());
+    }
+    // The encoding of a POSIX locale, e.g., `en_US.UTF-8`
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier.to_ascii_lowercase())),
+        None => (locale, None),
+    };
+
+    let mut subtags = locale.split(['_', '-']).filter(|subtag| !subtag.is_empty());
+    let language = subtags.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut script = match modifier.as_deref() {
+        Some("latin") => Some("Latn".to_string()),
+        Some("cyrillic") => Some("Cyrl".to_string()),
+        Some("devanagari") => Some("Deva".to_string()),
+        _ => None,
+    };
+    let modifier = modifier.filter(|_| script.is_none());
+    let mut region = None;
+    let mut variants: Vec<String> = Vec::new();
+    for subtag in subtags {
+        let is_alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        if subtag.len() == 4 && is_alphabetic {
+            let (first, rest) = subtag.split_at(1);
+            script = Some(first.to_ascii_uppercase() + &rest.to_ascii_lowercase());
+        } else if (subtag.len() == 2 && is_alphabetic)
+            || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()))
+        {
+            region = Some(subtag.to_ascii_uppercase());
+        } else {
+            variants.push(subtag.to_ascii_lowercase());
+        }
+    }
+    // Other modifiers are variants, e.g., `ca@valencia`
+    variants.extend(modifier);
+
+    let mut tag = language.to_ascii_lowercase();
+    for subtag in script.into_iter().chain(region).chain(variants) {
+        tag.push('-');
+        tag.push_str(&subtag);
+    }
+
+    Some(tag)
+}
+
+/// Who an [`App`] is installed for.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InstallScope {
+    /// Installed in the user's home, e.g., `~/Applications`, `~/.local` or
+    /// `%LOCALAPPDATA%`, or registered under `HKEY_CURRENT_USER`.
+    User,
+    /// Installed in a system location, e.g., `/Applications`, `/usr` or
+    /// `Program Files`, or registered under `HKEY_LOCAL_MACHINE`.
+    #[default]
+    Machine,
+}
+
+impl InstallScope {
+    /// The scope of something installed at `path`, [`InstallScope::User`]
+    /// if it is inside the home directory.
+    pub(crate) fn of_path(path: &Path) -> Self {
+        let home_var = if cfg!(target_os = "windows") {
+            "USERPROFILE"
+        } else {
+            "HOME"
+        };
+        let Some(home) = std::env::var_os(home_var).filter(|home| !home.is_empty()) else {
+            return InstallScope::Machine;
+        };
+
+        // Windows paths are case-insensitive
+        let in_home = if cfg!(target_os = "windows") {
+            let lowercase = |path: &Path| PathBuf::from(path.to_string_lossy().to_lowercase());
+            lowercase(path).starts_with(lowercase(Path::new(&home)))
+        } else {
+            path.starts_with(&home)
+        };
+        if in_home {
+            InstallScope::User
+        } else {
+            InstallScope::Machine
+        }
+    }
+
+    /// [`InstallScope::of_path`] of a system below `root`, see
+    /// [`AppFinder::with_root`](crate::AppFinder::with_root), whose homes
+    /// are those of all of its users.
+    pub(crate) fn of_path_below(root: &Path, path: &Path) -> Self {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return InstallScope::Machine;
+        };
+        let mut names = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy());
+        let (first, user) = (names.next(), names.next());
+        // Only Linux file systems are case-sensitive, and `Public` and
+        // `Shared` are for all users
+        let in_home = if cfg!(target_os = "linux") {
+            first.is_some_and(|first| first == "home" || first == "root")
+        } else {
+            first.is_some_and(|first| first.eq_ignore_ascii_case("Users"))
+                && user.is_some_and(|user| {
+                    !user.eq_ignore_ascii_case("Public") && !user.eq_ignore_ascii_case("Shared")
+                })
+        };
+        if in_home {
+            InstallScope::User
+        } else {
+            InstallScope::Machine
+        }
+    }
+}
+
+/// A package manager an [`App`] was installed with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InstallSource {
+    /// `bucket` is where updates come from, e.g., `main` or `extras`.
+    Scoop { bucket: Option<String> },
+    /// `package` is the Chocolatey package ID.
+    Chocolatey { package: String },
+    /// Not installed at all, but run from where it was unpacked, e.g., a
+    /// PortableApps.com app on a USB stick.
+    Portable,
+}
+
+/// Where an [`App`] was found, see [`App::sources`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum DiscoverySource {
+    /// A desktop file, on Linux.
+    DesktopFile,
+    /// The desktop file of a Flatpak app.
+    Flatpak,
+    /// An `.app` bundle, as parsed by [`App::from_path`](AppTrait::from_path)
+    /// or a watcher, on macOS.
+    Bundle,
+    /// An `.app` bundle Spotlight finds with `mdfind`.
+    Mdfind,
+    /// A `.lnk` or `.url` shortcut in the Start Menu, or in another search
+    /// path.
+    StartMenu,
+    /// The `Uninstall` or `Run` registry keys.
+    Registry,
+    Scoop,
+    Chocolatey,
+    /// The packaged (MSIX/UWP) apps of the `PackageManager` API.
+    Uwp,
+    /// An App Execution Alias, e.g., `wt.exe`.
+    ExecutionAlias,
+    /// A portable app on a removable drive.
+    Portable,
+    /// `shell:AppsFolder`.
+    AppsFolder,
+    /// An `.app` bundle in the `Applications` of a mounted volume, on
+    /// macOS.
+    Volume,
+    /// An app that ships inside Xcode.
+    Xcode,
+}
+
+/// What kind of application an [`App`] is.
+#[derive(Debug, Clone, PartialEq, Default, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AppKind {
+    /// A regular graphical application.
+    #[default]
+    Gui,
+    /// A website installed as an app, e.g., a PWA. `url` is the site it opens,
+    /// if we can find it.
+    WebApp { url: Option<String> },
+    /// An Android app run by a compatibility layer such as Waydroid or the
+    /// Windows Subsystem for Android.
+    /// `package` is its package name, e.g., `org.mozilla.firefox`.
+    AndroidApp { package: String },
+    /// A Linux GUI app of a WSL distribution, started through WSLg.
+    /// `command` is what runs inside `distro`, e.g., `gedit`.
+    WslApp { distro: String, command: String },
+    /// A command line program, run in a terminal.
+    Cli,
+    /// A game. `url` is the link its store client starts it with, e.g.,
+    /// `steam://rungameid/570`, if it is started that way.
+    Game { url: Option<String> },
+    /// Part of the system rather than an app to launch, e.g., a settings
+    /// panel or an agent running in the background.
+    System,
+}
+
+/// A decoded icon image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels in the RGBA8 format, row by row from the top.
+    pub rgba: Vec<u8>,
+}
+
+/// Whether an executable carries a valid code signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SignatureStatus {
+    /// Signed, and the signature chains to a trusted root.
+    Valid,
+    /// Signed, but the signature is broken, expired or not trusted.
+    Invalid,
+    Unsigned,
+}
+
+/// Code signing information of an app's executable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SignatureInfo {
+    pub status: SignatureStatus,
+    /// Subject of the signing certificate, e.g., "Microsoft Corporation".
+    /// Unlike [`App::publisher`], it is verified when `status` is
+    /// [`SignatureStatus::Valid`].
+    pub subject: Option<String>,
+}
+
+/// The apps a scan found, and what it had to skip.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub apps: Vec<App>,
+    pub errors: Vec<ScanError>,
+}
+
+/// A file, directory or source a scan could not read.
+#[derive(Debug)]
+pub struct ScanError {
+    /// `None` for a source as a whole, e.g., the Windows package catalog.
+    pub path: Option<PathBuf>,
+    pub error: Error,
+}
+
+impl ScanReport {
+    pub(crate) fn skip(&mut self, path: Option<PathBuf>, error: impl Into<Error>) {
+        let error = error.into();
+        crate::trace::debug!(path = ?path, error = %error, "skipped");
+        self.errors.push(ScanError { path, error });
+    }
+}
+
+/// An application that is currently running.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RunningApp {
+    pub app: App,
+    /// IDs of the processes that belong to this app, in ascending order.
+    pub pids: Vec<u32>,
+    /// Titles of the app's visible top-level windows. Collected on Windows
+    /// and, for X11 and XWayland windows, on Linux; empty on macOS.
+    pub window_titles: Vec<String>,
 }
 
 /// This trait specifies the methods that an app should implement, such as loading its logo
@@ -39,4 +487,116 @@ where
     Self: Sized,
 {
     fn from_path(path: &Path) -> Result<Self>;
+
+    /// Start the app, without waiting for it to exit. Fails with
+    /// [`Error::UnsupportedPlatform`] unless it is implemented.
+    fn launch(&self) -> Result<()> {
+        Err(Error::UnsupportedPlatform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_name() {
+        let app = App {
+            name: "Finder".to_string(),
+            localized_app_names: [
+                ("zh-Hans", "访达"),
+                ("fr", "Finder"),
+                ("pt-BR", "Localizador"),
+            ]
+            .into_iter()
+            .map(|(locale, name)| (locale.to_string(), name.to_string()))
+            .collect(),
+            ..Default::default()
+        };
+        assert_eq!(app.display_name("zh_CN"), "访达");
+        assert_eq!(app.display_name("zh-Hans-CN"), "访达");
+        assert_eq!(app.display_name("fr-CA"), "Finder");
+        assert_eq!(app.display_name("pt"), "Localizador");
+        assert_eq!(app.display_name("de"), "Finder");
+        assert_eq!(app.display_name("C"), "Finder");
+    }
+
+    #[test]
+    fn test_normalize_locale() {
+        let normalize = |locale| normalize_locale(locale);
+        assert_eq!(normalize("zh_CN").as_deref(), Some("zh-CN"));
+        assert_eq!(normalize("zh-cn").as_deref(), Some("zh-CN"));
+        assert_eq!(normalize("zh-Hans").as_deref(), Some("zh-Hans"));
+        assert_eq!(normalize("zh_hant_TW").as_deref(), Some("zh-Hant-TW"));
+        assert_eq!(normalize("en_US.UTF-8").as_deref(), Some("en-US"));
+        assert_eq!(normalize("sr_RS@latin").as_deref(), Some("sr-Latn-RS"));
+        assert_eq!(normalize("ca@valencia").as_deref(), Some("ca-valencia"));
+        assert_eq!(normalize("es_419").as_deref(), Some("es-419"));
+        assert_eq!(normalize("English").as_deref(), Some("en"));
+        assert_eq!(normalize("Base"), None);
+        assert_eq!(normalize(""), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        let app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: PathBuf::from("/usr/share/applications/foo.desktop"),
+            kind: AppKind::WebApp {
+                url: Some("https://example.com/".to_string()),
+            },
+            sources: vec![DiscoverySource::DesktopFile],
+            ..Default::default()
+        };
+        let json = app.to_json().unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"name":"Foo","#));
+        assert_eq!(App::from_json(&json).unwrap(), app);
+
+        // Written before `schema_version` and most fields existed
+        let old = App::from_json(r#"{"name":"Foo","app_desktop_path":"/foo"}"#).unwrap();
+        assert_eq!(old.name, "Foo");
+        assert_eq!(old.scope, InstallScope::Machine);
+
+        assert!(matches!(
+            App::from_json(r#"{"schema_version":2,"name":"Foo"}"#),
+            Err(Error::UnsupportedSchemaVersion(2))
+        ));
+    }
+
+    /// The schema is written by hand, it has to list what `serde` writes.
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_schema() {
+        use serde_json::{Map, Value};
+        use std::collections::BTreeSet;
+
+        let keys =
+            |object: &Map<String, Value>| -> BTreeSet<String> { object.keys().cloned().collect() };
+        let schema: Value = serde_json::from_str(App::JSON_SCHEMA).unwrap();
+        let app = App {
+            uninstaller: Some(UninstallCommand {
+                program: PathBuf::from("/usr/bin/foo-uninstall"),
+                args: Vec::new(),
+                quiet: false,
+            }),
+            ..Default::default()
+        };
+        let json: Value = serde_json::from_str(&app.to_json().unwrap()).unwrap();
+        assert_eq!(
+            keys(json.as_object().unwrap()),
+            keys(schema["properties"].as_object().unwrap())
+        );
+        for required in schema["required"].as_array().unwrap() {
+            assert!(json.get(required.as_str().unwrap()).is_some(), "{required}");
+        }
+        assert_eq!(
+            keys(json["uninstaller"].as_object().unwrap()),
+            keys(
+                schema["$defs"]["UninstallCommand"]["properties"]
+                    .as_object()
+                    .unwrap()
+            )
+        );
+    }
 }
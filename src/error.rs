@@ -0,0 +1,164 @@
+//! The error type of the crate
+
+use std::path::PathBuf;
+
+/// Why an operation failed.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// `path` is not a valid desktop file, app bundle, plist or shortcut.
+    #[error("invalid [{}]: {message}", .path.display())]
+    Parse { path: PathBuf, message: String },
+    /// The app lacks what the operation needs, e.g., an `Exec` to launch it
+    /// with or an icon to load.
+    #[error("[{app}] has no {missing}")]
+    Missing { app: String, missing: &'static str },
+    /// The app bundle at `path` is still being installed, e.g., copied by
+    /// the Finder, it has no `missing` yet.
+    #[error("[{}] is incomplete, it has no {missing} yet", .path.display())]
+    Incomplete {
+        path: PathBuf,
+        missing: &'static str,
+    },
+    /// The desktop file at `path` has no `[Desktop Action <action>]` group.
+    #[error("action [{action}] not found in [{}]", .path.display())]
+    ActionNotFound { path: PathBuf, action: String },
+    /// `program` exited with an error.
+    #[error("failed to run {program}, stderr [{stderr}]")]
+    Command { program: String, stderr: String },
+    /// `program` ran for longer than `timeout` and was killed, see
+    /// [`AppFinder::command_timeout`](crate::AppFinder::command_timeout).
+    #[error("{program} did not finish within {timeout:?}")]
+    Timeout {
+        program: String,
+        timeout: std::time::Duration,
+    },
+    /// Walking the mounted `volume` took longer than `timeout`, its apps
+    /// were skipped, see
+    /// [`AppFinder::command_timeout`](crate::AppFinder::command_timeout).
+    #[error("walking [{}] did not finish within {timeout:?}", .volume.display())]
+    VolumeTimeout {
+        volume: PathBuf,
+        timeout: std::time::Duration,
+    },
+    /// The [`CancelToken`](crate::CancelToken) was cancelled.
+    #[error("cancelled")]
+    Cancelled,
+    /// `program` was not run, because subprocesses are disabled, see
+    /// [`AppFinder::subprocesses`](crate::AppFinder::subprocesses).
+    #[error("{0} was not run, subprocesses are disabled")]
+    SubprocessDisabled(String),
+    /// The app cannot be uninstalled by [`App::uninstall`](crate::App::uninstall),
+    /// e.g., because it is part of the OS or belongs to a package of the
+    /// system package manager. `reason` says so, and what to do instead.
+    #[error("[{app}] cannot be uninstalled, {reason}")]
+    NotUninstallable { app: String, reason: String },
+    /// `mdfind` failed, e.g., because Spotlight indexing is disabled.
+    #[error("Spotlight is unavailable, stderr [{0}]")]
+    SpotlightUnavailable(String),
+    /// The desktop or OS version does not provide what is needed, e.g.,
+    /// the focused window on Wayland compositors without the wlroots
+    /// foreign toplevel protocol, other than GNOME Shell.
+    #[error("not supported on this platform")]
+    UnsupportedPlatform,
+    #[error("[{}] is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+    /// Unwatching a search path the watcher does not watch.
+    #[error("[{}] is not watched", .0.display())]
+    NotWatched(PathBuf),
+    /// No app is at the path a command of the [Tauri plugin](crate::plugin)
+    /// was given.
+    #[error("no app at [{}]", .0.display())]
+    AppNotFound(PathBuf),
+    /// The root of [`AppFinder::with_root`](crate::AppFinder::with_root)
+    /// is a system of another platform, whose apps only that platform can
+    /// read.
+    #[error("[{}] is a {platform} system, it cannot be scanned on {}", .root.display(), crate::platforms::PLATFORM)]
+    ForeignRoot {
+        root: PathBuf,
+        platform: &'static str,
+    },
+    /// JSON written for a newer version of the schema of [`App`], see
+    /// [`App::SCHEMA_VERSION`](crate::App::SCHEMA_VERSION).
+    #[error("unsupported schema version {0}")]
+    UnsupportedSchemaVersion(u32),
+    /// Returned by a watcher once it is closed.
+    #[error("the watcher is closed")]
+    WatcherClosed,
+    /// An OS API failed, e.g., inotify, FSEvents or COM.
+    #[error(transparent)]
+    Platform(Box<dyn std::error::Error + Send + Sync>),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub(crate) fn parse(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The error of `program`, which ran but failed.
+    pub(crate) fn command(program: &str, output: &std::process::Output) -> Self {
+        Self::Command {
+            program: program.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
+
+impl From<std::env::VarError> for Error {
+    fn from(e: std::env::VarError) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
+
+#[cfg(all(unix, feature = "watcher"))]
+impl From<nix::errno::Errno> for Error {
+    fn from(e: nix::errno::Errno) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
+
+/// Tauri commands fail with the message of the error.
+#[cfg(feature = "tauri-plugin")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl From<plist::Error> for Error {
+    fn from(e: plist::Error) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl From<windows::core::Error> for Error {
+    fn from(e: windows::core::Error) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "watcher"))]
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Self::Platform(Box::new(e))
+    }
+}
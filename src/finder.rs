@@ -0,0 +1,1207 @@
+//! [`AppFinder`], a scan of the installed apps with options.
+
+use crate::common::{App, InstallScope, ScanReport};
+use crate::fs::{DirEntry, FileKind, FileSystem, RealFs, RootedFs, Walk, WalkOptions};
+use crate::identity::AppIdentity;
+use crate::pattern::Pattern;
+use crate::platforms::{
+    PLATFORM, find, get_default_search_paths, root_platform, root_search_paths,
+};
+use crate::process::{CancelToken, DEFAULT_TIMEOUT};
+use crate::trace::{debug, timed};
+use crate::{Error, Result};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Where apps come from, besides the search paths of an [`AppFinder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum Provider {
+    /// Desktop files, `.app` bundles and shortcuts in the search paths.
+    SearchPaths,
+    /// The `app` directories of Flatpak installations in the search paths.
+    /// Linux only.
+    Flatpak,
+    /// Programs in the `Uninstall` registry keys that have no shortcut.
+    /// Windows only.
+    Registry,
+    /// Scoop and Chocolatey. Windows only.
+    PackageManagers,
+    /// Packaged (MSIX/UWP) apps. Windows only.
+    Uwp,
+    /// App Execution Aliases, e.g., `wt.exe`. Windows only.
+    ExecutionAliases,
+    /// Portable apps on removable drives. Windows only, not used unless
+    /// enabled.
+    Portable,
+    /// Apps in the `Applications` of the volumes mounted in `/Volumes`,
+    /// external drives and network shares, which Spotlight often does not
+    /// index. macOS only, not used unless enabled.
+    Volumes,
+    /// The apps that ship inside the `Xcode*.app` bundles in the search
+    /// paths, e.g., Simulator and Accessibility Inspector. macOS only.
+    DeveloperTools,
+}
+
+impl Provider {
+    const DEFAULT: &[Provider] = &[
+        Provider::SearchPaths,
+        Provider::Flatpak,
+        Provider::DeveloperTools,
+        Provider::Registry,
+        Provider::PackageManagers,
+        Provider::Uwp,
+        Provider::ExecutionAliases,
+    ];
+}
+
+/// Told how far a scan is, e.g., to show a progress bar during the first
+/// scan, see [`AppFinder::progress`].
+///
+/// The finder and its clones share one observer, which is called from the
+/// threads of the scan at once, hence the `Send + Sync` bound and `&self`:
+/// keep counts in atomics or behind a `Mutex`.
+///
+/// ```no_run
+/// use applications::{AppFinder, Provider, ScanProgress};
+///
+/// struct PrintProgress;
+///
+/// impl ScanProgress for PrintProgress {
+///     fn on_path_scanned(&self, scanned: usize, total: usize) {
+///         println!("{scanned}/{total}");
+///     }
+/// }
+///
+/// let apps = AppFinder::new().progress(PrintProgress).find().unwrap();
+/// ```
+pub trait ScanProgress: Send + Sync {
+    /// `provider` has started looking for apps.
+    fn on_provider_start(&self, provider: Provider) {
+        let _ = provider;
+    }
+
+    /// A desktop file, `.app` bundle or shortcut found in the search paths
+    /// has been read, `scanned` of `total`. Walking the search paths comes
+    /// first, so `total` does not change.
+    fn on_path_scanned(&self, scanned: usize, total: usize) {
+        let _ = (scanned, total);
+    }
+}
+
+/// A [`ScanProgress`] that [`AppFinder`] can derive its traits with.
+#[derive(Clone)]
+struct Progress(Arc<dyn ScanProgress>);
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+impl PartialEq for Progress {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Progress {}
+
+/// Two progress observers, told the same.
+#[cfg(feature = "json")]
+struct Both(Arc<dyn ScanProgress>, Arc<dyn ScanProgress>);
+
+#[cfg(feature = "json")]
+impl ScanProgress for Both {
+    fn on_provider_start(&self, provider: Provider) {
+        self.0.on_provider_start(provider);
+        self.1.on_provider_start(provider);
+    }
+
+    fn on_path_scanned(&self, scanned: usize, total: usize) {
+        self.0.on_path_scanned(scanned, total);
+        self.1.on_path_scanned(scanned, total);
+    }
+}
+
+/// The [`FileSystem`] of a finder, which [`AppFinder`] can derive its
+/// traits with.
+#[derive(Clone, Default)]
+enum Fs {
+    #[default]
+    Real,
+    Custom(Arc<dyn FileSystem>),
+}
+
+impl Fs {
+    fn get(&self) -> &dyn FileSystem {
+        match self {
+            Fs::Real => &RealFs,
+            Fs::Custom(fs) => fs.as_ref(),
+        }
+    }
+}
+
+impl fmt::Debug for Fs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fs::Real => f.write_str("Real"),
+            Fs::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl PartialEq for Fs {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Fs::Real, Fs::Real) => true,
+            (Fs::Custom(fs), Fs::Custom(other)) => Arc::ptr_eq(fs, other),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Fs {}
+
+/// The `MimeType` lists of the desktop files a scan parses, by their paths,
+/// kept so that what opens a file is found without parsing them again.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MimeTypeLists(Arc<Mutex<HashMap<PathBuf, Vec<String>>>>);
+
+#[cfg(target_os = "linux")]
+impl MimeTypeLists {
+    pub(crate) fn insert(&self, desktop_file: PathBuf, mime_types: Vec<String>) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(desktop_file, mime_types);
+    }
+
+    pub(crate) fn take(&self) -> HashMap<PathBuf, Vec<String>> {
+        std::mem::take(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PartialEq for MimeTypeLists {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Eq for MimeTypeLists {}
+
+/// Counts the paths a scan has read, for [`ScanProgress::on_path_scanned`].
+pub(crate) struct PathCounter<'a> {
+    progress: Option<&'a dyn ScanProgress>,
+    scanned: AtomicUsize,
+    total: usize,
+}
+
+impl PathCounter<'_> {
+    pub(crate) fn scanned(&self) {
+        if let Some(progress) = self.progress {
+            let scanned = self.scanned.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.on_path_scanned(scanned, self.total);
+        }
+    }
+}
+
+/// What to do with symbolic links in the search paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Skip them.
+    Skip,
+    /// Read links to files, but do not descend into links to directories.
+    #[default]
+    Files,
+    /// Follow all links, loops are reported as errors.
+    Follow,
+}
+
+/// Options of one search path, see [`AppFinder::path_options`]. `None`
+/// leaves the option of the finder as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PathOptions {
+    /// See [`AppFinder::max_depth`].
+    pub max_depth: Option<usize>,
+    /// See [`AppFinder::symlinks`].
+    pub symlinks: Option<SymlinkPolicy>,
+}
+
+/// Which apps count as the same one, only the first of them is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dedup {
+    /// Apps that are the same app found by different providers or in
+    /// different places, with the same [`App::identity`]. What one of them
+    /// lacks, e.g., a version, is taken from the others, and an app
+    /// installed for the user shadows the same one installed for everyone.
+    #[default]
+    Identity,
+    /// Apps that are equal in every field.
+    Exact,
+    /// Apps with the same [`App::app_desktop_path`].
+    Path,
+    /// Apps with the same [`App::app_path_exe`], e.g., a shortcut and the
+    /// registry entry of the program it starts. Apps without one are kept.
+    Executable,
+    /// Apps with the same [`App::name`], ignoring case.
+    Name,
+    /// Keep all of them.
+    None,
+}
+
+/// The order of the apps a scan returns, the same in every scan of the
+/// same apps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AppOrder {
+    /// By [`App::app_desktop_path`], then by what else identifies them:
+    /// [`App::aumid`], [`App::app_path_exe`], [`App::launch_args`] and
+    /// [`App::name`].
+    #[default]
+    Path,
+    /// As the providers found them, one after the other, the search paths
+    /// in their order and each walked in the order of the file names.
+    /// Providers that ask the system, e.g., Spotlight, may return the same
+    /// apps in another order.
+    Discovery,
+    /// By their [display name](App::display_name) in the locale, see
+    /// [`Collator`](crate::Collator).
+    Name(String),
+}
+
+/// A scan of the installed apps, for when [`get_all_apps`](crate::get_all_apps)
+/// is not enough:
+///
+/// ```no_run
+/// use applications::{AppFinder, Dedup};
+///
+/// let apps = AppFinder::new()
+///     .path("/opt/apps")
+///     .exclude("/usr/share/applications/screensavers")
+///     .exclude_pattern("*uninstall*")
+///     .max_depth(3)
+///     .dedup(Dedup::Name)
+///     .find()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppFinder {
+    search_paths: Vec<PathBuf>,
+    excluded_paths: Vec<PathBuf>,
+    include_patterns: Vec<Pattern>,
+    exclude_patterns: Vec<Pattern>,
+    max_depth: Option<usize>,
+    symlinks: SymlinkPolicy,
+    path_options: Vec<(PathBuf, PathOptions)>,
+    include_hidden: bool,
+    include_system: bool,
+    providers: HashSet<Provider>,
+    dedup: Dedup,
+    order: AppOrder,
+    command_timeout: Duration,
+    cancel: Option<CancelToken>,
+    subprocesses: bool,
+    lazy_metadata: bool,
+    progress: Option<Progress>,
+    fs: Fs,
+    /// The platform of the root of [`AppFinder::with_root`], if it is not
+    /// the running one.
+    foreign_root: Option<&'static str>,
+    #[cfg(target_os = "linux")]
+    mime_type_lists: Option<MimeTypeLists>,
+    #[cfg(target_os = "windows")]
+    exclude_noise: bool,
+    #[cfg(target_os = "macos")]
+    nested_apps: bool,
+    #[cfg(target_os = "macos")]
+    other_users: bool,
+    #[cfg(target_os = "macos")]
+    system_localized_names: bool,
+}
+
+impl Default for AppFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppFinder {
+    /// A finder for the [default search paths](crate::get_default_search_paths).
+    pub fn new() -> Self {
+        Self::with_paths(get_default_search_paths())
+    }
+
+    /// A finder for `search_paths` only.
+    pub fn with_paths(search_paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            search_paths: search_paths.into_iter().map(Into::into).collect(),
+            excluded_paths: Vec::new(),
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            max_depth: None,
+            symlinks: SymlinkPolicy::default(),
+            path_options: Vec::new(),
+            include_hidden: false,
+            include_system: true,
+            providers: Provider::DEFAULT.iter().copied().collect(),
+            dedup: Dedup::default(),
+            order: AppOrder::default(),
+            command_timeout: DEFAULT_TIMEOUT,
+            cancel: None,
+            subprocesses: !cfg!(feature = "no-subprocess"),
+            lazy_metadata: false,
+            progress: None,
+            fs: Fs::Real,
+            foreign_root: None,
+            #[cfg(target_os = "linux")]
+            mime_type_lists: None,
+            #[cfg(target_os = "windows")]
+            exclude_noise: true,
+            #[cfg(target_os = "macos")]
+            nested_apps: false,
+            #[cfg(target_os = "macos")]
+            other_users: true,
+            #[cfg(target_os = "macos")]
+            system_localized_names: false,
+        }
+    }
+
+    /// A finder for the system below `root`, e.g., a disk image mounted
+    /// at `/mnt/backup` or a chroot, of the same platform as the running
+    /// one: the default search paths of each of its users, below `root`.
+    /// The absolute links and paths in it, e.g., the `Exec` of a desktop
+    /// file or the target of a shortcut, are taken to be below `root` too.
+    ///
+    /// Only the search paths are walked, the providers and subprocesses
+    /// that ask the running system, e.g., Spotlight or the registry, are
+    /// not used unless they are enabled again. A scan of the system of
+    /// another platform, e.g., a Windows drive mounted on Linux, fails
+    /// with [`Error::ForeignRoot`].
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let fs = RootedFs::new(&root);
+        Self {
+            foreign_root: root_platform(&fs, &root).filter(|platform| *platform != PLATFORM),
+            providers: [
+                Provider::SearchPaths,
+                Provider::Flatpak,
+                Provider::DeveloperTools,
+            ]
+            .into(),
+            subprocesses: false,
+            fs: Fs::Custom(Arc::new(fs)),
+            ..Self::with_paths(root_search_paths(&root))
+        }
+    }
+
+    /// Also search `path`.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    /// Skip `path` and everything inside it, whichever provider finds it.
+    pub fn exclude(mut self, path: impl Into<PathBuf>) -> Self {
+        self.excluded_paths.push(path.into());
+        self
+    }
+
+    /// Only find the apps that match one of the include patterns, a glob
+    /// pattern matched ignoring case: `*` stands for anything but a `/`,
+    /// `**` for anything, `?` for any character but a `/`. A pattern with
+    /// a `/` is matched against the path of the desktop file, bundle or
+    /// shortcut and the executable, e.g., `/Applications/**`, and the
+    /// directories that cannot contain a match are not walked. Other
+    /// patterns are matched against the name of the app and its file.
+    pub fn include_pattern(mut self, pattern: &str) -> Self {
+        self.include_patterns.push(Pattern::new(pattern));
+        self
+    }
+
+    /// Skip what matches `pattern`, see [`AppFinder::include_pattern`]
+    /// for the syntax, e.g., `*uninstall*`. The directories that match
+    /// are not walked.
+    pub fn exclude_pattern(mut self, pattern: &str) -> Self {
+        self.exclude_patterns.push(Pattern::new(pattern));
+        self
+    }
+
+    /// How deep to look into the search paths, `1` being what is right in
+    /// them. Unlimited by default, except on Windows, where the Start Menu
+    /// is searched two levels deep.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Whether to follow symbolic links. Links to files are read, but not
+    /// descended into when they link to directories, by default.
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Walk the search path `path` with `options` rather than the
+    /// options of the finder, e.g., deeper into a vendor folder, or
+    /// following the links of a directory of links to apps. Links that
+    /// loop are reported, not followed.
+    pub fn path_options(mut self, path: impl Into<PathBuf>, options: PathOptions) -> Self {
+        let path = path.into();
+        self.path_options.retain(|(known, _)| *known != path);
+        self.path_options.push((path, options));
+        self
+    }
+
+    /// Also find what is hidden: files and directories whose name starts
+    /// with a dot, or that have the hidden attribute on Windows, and
+    /// desktop entries with `NoDisplay` on Linux. Disabled by default.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Also find the apps that ship with the system: the ones under
+    /// `/System` on macOS and `%WINDIR%` on Windows. Enabled by default, it
+    /// has no effect on Linux.
+    pub fn include_system(mut self, include_system: bool) -> Self {
+        self.include_system = include_system;
+        self
+    }
+
+    /// Use only `providers`.
+    pub fn providers(mut self, providers: impl IntoIterator<Item = Provider>) -> Self {
+        self.providers = providers.into_iter().collect();
+        self
+    }
+
+    /// Use `provider` as well, or not.
+    pub fn provider(mut self, provider: Provider, enabled: bool) -> Self {
+        if enabled {
+            self.providers.insert(provider);
+        } else {
+            self.providers.remove(&provider);
+        }
+        self
+    }
+
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// The order of the apps, [`AppOrder::Path`] by default. Duplicates
+    /// are dropped in the order they were found whatever it is.
+    pub fn order(mut self, order: AppOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sort the apps by their [display name](App::display_name) in
+    /// `locale`, [`AppOrder::Name`].
+    pub fn sort_by_name(self, locale: &str) -> Self {
+        self.order(AppOrder::Name(locale.to_string()))
+    }
+
+    /// How long an external command, e.g., `mdfind` on macOS, may run
+    /// before it is killed and its source is reported as failed with
+    /// [`Error::Timeout`], and how long the volumes of
+    /// [`Provider::Volumes`] may take to walk before they are reported with
+    /// [`Error::VolumeTimeout`]. 30 seconds by default.
+    pub fn command_timeout(mut self, command_timeout: Duration) -> Self {
+        self.command_timeout = command_timeout;
+        self
+    }
+
+    /// Abort the scan once `cancel` is cancelled, e.g., when the user
+    /// closes the window waiting for it. [`AppFinder::find`] then fails
+    /// with [`Error::Cancelled`], [`AppFinder::find_with_report`] returns
+    /// what was found so far.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Run external commands, or use native APIs and the file system
+    /// only, for sandboxed apps that may not spawn processes. Only macOS
+    /// scans run one, `mdfind`: without it, the search paths are walked
+    /// for `.app` bundles instead of asking Spotlight. Enabled by default,
+    /// with the `no-subprocess` feature it cannot be enabled.
+    pub fn subprocesses(mut self, subprocesses: bool) -> Self {
+        self.subprocesses = subprocesses && !cfg!(feature = "no-subprocess");
+        self
+    }
+
+    /// Skip what is slow to read for each app, for when most apps are
+    /// never shown: localized names on macOS and Windows, and the version
+    /// and publisher of executables on Windows. [`App::metadata`] reads
+    /// them for one app. Disabled by default.
+    pub fn lazy_metadata(mut self, lazy_metadata: bool) -> Self {
+        self.lazy_metadata = lazy_metadata;
+        self
+    }
+
+    /// Tell `progress` how far the scan is. It is shared, not cloned, by
+    /// the clones of the finder and the threads of the scan.
+    pub fn progress(mut self, progress: impl ScanProgress + 'static) -> Self {
+        self.progress = Some(Progress(Arc::new(progress)));
+        self
+    }
+
+    /// Skip uninstallers, setup stubs, redistributables, readmes and the
+    /// like, see [`is_noise_app`](crate::is_noise_app). Enabled by default.
+    #[cfg(target_os = "windows")]
+    pub fn exclude_noise(mut self, exclude_noise: bool) -> Self {
+        self.exclude_noise = exclude_noise;
+        self
+    }
+
+    /// Also find the apps embedded in the `Contents/Applications` and
+    /// `Contents/SharedSupport` of the bundles found, e.g., the tools of an
+    /// audio suite, with the bundle as their [`App::parent`]. Disabled by
+    /// default.
+    #[cfg(target_os = "macos")]
+    pub fn nested_apps(mut self, nested_apps: bool) -> Self {
+        self.nested_apps = nested_apps;
+        self
+    }
+
+    /// Include the apps in the home folders of other users, which
+    /// Spotlight lists to every user, with their [`App::other_user`]. Apps
+    /// the user cannot read are left out either way. Enabled by default,
+    /// scans of [`AppFinder::with_root`] find the apps of all users.
+    #[cfg(target_os = "macos")]
+    pub fn other_users(mut self, other_users: bool) -> Self {
+        self.other_users = other_users;
+        self
+    }
+
+    /// Also ask CoreFoundation for the name of each app in the language
+    /// of the user, when its `.lproj` directories have none: from the
+    /// `Localizable.strings` of the bundle, or the name the Finder
+    /// displays. Slower, as it loads each bundle. Disabled by default.
+    #[cfg(target_os = "macos")]
+    pub fn system_localized_names(mut self, system_localized_names: bool) -> Self {
+        self.system_localized_names = system_localized_names;
+        self
+    }
+
+    pub fn find(&self) -> Result<Vec<App>> {
+        if let Some(error) = self.foreign_root_error() {
+            return Err(error);
+        }
+        let report = self.find_with_report();
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        Ok(report.apps)
+    }
+
+    /// Like [`AppFinder::find`], with what could not be read, see
+    /// [`get_all_apps_with_report`](crate::get_all_apps_with_report).
+    pub fn find_with_report(&self) -> ScanReport {
+        if let Some(error) = self.foreign_root_error() {
+            let mut report = ScanReport::default();
+            report.skip(self.fs().root().map(Path::to_path_buf), error);
+            return report;
+        }
+        debug!(search_paths = ?self.search_paths, "scanning");
+        let mut report = timed("all", || find(self));
+        if self.is_cancelled() {
+            report.skip(None, Error::Cancelled);
+        }
+        debug!(apps = report.apps.len(), "found");
+        report.apps.retain(|app| {
+            !self.is_excluded(&app.app_desktop_path)
+                && app
+                    .app_path_exe
+                    .as_deref()
+                    .is_none_or(|exe| !self.is_excluded(exe))
+                && !self
+                    .exclude_patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_name(&app.name))
+                && self.is_included(app)
+        });
+        debug!(apps = report.apps.len(), "without the excluded paths");
+        dedup(&mut report.apps, self.dedup);
+        debug!(
+            apps = report.apps.len(),
+            skipped = report.errors.len(),
+            "without duplicates"
+        );
+        match &self.order {
+            // In place, a stable sort of the apps would copy half of them
+            AppOrder::Path => report
+                .apps
+                .sort_unstable_by(|app, other| path_order(app).cmp(&path_order(other))),
+            AppOrder::Discovery => {}
+            AppOrder::Name(locale) => crate::Collator::new(locale).sort(&mut report.apps),
+        }
+
+        report
+    }
+
+    /// The search paths that exist and are not excluded, without
+    /// duplicates.
+    pub(crate) fn search_paths(&self) -> Vec<&Path> {
+        let mut seen = HashSet::new();
+        self.search_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(|path| {
+                // Patterns apply to what is in the search paths
+                seen.insert(*path)
+                    && self.fs().exists(path)
+                    && !self
+                        .excluded_paths
+                        .iter()
+                        .any(|excluded| path.starts_with(excluded))
+                    && self.may_include_in(path)
+            })
+            .collect()
+    }
+
+    pub(crate) fn is_excluded(&self, path: &Path) -> bool {
+        self.excluded_paths
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+            || self
+                .exclude_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(path))
+    }
+
+    /// Whether `app` matches an include pattern, if there are any.
+    fn is_included(&self, app: &App) -> bool {
+        self.include_patterns.is_empty()
+            || self.include_patterns.iter().any(|pattern| {
+                pattern.matches_name(&app.name)
+                    || pattern.matches_path(&app.app_desktop_path)
+                    || app
+                        .app_path_exe
+                        .as_deref()
+                        .is_some_and(|exe| pattern.matches_path(exe))
+            })
+    }
+
+    /// Whether the directory `dir` may contain what an include pattern
+    /// matches. Name patterns may match anything.
+    fn may_include_in(&self, dir: &Path) -> bool {
+        self.include_patterns.is_empty()
+            || self
+                .include_patterns
+                .iter()
+                .any(|pattern| pattern.may_match_in(dir))
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Run `command` with the timeout and cancel token of the scan, unless
+    /// subprocesses are disabled.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn output(
+        &self,
+        command: &mut std::process::Command,
+    ) -> Result<std::process::Output> {
+        if !self.subprocesses {
+            let program = command.get_program().to_string_lossy().into_owned();
+            return Err(Error::SubprocessDisabled(program));
+        }
+        crate::process::output(command, self.command_timeout, self.cancel.as_ref())
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn allows_subprocesses(&self) -> bool {
+        self.subprocesses
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn timeout(&self) -> Duration {
+        self.command_timeout
+    }
+
+    /// The finder, also telling `observer` how far the scan is, after the
+    /// progress observer it has.
+    #[cfg(feature = "json")]
+    pub(crate) fn observed_by(&self, observer: Arc<dyn ScanProgress>) -> AppFinder {
+        let progress = match &self.progress {
+            Some(Progress(progress)) => Arc::new(Both(progress.clone(), observer)),
+            None => observer,
+        };
+
+        AppFinder {
+            progress: Some(Progress(progress)),
+            ..self.clone()
+        }
+    }
+
+    /// Read the search paths from `fs` rather than the file system.
+    #[cfg(test)]
+    pub(crate) fn file_system(mut self, fs: impl FileSystem + 'static) -> Self {
+        self.fs = Fs::Custom(Arc::new(fs));
+        self
+    }
+
+    /// What the search paths are read from.
+    pub(crate) fn fs(&self) -> &dyn FileSystem {
+        self.fs.get()
+    }
+
+    /// Tell the progress observer that `provider` starts.
+    pub(crate) fn start(&self, provider: Provider) {
+        if let Some(Progress(progress)) = &self.progress {
+            progress.on_provider_start(provider);
+        }
+    }
+
+    /// A counter of the `total` paths to be read.
+    pub(crate) fn path_counter(&self, total: usize) -> PathCounter<'_> {
+        PathCounter {
+            progress: self
+                .progress
+                .as_ref()
+                .map(|Progress(progress)| progress.as_ref()),
+            scanned: AtomicUsize::new(0),
+            total,
+        }
+    }
+
+    pub(crate) fn uses(&self, provider: Provider) -> bool {
+        self.providers.contains(&provider)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn reads_metadata(&self) -> bool {
+        !self.lazy_metadata
+    }
+
+    pub(crate) fn includes_hidden(&self) -> bool {
+        self.include_hidden
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn includes_system(&self) -> bool {
+        self.include_system
+    }
+
+    /// Keep the `MimeType` lists of the desktop files the scan parses in
+    /// `lists`.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn keep_mime_types(mut self, lists: MimeTypeLists) -> Self {
+        self.mime_type_lists = Some(lists);
+        self
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn mime_type_lists(&self) -> Option<&MimeTypeLists> {
+        self.mime_type_lists.as_ref()
+    }
+
+    #[cfg(target_os = "windows")]
+    pub(crate) fn excludes_noise(&self) -> bool {
+        self.exclude_noise
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn finds_nested_apps(&self) -> bool {
+        self.nested_apps
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn includes_other_users(&self) -> bool {
+        self.other_users
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn uses_system_localized_names(&self) -> bool {
+        self.system_localized_names
+    }
+
+    /// Walk `dir` with the depth, symlink, hidden and exclusion options,
+    /// the ones of `dir` if it is a search path with its own options.
+    /// `default_depth` applies unless a depth was set.
+    pub(crate) fn walk(&self, dir: &Path, default_depth: Option<usize>) -> Walk<'_> {
+        let options = self.options_of(dir);
+        let symlinks = options.symlinks.unwrap_or(self.symlinks);
+        let follow_links = symlinks == SymlinkPolicy::Follow;
+        let filter = move |entry: &DirEntry| {
+            // The search path itself may be hidden, e.g., `~/.local/share/applications`
+            if entry.depth == 0 {
+                return true;
+            }
+            if symlinks == SymlinkPolicy::Skip && entry.is_symlink {
+                return false;
+            }
+            // What is inside an app bundle is part of the app
+            if cfg!(target_os = "macos")
+                && entry.depth > 1
+                && entry
+                    .path
+                    .parent()
+                    .and_then(Path::extension)
+                    .is_some_and(|extension| extension == "app")
+            {
+                return false;
+            }
+            let is_dir = entry.kind == Some(FileKind::Dir) && (follow_links || !entry.is_symlink);
+            (self.include_hidden || !is_hidden(entry))
+                && !self.is_excluded(&entry.path)
+                && (!is_dir || self.may_include_in(&entry.path))
+        };
+
+        self.fs().walk(
+            dir,
+            WalkOptions {
+                follow_links,
+                max_depth: options.max_depth.or(self.max_depth).or(default_depth),
+                filter: Box::new(filter),
+                cancelled: Box::new(|| self.is_cancelled()),
+            },
+        )
+    }
+
+    fn foreign_root_error(&self) -> Option<Error> {
+        Some(Error::ForeignRoot {
+            root: self.fs().root()?.to_path_buf(),
+            platform: self.foreign_root?,
+        })
+    }
+
+    fn options_of(&self, search_path: &Path) -> PathOptions {
+        self.path_options
+            .iter()
+            .find(|(path, _)| path == search_path)
+            .map(|(_, options)| *options)
+            .unwrap_or_default()
+    }
+
+    /// Whether `path`, found in `search_path`, obeys the depth and hidden
+    /// options, for providers that do not walk the search paths.
+    #[cfg(any(target_os = "macos", test))]
+    pub(crate) fn accepts(&self, search_path: &Path, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(search_path) else {
+            return true;
+        };
+        if self
+            .options_of(search_path)
+            .max_depth
+            .or(self.max_depth)
+            .is_some_and(|max_depth| relative.components().count() > max_depth)
+        {
+            return false;
+        }
+
+        self.include_hidden
+            || !relative
+                .components()
+                .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+    }
+}
+
+fn file_name_is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_hidden(entry: &DirEntry) -> bool {
+    file_name_is_hidden(entry)
+}
+
+#[cfg(target_os = "windows")]
+fn is_hidden(entry: &DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    file_name_is_hidden(entry)
+        || std::fs::symlink_metadata(&entry.path)
+            .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+/// `items.map(f)`, on all cores with the `rayon` feature. The order is
+/// kept either way.
+pub(crate) fn par_map<T: Send, U: Send>(items: Vec<T>, f: impl Fn(T) -> U + Sync + Send) -> Vec<U> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        items.into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.into_iter().map(f).collect()
+    }
+}
+
+/// What [`AppOrder::Path`] sorts by.
+fn path_order(app: &App) -> (&Path, Option<&str>, Option<&Path>, &[String], &str) {
+    (
+        &app.app_desktop_path,
+        app.aumid.as_deref(),
+        app.app_path_exe.as_deref(),
+        &app.launch_args,
+        &app.name,
+    )
+}
+
+/// What two apps share when they are duplicates, borrowed from the app
+/// where it can be.
+#[derive(PartialEq, Eq, Hash)]
+enum DedupKey<'a> {
+    /// An app of which [`App::sources`] is taken out.
+    App(&'a App),
+    Path(&'a Path),
+    Name(String),
+    Identity(AppIdentity),
+}
+
+/// Add what `other`, a duplicate of `app`, knows and `app` does not.
+fn merge(app: &mut App, mut other: App) {
+    if app.scope == InstallScope::Machine && other.scope == InstallScope::User {
+        std::mem::swap(app, &mut other);
+    }
+    for source in other.sources {
+        if !app.sources.contains(&source) {
+            app.sources.push(source);
+        }
+    }
+    for category in other.categories {
+        if !app.categories.contains(&category) {
+            app.categories.push(category);
+        }
+    }
+    for (locale, name) in other.localized_app_names {
+        app.localized_app_names.entry(locale).or_insert(name);
+    }
+    if app.icon_path.is_none() {
+        app.icon_path = other.icon_path;
+        app.icon_index = other.icon_index;
+    }
+    app.app_path_exe = app.app_path_exe.take().or(other.app_path_exe);
+    app.version = app.version.take().or(other.version);
+    app.publisher = app.publisher.take().or(other.publisher);
+    app.install_source = app.install_source.take().or(other.install_source);
+    app.winget_id = app.winget_id.take().or(other.winget_id);
+    app.product_code = app.product_code.take().or(other.product_code);
+    app.uninstaller = app.uninstaller.take().or(other.uninstaller);
+}
+
+/// Drop the duplicates in `apps`, the sources of what is dropped are added
+/// to [`App::sources`] of what is kept.
+fn dedup(apps: &mut Vec<App>, dedup: Dedup) {
+    if dedup == Dedup::None {
+        return;
+    }
+    // Found by two sources, it is still the same app
+    let mut sources: Vec<_> = if dedup == Dedup::Exact {
+        apps.iter_mut()
+            .map(|app| std::mem::take(&mut app.sources))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // The index of the first app each one is a duplicate of, without
+    // cloning what the apps are compared by
+    let key = |app| match dedup {
+        Dedup::Identity => Some(DedupKey::Identity(App::identity(app))),
+        Dedup::Exact => Some(DedupKey::App(app)),
+        Dedup::Path => Some(DedupKey::Path(&app.app_desktop_path)),
+        Dedup::Executable => app.app_path_exe.as_deref().map(DedupKey::Path),
+        Dedup::Name => Some(DedupKey::Name(app.name.to_lowercase())),
+        Dedup::None => None,
+    };
+    let mut seen: HashMap<DedupKey, usize> = HashMap::with_capacity(apps.len());
+    let duplicate_of: Vec<Option<usize>> = apps
+        .iter()
+        .enumerate()
+        .map(|(index, app)| {
+            let first = *seen.entry(key(app)?).or_insert(index);
+            (first != index).then_some(first)
+        })
+        .collect();
+    drop(seen);
+
+    for (app, app_sources) in apps.iter_mut().zip(&mut sources) {
+        app.sources = std::mem::take(app_sources);
+    }
+    // In place, the duplicates come after the app they are merged into
+    for (index, first) in duplicate_of.iter().enumerate() {
+        let Some(first) = *first else {
+            continue;
+        };
+        let (kept, rest) = apps.split_at_mut(index);
+        let (kept, app) = (&mut kept[first], std::mem::take(&mut rest[0]));
+        if dedup == Dedup::Identity {
+            merge(kept, app);
+            continue;
+        }
+        for source in app.sources {
+            if !kept.sources.contains(&source) {
+                kept.sources.push(source);
+            }
+        }
+    }
+    let mut duplicates = duplicate_of.iter();
+    apps.retain(|_| duplicates.next().is_some_and(Option::is_none));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+    use crate::{DiscoverySource, ScanError};
+
+    fn app(name: &str, desktop_path: &str, exe: Option<&str>) -> App {
+        App {
+            name: name.to_string(),
+            app_desktop_path: desktop_path.into(),
+            app_path_exe: exe.map(PathBuf::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dedup() {
+        let apps = vec![
+            app("Foo", "/a/foo", Some("/bin/foo")),
+            app("Foo", "/a/foo", Some("/bin/foo")),
+            app("foo", "/b/foo", Some("/bin/foo")),
+            app("Bar", "/b/foo", None),
+            app("Baz", "/c/baz", None),
+        ];
+        let names = |dedup_by| {
+            let mut apps = apps.clone();
+            dedup(&mut apps, dedup_by);
+            apps.into_iter().map(|app| app.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(Dedup::None), ["Foo", "Foo", "foo", "Bar", "Baz"]);
+        assert_eq!(names(Dedup::Exact), ["Foo", "foo", "Bar", "Baz"]);
+        assert_eq!(names(Dedup::Path), ["Foo", "foo", "Baz"]);
+        assert_eq!(names(Dedup::Executable), ["Foo", "Bar", "Baz"]);
+        assert_eq!(names(Dedup::Name), ["Foo", "Bar", "Baz"]);
+
+        let mut apps = vec![
+            App {
+                sources: vec![DiscoverySource::StartMenu],
+                ..app("Foo", "/a/foo", Some("/bin/foo"))
+            },
+            App {
+                sources: vec![DiscoverySource::Registry],
+                ..app("Foo", "/a/foo", Some("/bin/foo"))
+            },
+        ];
+        dedup(&mut apps, Dedup::Exact);
+        assert_eq!(
+            apps[0].sources,
+            [DiscoverySource::StartMenu, DiscoverySource::Registry]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_dedup_identity() {
+        let mut apps = vec![
+            App {
+                version: Some("1.0".to_string()),
+                categories: vec!["Utility".to_string()],
+                sources: vec![DiscoverySource::DesktopFile],
+                ..app("Foo", "/usr/share/applications/foo.desktop", None)
+            },
+            App {
+                scope: InstallScope::User,
+                categories: vec!["Development".to_string()],
+                ..app(
+                    "My Foo",
+                    "/home/a/.local/share/applications/foo.desktop",
+                    None,
+                )
+            },
+            app("Bar", "/opt/bar/bar", Some("/opt/bar/bin/bar")),
+            app("Bar 2", "/opt/bar/bar2", Some("/opt/bar/bin/bar")),
+            App {
+                launch_args: vec!["--profile".to_string()],
+                ..app("Bar profile", "/opt/bar/bar3", Some("/opt/bar/bin/bar"))
+            },
+        ];
+        dedup(&mut apps, Dedup::Identity);
+        let names: Vec<&str> = apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, ["My Foo", "Bar", "Bar profile"]);
+        assert_eq!(apps[0].version.as_deref(), Some("1.0"));
+        assert_eq!(apps[0].categories, ["Development", "Utility"]);
+        assert_eq!(apps[0].sources, [DiscoverySource::DesktopFile]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let cancel = CancelToken::new();
+        let finder = AppFinder::new().cancel_token(cancel.clone());
+        assert_eq!(finder, finder.clone());
+        cancel.cancel();
+        assert!(matches!(finder.find(), Err(Error::Cancelled)));
+        let report = finder.find_with_report();
+        assert!(report.apps.is_empty());
+        assert!(matches!(report.errors.last(), Some(e) if matches!(e.error, Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_accepts() {
+        let finder = AppFinder::with_paths(["/apps"]).max_depth(2);
+        assert!(finder.accepts(Path::new("/apps"), Path::new("/apps/Foo.app")));
+        assert!(finder.accepts(Path::new("/apps"), Path::new("/apps/tools/Foo.app")));
+        assert!(!finder.accepts(Path::new("/apps"), Path::new("/apps/a/b/Foo.app")));
+        assert!(!finder.accepts(Path::new("/apps"), Path::new("/apps/.old/Foo.app")));
+        assert!(
+            finder
+                .include_hidden(true)
+                .accepts(Path::new("/apps"), Path::new("/apps/.old/Foo.app"))
+        );
+    }
+
+    #[test]
+    fn test_foreign_root() {
+        let temp_dir = TempDir::new("foreign-root");
+        let root = temp_dir.path().to_path_buf();
+        let (platform, marker) = if cfg!(target_os = "windows") {
+            ("macOS", "System/Library/CoreServices/SystemVersion.plist")
+        } else {
+            ("Windows", "Windows/System32/kernel32.dll")
+        };
+        std::fs::create_dir_all(root.join(marker).parent().unwrap()).unwrap();
+        std::fs::write(root.join(marker), "").unwrap();
+
+        let finder = AppFinder::with_root(&root);
+        assert!(matches!(
+            finder.find(),
+            Err(Error::ForeignRoot { root: error_root, platform: error_platform })
+                if error_root == root && error_platform == platform
+        ));
+        let report = finder.find_with_report();
+        assert!(report.apps.is_empty());
+        assert!(matches!(
+            &report.errors[..],
+            [ScanError {
+                error: Error::ForeignRoot { .. },
+                ..
+            }]
+        ));
+    }
+}
@@ -0,0 +1,634 @@
+//! [`FileSystem`], what scans read the search paths through: the real
+//! file system, or a tree of fixtures in tests.
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// What is at a path, with symbolic links followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    File,
+    Dir,
+    Other,
+}
+
+/// An entry of a directory, or of a walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DirEntry {
+    pub(crate) path: PathBuf,
+    /// `None` for a link to nothing.
+    pub(crate) kind: Option<FileKind>,
+    pub(crate) is_symlink: bool,
+    /// Below the root of the walk, 0 for the root itself.
+    pub(crate) depth: usize,
+}
+
+/// An entry of a directory, or of a walk, or the path that could not be
+/// read.
+pub(crate) type Walked = Result<DirEntry, (PathBuf, io::Error)>;
+
+/// The file system operations of a scan.
+pub(crate) trait FileSystem: Send + Sync {
+    /// The entries of `dir`, in no particular order, with an error for
+    /// each entry that could not be read.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Walked>>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// What is at `path`, `None` if there is nothing.
+    fn kind(&self, path: &Path) -> Option<FileKind>;
+
+    /// `path` with the symbolic links resolved.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// The entries below `root` and `root` itself, depth first, each
+    /// directory before what is in it and its entries in the order of
+    /// their names.
+    fn walk<'a>(&'a self, root: &Path, options: WalkOptions<'a>) -> Walk<'a>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.kind(path).is_some()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.kind(path) == Some(FileKind::File)
+    }
+
+    /// The directory that is `/` to the paths it reads, see
+    /// [`RootedFs`]. `None` for the file system of the machine.
+    fn root(&self) -> Option<&Path> {
+        None
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// The file system of the machine.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RealFs;
+
+fn kind_of(file_type: std::fs::FileType) -> FileKind {
+    if file_type.is_dir() {
+        FileKind::Dir
+    } else if file_type.is_file() {
+        FileKind::File
+    } else {
+        FileKind::Other
+    }
+}
+
+impl FileSystem for RealFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Walked>> {
+        Ok(std::fs::read_dir(dir)?
+            .map(|entry| {
+                let entry = entry.map_err(|e| (dir.to_path_buf(), e))?;
+                let path = entry.path();
+                let file_type = entry.file_type().map_err(|e| (path.clone(), e))?;
+                let is_symlink = file_type.is_symlink();
+                let kind = if is_symlink {
+                    self.kind(&path)
+                } else {
+                    Some(kind_of(file_type))
+                };
+
+                Ok(DirEntry {
+                    path,
+                    kind,
+                    is_symlink,
+                    depth: 0,
+                })
+            })
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        std::fs::metadata(path)
+            .ok()
+            .map(|metadata| kind_of(metadata.file_type()))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn walk<'a>(&'a self, root: &Path, options: WalkOptions<'a>) -> Walk<'a> {
+        let WalkOptions {
+            follow_links,
+            max_depth,
+            filter,
+            cancelled,
+        } = options;
+        let mut walker = walkdir::WalkDir::new(root)
+            .follow_links(follow_links)
+            .sort_by_file_name();
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        let root = root.to_path_buf();
+
+        Box::new(
+            walker
+                .into_iter()
+                .filter_entry(move |entry| filter(&walked_entry(entry)))
+                .take_while(move |_| !cancelled())
+                .map(move |walked| match walked {
+                    Ok(entry) => Ok(walked_entry(&entry)),
+                    Err(e) => Err((e.path().unwrap_or(&root).to_path_buf(), e.into())),
+                }),
+        )
+    }
+}
+
+/// The [`DirEntry`] of an entry of a `walkdir` walk.
+fn walked_entry(entry: &walkdir::DirEntry) -> DirEntry {
+    let kind = if entry.file_type().is_symlink() {
+        RealFs.kind(entry.path())
+    } else {
+        Some(kind_of(entry.file_type()))
+    };
+
+    DirEntry {
+        path: entry.path().to_path_buf(),
+        kind,
+        is_symlink: entry.path_is_symlink(),
+        depth: entry.depth(),
+    }
+}
+
+/// The file system below `root`, e.g., a mounted disk image or a chroot,
+/// which it takes the paths below: an absolute link in it points to what
+/// is below `root`, not to what is on the machine.
+#[derive(Debug, Clone)]
+pub(crate) struct RootedFs {
+    root: PathBuf,
+}
+
+impl RootedFs {
+    /// Links resolved before giving up, like `MAXSYMLINKS` of Linux.
+    const MAX_LINKS: usize = 40;
+
+    pub(crate) fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Where `path`, below the root, is on the machine, with its links
+    /// resolved in the root.
+    fn resolve(&self, path: &Path) -> PathBuf {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return path.to_path_buf();
+        };
+        let mut pending = Vec::new();
+        push_components(&mut pending, relative);
+        let mut resolved = self.root.clone();
+        let mut depth = 0;
+        let mut links = 0;
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                if depth > 0 {
+                    resolved.pop();
+                    depth -= 1;
+                }
+                continue;
+            }
+            let candidate = resolved.join(&name);
+            match std::fs::read_link(&candidate) {
+                Ok(target) if links < Self::MAX_LINKS => {
+                    links += 1;
+                    if target.has_root() {
+                        resolved.clone_from(&self.root);
+                        depth = 0;
+                    }
+                    push_components(&mut pending, &target);
+                }
+                _ => {
+                    resolved = candidate;
+                    depth += 1;
+                }
+            }
+        }
+
+        resolved
+    }
+}
+
+/// Push the names and `..` of `path` on `pending`, the first last.
+fn push_components(pending: &mut Vec<std::ffi::OsString>, path: &Path) {
+    let start = pending.len();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => pending.push(name.to_os_string()),
+            Component::ParentDir => pending.push("..".into()),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    pending[start..].reverse();
+}
+
+/// The directories in `dir`, in the order of their paths, e.g., the home
+/// directories in `/home`.
+pub(crate) fn subdirs(fs: &dyn FileSystem, dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs
+        .read_dir(dir)
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.kind == Some(FileKind::Dir))
+        .map(|entry| entry.path)
+        .collect();
+    dirs.sort();
+
+    dirs
+}
+
+/// `path` of another system, e.g., `C:\Program Files\Foo\foo.exe` or
+/// `/usr/bin/foo`, below `root`.
+pub(crate) fn rebase(root: &Path, path: &Path) -> PathBuf {
+    if path.starts_with(root) {
+        return path.to_path_buf();
+    }
+    let relative: PathBuf = path
+        .components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::Prefix(_)))
+        .collect();
+
+    root.join(relative)
+}
+
+impl FileSystem for RootedFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Walked>> {
+        let mut entries = RealFs.read_dir(&self.resolve(dir))?;
+        for entry in entries.iter_mut().flatten() {
+            if let Some(name) = entry.path.file_name() {
+                entry.path = dir.join(name);
+            }
+            if entry.is_symlink {
+                entry.kind = self.kind(&entry.path);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        RealFs.read(&self.resolve(path))
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        RealFs.kind(&self.resolve(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path);
+        match resolved.exists() {
+            true => Ok(resolved),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn walk<'a>(&'a self, root: &Path, options: WalkOptions<'a>) -> Walk<'a> {
+        Box::new(DirWalk::new(self, root, options))
+    }
+
+    fn root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// Files in memory, the directories are those the files are in. For
+/// tests of the providers that need no files on disk.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemoryFs {
+    files: std::collections::BTreeMap<PathBuf, Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemoryFs {
+    pub(crate) fn new<P: Into<PathBuf>, C: Into<Vec<u8>>>(
+        files: impl IntoIterator<Item = (P, C)>,
+    ) -> Self {
+        Self {
+            files: files
+                .into_iter()
+                .map(|(path, content)| (path.into(), content.into()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<Walked>> {
+        if self.kind(dir) != Some(FileKind::Dir) {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        let mut children = std::collections::BTreeMap::new();
+        for path in self.files.keys() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let mut components = relative.components();
+            let Some(name) = components.next() else {
+                continue;
+            };
+            let kind = match components.next() {
+                Some(_) => FileKind::Dir,
+                None => FileKind::File,
+            };
+            children.insert(dir.join(name), kind);
+        }
+
+        Ok(children
+            .into_iter()
+            .map(|(path, kind)| {
+                Ok(DirEntry {
+                    path,
+                    kind: Some(kind),
+                    is_symlink: false,
+                    depth: 0,
+                })
+            })
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        if self.files.contains_key(path) {
+            Some(FileKind::File)
+        } else if self.files.keys().any(|file| file.starts_with(path)) {
+            Some(FileKind::Dir)
+        } else {
+            None
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.exists(path) {
+            true => Ok(path.to_path_buf()),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn walk<'a>(&'a self, root: &Path, options: WalkOptions<'a>) -> Walk<'a> {
+        Box::new(DirWalk::new(self, root, options))
+    }
+}
+
+/// How [`FileSystem::walk`] walks.
+pub(crate) struct WalkOptions<'a> {
+    /// Descend into links to directories, a link to one of its ancestors
+    /// is reported as an error.
+    pub(crate) follow_links: bool,
+    pub(crate) max_depth: Option<usize>,
+    /// What it refuses is left out, with everything below it.
+    pub(crate) filter: Box<dyn Fn(&DirEntry) -> bool + 'a>,
+    /// Stop walking once it returns `true`.
+    pub(crate) cancelled: Box<dyn Fn() -> bool + 'a>,
+}
+
+/// The iterator of [`FileSystem::walk`].
+pub(crate) type Walk<'a> = Box<dyn Iterator<Item = Walked> + 'a>;
+
+/// [`FileSystem::walk`] of the file systems `walkdir` cannot walk, whose
+/// links it would resolve on the machine. One directory is read at a
+/// time, as the walk gets to it.
+struct DirWalk<'a> {
+    fs: &'a dyn FileSystem,
+    options: WalkOptions<'a>,
+    /// What is left of the directories being walked, the innermost last.
+    dirs: Vec<std::vec::IntoIter<Walked>>,
+    /// The canonical paths of the directories being walked, when links
+    /// are followed.
+    ancestors: Vec<PathBuf>,
+    /// What is returned before walking on.
+    pending: VecDeque<Walked>,
+}
+
+impl<'a> DirWalk<'a> {
+    fn new(fs: &'a dyn FileSystem, root: &Path, options: WalkOptions<'a>) -> Self {
+        let mut walk = Self {
+            fs,
+            options,
+            dirs: Vec::new(),
+            ancestors: Vec::new(),
+            pending: VecDeque::new(),
+        };
+        match fs.kind(root) {
+            Some(kind) => {
+                let root = DirEntry {
+                    path: root.to_path_buf(),
+                    kind: Some(kind),
+                    is_symlink: false,
+                    depth: 0,
+                };
+                if (walk.options.filter)(&root) {
+                    walk.enter(root);
+                }
+            }
+            None => walk
+                .pending
+                .push_back(Err((root.to_path_buf(), io::ErrorKind::NotFound.into()))),
+        }
+
+        walk
+    }
+
+    /// Return `entry` next, and walk what is in it after it if it is a
+    /// directory to descend into.
+    fn enter(&mut self, entry: DirEntry) {
+        let descend = entry.kind == Some(FileKind::Dir)
+            && (entry.depth == 0 || !entry.is_symlink || self.options.follow_links)
+            && self
+                .options
+                .max_depth
+                .is_none_or(|max_depth| entry.depth < max_depth);
+        if !descend {
+            self.pending.push_back(Ok(entry));
+            return;
+        }
+
+        let canonical = if self.options.follow_links {
+            let canonical = self
+                .fs
+                .canonicalize(&entry.path)
+                .unwrap_or_else(|_| entry.path.clone());
+            if self.ancestors.contains(&canonical) {
+                let error = io::Error::other(format!(
+                    "file system loop: {} links to one of its ancestors",
+                    entry.path.display()
+                ));
+                self.pending.push_back(Err((entry.path, error)));
+                return;
+            }
+            Some(canonical)
+        } else {
+            None
+        };
+        match self.fs.read_dir(&entry.path) {
+            Ok(mut children) => {
+                let name = |child: &Walked| match child {
+                    Ok(child) => child.path.file_name().map(ToOwned::to_owned),
+                    Err((path, _)) => path.file_name().map(ToOwned::to_owned),
+                };
+                children.sort_by_key(name);
+                for child in children.iter_mut().flatten() {
+                    child.depth = entry.depth + 1;
+                }
+                self.dirs.push(children.into_iter());
+                self.ancestors.extend(canonical);
+                self.pending.push_back(Ok(entry));
+            }
+            Err(e) => {
+                let path = entry.path.clone();
+                self.pending.push_back(Ok(entry));
+                self.pending.push_back(Err((path, e)));
+            }
+        }
+    }
+}
+
+impl Iterator for DirWalk<'_> {
+    type Item = Walked;
+
+    fn next(&mut self) -> Option<Walked> {
+        loop {
+            if let Some(walked) = self.pending.pop_front() {
+                return Some(walked);
+            }
+            if (self.options.cancelled)() {
+                return None;
+            }
+            let dir = self.dirs.last_mut()?;
+            match dir.next() {
+                Some(Ok(child)) => {
+                    if (self.options.filter)(&child) {
+                        self.enter(child);
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.dirs.pop();
+                    if self.options.follow_links {
+                        self.ancestors.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_walk() {
+        let fs = MemoryFs::new([
+            ("/root/b/c.txt", "c"),
+            ("/root/a.txt", "a"),
+            ("/root/b/d/e.txt", "e"),
+            ("/other/f.txt", "f"),
+        ]);
+        assert_eq!(fs.kind(Path::new("/root/b")), Some(FileKind::Dir));
+        assert_eq!(fs.read_to_string(Path::new("/root/a.txt")).unwrap(), "a");
+        assert!(!fs.exists(Path::new("/root/missing")));
+
+        let paths = |max_depth, filter: &dyn Fn(&DirEntry) -> bool| -> Vec<PathBuf> {
+            let options = WalkOptions {
+                follow_links: false,
+                max_depth,
+                filter: Box::new(filter),
+                cancelled: Box::new(|| false),
+            };
+            fs.walk(Path::new("/root"), options)
+                .map(|walked| walked.unwrap().path)
+                .collect()
+        };
+        assert_eq!(
+            paths(None, &|_| true),
+            [
+                "/root",
+                "/root/a.txt",
+                "/root/b",
+                "/root/b/c.txt",
+                "/root/b/d",
+                "/root/b/d/e.txt"
+            ]
+            .map(PathBuf::from)
+        );
+        assert_eq!(
+            paths(Some(1), &|_| true),
+            ["/root", "/root/a.txt", "/root/b"].map(PathBuf::from)
+        );
+        assert_eq!(
+            paths(None, &|entry| !entry.path.ends_with("d")),
+            ["/root", "/root/a.txt", "/root/b", "/root/b/c.txt"].map(PathBuf::from)
+        );
+
+        let options = WalkOptions {
+            follow_links: false,
+            max_depth: None,
+            filter: Box::new(|_| true),
+            cancelled: Box::new(|| false),
+        };
+        let walked: Vec<Walked> = fs.walk(Path::new("/missing"), options).collect();
+        assert!(matches!(&walked[..], [Err((path, _))] if path == Path::new("/missing")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_real_fs() {
+        let temp_dir = TempDir::new("walk");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("b/loop")).unwrap();
+
+        let walk = |follow_links| -> Vec<Walked> {
+            let options = WalkOptions {
+                follow_links,
+                max_depth: None,
+                filter: Box::new(|_| true),
+                cancelled: Box::new(|| false),
+            };
+            RealFs.walk(&dir, options).collect()
+        };
+        let walked = walk(false);
+        let entries: Vec<&DirEntry> = walked.iter().flatten().collect();
+        assert_eq!(
+            entries.iter().map(|entry| &entry.path).collect::<Vec<_>>(),
+            [
+                &dir,
+                &dir.join("a.txt"),
+                &dir.join("b"),
+                &dir.join("b/loop")
+            ]
+        );
+        assert_eq!(entries[3].kind, Some(FileKind::Dir));
+        assert!(entries[3].is_symlink);
+        assert_eq!(entries[3].depth, 2);
+
+        // A link to an ancestor is an error when links are followed
+        let walked = walk(true);
+        assert!(
+            walked
+                .iter()
+                .any(|walked| matches!(walked, Err((path, _)) if path == &dir.join("b/loop")))
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! [`get_default_handlers`], the apps the system opens web pages and mail
+//! with, and runs command line programs in, [`set_default_app_for_extension`]
+//! [`App::can_open`] and [`get_apps_for_file`].
+
+use crate::Result;
+use crate::common::App;
+use crate::platforms::{apps_for_file, can_open, default_handlers, set_default_for_extension};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The media types of MIME types, which tell a MIME type from a path.
+const MEDIA_TYPES: &[&str] = &[
+    "application",
+    "audio",
+    "font",
+    "image",
+    "inode",
+    "message",
+    "model",
+    "multipart",
+    "text",
+    "video",
+    "x-scheme-handler",
+];
+
+/// What [`App::can_open`] is asked about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OpenTarget<'a> {
+    Path(&'a Path),
+    MimeType(&'a str),
+}
+
+impl<'a> OpenTarget<'a> {
+    /// A MIME type if `path_or_mime` has the form of one, e.g.,
+    /// `text/plain`, and is no file.
+    fn new(path_or_mime: &'a str) -> Self {
+        let is_mime_type = path_or_mime
+            .split_once('/')
+            .is_some_and(|(media_type, subtype)| {
+                MEDIA_TYPES.contains(&media_type) && !subtype.is_empty() && !subtype.contains('/')
+            });
+        if is_mime_type && !Path::new(path_or_mime).exists() {
+            OpenTarget::MimeType(path_or_mime)
+        } else {
+            OpenTarget::Path(Path::new(path_or_mime))
+        }
+    }
+}
+
+/// The apps the user picked, or the system ships, to handle what every
+/// system has an app for, see [`get_default_handlers`]. `None` if there is
+/// none, or it is not an app the finder knows about.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DefaultHandlers {
+    /// Opens `http` and `https` URLs.
+    pub browser: Option<App>,
+    /// Opens `mailto` URLs.
+    pub mail: Option<App>,
+    /// Runs command line programs: the first of the `xdg-terminals.list`
+    /// files on Linux, the app that opens shell scripts on macOS, and
+    /// Windows Terminal if it is the default terminal application of
+    /// Windows, rather than the console host.
+    pub terminal: Option<App>,
+}
+
+/// The default web browser, mail client and terminal: those of the
+/// `mimeapps.list` files on Linux, of Launch Services on macOS, and of the
+/// `UserChoice` registry keys on Windows.
+///
+/// ```no_run
+/// let handlers = applications::get_default_handlers().unwrap();
+/// if let Some(browser) = handlers.browser {
+///     println!("{} opens web pages", browser.name);
+/// }
+/// ```
+pub fn get_default_handlers() -> Result<DefaultHandlers> {
+    default_handlers()
+}
+
+/// All apps that open the file at `path`, for an "Open with" menu, the
+/// one the system prefers first: in the order of Launch Services on macOS,
+/// of the defaults and added associations of the `mimeapps.list` files,
+/// then the apps whose desktop entries declare the MIME type, on Linux,
+/// and of the `UserChoice`, ProgIDs and `OpenWithList`s of the extension on
+/// Windows. The file need not exist on Linux and Windows, which go by its
+/// extension.
+///
+/// ```no_run
+/// for app in applications::get_apps_for_file("notes.md") {
+///     println!("Open with {}", app.name);
+/// }
+/// ```
+pub fn get_apps_for_file(path: impl AsRef<Path>) -> Vec<App> {
+    apps_for_file(path.as_ref())
+}
+
+/// Make `app` the default app of the files with `extension`, e.g., `md`
+/// or `.md`: of its MIME types with `xdg-mime default` on Linux, and of its
+/// uniform type with Launch Services on macOS, which may ask the user to
+/// confirm. Windows lets only the user pick the default app of a type, in
+/// its settings or the "Open with" dialog, so this fails with
+/// [`Error::UnsupportedPlatform`](crate::Error::UnsupportedPlatform) there.
+pub fn set_default_app_for_extension(extension: &str, app: &App) -> Result<()> {
+    set_default_for_extension(extension.trim_start_matches('.'), app)
+}
+
+impl App {
+    /// Whether the app says it opens `path_or_mime`, a file or a MIME type,
+    /// e.g., `text/plain`, to tell the apps of an "Open With…" menu from
+    /// those that would fail to open it: by the `MimeType` of its desktop
+    /// entry and the associations of the `mimeapps.list` files on Linux,
+    /// the `CFBundleDocumentTypes` of its `Info.plist` on macOS, and the
+    /// associations of the registry on Windows. A file is known by its
+    /// extension, it does not need to exist.
+    pub fn can_open(&self, path_or_mime: &str) -> bool {
+        can_open(self, &OpenTarget::new(path_or_mime))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_target() {
+        assert_eq!(
+            OpenTarget::new("text/plain"),
+            OpenTarget::MimeType("text/plain")
+        );
+        assert_eq!(
+            OpenTarget::new("x-scheme-handler/https"),
+            OpenTarget::MimeType("x-scheme-handler/https")
+        );
+        assert_eq!(
+            OpenTarget::new("notes/todo.txt"),
+            OpenTarget::Path(Path::new("notes/todo.txt"))
+        );
+        assert_eq!(
+            OpenTarget::new("/home/foo/text/plain"),
+            OpenTarget::Path(Path::new("/home/foo/text/plain"))
+        );
+        assert_eq!(
+            OpenTarget::new("report.pdf"),
+            OpenTarget::Path(Path::new("report.pdf"))
+        );
+    }
+}
@@ -0,0 +1,1116 @@
+//! Icons rendered to PNG files once, for launchers that show hundreds of
+//! them.
+
+use crate::platforms::render_icon;
+use crate::{App, Error, Icon, Result};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A directory of app icons rendered to PNG at the sizes asked for.
+///
+/// Each file is named after the app's [`App::app_desktop_path`] and the
+/// modification time of its icon, so an app update that changes the icon
+/// renders it again, and the stale files of the app are removed then.
+///
+/// Rendering decodes `.icns` files on macOS and icons inside EXE and DLL
+/// files on Windows. On Linux, icons are image files already: PNG ones are
+/// scaled, or copied as they are when they have the size asked for, and
+/// [`IconCache::get`] fails for the others, e.g., SVG, which are better
+/// rendered by a toolkit at the size they are shown.
+#[derive(Debug, Clone)]
+pub struct IconCache {
+    dir: PathBuf,
+}
+
+impl IconCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Where icons are cached unless told otherwise:
+    /// `$XDG_CACHE_HOME/applications-rs/icons` on Linux,
+    /// `~/Library/Caches/applications-rs/icons` on macOS and
+    /// `%LOCALAPPDATA%\applications-rs\icons` on Windows.
+    pub fn default_dir() -> Option<PathBuf> {
+        let cache_dir = if cfg!(target_os = "windows") {
+            PathBuf::from(std::env::var_os("LOCALAPPDATA")?)
+        } else if cfg!(target_os = "macos") {
+            PathBuf::from(std::env::var_os("HOME")?).join("Library/Caches")
+        } else {
+            match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+                Some(dir) => PathBuf::from(dir),
+                None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+            }
+        };
+
+        Some(cache_dir.join("applications-rs").join("icons"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// A PNG file of the icon of `app`, `size` pixels wide and high,
+    /// rendered now if it is not cached yet.
+    pub fn get(&self, app: &App, size: u32) -> Result<PathBuf> {
+        let id = app_id(app);
+        let path = self
+            .dir
+            .join(format!("{id:016x}-{:x}-{size}.png", source_modified(app)));
+        if path.is_file() {
+            return Ok(path);
+        }
+
+        let png = render_icon(app, size)?;
+        std::fs::create_dir_all(&self.dir)?;
+        self.remove_stale(id, &path);
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, png)?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        Ok(path)
+    }
+
+    /// Remove the cached icons of `app`, e.g., once it is uninstalled.
+    pub fn remove(&self, app: &App) -> Result<()> {
+        let prefix = format!("{:016x}-", app_id(app));
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if is_icon_of(&path, &prefix) {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the icons of the app `id` rendered before its icon changed,
+    /// the ones that are not at `current` modification time.
+    fn remove_stale(&self, id: u64, current: &Path) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let prefix = format!("{id:016x}-");
+        let current_prefix = current
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .and_then(|name| {
+                let (prefix, _size) = name.rsplit_once('-')?;
+                Some(format!("{prefix}-"))
+            });
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let is_current = current_prefix.as_deref().is_some_and(|current_prefix| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(current_prefix)
+            });
+            if is_icon_of(&path, &prefix) && !is_current {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+fn is_icon_of(path: &Path, prefix: &str) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(prefix))
+        && path.extension().is_some_and(|ext| ext == "png")
+}
+
+/// FNV-1a of the app's desktop path, which, unlike `DefaultHasher`, stays
+/// the same across Rust versions.
+fn app_id(app: &App) -> u64 {
+    app.app_desktop_path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Seconds since the epoch the icon, or what holds it, was modified.
+fn source_modified(app: &App) -> u64 {
+    [
+        app.icon_path.as_deref(),
+        app.app_path_exe.as_deref(),
+        Some(app.app_desktop_path.as_path()),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|path| std::fs::metadata(path).ok()?.modified().ok())
+    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+    .map_or(0, |modified| modified.as_secs())
+}
+
+/// `icon` scaled to `size`x`size`, by averaging the pixels each one covers
+/// when shrinking. Colors are weighted by their alpha, so that transparent
+/// pixels do not darken the edges.
+pub(crate) fn resize(icon: &Icon, size: u32) -> Result<Icon> {
+    let (width, height) = (icon.width as usize, icon.height as usize);
+    if width == 0 || height == 0 || size == 0 || icon.rgba.len() != width * height * 4 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "cannot scale a {width}x{height} icon of {} bytes to {size}x{size}",
+                icon.rgba.len()
+            ),
+        )
+        .into());
+    }
+    if icon.width == size && icon.height == size {
+        return Ok(icon.clone());
+    }
+
+    let target = size as usize;
+    let mut rgba = Vec::with_capacity(target * target * 4);
+    for y in 0..target {
+        let y0 = y * height / target;
+        let y1 = ((y + 1) * height / target).max(y0 + 1);
+        for x in 0..target {
+            let x0 = x * width / target;
+            let x1 = ((x + 1) * width / target).max(x0 + 1);
+            let mut sums = [0u64; 4];
+            for source_y in y0..y1 {
+                for source_x in x0..x1 {
+                    let i = (source_y * width + source_x) * 4;
+                    let alpha = u64::from(icon.rgba[i + 3]);
+                    for (channel, sum) in sums.iter_mut().take(3).enumerate() {
+                        *sum += u64::from(icon.rgba[i + channel]) * alpha;
+                    }
+                    sums[3] += alpha;
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u64;
+            let alpha = sums[3];
+            for sum in &sums[..3] {
+                rgba.push(sum.checked_div(alpha).unwrap_or(0) as u8);
+            }
+            rgba.push((alpha / count) as u8);
+        }
+    }
+
+    Ok(Icon {
+        width: size,
+        height: size,
+        rgba,
+    })
+}
+
+/// The PNG image `png`, read from `path`, scaled to `size`x`size`. It is
+/// copied as it is if it has that size already.
+pub(crate) fn scale_png(path: &Path, png: &[u8], size: u32) -> Result<Vec<u8>> {
+    if !png.starts_with(PNG_SIGNATURE) {
+        return Err(Error::parse(path, "not a PNG image, only those are scaled"));
+    }
+    if png_size(png) == Some((size, size)) {
+        return Ok(png.to_vec());
+    }
+    let icon =
+        decode_png(png).ok_or_else(|| Error::parse(path, "invalid or unsupported PNG image"))?;
+
+    Ok(encode_png(&resize(&icon, size)?))
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// Larger PNG images are not decoded, icons are far smaller than that.
+const MAX_PNG_PIXELS: u64 = 4096 * 4096;
+
+/// The width and height in the header of `png`.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    // The length and type of the IHDR chunk, then the width and height
+    let header = png.strip_prefix(PNG_SIGNATURE)?.get(..16)?;
+    if &header[4..8] != b"IHDR" {
+        return None;
+    }
+
+    Some((be_u32(&header[8..12]), be_u32(&header[12..16])))
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Decode a PNG image of any color type and bit depth, `None` if it is
+/// invalid, interlaced or too large.
+pub(crate) fn decode_png(png: &[u8]) -> Option<Icon> {
+    let mut chunks = png.strip_prefix(PNG_SIGNATURE)?;
+    let mut header: &[u8] = &[];
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut zlib = Vec::new();
+    // Each chunk is its length, its type, its data and a CRC
+    while chunks.len() >= 12 {
+        let len = be_u32(chunks) as usize;
+        let data = chunks.get(8..8 + len)?;
+        match &chunks[4..8] {
+            b"IHDR" => header = data,
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = data,
+            b"IDAT" => zlib.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+        chunks = chunks.get(12 + len..)?;
+    }
+
+    if header.len() != 13 {
+        return None;
+    }
+    let (width, height) = (be_u32(&header[..4]), be_u32(&header[4..8]));
+    let (depth, color) = (usize::from(header[8]), header[9]);
+    // Deflate, adaptive filtering, no interlace
+    if header[10..] != [0, 0, 0]
+        || width == 0
+        || height == 0
+        || u64::from(width) * u64::from(height) > MAX_PNG_PIXELS
+    {
+        return None;
+    }
+    let channels = match (color, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => return None,
+    };
+    let (width, height) = (width as usize, height as usize);
+    let stride = (width * channels * depth).div_ceil(8);
+    let pixel_len = (channels * depth).div_ceil(8);
+
+    // Each row starts with its filter type
+    let raw = inflate_zlib(&zlib, (stride + 1) * height)?;
+    let pixels = unfilter(raw.get(..(stride + 1) * height)?, stride, pixel_len)?;
+
+    let sample = |row: &[u8], index: usize| -> u16 {
+        match depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+            8 => row[index].into(),
+            _ => {
+                let bit = index * depth;
+                u16::from(row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1)
+            }
+        }
+    };
+    let to_u8 = |sample: u16| -> u8 {
+        match depth {
+            16 => (sample >> 8) as u8,
+            8 => sample as u8,
+            _ => (u32::from(sample) * 255 / ((1 << depth) - 1)) as u8,
+        }
+    };
+    // Gray and RGB images may have one color that is transparent
+    let transparent: Option<Vec<u16>> =
+        (color != 3 && transparency.len() == channels * 2).then(|| {
+            transparency
+                .chunks(2)
+                .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+                .collect()
+        });
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in pixels.chunks(stride) {
+        for x in 0..width {
+            let mut samples = [0u16; 4];
+            for (channel, sample_value) in samples.iter_mut().take(channels).enumerate() {
+                *sample_value = sample(row, x * channels + channel);
+            }
+            let opaque = if transparent.as_deref() == Some(&samples[..channels]) {
+                0
+            } else {
+                255
+            };
+            let pixel = match color {
+                0 => {
+                    let gray = to_u8(samples[0]);
+                    [gray, gray, gray, opaque]
+                }
+                2 => [
+                    to_u8(samples[0]),
+                    to_u8(samples[1]),
+                    to_u8(samples[2]),
+                    opaque,
+                ],
+                3 => {
+                    let index = usize::from(samples[0]);
+                    let rgb = palette.get(index * 3..index * 3 + 3)?;
+                    let alpha = transparency.get(index).copied().unwrap_or(255);
+                    [rgb[0], rgb[1], rgb[2], alpha]
+                }
+                4 => {
+                    let gray = to_u8(samples[0]);
+                    [gray, gray, gray, to_u8(samples[1])]
+                }
+                _ => samples.map(to_u8),
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+
+    Some(Icon {
+        width: width as u32,
+        height: height as u32,
+        rgba,
+    })
+}
+
+/// Undo the filter of each row of `raw`, a filter type followed by
+/// `stride` bytes, predicted from the bytes `pixel_len` to the left and
+/// from the row above.
+fn unfilter(raw: &[u8], stride: usize, pixel_len: usize) -> Option<Vec<u8>> {
+    let mut pixels = vec![0u8; raw.len() / (stride + 1) * stride];
+    for (y, row) in raw.chunks(stride + 1).enumerate() {
+        let (above, current) = pixels.split_at_mut(y * stride);
+        let above = (y > 0).then(|| &above[(y - 1) * stride..]);
+        let current = &mut current[..stride];
+        for x in 0..stride {
+            let left = if x >= pixel_len {
+                current[x - pixel_len]
+            } else {
+                0
+            };
+            let up = above.map_or(0, |above| above[x]);
+            let up_left = if x >= pixel_len {
+                above.map_or(0, |above| above[x - pixel_len])
+            } else {
+                0
+            };
+            let predicted = match row[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return None,
+            };
+            current[x] = row[x + 1].wrapping_add(predicted);
+        }
+    }
+
+    Some(pixels)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let (a, b, c) = (i16::from(left), i16::from(up), i16::from(up_left));
+    let p = a + b - c;
+    let (pa, pb, pc) = ((p - a).abs(), (p - b).abs(), (p - c).abs());
+    if pa <= pb && pa <= pc {
+        left
+    } else if pb <= pc {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Inflate the zlib stream `zlib`, `None` if it is invalid or inflates to
+/// more than `max_len` bytes.
+fn inflate_zlib(zlib: &[u8], max_len: usize) -> Option<Vec<u8>> {
+    let ([method, flags], deflate) = zlib.split_first_chunk::<2>()?;
+    // Deflate, with a valid header check and no preset dictionary
+    if method & 0x0f != 8
+        || (u16::from(*method) << 8 | u16::from(*flags)) % 31 != 0
+        || flags & 0x20 != 0
+    {
+        return None;
+    }
+
+    inflate(deflate, max_len)
+}
+
+/// Inflate the deflate stream `deflate`, RFC 1951.
+fn inflate(deflate: &[u8], max_len: usize) -> Option<Vec<u8>> {
+    // The order the lengths of the code length code are stored in
+    const CODE_LENGTH_ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let mut bits = Bits {
+        data: deflate,
+        pos: 0,
+        bit: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let is_last = bits.bit()? == 1;
+        match bits.bits(2)? {
+            // Stored
+            0 => {
+                bits.align();
+                let header = bits.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return None;
+                }
+                out.extend_from_slice(bits.bytes(len.into())?);
+                if out.len() > max_len {
+                    return None;
+                }
+            }
+            // Fixed Huffman codes
+            1 => {
+                let literals = [[8; 144].as_slice(), &[9; 112], &[7; 24], &[8; 8]].concat();
+                let literals = Huffman::new(&literals);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut bits, &mut out, max_len, &literals, &distances)?;
+            }
+            // Dynamic Huffman codes
+            2 => {
+                let literal_count = bits.bits(5)? as usize + 257;
+                let distance_count = bits.bits(5)? as usize + 1;
+                let code_length_count = bits.bits(4)? as usize + 4;
+                let mut code_lengths = [0u8; 19];
+                for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+                    code_lengths[symbol] = bits.bits(3)? as u8;
+                }
+                let code_lengths = Huffman::new(&code_lengths);
+
+                let mut lengths = Vec::with_capacity(literal_count + distance_count);
+                while lengths.len() < literal_count + distance_count {
+                    let (length, repeat) = match code_lengths.decode(&mut bits)? {
+                        symbol @ 0..=15 => (symbol as u8, 1),
+                        16 => (*lengths.last()?, 3 + bits.bits(2)?),
+                        17 => (0, 3 + bits.bits(3)?),
+                        18 => (0, 11 + bits.bits(7)?),
+                        _ => return None,
+                    };
+                    lengths.extend(std::iter::repeat_n(length, repeat as usize));
+                }
+                if lengths.len() != literal_count + distance_count {
+                    return None;
+                }
+                let (literals, distances) = lengths.split_at(literal_count);
+                let (literals, distances) = (Huffman::new(literals), Huffman::new(distances));
+                inflate_block(&mut bits, &mut out, max_len, &literals, &distances)?;
+            }
+            _ => return None,
+        }
+        if is_last {
+            return Some(out);
+        }
+    }
+}
+
+/// Inflate one block coded with `literals` and `distances` into `out`.
+fn inflate_block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    max_len: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Option<()> {
+    const LENGTH_BASES: [u16; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const LENGTH_EXTRA_BITS: [u32; 29] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+    ];
+    const DISTANCE_BASES: [u16; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+    const DISTANCE_EXTRA_BITS: [u32; 30] = [
+        0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12,
+        13, 13,
+    ];
+
+    loop {
+        if out.len() > max_len {
+            return None;
+        }
+        match literals.decode(bits)? {
+            symbol @ 0..=255 => out.push(symbol as u8),
+            256 => return Some(()),
+            symbol => {
+                let i = usize::from(symbol - 257);
+                let len =
+                    usize::from(*LENGTH_BASES.get(i)?) + bits.bits(LENGTH_EXTRA_BITS[i])? as usize;
+                let i = usize::from(distances.decode(bits)?);
+                let distance = usize::from(*DISTANCE_BASES.get(i)?)
+                    + bits.bits(DISTANCE_EXTRA_BITS[i])? as usize;
+                // The copy may overlap what it appends
+                let start = out.len().checked_sub(distance)?;
+                for i in start..start + len {
+                    out.push(out[i]);
+                }
+            }
+        }
+    }
+}
+
+/// The bits of a deflate stream, least significant first.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.data.get(self.pos)?;
+        let bit = u32::from(byte >> self.bit) & 1;
+        self.bit += 1;
+        if self.bit == 8 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+
+        Some(bit)
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        (0..count).try_fold(0, |value, i| Some(value | self.bit()? << i))
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+
+        Some(bytes)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the
+/// symbols ordered by their codes.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// The code of symbols with the code `lengths`, 0 for unused symbols.
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                let offset = &mut offsets[usize::from(len)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Option<u16> {
+        // The first code of each length, and the index of its symbol
+        let (mut code, mut first, mut index) = (0u32, 0u32, 0u32);
+        for &count in &self.counts[1..] {
+            code |= bits.bit()?;
+            let count = u32::from(count);
+            if code < first + count {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        None
+    }
+}
+
+/// Encode `icon` as a PNG file. The image data is stored rather than
+/// compressed, which is what keeps this free of dependencies, icons are
+/// small enough for that.
+pub(crate) fn encode_png(icon: &Icon) -> Vec<u8> {
+    // Deflate stores at most this many bytes per block
+    const MAX_STORED_BLOCK: usize = 0xffff;
+
+    // Each row starts with its filter type, 0 being none
+    let row_len = icon.width as usize * 4;
+    let mut raw = Vec::with_capacity((row_len + 1) * icon.height as usize);
+    for row in icon.rgba.chunks(row_len) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // zlib header, no compression
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(u8::from(is_last));
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&icon.width.to_be_bytes());
+    header.extend_from_slice(&icon.height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % MOD;
+        (a, (b + a) % MOD)
+    });
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// 12x12 RGBA, each row filtered differently, with dynamic Huffman
+    /// codes.
+    const RGBA_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x0c, 0x08, 0x06, 0x00, 0x00, 0x00, 0x56,
+        0x75, 0x5c, 0xe7, 0x00, 0x00, 0x00, 0xf3, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x9d, 0xcf,
+        0x2d, 0x50, 0xc3, 0x40, 0x10, 0x86, 0xe1, 0x0d, 0xa0, 0xa2, 0x4f, 0xa3, 0xcf, 0x60, 0xaa,
+        0x4f, 0x57, 0x9f, 0x8e, 0x5e, 0x1d, 0x7d, 0xb2, 0x53, 0x7d, 0x06, 0x53, 0x83, 0x89, 0xc1,
+        0x60, 0x30, 0x31, 0x18, 0x0c, 0x66, 0x4d, 0x4d, 0x4d, 0x4d, 0x4c, 0x0d, 0xa6, 0x06, 0x13,
+        0x92, 0x5c, 0x7e, 0x96, 0x4d, 0xe7, 0x66, 0x50, 0x65, 0x32, 0x88, 0x47, 0x7e, 0xf3, 0xcd,
+        0x0b, 0x00, 0xc0, 0x0a, 0x52, 0xd6, 0xa0, 0xd8, 0xc0, 0x3d, 0x5b, 0xd0, 0x8c, 0xb0, 0x62,
+        0x07, 0x86, 0x3d, 0xac, 0xb9, 0x00, 0xcb, 0x25, 0x64, 0x4c, 0x80, 0x5c, 0x41, 0xce, 0x09,
+        0xa8, 0xf4, 0x32, 0x58, 0xea, 0x46, 0x06, 0x30, 0x8f, 0xc4, 0x24, 0x46, 0x31, 0x88, 0x5e,
+        0x04, 0xd1, 0x89, 0x56, 0x34, 0xe2, 0x5b, 0xd4, 0xb7, 0xa0, 0xd5, 0x26, 0x4d, 0xd3, 0x29,
+        0x1a, 0xa3, 0x21, 0xea, 0xa3, 0x10, 0x75, 0x77, 0x97, 0x07, 0xb9, 0x12, 0x93, 0x18, 0xc5,
+        0xf0, 0x37, 0x5c, 0xb1, 0x42, 0x13, 0x34, 0xae, 0x6b, 0x83, 0xf6, 0xcb, 0x62, 0x76, 0x46,
+        0xc4, 0x4f, 0x87, 0xf9, 0xc9, 0xa3, 0xab, 0x0a, 0xdc, 0x1e, 0x4b, 0xf4, 0x07, 0xc2, 0xdd,
+        0xbe, 0xc2, 0x82, 0x12, 0x70, 0x66, 0x8e, 0x09, 0x4b, 0xfd, 0x23, 0x3a, 0x7b, 0xd8, 0x5c,
+        0x0b, 0x8c, 0xda, 0xa8, 0x99, 0x5d, 0x8b, 0xee, 0x45, 0x10, 0x9d, 0x68, 0x45, 0xf3, 0x8b,
+        0x90, 0x15, 0xe5, 0xb5, 0x26, 0x77, 0x36, 0xb4, 0x3d, 0x59, 0xf2, 0x47, 0xa4, 0xdd, 0xde,
+        0x51, 0xf1, 0xe1, 0xe9, 0xe5, 0xad, 0xa0, 0xf2, 0xb5, 0xa4, 0xf7, 0x67, 0x22, 0x7a, 0xaa,
+        0xe8, 0xf0, 0x98, 0x40, 0x95, 0xcf, 0xd1, 0xf5, 0x52, 0x3f, 0x2c, 0x47, 0xbe, 0x6e, 0x98,
+        0xad, 0x78, 0x31, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    /// 3x2, 2 bits per pixel indexing a palette with transparency, with
+    /// fixed Huffman codes.
+    const PALETTE_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02, 0x02, 0x03, 0x00, 0x00, 0x00, 0xe0,
+        0x1a, 0x8e, 0x89, 0x00, 0x00, 0x00, 0x0c, 0x50, 0x4c, 0x54, 0x45, 0xff, 0x00, 0x00, 0x00,
+        0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xfb, 0xbe, 0x46, 0xe4, 0x00, 0x00, 0x00,
+        0x04, 0x74, 0x52, 0x4e, 0x53, 0xff, 0xff, 0x80, 0x00, 0xe8, 0x8d, 0xcc, 0xcd, 0x00, 0x00,
+        0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x90, 0x60, 0x78, 0x02, 0x00, 0x01,
+        0x30, 0x00, 0xfd, 0x68, 0x30, 0xcf, 0xdf, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+        0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_resize() {
+        // A 2x2 icon with one transparent pixel
+        let icon = Icon {
+            width: 2,
+            height: 2,
+            rgba: [
+                [255, 0, 0, 255],
+                [0, 0, 0, 0],
+                [255, 0, 0, 255],
+                [255, 0, 0, 255],
+            ]
+            .concat(),
+        };
+        let small = resize(&icon, 1).unwrap();
+        assert_eq!(small.rgba, [255, 0, 0, 191]);
+        let large = resize(&icon, 4).unwrap();
+        assert_eq!(large.rgba.len(), 4 * 4 * 4);
+        assert_eq!(&large.rgba[..4], &[255, 0, 0, 255]);
+        assert_eq!(&large.rgba[8..12], &[0, 0, 0, 0]);
+
+        let empty = Icon {
+            width: 0,
+            height: 0,
+            rgba: Vec::new(),
+        };
+        assert!(resize(&empty, 32).is_err());
+        assert!(resize(&icon, 0).is_err());
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let icon = decode_png(RGBA_PNG).unwrap();
+        assert_eq!((icon.width, icon.height), (12, 12));
+        for (i, pixel) in icon.rgba.chunks(4).enumerate() {
+            let (x, y) = (i as u32 % 12, i as u32 / 12);
+            let expected = [x * 20, y * 20, (x + y) * 10, 255 - x * y].map(|value| value as u8);
+            assert_eq!(pixel, expected, "({x}, {y})");
+        }
+
+        let icon = decode_png(PALETTE_PNG).unwrap();
+        assert_eq!((icon.width, icon.height), (3, 2));
+        assert_eq!(
+            icon.rgba,
+            [
+                [255, 0, 0, 255],
+                [0, 255, 0, 255],
+                [0, 0, 255, 128],
+                [0, 0, 0, 0],
+                [0, 0, 255, 128],
+                [0, 255, 0, 255],
+            ]
+            .concat()
+        );
+
+        // What is encoded, with stored blocks, decodes the same
+        let icon = resize(&icon, 200).unwrap();
+        assert_eq!(decode_png(&encode_png(&icon)).unwrap(), icon);
+
+        assert_eq!(decode_png(b"<svg/>"), None);
+        assert_eq!(decode_png(&RGBA_PNG[..RGBA_PNG.len() / 2]), None);
+    }
+
+    /// A PNG file of `chunks`, with their CRCs.
+    fn png_of(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (kind, data) in chunks {
+            write_chunk(&mut png, kind, data);
+        }
+        png
+    }
+
+    fn header(width: u32, height: u32, depth: u8, color: u8) -> Vec<u8> {
+        [
+            &width.to_be_bytes()[..],
+            &height.to_be_bytes(),
+            &[depth, color, 0, 0, 0],
+        ]
+        .concat()
+    }
+
+    /// A zlib stream of one stored block of `raw`.
+    fn stored(raw: &[u8]) -> Vec<u8> {
+        let len = raw.len() as u16;
+        [
+            &[0x78, 0x01, 1][..],
+            &len.to_le_bytes(),
+            &(!len).to_le_bytes(),
+            raw,
+            &adler32(raw).to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    /// PNG files that are cut short, inconsistent or unsupported, with
+    /// whether they decode.
+    #[test]
+    fn test_decode_malformed_pngs() {
+        let rgba = header(1, 1, 8, 6);
+        let pixel = [0, 1, 2, 3, 4];
+        let well_formed = png_of(&[(b"IHDR", &rgba), (b"IDAT", &stored(&pixel)), (b"IEND", &[])]);
+        // A match 1 byte back in an empty output, with fixed Huffman codes
+        let distance_too_far = [0x78, 0x01, 0x03, 0x02, 0x00];
+        let indexed = header(1, 1, 8, 3);
+        let corpus: Vec<(&str, Vec<u8>, bool)> = vec![
+            ("well-formed", well_formed.clone(), true),
+            (
+                "unknown chunk",
+                png_of(&[
+                    (b"IHDR", &rgba),
+                    (b"tEXt", b"Comment\0hi"),
+                    (b"IDAT", &stored(&pixel)),
+                    (b"IEND", &[]),
+                ]),
+                true,
+            ),
+            (
+                "image data split across chunks",
+                {
+                    let zlib = stored(&pixel);
+                    png_of(&[
+                        (b"IHDR", &rgba),
+                        (b"IDAT", &zlib[..3]),
+                        (b"IDAT", &zlib[3..]),
+                    ])
+                },
+                true,
+            ),
+            (
+                "no end chunk",
+                png_of(&[(b"IHDR", &rgba), (b"IDAT", &stored(&pixel))]),
+                true,
+            ),
+            ("empty", Vec::new(), false),
+            ("signature only", PNG_SIGNATURE.to_vec(), false),
+            ("truncated header", well_formed[..20].to_vec(), false),
+            (
+                "no header",
+                png_of(&[(b"IDAT", &stored(&pixel)), (b"IEND", &[])]),
+                false,
+            ),
+            (
+                "short header",
+                png_of(&[(b"IHDR", &rgba[..12]), (b"IDAT", &stored(&pixel))]),
+                false,
+            ),
+            (
+                "zero width",
+                png_of(&[(b"IHDR", &header(0, 1, 8, 6)), (b"IDAT", &stored(&pixel))]),
+                false,
+            ),
+            (
+                "too large",
+                png_of(&[
+                    (b"IHDR", &header(5000, 5000, 8, 6)),
+                    (b"IDAT", &stored(&pixel)),
+                ]),
+                false,
+            ),
+            (
+                "interlaced",
+                png_of(&[
+                    (b"IHDR", &[&rgba[..12], &[1]].concat()),
+                    (b"IDAT", &stored(&pixel)),
+                ]),
+                false,
+            ),
+            (
+                "16 bits per palette index",
+                png_of(&[
+                    (b"IHDR", &header(1, 1, 16, 3)),
+                    (b"PLTE", &[0; 3]),
+                    (b"IDAT", &stored(&[0, 0, 0])),
+                ]),
+                false,
+            ),
+            (
+                "unknown color type",
+                png_of(&[(b"IHDR", &header(1, 1, 8, 5)), (b"IDAT", &stored(&pixel))]),
+                false,
+            ),
+            (
+                "no image data",
+                png_of(&[(b"IHDR", &rgba), (b"IEND", &[])]),
+                false,
+            ),
+            (
+                "chunk longer than the file",
+                [&well_formed[..33], &[0xff, 0xff, 0xff, 0xff], b"IDAT"].concat(),
+                false,
+            ),
+            (
+                "preset dictionary",
+                png_of(&[(b"IHDR", &rgba), (b"IDAT", &[0x78, 0x20, 0, 0, 0, 0])]),
+                false,
+            ),
+            (
+                "bad zlib header check",
+                png_of(&[(b"IHDR", &rgba), (b"IDAT", &[0x78, 0x00, 0x03, 0x00])]),
+                false,
+            ),
+            (
+                "stored length mismatch",
+                png_of(&[
+                    (b"IHDR", &rgba),
+                    (b"IDAT", &[0x78, 0x01, 1, 5, 0, 0, 0, 0, 1, 2, 3, 4]),
+                ]),
+                false,
+            ),
+            (
+                "too little image data",
+                png_of(&[(b"IHDR", &header(1, 2, 8, 6)), (b"IDAT", &stored(&pixel))]),
+                false,
+            ),
+            (
+                "more image data than pixels",
+                png_of(&[
+                    (b"IHDR", &rgba),
+                    (b"IDAT", &stored(&[pixel, pixel].concat())),
+                ]),
+                false,
+            ),
+            (
+                "unknown filter type",
+                png_of(&[(b"IHDR", &rgba), (b"IDAT", &stored(&[5, 1, 2, 3, 4]))]),
+                false,
+            ),
+            (
+                "palette index out of range",
+                png_of(&[
+                    (b"IHDR", &indexed),
+                    (b"PLTE", &[0; 3]),
+                    (b"IDAT", &stored(&[0, 1])),
+                ]),
+                false,
+            ),
+            (
+                "no palette",
+                png_of(&[(b"IHDR", &indexed), (b"IDAT", &stored(&[0, 0]))]),
+                false,
+            ),
+            (
+                "distance too far back",
+                png_of(&[(b"IHDR", &rgba), (b"IDAT", &distance_too_far)]),
+                false,
+            ),
+        ];
+
+        for (case, png, decodes) in corpus {
+            assert_eq!(decode_png(&png).is_some(), decodes, "{case}");
+        }
+    }
+
+    /// Truncations and random corruptions of the fixtures, and random
+    /// deflate streams, never panic, and what decodes is consistent.
+    #[test]
+    fn test_decode_corrupted_pngs() {
+        // xorshift, for the same inputs on every run
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let check = |png: &[u8]| {
+            if let Some(icon) = decode_png(png) {
+                assert_eq!(
+                    icon.rgba.len(),
+                    icon.width as usize * icon.height as usize * 4
+                );
+            }
+        };
+
+        for fixture in [RGBA_PNG, PALETTE_PNG] {
+            for len in 0..fixture.len() {
+                check(&fixture[..len]);
+            }
+            for _ in 0..2000 {
+                let mut png = fixture.to_vec();
+                for _ in 0..=random() % 4 {
+                    let i = random() as usize % png.len();
+                    png[i] ^= 1 << (random() % 8);
+                }
+                check(&png);
+            }
+        }
+
+        for _ in 0..2000 {
+            let deflate: Vec<u8> = (0..random() % 64).map(|_| random() as u8).collect();
+            if let Some(out) = inflate(&deflate, 1024) {
+                assert!(out.len() <= 1024);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_png() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let icon = Icon {
+            width: 1,
+            height: 1,
+            rgba: vec![1, 2, 3, 4],
+        };
+        let png = encode_png(&icon);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        // The filter byte and the pixel, stored
+        let raw = [0, 1, 2, 3, 4];
+        assert!(png.windows(raw.len()).any(|window| window == raw));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_icon_cache() {
+        let temp_dir = TempDir::new("icons");
+        let dir = temp_dir.path().to_path_buf();
+        let icon_path = dir.join("foo.png");
+        std::fs::write(
+            &icon_path,
+            encode_png(
+                &resize(
+                    &Icon {
+                        width: 1,
+                        height: 1,
+                        rgba: vec![0; 4],
+                    },
+                    2,
+                )
+                .unwrap(),
+            ),
+        )
+        .unwrap();
+        let app = App {
+            name: "Foo".to_string(),
+            icon_path: Some(icon_path.clone()),
+            app_desktop_path: dir.join("foo.desktop"),
+            ..Default::default()
+        };
+
+        let cache = IconCache::new(dir.join("cache"));
+        let cached = cache.get(&app, 32).unwrap();
+        assert!(cached.starts_with(cache.dir()));
+        let icon = decode_png(&std::fs::read(&cached).unwrap()).unwrap();
+        assert_eq!((icon.width, icon.height), (32, 32));
+        assert_eq!(cache.get(&app, 32).unwrap(), cached);
+        // Icons of the size asked for are copied as they are
+        assert_eq!(
+            std::fs::read(cache.get(&app, 2).unwrap()).unwrap(),
+            std::fs::read(&icon_path).unwrap()
+        );
+
+        cache.remove(&app).unwrap();
+        assert!(!cached.exists());
+
+        std::fs::write(dir.join("foo.svg"), "<svg/>").unwrap();
+        let svg = App {
+            icon_path: Some(dir.join("foo.svg")),
+            ..app
+        };
+        assert!(matches!(cache.get(&svg, 32), Err(Error::Parse { .. })));
+    }
+}
@@ -0,0 +1,172 @@
+//! [`AppIdentity`], what makes two [`App`]s the same app whatever else
+//! differs between them.
+
+use crate::App;
+use std::ffi::OsString;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// What identifies an app, see [`App::identity`]. Two scans of the same
+/// app have the same identity, even if, e.g., its icon or version
+/// changed in between.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppIdentity(Key);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Aumid(String),
+    /// The entries with the same desktop file name in different search
+    /// paths are one app on Linux.
+    DesktopId(OsString),
+    /// The executable and its arguments.
+    Command(PathBuf, Vec<String>),
+    /// The bundle or shortcut.
+    Path(PathBuf),
+}
+
+impl App {
+    /// What identifies the app: its [`App::aumid`], its desktop file ID on
+    /// Linux, its executable and arguments, or else its bundle or
+    /// shortcut, with symbolic links resolved. It reads the file system
+    /// for the latter.
+    pub fn identity(&self) -> AppIdentity {
+        if let Some(aumid) = &self.aumid {
+            return AppIdentity(Key::Aumid(aumid.to_lowercase()));
+        }
+        if cfg!(target_os = "linux")
+            && self
+                .app_desktop_path
+                .extension()
+                .is_some_and(|ext| ext == "desktop")
+            && let Some(name) = self.app_desktop_path.file_name()
+        {
+            return AppIdentity(Key::DesktopId(name.to_os_string()));
+        }
+        // The executable of a bundle is inside it
+        if !cfg!(target_os = "macos")
+            && let Some(exe) = &self.app_path_exe
+        {
+            return AppIdentity(Key::Command(canonical(exe), self.launch_args.clone()));
+        }
+
+        AppIdentity(Key::Path(canonical(&self.app_desktop_path)))
+    }
+}
+
+/// `path` with symbolic links resolved, in lowercase on Windows, where
+/// paths are case-insensitive.
+fn canonical(path: &Path) -> PathBuf {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if cfg!(target_os = "windows") {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path
+    }
+}
+
+/// An [`App`] that is equal to another, and hashes the same, when they
+/// have the same [identity](App::identity), e.g., to keep apps from two
+/// scans in a `HashSet`:
+///
+/// ```no_run
+/// use applications::{ByIdentity, get_all_apps, get_default_search_paths};
+/// use std::collections::HashSet;
+///
+/// let seen: HashSet<ByIdentity> = get_all_apps(&get_default_search_paths())
+///     .unwrap()
+///     .into_iter()
+///     .map(ByIdentity::new)
+///     .collect();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByIdentity {
+    identity: AppIdentity,
+    app: App,
+}
+
+impl ByIdentity {
+    pub fn new(app: App) -> Self {
+        Self {
+            identity: app.identity(),
+            app,
+        }
+    }
+
+    pub fn identity(&self) -> &AppIdentity {
+        &self.identity
+    }
+
+    pub fn into_inner(self) -> App {
+        self.app
+    }
+}
+
+impl Deref for ByIdentity {
+    type Target = App;
+
+    fn deref(&self) -> &App {
+        &self.app
+    }
+}
+
+impl From<App> for ByIdentity {
+    fn from(app: App) -> Self {
+        Self::new(app)
+    }
+}
+
+impl PartialEq for ByIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity == other.identity
+    }
+}
+
+impl Eq for ByIdentity {}
+
+impl Hash for ByIdentity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_identity() {
+        let app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: "/opt/foo/foo.lnk".into(),
+            app_path_exe: Some("/opt/foo/bin/foo".into()),
+            icon_path: Some("/opt/foo/foo.png".into()),
+            ..Default::default()
+        };
+        let rescanned = App {
+            icon_path: Some("/opt/foo/foo-2.png".into()),
+            version: Some("2.0".to_string()),
+            ..app.clone()
+        };
+        assert_ne!(app, rescanned);
+        assert_eq!(app.identity(), rescanned.identity());
+
+        let packaged = App {
+            aumid: Some("Foo_123!App".to_string()),
+            ..app.clone()
+        };
+        let repackaged = App {
+            aumid: Some("foo_123!app".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(app.identity(), packaged.identity());
+        assert_eq!(packaged.identity(), repackaged.identity());
+
+        let set: HashSet<ByIdentity> = [app, rescanned, packaged, repackaged]
+            .into_iter()
+            .map(ByIdentity::new)
+            .collect();
+        assert_eq!(set.len(), 2);
+    }
+}
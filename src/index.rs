@@ -0,0 +1,417 @@
+//! An index of the installed apps that keeps itself up to date.
+
+#[cfg(feature = "cache")]
+use crate::AppCache;
+use crate::watcher::{Change, Closer, Watcher};
+use crate::{App, AppIdentity, Error, Result, get_all_apps};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+/// A change made to an [`AppIndex`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndexChange {
+    Added(App),
+    /// An app was installed again, e.g., upgraded, and it replaces the one
+    /// we had.
+    Updated(App),
+    Removed(App),
+}
+
+#[derive(Default)]
+struct Shared {
+    /// Apps keyed by their identity, as apps may share an
+    /// `app_desktop_path`, e.g., the Windows shortcuts with the same
+    /// working directory.
+    apps: RwLock<HashMap<AppIdentity, App>>,
+    subscribers: Mutex<Vec<Sender<IndexChange>>>,
+}
+
+impl Shared {
+    fn apply(&self, change: Change) {
+        let index_changes = {
+            let mut apps = self.apps.write().unwrap();
+            match change {
+                Change::AppInstalled { app, .. } => {
+                    let app = *app;
+                    let index_change = match apps.insert(app.identity(), app.clone()) {
+                        Some(_) => IndexChange::Updated(app),
+                        None => IndexChange::Added(app),
+                    };
+                    vec![index_change]
+                }
+                Change::AppDeleted {
+                    app: Some(app),
+                    app_path,
+                } => match apps.remove(&app.identity()) {
+                    Some(removed) => vec![IndexChange::Removed(removed)],
+                    None => remove_at(&mut apps, &app_path),
+                },
+                // Apps only registered somewhere else, e.g., in the Windows
+                // registry, are deleted by their executable
+                Change::AppDeleted {
+                    app: None,
+                    app_path,
+                } => remove_at(&mut apps, &app_path),
+                Change::AppMoved { from, app, .. } => {
+                    let app = *app;
+                    let mut index_changes = remove_at(&mut apps, &from);
+                    if let Some(replaced) = apps.insert(app.identity(), app.clone()) {
+                        index_changes.push(IndexChange::Removed(replaced));
+                    }
+                    index_changes.push(IndexChange::Added(app));
+                    index_changes
+                }
+                // Reported as installed again once the search path is back
+                Change::WatchLost { search_path } => remove_where(&mut apps, |app| {
+                    app.app_desktop_path.starts_with(&search_path)
+                }),
+            }
+        };
+
+        self.notify(index_changes);
+    }
+
+    /// Replace all apps with `apps`, e.g., the cached ones by a scan.
+    #[cfg(feature = "cache")]
+    fn replace(&self, apps: Vec<App>) {
+        let index_changes = {
+            let mut known = self.apps.write().unwrap();
+            let mut index_changes = Vec::new();
+            let mut replaced = HashMap::new();
+            for (identity, app) in keyed(apps) {
+                match known.remove(&identity) {
+                    Some(old) if old == app => {}
+                    Some(_) => index_changes.push(IndexChange::Updated(app.clone())),
+                    None => index_changes.push(IndexChange::Added(app.clone())),
+                }
+                replaced.insert(identity, app);
+            }
+            index_changes.extend(known.drain().map(|(_id, app)| IndexChange::Removed(app)));
+            *known = replaced;
+            index_changes
+        };
+
+        self.notify(index_changes);
+    }
+
+    fn notify(&self, index_changes: Vec<IndexChange>) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        for index_change in index_changes {
+            subscribers.retain(|subscriber| subscriber.send(index_change.clone()).is_ok());
+        }
+    }
+
+    fn snapshot(&self) -> Vec<App> {
+        self.apps.read().unwrap().values().cloned().collect()
+    }
+}
+
+/// `apps` keyed by their identity.
+fn keyed(apps: Vec<App>) -> HashMap<AppIdentity, App> {
+    apps.into_iter().map(|app| (app.identity(), app)).collect()
+}
+
+/// Remove the apps at `app_path`, their `app_desktop_path` or executable.
+fn remove_at(apps: &mut HashMap<AppIdentity, App>, app_path: &Path) -> Vec<IndexChange> {
+    remove_where(apps, |app| {
+        app.app_desktop_path == app_path || app.app_path_exe.as_deref() == Some(app_path)
+    })
+}
+
+fn remove_where(
+    apps: &mut HashMap<AppIdentity, App>,
+    remove: impl Fn(&App) -> bool,
+) -> Vec<IndexChange> {
+    let ids: Vec<AppIdentity> = apps
+        .iter()
+        .filter(|(_id, app)| remove(app))
+        .map(|(id, _app)| id.clone())
+        .collect();
+
+    ids.iter()
+        .filter_map(|id| apps.remove(id))
+        .map(IndexChange::Removed)
+        .collect()
+}
+
+/// The installed apps, found with [`get_all_apps`] and then kept up to date
+/// by a [`Watcher`] running on a background thread, which stops when the
+/// index is dropped.
+pub struct AppIndex {
+    shared: Arc<Shared>,
+    closer: Closer,
+}
+
+impl AppIndex {
+    /// Index the apps in `search_paths`, the ones that do not exist are
+    /// skipped.
+    pub fn new(search_paths: &[PathBuf]) -> Result<Self> {
+        let search_paths: Vec<PathBuf> = search_paths
+            .iter()
+            .filter(|path| path.is_dir())
+            .cloned()
+            .collect();
+        let shared = Arc::new(Shared::default());
+        let weak = Arc::downgrade(&shared);
+        let (ready_tx, ready_rx) = channel();
+
+        // Watchers are not necessarily `Send`, so ours lives on the thread
+        std::thread::spawn(move || {
+            let mut watcher = match Watcher::new(&search_paths) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            // Scanned once we are watching, so that nothing installed in
+            // the meantime is missed
+            let apps = match get_all_apps(&search_paths) {
+                Ok(apps) => apps,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if let Some(shared) = weak.upgrade() {
+                *shared.apps.write().unwrap() = keyed(apps);
+            }
+            let _ = ready_tx.send(Ok(watcher.closer()));
+
+            watch(&mut watcher, &weak, |_| {});
+        });
+        let closer = ready_rx.recv().map_err(|_| Error::WatcherClosed)??;
+
+        Ok(Self { shared, closer })
+    }
+
+    /// Index the apps of `cache`, starting with the cached ones, so that it
+    /// is ready without waiting for a scan. The scan runs on the background
+    /// thread, what it finds changed is sent to the subscribers, then the
+    /// cache is saved after every change.
+    #[cfg(feature = "cache")]
+    pub fn with_cache(cache: AppCache) -> Result<Self> {
+        let Some(cached_apps) = cache.load() else {
+            let index = Self::new(cache.search_paths())?;
+            let _ = cache.save(&index.snapshot());
+            return Ok(index.keep_saved(cache));
+        };
+
+        let search_paths: Vec<PathBuf> = cache
+            .search_paths()
+            .iter()
+            .filter(|path| path.is_dir())
+            .cloned()
+            .collect();
+        let shared = Arc::new(Shared {
+            apps: RwLock::new(keyed(cached_apps)),
+            subscribers: Mutex::default(),
+        });
+        let weak = Arc::downgrade(&shared);
+        let (ready_tx, ready_rx) = channel();
+
+        std::thread::spawn(move || {
+            let mut watcher = match Watcher::new(&search_paths) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(watcher.closer()));
+
+            // The cached apps stay if the scan fails
+            if let Ok(apps) = get_all_apps(&search_paths)
+                && let Some(shared) = weak.upgrade()
+            {
+                shared.replace(apps);
+                let _ = cache.save(&shared.snapshot());
+            }
+            watch(&mut watcher, &weak, |shared| {
+                let _ = cache.save(&shared.snapshot());
+            });
+        });
+        let closer = ready_rx.recv().map_err(|_| Error::WatcherClosed)??;
+
+        Ok(Self { shared, closer })
+    }
+
+    /// Save `cache` whenever `index` changes.
+    #[cfg(feature = "cache")]
+    fn keep_saved(self, cache: AppCache) -> Self {
+        let changes = self.subscribe();
+        let weak = Arc::downgrade(&self.shared);
+        std::thread::spawn(move || {
+            while changes.recv().is_ok() {
+                // Batches of changes are saved once
+                while changes.try_recv().is_ok() {}
+                let Some(shared) = weak.upgrade() else {
+                    return;
+                };
+                let _ = cache.save(&shared.snapshot());
+            }
+        });
+
+        self
+    }
+
+    /// The apps currently installed.
+    pub fn snapshot(&self) -> Vec<App> {
+        self.shared.snapshot()
+    }
+
+    /// The app with `identity`.
+    pub fn get(&self, identity: &AppIdentity) -> Option<App> {
+        self.shared.apps.read().unwrap().get(identity).cloned()
+    }
+
+    /// An app found at `id`, its `app_desktop_path`, which more than one
+    /// app may have, e.g., the Windows shortcuts with the same working
+    /// directory, see [`AppIndex::get`].
+    pub fn get_by_id<P: AsRef<Path>>(&self, id: P) -> Option<App> {
+        let id = id.as_ref();
+        self.shared
+            .apps
+            .read()
+            .unwrap()
+            .values()
+            .find(|app| app.app_desktop_path == id)
+            .cloned()
+    }
+
+    /// Changes made to the index from now on.
+    pub fn subscribe(&self) -> Receiver<IndexChange> {
+        let (tx, rx) = channel();
+        self.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+impl Drop for AppIndex {
+    fn drop(&mut self) {
+        self.closer.close();
+    }
+}
+
+/// Apply the changes of `watcher` until it is closed, `changed` is called
+/// after each batch.
+fn watch(watcher: &mut Watcher, weak: &Weak<Shared>, changed: impl Fn(&Shared)) {
+    while let Ok(changes) = watcher.recv() {
+        let Some(shared) = weak.upgrade() else {
+            return;
+        };
+        for change in changes {
+            shared.apply(change);
+        }
+        changed(&shared);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_apply_changes() {
+        let shared = Shared::default();
+        let (tx, rx) = channel();
+        shared.subscribers.lock().unwrap().push(tx);
+
+        let app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: PathBuf::from("/apps/foo.desktop"),
+            app_path_exe: Some(PathBuf::from("/bin/foo")),
+            ..Default::default()
+        };
+        let installed = || Change::AppInstalled {
+            app_path: app.app_desktop_path.clone(),
+            app: Box::new(app.clone()),
+            search_path: None,
+        };
+        shared.apply(installed());
+        shared.apply(installed());
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Added(app.clone())));
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Updated(app.clone())));
+
+        shared.apply(Change::AppDeleted {
+            app_path: PathBuf::from("/bin/foo"),
+            app: None,
+        });
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Removed(app)));
+        assert!(shared.apps.read().unwrap().is_empty());
+    }
+
+    // Bundles are identified by their path on macOS
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_apps_sharing_a_working_directory() {
+        let shared = Shared::default();
+        let (tx, rx) = channel();
+        shared.subscribers.lock().unwrap().push(tx);
+
+        // Shortcuts to two programs of the same folder
+        let app = |name: &str| App {
+            name: name.to_string(),
+            app_desktop_path: PathBuf::from("/windows/system32"),
+            app_path_exe: Some(PathBuf::from(format!("/windows/system32/{name}.exe"))),
+            ..Default::default()
+        };
+        let (notepad, calc) = (app("notepad"), app("calc"));
+        for app in [&notepad, &calc] {
+            shared.apply(Change::AppInstalled {
+                app_path: PathBuf::from(format!("/start menu/{}.lnk", app.name)),
+                app: Box::new(app.clone()),
+                search_path: None,
+            });
+        }
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Added(notepad.clone())));
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Added(calc.clone())));
+        assert_eq!(shared.snapshot().len(), 2);
+
+        shared.apply(Change::AppDeleted {
+            app_path: PathBuf::from("/start menu/calc.lnk"),
+            app: Some(Box::new(calc.clone())),
+        });
+        assert_eq!(rx.try_recv(), Ok(IndexChange::Removed(calc)));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(shared.snapshot(), [notepad]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_index_follows_changes() {
+        let temp_dir = TempDir::new("index");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let write_entry = |name: &str| {
+            let path = root.join(format!("{name}.desktop"));
+            std::fs::write(
+                &path,
+                format!(
+                    "[Desktop Entry]\nType=Application\nName={name}\nExec=sh\nIcon={}",
+                    root.join("icon.png").display()
+                ),
+            )
+            .unwrap();
+            path
+        };
+        let foo = write_entry("Foo");
+
+        let index = AppIndex::new(std::slice::from_ref(&root)).unwrap();
+        assert_eq!(index.get_by_id(&foo).unwrap().name, "Foo");
+
+        let changes = index.subscribe();
+        let bar = write_entry("Bar");
+        let added = changes
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap();
+        assert!(matches!(added, IndexChange::Added(app) if app.app_desktop_path == bar));
+        assert_eq!(index.snapshot().len(), 2);
+
+        drop(index);
+    }
+}
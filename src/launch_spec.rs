@@ -0,0 +1,136 @@
+//! [`App::launch_spec`], the command line an app is started with, for
+//! starting it with files or URLs.
+
+use crate::Result;
+use crate::common::App;
+use crate::platforms::launch_spec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// An argument of a [`LaunchSpec`]: one to pass as it is, or the place of
+/// the files or URLs to open, the field codes of a desktop entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LaunchArg {
+    Literal(String),
+    /// `%f`, a file. Apps with it open one file per launch.
+    File,
+    /// `%F`, all the files.
+    Files,
+    /// `%u`, a file or URL. Apps with it open one per launch.
+    Url,
+    /// `%U`, all the files and URLs.
+    Urls,
+}
+
+/// How an app is started, see [`App::launch_spec`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LaunchSpec {
+    /// A path, or a name to find in `$PATH`.
+    pub program: PathBuf,
+    pub args: Vec<LaunchArg>,
+    /// The directory to start it in, the `Path` of a desktop entry or the
+    /// start-in folder of a shortcut.
+    pub working_dir: Option<PathBuf>,
+    /// Whether it needs a terminal to run in, `Terminal=true` of a desktop
+    /// entry.
+    pub terminal: bool,
+}
+
+impl App {
+    /// The program and arguments the app is started with, from the `Exec`
+    /// of its desktop entry on Linux and the target of its shortcut on
+    /// Windows, with the field codes of the files and URLs left for the
+    /// caller to fill in with [`LaunchSpec::args_with`]:
+    ///
+    /// ```no_run
+    /// # let app = applications::App::default();
+    /// let spec = app.launch_spec().unwrap();
+    /// spec.to_command(&["https://example.org"]).spawn().unwrap();
+    /// ```
+    ///
+    /// The desktop entry is read each time. Packaged apps of Windows, which
+    /// are activated rather than run, fail with
+    /// [`Error::Missing`](crate::Error::Missing), and macOS bundles with
+    /// [`Error::UnsupportedPlatform`](crate::Error::UnsupportedPlatform).
+    pub fn launch_spec(&self) -> Result<LaunchSpec> {
+        launch_spec(self)
+    }
+}
+
+impl LaunchSpec {
+    /// The arguments, with `targets`, files or URLs, in their places: all
+    /// of them for [`LaunchArg::Files`] and [`LaunchArg::Urls`], the first
+    /// for [`LaunchArg::File`] and [`LaunchArg::Url`]. They are appended if
+    /// the spec has no place for them.
+    pub fn args_with(&self, targets: &[&str]) -> Vec<String> {
+        let mut args = Vec::with_capacity(self.args.len() + targets.len());
+        let mut placed = false;
+        for arg in &self.args {
+            match arg {
+                LaunchArg::Literal(arg) => args.push(arg.clone()),
+                LaunchArg::File | LaunchArg::Url => {
+                    args.extend(targets.first().map(|target| target.to_string()));
+                    placed = true;
+                }
+                LaunchArg::Files | LaunchArg::Urls => {
+                    args.extend(targets.iter().map(|target| target.to_string()));
+                    placed = true;
+                }
+            }
+        }
+        if !placed {
+            args.extend(targets.iter().map(|target| target.to_string()));
+        }
+
+        args
+    }
+
+    /// A command that starts the app with `targets`, see
+    /// [`LaunchSpec::args_with`], ready to be spawned.
+    pub fn to_command(&self, targets: &[&str]) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(self.args_with(targets));
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_with() {
+        let spec = |args: Vec<LaunchArg>| LaunchSpec {
+            program: "foo".into(),
+            args,
+            working_dir: None,
+            terminal: false,
+        };
+        let literal = |arg: &str| LaunchArg::Literal(arg.to_string());
+
+        let files = spec(vec![
+            literal("--new-window"),
+            LaunchArg::Files,
+            literal("--"),
+        ]);
+        assert_eq!(
+            files.args_with(&["a.txt", "b.txt"]),
+            ["--new-window", "a.txt", "b.txt", "--"]
+        );
+        assert_eq!(files.args_with(&[]), ["--new-window", "--"]);
+
+        let url = spec(vec![LaunchArg::Url]);
+        assert_eq!(url.args_with(&["https://a", "https://b"]), ["https://a"]);
+
+        let shortcut = spec(vec![literal("--profile")]);
+        assert_eq!(shortcut.args_with(&["a.txt"]), ["--profile", "a.txt"]);
+    }
+}
@@ -1,8 +1,79 @@
+#[cfg(feature = "cache")]
+mod cache;
+mod collate;
 mod common;
+mod error;
+mod finder;
+mod fs;
+mod handlers;
+#[cfg(feature = "icons")]
+mod icon_cache;
+mod identity;
+#[cfg(feature = "watcher")]
+mod index;
+mod launch_spec;
+mod metadata;
+mod pattern;
 // difference platforms may have different implementation and signatures for each function, so platforms will not be public
 mod platforms;
+#[cfg(feature = "tauri-plugin")]
+pub mod plugin;
+mod process;
+mod search;
+#[cfg(feature = "json")]
+mod snapshot;
+#[cfg(test)]
+mod test_utils;
+mod trace;
+mod uninstall;
 mod utils;
+#[cfg(feature = "watcher")]
 pub mod watcher;
 
-pub use common::{App, AppTrait};
-pub use platforms::{get_all_apps, get_default_search_paths};
+#[cfg(feature = "cache")]
+pub use cache::AppCache;
+pub use collate::Collator;
+pub use common::{
+    App, AppKind, AppTrait, DiscoverySource, Icon, InstallScope, InstallSource, RunningApp,
+    ScanError, ScanReport, SignatureInfo, SignatureStatus, UninstallCommand, normalize_locale,
+};
+pub use error::{Error, Result};
+pub use finder::{AppFinder, AppOrder, Dedup, PathOptions, Provider, ScanProgress, SymlinkPolicy};
+pub use handlers::{
+    DefaultHandlers, get_apps_for_file, get_default_handlers, set_default_app_for_extension,
+};
+#[cfg(feature = "icons")]
+pub use icon_cache::IconCache;
+pub use identity::{AppIdentity, ByIdentity};
+#[cfg(feature = "watcher")]
+pub use index::{AppIndex, IndexChange};
+pub use launch_spec::{LaunchArg, LaunchSpec};
+pub use metadata::{AppMetadata, SpotlightMetadata};
+pub use platforms::{
+    find_app, get_all_apps, get_all_apps_with_report, get_autostart_apps, get_default_search_paths,
+};
+pub use process::CancelToken;
+pub use search::AppSearch;
+#[cfg(feature = "json")]
+pub use snapshot::{AppSnapshot, ProviderStart, SnapshotError};
+pub use uninstall::UninstallAction;
+
+#[cfg(target_os = "linux")]
+pub use platforms::{
+    AppStreamIndex, AppStreamMetadata, get_default_appstream_paths, get_frontmost_application,
+    get_running_apps, is_stale, launch_action,
+};
+
+#[cfg(target_os = "macos")]
+pub use platforms::get_pinned_apps;
+#[cfg(all(any(target_os = "macos", target_os = "windows"), feature = "icons"))]
+pub use platforms::load_icon;
+
+#[cfg(target_os = "windows")]
+pub use platforms::{
+    Architecture, ExecutionAlias, RegistryApp, apply_winget_ids, get_apps_folder_apps,
+    get_chocolatey_apps, get_exe_architecture, get_execution_aliases, get_frontmost_application,
+    get_native_architecture, get_pinned_apps, get_portable_apps, get_registry_apps,
+    get_running_apps, get_scoop_apps, get_signature_info, get_uwp_apps, get_uwp_logo, get_wsa_apps,
+    get_wsl_desktop_entry, is_noise_app,
+};
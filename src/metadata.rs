@@ -0,0 +1,236 @@
+//! [`App::metadata`], what is slow to read for every app of a scan, read
+//! for one app when it is needed.
+
+use crate::common::{App, SignatureInfo};
+use crate::platforms::read_metadata;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+/// What [`App::metadata`] reads.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AppMetadata {
+    /// See [`App::localized_app_names`].
+    pub localized_app_names: BTreeMap<String, String>,
+    /// See [`App::version`].
+    pub version: Option<String>,
+    /// See [`App::publisher`].
+    pub publisher: Option<String>,
+    /// Bytes the app takes on disk: its `.app` bundle on macOS, its
+    /// executable elsewhere.
+    pub size: Option<u64>,
+    /// The signature of the executable. Windows only.
+    pub signature: Option<SignatureInfo>,
+    /// What Spotlight knows about the bundle. macOS only.
+    pub spotlight: Option<SpotlightMetadata>,
+}
+
+/// The Spotlight attributes of an app bundle, for searching apps by more
+/// than their names.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpotlightMetadata {
+    /// `kMDItemKeywords`, e.g., `["text", "editor"]`.
+    pub keywords: Vec<String>,
+    /// `kMDItemFinderComment`, the comment of the Get Info window.
+    pub comment: Option<String>,
+    /// `kMDItemAppStoreCategory`, e.g., `Productivity`.
+    pub app_store_category: Option<String>,
+    /// `kMDItemCopyright`, e.g., `Copyright © 2024 Foo, Inc.`.
+    pub copyright: Option<String>,
+}
+
+/// How many apps [`App::metadata`] keeps the metadata of.
+const CACHE_CAPACITY: usize = 256;
+
+/// The paths metadata is read from: the desktop file, bundle or shortcut
+/// and the executable, which is not in the shortcut on Windows.
+type CacheKey = (PathBuf, Option<PathBuf>);
+
+/// The modification times of the [`CacheKey`] paths.
+type Modified = (Option<SystemTime>, Option<SystemTime>);
+
+/// Metadata already read, with the modification times of its paths when
+/// it was read, the least recently used dropped once there are more than
+/// `capacity`.
+struct Cache {
+    capacity: usize,
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Counts the lookups, to tell which entry was used least recently.
+    clock: u64,
+}
+
+struct CacheEntry {
+    modified: Modified,
+    metadata: AppMetadata,
+    used: u64,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// The metadata of `key`, read with `read` unless it has been since its
+    /// paths were last `modified`.
+    fn get_or_read(
+        &mut self,
+        key: &CacheKey,
+        modified: Modified,
+        read: impl FnOnce() -> AppMetadata,
+    ) -> AppMetadata {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(key)
+            && entry.modified == modified
+        {
+            entry.used = self.clock;
+            return entry.metadata.clone();
+        }
+
+        let metadata = read();
+        self.entries.insert(
+            key.clone(),
+            CacheEntry {
+                modified,
+                metadata: metadata.clone(),
+                used: self.clock,
+            },
+        );
+        if self.entries.len() > self.capacity
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+
+        metadata
+    }
+}
+
+static CACHE: LazyLock<Mutex<Cache>> = LazyLock::new(|| Mutex::new(Cache::new(CACHE_CAPACITY)));
+
+impl App {
+    /// Read the metadata of the app a scan with
+    /// [`AppFinder::lazy_metadata`](crate::AppFinder::lazy_metadata)
+    /// skips, e.g., the localized names of a macOS bundle, which are in
+    /// all of its `.lproj` directories, and what no scan reads, the
+    /// Spotlight attributes of a bundle. It is read once, until the
+    /// desktop file, bundle or shortcut, or the executable, is modified,
+    /// and kept for the most recently used apps.
+    pub fn metadata(&self) -> AppMetadata {
+        let modified = |path: &Path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        let key = (self.app_desktop_path.clone(), self.app_path_exe.clone());
+        let stamps = (
+            modified(&self.app_desktop_path),
+            self.app_path_exe.as_deref().and_then(modified),
+        );
+
+        CACHE
+            .lock()
+            .unwrap()
+            .get_or_read(&key, stamps, || read_metadata(self))
+    }
+
+    /// Fill in the localized names, version and publisher from
+    /// [`App::metadata`], unless the app has them already.
+    pub fn load_metadata(&mut self) {
+        let metadata = self.metadata();
+        for (locale, name) in metadata.localized_app_names {
+            self.localized_app_names.entry(locale).or_insert(name);
+        }
+        self.version = self.version.take().or(metadata.version);
+        self.publisher = self.publisher.take().or(metadata.publisher);
+    }
+}
+
+/// The size of `path`, of all files in it if it is a directory.
+pub(crate) fn disk_size(path: &Path) -> Option<u64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return Some(metadata.len());
+    }
+
+    Some(
+        walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_disk_size() {
+        let temp_dir = TempDir::new("size");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("Contents")).unwrap();
+        std::fs::write(dir.join("Contents/a"), [0; 10]).unwrap();
+        std::fs::write(dir.join("b"), [0; 5]).unwrap();
+        assert_eq!(disk_size(&dir), Some(15));
+        assert_eq!(disk_size(&dir.join("b")), Some(5));
+        assert_eq!(disk_size(&dir.join("missing")), None);
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = Cache::new(2);
+        let metadata = |version: &str| AppMetadata {
+            version: Some(version.to_string()),
+            ..Default::default()
+        };
+        let key = |name: &str| (PathBuf::from(format!("/apps/{name}")), None);
+        let then = (Some(SystemTime::UNIX_EPOCH), None);
+        let now = (
+            Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1)),
+            None,
+        );
+
+        assert_eq!(
+            cache.get_or_read(&key("foo"), then, || metadata("1")),
+            metadata("1")
+        );
+        // Read once
+        assert_eq!(
+            cache.get_or_read(&key("foo"), then, || metadata("2")),
+            metadata("1")
+        );
+        // Read again once modified
+        assert_eq!(
+            cache.get_or_read(&key("foo"), now, || metadata("2")),
+            metadata("2")
+        );
+
+        // The least recently used is dropped
+        cache.get_or_read(&key("bar"), then, || metadata("1"));
+        cache.get_or_read(&key("foo"), now, || metadata("3"));
+        cache.get_or_read(&key("baz"), then, || metadata("1"));
+        assert_eq!(cache.entries.len(), 2);
+        assert!(!cache.entries.contains_key(&key("bar")));
+        assert_eq!(
+            cache.get_or_read(&key("foo"), now, || metadata("3")),
+            metadata("2")
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Glob patterns of [`AppFinder::include_pattern`](crate::AppFinder::include_pattern) and
+//! [`AppFinder::exclude_pattern`](crate::AppFinder::exclude_pattern).
+
+use std::path::{Path, PathBuf};
+
+/// A glob pattern, matched ignoring case: `*` stands for anything but a
+/// `/`, `**` for anything, `?` for any character but a `/`. A pattern with
+/// a `/` is matched against whole paths, e.g., `/Applications/**`, others
+/// against names, e.g., `*uninstall*`. `\` is taken for `/`, so that
+/// Windows paths can be written as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern {
+    chars: Vec<char>,
+    is_path: bool,
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = normalize(pattern).chars().collect();
+        let is_path = chars.contains(&'/');
+        Self { chars, is_path }
+    }
+
+    /// A path pattern matches all of `path`, a name pattern its file name.
+    pub(crate) fn matches_path(&self, path: &Path) -> bool {
+        if self.is_path {
+            self.matches(&path.to_string_lossy())
+        } else {
+            path.file_name()
+                .is_some_and(|name| self.matches(&name.to_string_lossy()))
+        }
+    }
+
+    /// Whether a name pattern matches `name`, a path pattern never does.
+    pub(crate) fn matches_name(&self, name: &str) -> bool {
+        !self.is_path && self.matches(name)
+    }
+
+    /// Whether what is inside the directory `dir` may match a path
+    /// pattern, judging by the part of the pattern before its first
+    /// wildcard. Always true for name patterns.
+    pub(crate) fn may_match_in(&self, dir: &Path) -> bool {
+        if !self.is_path {
+            return true;
+        }
+        let literal: String = self
+            .chars
+            .iter()
+            .take_while(|c| !matches!(c, '*' | '?'))
+            .collect();
+        // Only whole components of the literal part are certain
+        let prefix = match literal.rfind('/') {
+            Some(end) if self.chars.len() > literal.len() => &literal[..end],
+            _ => literal.as_str(),
+        };
+        let prefix = PathBuf::from(prefix);
+        let dir = PathBuf::from(normalize(&dir.to_string_lossy()));
+        dir.starts_with(&prefix) || prefix.starts_with(&dir)
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = normalize(text).chars().collect();
+        glob(&self.chars, &text)
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase().replace('\\', "/")
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `a/**/b` matches `a/b` too
+            let without_slash = rest.strip_prefix(&['/']);
+            (0..=text.len()).any(|start| {
+                glob(rest, &text[start..])
+                    || without_slash.is_some_and(|rest| glob(rest, &text[start..]))
+            })
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&start| start == 0 || text[start - 1] != '/')
+            .any(|start| glob(rest, &text[start..])),
+        ['?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, text)| *c != '/' && glob(rest, text)),
+        [c, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(first, text)| first == c && glob(rest, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern() {
+        let uninstall = Pattern::new("*uninstall*");
+        assert!(!uninstall.is_path);
+        assert!(uninstall.matches_name("Uninstall Foo"));
+        assert!(uninstall.matches_path(Path::new("/opt/foo/uninstall.desktop")));
+        assert!(!uninstall.matches_path(Path::new("/opt/uninstall/foo.desktop")));
+        assert!(uninstall.may_match_in(Path::new("/anywhere")));
+
+        let applications = Pattern::new("/Applications/**");
+        assert!(applications.is_path);
+        assert!(!applications.matches_name("Applications"));
+        assert!(applications.matches_path(Path::new("/Applications/Foo.app")));
+        assert!(applications.matches_path(Path::new("/applications/Tools/Foo.app")));
+        assert!(!applications.matches_path(Path::new("/System/Applications/Foo.app")));
+        assert!(applications.may_match_in(Path::new("/")));
+        assert!(applications.may_match_in(Path::new("/Applications/Tools")));
+        assert!(!applications.may_match_in(Path::new("/System")));
+
+        let desktop = Pattern::new("/usr/share/**/org.*.desktop");
+        assert!(desktop.matches_path(Path::new("/usr/share/org.foo.desktop")));
+        assert!(desktop.matches_path(Path::new("/usr/share/applications/org.foo.desktop")));
+        assert!(!desktop.matches_path(Path::new("/usr/share/applications/org/foo.desktop")));
+        assert!(desktop.may_match_in(Path::new("/usr/share/applications")));
+        assert!(!desktop.may_match_in(Path::new("/usr/local")));
+
+        let windows = Pattern::new(r"C:\Program Files\*\?oo.exe");
+        assert!(windows.matches_path(Path::new("C:/Program Files/Foo/foo.exe")));
+        assert!(!windows.matches_path(Path::new("C:/Program Files/Foo/Bar/foo.exe")));
+    }
+}
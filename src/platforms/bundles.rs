@@ -0,0 +1,176 @@
+//! Where apps are in the bundles and volumes of macOS, read through a
+//! [`FileSystem`], so that it is tested on every platform.
+
+use crate::finder::AppFinder;
+use crate::fs::{FileKind, FileSystem, subdirs};
+use std::path::{Path, PathBuf};
+
+/// Where bundles embed other apps.
+pub(crate) const NESTED_APP_DIRS: [&str; 2] = ["Contents/Applications", "Contents/SharedSupport"];
+
+/// How many directories below [`NESTED_APP_DIRS`] embedded apps may be.
+pub(crate) const NESTED_APP_DEPTH: usize = 3;
+
+/// The bundles embedded in `bundle`, and those embedded in them, for
+/// [`AppFinder::nested_apps`].
+pub(crate) fn nested_bundles(fs: &dyn FileSystem, bundle: &Path) -> Vec<PathBuf> {
+    fn collect(fs: &dyn FileSystem, dir: &Path, depth: usize, bundles: &mut Vec<PathBuf>) {
+        for subdir in subdirs(fs, dir) {
+            if subdir.extension().is_some_and(|ext| ext == "app") {
+                let nested = nested_bundles(fs, &subdir);
+                bundles.push(subdir);
+                bundles.extend(nested);
+            } else if depth > 1 {
+                collect(fs, &subdir, depth - 1, bundles);
+            }
+        }
+    }
+
+    let mut bundles = Vec::new();
+    for dir in NESTED_APP_DIRS {
+        collect(fs, &bundle.join(dir), NESTED_APP_DEPTH, &mut bundles);
+    }
+
+    bundles
+}
+
+/// Where Xcode keeps its own apps, e.g., Accessibility Inspector in
+/// `Contents/Applications` and Simulator in `Contents/Developer/Applications`.
+pub(crate) const XCODE_APP_DIRS: [&str; 2] =
+    ["Contents/Applications", "Contents/Developer/Applications"];
+
+/// The apps inside each `Xcode*.app` of the search paths, e.g., a beta
+/// next to the release.
+pub(crate) fn developer_tool_bundles(finder: &AppFinder) -> Vec<PathBuf> {
+    let fs = finder.fs();
+    let is_xcode = |path: &PathBuf| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("Xcode") && name.ends_with(".app"))
+    };
+
+    finder
+        .search_paths()
+        .into_iter()
+        .flat_map(|search_path| subdirs(fs, search_path))
+        .filter(is_xcode)
+        .flat_map(|xcode| {
+            XCODE_APP_DIRS
+                .iter()
+                .flat_map(|dir| subdirs(fs, &xcode.join(dir)))
+                .collect::<Vec<_>>()
+        })
+        .filter(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .collect()
+}
+
+/// The volumes mounted in `/Volumes` of the root of `finder`, without the
+/// startup disk, which is a link to `/`, and hidden ones, e.g., of Time
+/// Machine.
+pub(crate) fn mounted_volumes(finder: &AppFinder) -> Vec<PathBuf> {
+    let root = finder.fs().root().unwrap_or(Path::new("/"));
+    let mut volumes: Vec<PathBuf> = finder
+        .fs()
+        .read_dir(&root.join("Volumes"))
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.kind == Some(FileKind::Dir) && !entry.is_symlink)
+        .map(|entry| entry.path)
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    volumes.sort();
+
+    volumes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MemoryFs;
+
+    #[test]
+    fn test_developer_tool_bundles() {
+        let finder = AppFinder::with_paths(["/Applications"]).file_system(
+            MemoryFs::new([
+                (
+                    "/Applications/Xcode.app/Contents/Applications/Accessibility Inspector.app/Contents/Info.plist",
+                    "",
+                ),
+                (
+                    "/Applications/Xcode-beta.app/Contents/Developer/Applications/Simulator.app/Contents/Info.plist",
+                    "",
+                ),
+                (
+                    "/Applications/Xcode.app/Contents/Applications/README",
+                    "",
+                ),
+                (
+                    "/Applications/Other.app/Contents/Applications/Tool.app/Contents/Info.plist",
+                    "",
+                ),
+            ]),
+        );
+        assert_eq!(
+            developer_tool_bundles(&finder),
+            [
+                PathBuf::from(
+                    "/Applications/Xcode-beta.app/Contents/Developer/Applications/Simulator.app"
+                ),
+                PathBuf::from(
+                    "/Applications/Xcode.app/Contents/Applications/Accessibility Inspector.app"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mounted_volumes() {
+        let finder = AppFinder::with_paths(Vec::<PathBuf>::new()).file_system(MemoryFs::new([
+            ("/Volumes/SSD/Applications/Foo.app/Contents/Info.plist", ""),
+            ("/Volumes/NAS/Applications/Bar.app/Contents/Info.plist", ""),
+            ("/Volumes/.timemachine/backup/Info.plist", ""),
+            ("/Volumes/readme.txt", ""),
+        ]));
+        assert_eq!(
+            mounted_volumes(&finder),
+            [PathBuf::from("/Volumes/NAS"), PathBuf::from("/Volumes/SSD")]
+        );
+    }
+
+    #[test]
+    fn test_nested_bundles() {
+        let suite = Path::new("/Applications/Suite.app");
+        let fs = MemoryFs::new([
+            ("/Applications/Suite.app/Contents/Info.plist", ""),
+            (
+                "/Applications/Suite.app/Contents/Applications/Tool.app/Contents/Info.plist",
+                "",
+            ),
+            (
+                "/Applications/Suite.app/Contents/Applications/Tool.app/Contents/SharedSupport/Helper.app/Contents/Info.plist",
+                "",
+            ),
+            (
+                "/Applications/Suite.app/Contents/SharedSupport/Plug-Ins/Editor.app/Contents/Info.plist",
+                "",
+            ),
+            (
+                "/Applications/Suite.app/Contents/Frameworks/Hidden.app/Contents/Info.plist",
+                "",
+            ),
+        ]);
+        assert_eq!(
+            nested_bundles(&fs, suite),
+            [
+                suite.join("Contents/Applications/Tool.app"),
+                suite.join("Contents/Applications/Tool.app/Contents/SharedSupport/Helper.app"),
+                suite.join("Contents/SharedSupport/Plug-Ins/Editor.app"),
+            ]
+        );
+    }
+}
@@ -1,197 +1,2364 @@
 use crate::AppTrait;
-use crate::common::App;
-use anyhow::Result;
-use freedesktop_file_parser::{EntryType, parse};
-use serde_derive::{Deserialize, Serialize};
+use crate::common::{
+    App, AppKind, DiscoverySource, InstallScope, RunningApp, ScanReport, UninstallCommand,
+    normalize_locale,
+};
+use crate::finder::{AppFinder, MimeTypeLists, Provider, par_map};
+use crate::fs::{DirEntry, FileKind, FileSystem, RealFs, RootedFs, rebase, subdirs};
+use crate::handlers::{DefaultHandlers, OpenTarget};
+use crate::launch_spec::{LaunchArg, LaunchSpec};
+use crate::metadata::{AppMetadata, disk_size};
+use crate::process::spawn_detached;
+use crate::trace::debug;
+use crate::uninstall::UninstallAction;
+use crate::utils::linux::{
+    ExecContext, MimeApps, exec_program, exec_template, expand_exec_line, find_in_path,
+    is_executable_file, mime_types_of_extension, read_appstream_dir, read_toplevels,
+    split_exec_line, wayland_socket, with_mime_ancestors,
+};
+use crate::{Error, Result};
+use freedesktop_file_parser::{DesktopFile, EntryType, IconString, parse};
 use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
-use walkdir::WalkDir;
+use std::sync::{LazyLock, Mutex};
 
 const FLATPAK_GLOBAL_APP_PATH: &str = "/var/lib/flatpak/app";
-static FLATPAK_PERSONAL_APP_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
-    let home_dir =
-        PathBuf::from(std::env::var_os("HOME").expect("environment variable $HOME not found"));
-    home_dir.join(".local/share/flatpak/app")
+const SNAP_DESKTOP_PATH: &str = "/var/lib/snapd/desktop/applications";
+/// Where dpkg lists the files of each package, in `<package>.list`.
+const DPKG_INFO_PATH: &str = "/var/lib/dpkg/info";
+static FLATPAK_PERSONAL_APP_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
+    let home_dir = PathBuf::from(std::env::var_os("HOME")?);
+    Some(home_dir.join(".local/share/flatpak/app"))
 });
 
-#[derive(Debug, PartialEq, Clone, Default, Eq, Hash, Serialize, Deserialize)]
-pub struct AppIcon {
-    name: String,
-    path: PathBuf,
-    dimensions: Option<u16>,
-}
+pub use crate::utils::linux::AppStreamMetadata;
 
+#[cfg(any(feature = "watcher", test))]
 pub(crate) fn parse_desktop_file_content(
     content: &str,
 ) -> Option<(String, BTreeMap<String, String>, Option<PathBuf>)> {
     // When parsing fails, we return None rather than erroring out
     // Because not everybody obeys the rules.
-    let desktop_file = parse(content).ok()?;
-    let desktop_file_entry = desktop_file.entry;
+    parse_desktop_file(&parse(content).ok()?, false, &RealFs)
+}
+
+/// [`parse_desktop_file_content`] of a parsed desktop file, keeping the
+/// entries with `NoDisplay` if `include_no_display` is set, with the icon
+/// found in `fs`.
+fn parse_desktop_file(
+    desktop_file: &DesktopFile,
+    include_no_display: bool,
+    fs: &dyn FileSystem,
+) -> Option<(String, BTreeMap<String, String>, Option<PathBuf>)> {
+    let desktop_file_entry = &desktop_file.entry;
 
-    let EntryType::Application(app_fields) = desktop_file_entry.entry_type else {
+    let EntryType::Application(app_fields) = &desktop_file_entry.entry_type else {
         return None;
     };
 
     let no_display = desktop_file_entry.no_display.unwrap_or(false);
 
-    if no_display {
+    if no_display && !include_no_display {
+        return None;
+    }
+
+    // Hidden entries should be treated as if they did not exist, this is
+    // how users disable autostart entries and uninstalled system entries.
+    if desktop_file_entry.hidden.unwrap_or(false) {
+        return None;
+    }
+
+    app_fields.exec.as_ref()?;
+
+    let icon = desktop_file_entry.icon.as_ref()?;
+
+    let name = desktop_file_entry.name.default.clone();
+    let localized_names = if cfg!(feature = "localization") {
+        desktop_file_entry
+            .name
+            .variants
+            .iter()
+            .filter_map(|(locale, name)| Some((normalize_locale(locale)?, name.clone())))
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+
+    Some((name, localized_names, icon_path(icon, fs)))
+}
+
+/// The directories of `$PATH` on most systems, for the programs of a system
+/// below a root, whose `$PATH` is not known.
+const ROOT_PATH: [&str; 6] = [
+    "usr/local/sbin",
+    "usr/local/bin",
+    "usr/sbin",
+    "usr/bin",
+    "sbin",
+    "bin",
+];
+
+/// Where the icons of apps are below a root, the larger first: those of
+/// the `hicolor` theme, which every theme falls back to, and the pixmaps.
+const ROOT_ICON_DIRS: [&str; 6] = [
+    "usr/share/icons/hicolor/scalable/apps",
+    "usr/share/icons/hicolor/256x256/apps",
+    "usr/share/icons/hicolor/128x128/apps",
+    "usr/share/icons/hicolor/64x64/apps",
+    "usr/share/icons/hicolor/48x48/apps",
+    "usr/share/pixmaps",
+];
+
+/// The file of `icon`, either its path or found in the icon theme.
+/// [`IconString::get_icon_path`] reads all of the file to tell whether it
+/// exists, so the path is checked here first.
+fn icon_path(icon: &IconString, fs: &dyn FileSystem) -> Option<PathBuf> {
+    if let Some(root) = fs.root() {
+        let candidates = if icon.content.starts_with('/') {
+            vec![rebase(root, Path::new(&icon.content))]
+        } else {
+            ROOT_ICON_DIRS
+                .iter()
+                .flat_map(|dir| {
+                    ["png", "svg", "xpm"].map(|extension| {
+                        root.join(dir).join(format!("{}.{extension}", icon.content))
+                    })
+                })
+                .collect()
+        };
+        return candidates
+            .iter()
+            .find(|candidate| fs.is_file(candidate))
+            .and_then(|candidate| fs.canonicalize(candidate).ok());
+    }
+    let path = Path::new(&icon.content);
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+
+    icon.get_icon_path()
+}
+
+/// Keys of a desktop entry that are not part of [`App`] but needed to
+/// run or identify the application.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct DesktopEntryDetails {
+    pub(crate) name: String,
+    /// Raw value of the `Icon` key.
+    pub(crate) icon: Option<String>,
+    pub(crate) exec: Option<String>,
+    pub(crate) try_exec: Option<String>,
+    /// Working directory to run the program in.
+    pub(crate) path: Option<String>,
+    pub(crate) startup_wm_class: Option<String>,
+    pub(crate) dbus_activatable: bool,
+    /// Whether the program runs in a terminal.
+    pub(crate) terminal: bool,
+    /// Values of the `Categories` key, e.g., `["Utility", "TextEditor"]`.
+    pub(crate) categories: Vec<String>,
+    /// Values of the `MimeType` key, the types the app opens, e.g.,
+    /// `["text/plain", "x-scheme-handler/zed"]`.
+    pub(crate) mime_types: Vec<String>,
+    /// `Exec` of the desktop actions, keyed by the action identifier.
+    pub(crate) actions: HashMap<String, Option<String>>,
+}
+
+pub(crate) fn parse_desktop_entry_details(content: &str) -> Option<DesktopEntryDetails> {
+    desktop_entry_details(parse(content).ok()?)
+}
+
+/// [`parse_desktop_entry_details`] of a parsed desktop file.
+fn desktop_entry_details(desktop_file: DesktopFile) -> Option<DesktopEntryDetails> {
+    let EntryType::Application(app_fields) = desktop_file.entry.entry_type else {
+        return None;
+    };
+
+    Some(DesktopEntryDetails {
+        name: desktop_file.entry.name.default,
+        icon: desktop_file.entry.icon.map(|icon| icon.content),
+        exec: app_fields.exec,
+        try_exec: app_fields.try_exec,
+        path: app_fields.path,
+        startup_wm_class: app_fields.startup_wm_class,
+        dbus_activatable: desktop_file.entry.dbus_activatable.unwrap_or(false),
+        terminal: app_fields.terminal.unwrap_or(false),
+        categories: app_fields
+            .categories
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|category| !category.is_empty())
+            .collect(),
+        mime_types: app_fields
+            .mime_type
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|mime_type| !mime_type.is_empty())
+            .collect(),
+        actions: desktop_file
+            .actions
+            .into_iter()
+            .map(|(id, action)| (id, action.exec))
+            .collect(),
+    })
+}
+
+/// Value of `key` in the `[Desktop Entry]` group. Used for the `X-` keys,
+/// which the parser does not expose.
+fn raw_desktop_entry_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let mut in_desktop_entry = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_desktop_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_desktop_entry {
+            continue;
+        }
+        if let Some((line_key, value)) = line.split_once('=')
+            && line_key.trim() == key
+        {
+            return Some(value.trim());
+        }
+    }
+
+    None
+}
+
+/// Recognize the web apps created by browsers and web app managers:
+///
+/// * Chrome/Chromium (`--app=<url>` and installed PWAs with `--app-id=<id>`)
+/// * Firefox PWA (`firefoxpwa site launch <id>`)
+/// * GNOME Web (`epiphany --application-mode ... <url>`)
+/// * Web app managers setting `X-WebApp-URL`
+pub(crate) fn classify_web_app(path: &Path, content: &str, exec: Option<&str>) -> Option<AppKind> {
+    if let Some(url) = raw_desktop_entry_value(content, "X-WebApp-URL") {
+        return Some(AppKind::WebApp {
+            url: (!url.is_empty()).then(|| url.to_string()),
+        });
+    }
+
+    let args = split_exec_line(exec?);
+    let program = Path::new(args.first()?).file_name()?.to_str()?;
+
+    if program == "firefoxpwa" {
+        if args.get(1).map(String::as_str) != Some("site")
+            || args.get(2).map(String::as_str) != Some("launch")
+        {
+            return None;
+        }
+        let url = args
+            .get(3)
+            .and_then(|site_id| firefoxpwa_start_url(site_id));
+        return Some(AppKind::WebApp { url });
+    }
+
+    if program.starts_with("epiphany") && args.iter().any(|arg| arg == "--application-mode") {
+        let url = args
+            .iter()
+            .skip(1)
+            .find(|arg| arg.starts_with("http://") || arg.starts_with("https://"))
+            .cloned();
+        return Some(AppKind::WebApp { url });
+    }
+
+    let is_chromium_based = args
+        .iter()
+        .any(|arg| arg.starts_with("--profile-directory="))
+        || ["chrome", "chromium", "brave", "msedge", "vivaldi"]
+            .iter()
+            .any(|browser| program.contains(browser));
+    if !is_chromium_based {
+        return None;
+    }
+    if let Some(url) = args.iter().find_map(|arg| arg.strip_prefix("--app=")) {
+        return Some(AppKind::WebApp {
+            url: Some(url.to_string()),
+        });
+    }
+    let is_installed_pwa = args.iter().any(|arg| arg.starts_with("--app-id="))
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(is_chrome_app_file_name);
+    is_installed_pwa.then_some(AppKind::WebApp { url: None })
+}
+
+/// Whether `name` is `chrome-<app id>-<profile>.desktop`, Chrome app IDs
+/// being 32 letters from `a` to `p`.
+fn is_chrome_app_file_name(name: &str) -> bool {
+    name.strip_prefix("chrome-")
+        .and_then(|name| name.strip_suffix(".desktop"))
+        .and_then(|name| name.split_once('-'))
+        .is_some_and(|(app_id, profile)| {
+            app_id.len() == 32
+                && app_id.bytes().all(|byte| (b'a'..=b'p').contains(&byte))
+                && !profile.is_empty()
+        })
+}
+
+/// Recognize the Android apps Waydroid exports, their desktop files are
+/// named `waydroid.<package>.desktop` and run `waydroid app launch <package>`.
+pub(crate) fn classify_waydroid_app(path: &Path, exec: Option<&str>) -> Option<AppKind> {
+    if let Some(exec) = exec {
+        let args = split_exec_line(exec);
+        let is_waydroid = args
+            .first()
+            .and_then(|program| Path::new(program).file_name())
+            .is_some_and(|program| program == "waydroid");
+        if is_waydroid && args.get(1).map(String::as_str) == Some("app") {
+            if args.get(2).map(String::as_str) == Some("launch")
+                && let Some(package) = args.get(3)
+            {
+                return Some(AppKind::AndroidApp {
+                    package: package.clone(),
+                });
+            }
+            return None;
+        }
+    }
+
+    let package = path
+        .file_stem()?
+        .to_str()?
+        .strip_prefix("waydroid.")
+        .filter(|package| !package.is_empty())?;
+    Some(AppKind::AndroidApp {
+        package: package.to_string(),
+    })
+}
+
+/// Recognize games, by their category or by the game launchers the desktop
+/// files of Steam, Heroic and Lutris run.
+pub(crate) fn classify_game(exec: Option<&str>, categories: &[String]) -> Option<AppKind> {
+    const LAUNCH_URLS: &[&str] = &[
+        "steam://rungameid/",
+        "heroic://launch/",
+        "lutris:rungameid/",
+    ];
+    let url = exec.and_then(|exec| {
+        split_exec_line(exec)
+            .into_iter()
+            .find(|arg| LAUNCH_URLS.iter().any(|prefix| arg.starts_with(prefix)))
+    });
+    if url.is_some() || categories.iter().any(|category| category == "Game") {
+        return Some(AppKind::Game { url });
+    }
+
+    None
+}
+
+/// Recognize the settings panels of GNOME and KDE, which the settings app
+/// shows rather than the app menu.
+pub(crate) fn classify_system_app(content: &str) -> Option<AppKind> {
+    let is_settings_panel = raw_desktop_entry_value(content, "X-GNOME-Settings-Panel").is_some()
+        || raw_desktop_entry_value(content, "X-KDE-ServiceTypes")
+            .is_some_and(|types| types.split([',', ';']).any(|ty| ty.trim() == "KCModule"));
+    is_settings_panel.then_some(AppKind::System)
+}
+
+/// Look up the start URL of a Firefox PWA site in the firefoxpwa config,
+/// which stores sites as `"sites": { "<id>": { ..., "manifest": { "start_url": "..." } } }`.
+fn firefoxpwa_start_url(site_id: &str) -> Option<String> {
+    let home_dir = PathBuf::from(std::env::var_os("HOME")?);
+    let config =
+        std::fs::read_to_string(home_dir.join(".local/share/firefoxpwa/config.json")).ok()?;
+
+    site_start_url(&config, site_id)
+}
+
+/// The `start_url` of the manifest of the site `site_id` in the firefoxpwa
+/// config `config`.
+fn site_start_url(config: &str, site_id: &str) -> Option<String> {
+    let config: serde_json::Value = serde_json::from_str(config).ok()?;
+
+    config
+        .get("sites")?
+        .get(site_id)?
+        .get("manifest")?
+        .get("start_url")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// [`find_in_path`] in the system of `fs`: below its root, if it has one,
+/// in the usual directories of `$PATH`.
+fn find_program(program: &str, fs: &dyn FileSystem) -> Option<PathBuf> {
+    let Some(root) = fs.root() else {
+        return find_in_path(program);
+    };
+    let candidates = if program.contains('/') {
+        vec![rebase(root, Path::new(program))]
+    } else {
+        ROOT_PATH
+            .iter()
+            .map(|dir| root.join(dir).join(program))
+            .collect()
+    };
+
+    candidates
+        .iter()
+        .filter_map(|candidate| fs.canonicalize(candidate).ok())
+        .find(|path| is_executable_file(path))
+}
+
+/// Build an [`App`] from the desktop file at `path`, whose content is
+/// `content`.
+fn parse_app(path: &Path, content: &str, include_no_display: bool) -> Option<App> {
+    parse_app_in(path, content, include_no_display, &RealFs).map(|(app, _)| app)
+}
+
+/// [`parse_app`] of a desktop file in `fs`, whose icon and program are
+/// found in `fs` too, with the `MimeType` of the entry.
+fn parse_app_in(
+    path: &Path,
+    content: &str,
+    include_no_display: bool,
+    fs: &dyn FileSystem,
+) -> Option<(App, Vec<String>)> {
+    let desktop_file = parse(content).ok()?;
+    let (name, localized_app_names, icon_path) =
+        parse_desktop_file(&desktop_file, include_no_display, fs)?;
+    let (exec, categories, terminal, mime_types) = match desktop_entry_details(desktop_file) {
+        Some(details) => (
+            details.exec,
+            details.categories,
+            details.terminal,
+            details.mime_types,
+        ),
+        None => (None, Vec::new(), false, Vec::new()),
+    };
+    let kind = classify_web_app(path, content, exec.as_deref())
+        .or_else(|| classify_waydroid_app(path, exec.as_deref()))
+        .or_else(|| classify_game(exec.as_deref(), &categories))
+        .or_else(|| classify_system_app(content))
+        .or_else(|| terminal.then_some(AppKind::Cli))
+        .unwrap_or_default();
+    let app_path_exe = exec
+        .as_deref()
+        .and_then(exec_program)
+        .and_then(|program| find_program(&program, fs));
+    let scope = match fs.root() {
+        Some(root) => InstallScope::of_path_below(root, path),
+        None => InstallScope::of_path(path),
+    };
+
+    let app = App {
+        name,
+        localized_app_names,
+        icon_path,
+        app_path_exe,
+        app_desktop_path: path.to_path_buf(),
+        kind,
+        scope,
+        categories,
+        sources: vec![DiscoverySource::DesktopFile],
+        ..Default::default()
+    };
+
+    Some((app, mime_types))
+}
+
+impl DesktopEntryDetails {
+    /// Whether the program of this entry is missing, see [`is_stale`].
+    pub(crate) fn is_stale(&self) -> bool {
+        if let Some(try_exec) = self.try_exec.as_deref() {
+            return find_in_path(try_exec).is_none();
+        }
+
+        match self.exec.as_deref().and_then(exec_program) {
+            Some(program) => find_in_path(&program).is_none(),
+            // Apps without `Exec` can only be launched through D-Bus
+            None => !self.dbus_activatable,
+        }
+    }
+}
+
+/// Whether `app` is a stale desktop entry, e.g., one left behind by
+/// uninstalled software, whose program cannot be found.
+///
+/// `TryExec` is checked if it is set, otherwise the program of `Exec`, both
+/// are resolved with `$PATH`. To drop the stale entries from a scan:
+///
+/// ```no_run
+/// let mut apps = applications::get_all_apps(&applications::get_default_search_paths()).unwrap();
+/// apps.retain(|app| !applications::is_stale(app));
+/// ```
+pub fn is_stale(app: &App) -> bool {
+    std::fs::read_to_string(&app.app_desktop_path)
+        .ok()
+        .and_then(|content| parse_desktop_entry_details(&content))
+        .is_none_or(|details| details.is_stale())
+}
+
+/// The PNG icon of `app` scaled to `size` for an
+/// [`IconCache`](crate::IconCache). Other icons, e.g., SVG, are not
+/// rendered.
+#[cfg(feature = "icons")]
+pub(crate) fn render_icon(app: &App, size: u32) -> Result<Vec<u8>> {
+    let Some(path) = &app.icon_path else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "icon",
+        });
+    };
+
+    crate::icon_cache::scale_png(path, &std::fs::read(path)?, size)
+}
+
+/// The per-user paths are left out if `$HOME` is not set, e.g., for a
+/// system service.
+pub fn get_default_search_paths() -> Vec<PathBuf> {
+    let home_dir = std::env::var_os("HOME").map(PathBuf::from);
+
+    let mut paths = vec!["/usr/share/applications".into()];
+    if let Some(home_dir) = &home_dir {
+        paths.push(home_dir.join(".local/share/applications"));
+    }
+    // Snap
+    paths.push(SNAP_DESKTOP_PATH.into());
+    // Flatpak
+    paths.push(FLATPAK_GLOBAL_APP_PATH.into());
+    paths.extend(FLATPAK_PERSONAL_APP_PATH.clone());
+
+    paths
+}
+
+/// [`get_default_search_paths`] of the system below `root`, with those of
+/// each of its users, for [`AppFinder::with_root`].
+pub(crate) fn root_search_paths(root: &Path) -> Vec<PathBuf> {
+    let mut homes = subdirs(&RootedFs::new(root), &root.join("home"));
+    homes.push(root.join("root"));
+
+    let mut paths = vec![root.join("usr/share/applications")];
+    paths.extend(
+        homes
+            .iter()
+            .map(|home| home.join(".local/share/applications")),
+    );
+    paths.push(rebase(root, Path::new(SNAP_DESKTOP_PATH)));
+    paths.push(rebase(root, Path::new(FLATPAK_GLOBAL_APP_PATH)));
+    paths.extend(
+        homes
+            .iter()
+            .map(|home| home.join(".local/share/flatpak/app")),
+    );
+
+    paths
+}
+
+/// Whether `dir` is the `app` directory of a Flatpak installation, e.g.,
+/// `/var/lib/flatpak/app`, which has a directory for each app rather than
+/// desktop files.
+pub(crate) fn is_flatpak_app_path(dir: &Path) -> bool {
+    dir.ends_with("flatpak/app")
+}
+
+/// The desktop file of the Flatpak app in `app_dir`, which may not exist:
+///
+/// ```text
+/// <flatpak_app_path>/<app_identifer>/current/active/files/share/applications/<app_identifier>.desktop
+/// ```
+pub(crate) fn flatpak_desktop_file(app_dir: &Path) -> Option<PathBuf> {
+    let app_identifier = app_dir.file_name()?.to_str()?;
+    Some(
+        app_dir
+            .join("current/active/files/share/applications")
+            .join(format!("{}.desktop", app_identifier)),
+    )
+}
+
+/// Specialized implementation for Flatpak
+fn get_flatpak_desktop_files(
+    flatpak_app_path: &Path,
+    finder: &AppFinder,
+    report: &mut ScanReport,
+) -> Vec<PathBuf> {
+    let mut entries: Vec<DirEntry> = match finder.fs().read_dir(flatpak_app_path) {
+        Ok(entries) => entries
+            .into_iter()
+            .filter_map(|entry| entry.map_err(|(path, e)| report.skip(Some(path), e)).ok())
+            .collect(),
+        Err(e) => {
+            report.skip(Some(flatpak_app_path.to_path_buf()), e);
+            return Vec::new();
+        }
+    };
+    entries.sort_by(|entry, other| entry.path.cmp(&other.path));
+
+    let mut desktop_files = Vec::new();
+    for entry in entries {
+        if finder.is_excluded(&entry.path) {
+            continue;
+        }
+        let Some(app_desktop_file_path) = flatpak_desktop_file(&entry.path) else {
+            continue;
+        };
+
+        // Apps that are still being installed have no desktop file yet
+        if finder.fs().is_file(&app_desktop_file_path) {
+            desktop_files.push(app_desktop_file_path);
+        }
+    }
+
+    desktop_files
+}
+
+/// The desktop files in `dir` and its subdirectories.
+fn get_desktop_files(dir: &Path, finder: &AppFinder, report: &mut ScanReport) -> Vec<PathBuf> {
+    let mut desktop_files = Vec::new();
+    for entry in finder.walk(dir, None) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err((path, e)) => {
+                report.skip(Some(path), e);
+                continue;
+            }
+        };
+        if entry.path.extension() == Some(OsStr::new("desktop"))
+            && entry.kind == Some(FileKind::File)
+        {
+            desktop_files.push(entry.path);
+        }
+    }
+
+    desktop_files
+}
+
+/// Apps are only found in the search paths.
+#[cfg(feature = "cache")]
+pub(crate) fn cache_sources() -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    Vec::new()
+}
+
+/// The localized names come from the desktop file, which a scan reads
+/// anyway, it skips nothing.
+pub(crate) fn read_metadata(app: &App) -> AppMetadata {
+    let localized_app_names = std::fs::read_to_string(&app.app_desktop_path)
+        .ok()
+        .and_then(|content| parse_app(&app.app_desktop_path, &content, true))
+        .map_or_else(
+            || app.localized_app_names.clone(),
+            |app| app.localized_app_names,
+        );
+
+    AppMetadata {
+        localized_app_names,
+        version: app.version.clone(),
+        publisher: app.publisher.clone(),
+        size: app.app_path_exe.as_deref().and_then(disk_size),
+        signature: None,
+        spotlight: None,
+    }
+}
+
+/// The apps in the desktop files of `search_paths`. A desktop file or
+/// directory that cannot be read is skipped, it does not fail the scan.
+pub fn get_all_apps(search_paths: &[PathBuf]) -> Result<Vec<App>> {
+    AppFinder::with_paths(search_paths).find()
+}
+
+/// Like [`get_all_apps`], with the files and directories that could not be
+/// read, e.g., a desktop file that is not valid UTF-8.
+pub fn get_all_apps_with_report(search_paths: &[PathBuf]) -> ScanReport {
+    AppFinder::with_paths(search_paths).find_with_report()
+}
+
+/// The app of a desktop file, or of a desktop file ID, e.g.,
+/// `org.gnome.Nautilus` or `kde4-kate.desktop`, found in the default
+/// search paths without reading the others. A Flatpak app ID finds the
+/// desktop file of its installation.
+pub fn find_app(path_or_id: &str) -> Result<Option<App>> {
+    find_app_in(path_or_id, &get_default_search_paths())
+}
+
+fn find_app_in(path_or_id: &str, search_paths: &[PathBuf]) -> Result<Option<App>> {
+    // A relative path would shadow the desktop file ID of the same name
+    let path = Path::new(path_or_id);
+    if path.is_absolute() && path.is_file() {
+        return App::from_path(path).map(Some);
+    }
+    let id = path_or_id.strip_suffix(".desktop").unwrap_or(path_or_id);
+    if id.is_empty() || id.contains('/') {
+        return Ok(None);
+    }
+
+    for dir in search_paths {
+        let candidates = if is_flatpak_app_path(dir) {
+            flatpak_desktop_file(&dir.join(id)).into_iter().collect()
+        } else {
+            desktop_id_paths(dir, id)
+        };
+        if let Some(desktop_file) = candidates.into_iter().find(|path| path.is_file()) {
+            return App::from_path(&desktop_file).map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Flatpak and Snap apps are uninstalled by their package managers. The
+/// other apps are left to the user, an app of a dpkg package with the name
+/// of the package.
+pub(crate) fn uninstall_action(app: &App) -> Result<UninstallAction> {
+    let desktop_file = &app.app_desktop_path;
+    if let Some(id) = flatpak_app_id(desktop_file) {
+        let installation = match app.scope {
+            InstallScope::User => "--user",
+            InstallScope::Machine => "--system",
+        };
+        return Ok(UninstallAction::Command(UninstallCommand {
+            program: "flatpak".into(),
+            args: vec![
+                "uninstall".to_string(),
+                installation.to_string(),
+                "--noninteractive".to_string(),
+                id,
+            ],
+            quiet: true,
+        }));
+    }
+    if desktop_file.starts_with(SNAP_DESKTOP_PATH)
+        && let Some(stem) = desktop_file.file_stem().and_then(OsStr::to_str)
+    {
+        // `<snap>_<app>.desktop`
+        let snap = stem.split_once('_').map_or(stem, |(snap, _)| snap);
+        return Ok(UninstallAction::Command(UninstallCommand {
+            program: "snap".into(),
+            args: vec!["remove".to_string(), snap.to_string()],
+            quiet: true,
+        }));
+    }
+
+    let reason = match dpkg_package(Path::new(DPKG_INFO_PATH), desktop_file) {
+        Some(package) => {
+            format!("it belongs to the `{package}` package, remove it with the package manager")
+        }
+        None if desktop_file.starts_with("/usr") => {
+            "it belongs to the system, remove its package with the package manager".to_string()
+        }
+        None => "no uninstaller is known for it, remove it and its desktop file".to_string(),
+    };
+    Err(Error::NotUninstallable {
+        app: app.name.clone(),
+        reason,
+    })
+}
+
+/// The ID of the Flatpak app whose desktop file is `path`, in its
+/// installation, i.e., `<flatpak>/app/<id>/...`, or exported, i.e.,
+/// `<flatpak>/exports/share/applications/<id>.desktop`.
+fn flatpak_app_id(path: &Path) -> Option<String> {
+    let components: Vec<&OsStr> = path.iter().collect();
+    let installed = components
+        .windows(3)
+        .find(|window| window[0] == "flatpak" && window[1] == "app")
+        .map(|window| window[2]);
+    let exported = || {
+        path.parent()?
+            .ends_with("flatpak/exports/share/applications")
+            .then(|| path.file_stem())?
+    };
+
+    Some(installed.or_else(exported)?.to_str()?.to_string())
+}
+
+/// The dpkg package that installed `path`, found in the file lists of
+/// `info_dir`. Packages of other architectures are `<package>:<arch>`.
+fn dpkg_package(info_dir: &Path, path: &Path) -> Option<String> {
+    let path = path.to_str()?;
+    std::fs::read_dir(info_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|list| list.extension().is_some_and(|ext| ext == "list"))
+        .find(|list| {
+            std::fs::read_to_string(list)
+                .is_ok_and(|content| content.lines().any(|line| line == path))
+        })
+        .and_then(|list| Some(list.file_stem()?.to_str()?.to_string()))
+}
+
+/// Where the desktop file of `id` may be in `dir`: a `-` of a desktop file
+/// ID is either a dash or the `/` of a subdirectory the file is in, e.g.,
+/// `kde4-kate` may be `kde4/kate.desktop` and `foo-bar-baz` may be
+/// `foo-bar/baz.desktop`. Only the subdirectories that exist are tried.
+fn desktop_id_paths(dir: &Path, id: &str) -> Vec<PathBuf> {
+    let mut paths = vec![dir.join(format!("{id}.desktop"))];
+    for (dash, _) in id.match_indices('-') {
+        let subdir = dir.join(&id[..dash]);
+        if subdir.is_dir() {
+            paths.extend(desktop_id_paths(&subdir, &id[dash + 1..]));
+        }
+    }
+
+    paths
+}
+
+pub(crate) fn find(finder: &AppFinder) -> ScanReport {
+    let mut report = ScanReport::default();
+
+    let search_paths = finder.search_paths();
+    if finder.uses(Provider::SearchPaths) {
+        finder.start(Provider::SearchPaths);
+    }
+    if finder.uses(Provider::Flatpak) && search_paths.iter().any(|dir| is_flatpak_app_path(dir)) {
+        finder.start(Provider::Flatpak);
+    }
+
+    // for each dir, search for .desktop files
+    let walks = par_map(search_paths, |dir| {
+        let mut walk_report = ScanReport::default();
+        if finder.is_cancelled() {
+            return (Vec::new(), DiscoverySource::DesktopFile, walk_report);
+        }
+        // Specialized impl for Flatpak
+        let desktop_files = if is_flatpak_app_path(dir) {
+            if finder.uses(Provider::Flatpak) {
+                get_flatpak_desktop_files(dir, finder, &mut walk_report)
+            } else {
+                Vec::new()
+            }
+        } else if finder.uses(Provider::SearchPaths) {
+            get_desktop_files(dir, finder, &mut walk_report)
+        } else {
+            Vec::new()
+        };
+        let source = if is_flatpak_app_path(dir) {
+            DiscoverySource::Flatpak
+        } else {
+            DiscoverySource::DesktopFile
+        };
+        debug!(path = %dir.display(), desktop_files = desktop_files.len(), "walked");
+        (desktop_files, source, walk_report)
+    });
+    let mut desktop_files = Vec::new();
+    for (dir_desktop_files, source, walk_report) in walks {
+        desktop_files.extend(dir_desktop_files.into_iter().map(|path| (path, source)));
+        report.errors.extend(walk_report.errors);
+    }
+
+    let counter = finder.path_counter(desktop_files.len());
+    let parsed = par_map(desktop_files, |(path, source)| {
+        if finder.is_cancelled() {
+            return Ok(None);
+        }
+        let content = finder.fs().read_to_string(&path);
+        counter.scanned();
+        match content {
+            Ok(content) => {
+                let app = parse_app_in(&path, &content, finder.includes_hidden(), finder.fs());
+                if app.is_none() {
+                    debug!(
+                        path = %path.display(),
+                        "not an app: no Exec or Icon, NoDisplay, Hidden or not an Application"
+                    );
+                }
+                Ok(app.map(|(mut app, mime_types)| {
+                    if let Some(lists) = finder.mime_type_lists() {
+                        lists.insert(path, mime_types);
+                    }
+                    app.sources = vec![source];
+                    app
+                }))
+            }
+            Err(e) => Err((path, e)),
+        }
+    });
+    // Collected into the memory of `parsed`
+    let apps = parsed
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(app) => app,
+            Err((path, e)) => {
+                report.skip(Some(path), e);
+                None
+            }
+        })
+        .collect();
+    report.apps = apps;
+
+    report
+}
+
+/// Directories containing autostart desktop entries, ordered by priority:
+/// an entry shadows the entries with the same file name in the directories
+/// that come after it.
+pub fn get_autostart_paths() -> Vec<PathBuf> {
+    xdg_config_dirs()
+        .into_iter()
+        .map(|dir| dir.join("autostart"))
+        .collect()
+}
+
+/// `$XDG_CONFIG_HOME` and `$XDG_CONFIG_DIRS`, ordered by priority.
+fn xdg_config_dirs() -> Vec<PathBuf> {
+    xdg_dirs("XDG_CONFIG_HOME", ".config", "XDG_CONFIG_DIRS", "/etc/xdg")
+}
+
+/// `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`, ordered by priority.
+fn xdg_data_dirs() -> Vec<PathBuf> {
+    xdg_dirs(
+        "XDG_DATA_HOME",
+        ".local/share",
+        "XDG_DATA_DIRS",
+        "/usr/local/share:/usr/share",
+    )
+}
+
+/// The XDG base directories of a kind, ordered by priority: `$<home_var>`,
+/// or `~/<home_default>`, then those of `$<dirs_var>`, or `dirs_default`.
+fn xdg_dirs(
+    home_var: &str,
+    home_default: &str,
+    dirs_var: &str,
+    dirs_default: &str,
+) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    match std::env::var_os(home_var) {
+        Some(home) if !home.is_empty() => dirs.push(PathBuf::from(home)),
+        _ => {
+            if let Some(home_dir) = std::env::var_os("HOME") {
+                dirs.push(PathBuf::from(home_dir).join(home_default));
+            }
+        }
+    }
+
+    let system_dirs = std::env::var(dirs_var)
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| dirs_default.to_string());
+    dirs.extend(
+        system_dirs
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+
+    dirs
+}
+
+/// The `mimeapps.list` files, ordered by priority: in each of the config
+/// directories, then in the `applications` of each of the data directories,
+/// those of the desktop environments of `$XDG_CURRENT_DESKTOP` first,
+/// e.g., `gnome-mimeapps.list`.
+pub(crate) fn mimeapps_paths() -> Vec<PathBuf> {
+    let mut dirs = xdg_config_dirs();
+    dirs.extend(
+        xdg_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications")),
+    );
+
+    desktop_config_files(&dirs, "mimeapps.list")
+}
+
+/// The files named `name` in `dirs`, each after those of the desktop
+/// environments of `$XDG_CURRENT_DESKTOP`, which are named
+/// `<desktop>-<name>`.
+fn desktop_config_files(dirs: &[PathBuf], name: &str) -> Vec<PathBuf> {
+    let desktops: Vec<String> = std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    dirs.iter()
+        .flat_map(|dir| {
+            desktops
+                .iter()
+                .map(|desktop| dir.join(format!("{desktop}-{name}")))
+                .chain([dir.join(name)])
+        })
+        .collect()
+}
+
+/// The `mimeapps.list` files that can be read, ordered by priority.
+fn read_mimeapps_lists() -> Vec<MimeApps> {
+    mimeapps_paths()
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|content| MimeApps::parse(&content))
+        .collect()
+}
+
+/// The desktop file IDs `lists` make the default of `mime_type`, then
+/// those associated with it, that no list of a higher priority removed,
+/// in the order to try them in.
+fn associated_ids<'a>(lists: &'a [MimeApps], mime_type: &str) -> Vec<&'a str> {
+    let mut ids: Vec<&str> = lists
+        .iter()
+        .filter_map(|list| list.default.get(mime_type))
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    let mut removed: HashSet<&str> = HashSet::new();
+    for list in lists {
+        if let Some(added) = list.added.get(mime_type) {
+            ids.extend(
+                added
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|id| !removed.contains(id)),
+            );
+        }
+        if let Some(list_removed) = list.removed.get(mime_type) {
+            removed.extend(list_removed.iter().map(String::as_str));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    ids
+}
+
+/// The first of the desktop file IDs `ids` that is installed.
+fn first_installed<'a>(ids: impl IntoIterator<Item = &'a str>) -> Option<App> {
+    ids.into_iter().find_map(|id| find_app(id).ok().flatten())
+}
+
+/// The browser and mail client of the `x-scheme-handler` types in the
+/// `mimeapps.list` files, as `xdg-settings` reads them, and the terminal
+/// of the `xdg-terminals.list` files of the `xdg-terminal-exec` proposal.
+pub(crate) fn default_handlers() -> Result<DefaultHandlers> {
+    let lists = read_mimeapps_lists();
+    let handler = |mime_types: &[&str]| {
+        first_installed(
+            mime_types
+                .iter()
+                .flat_map(|mime_type| associated_ids(&lists, mime_type)),
+        )
+    };
+
+    Ok(DefaultHandlers {
+        browser: handler(&["x-scheme-handler/http", "x-scheme-handler/https"]),
+        mail: handler(&["x-scheme-handler/mailto"]),
+        terminal: default_terminal(),
+    })
+}
+
+/// The desktop file ID of the desktop file at `path`: its path below the
+/// last `applications` directory, with `-` for `/`, e.g.,
+/// `/usr/share/applications/kde4/kate.desktop` => `kde4-kate.desktop`.
+fn desktop_file_id(path: &Path) -> Option<String> {
+    if path.extension() != Some(OsStr::new("desktop")) {
         return None;
     }
+    let names: Vec<&str> = path
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    let applications = names.iter().rposition(|name| *name == "applications")?;
+
+    Some(names[applications + 1..].join("-"))
+}
+
+/// The MIME types of the files with `extension` in the shared MIME-info
+/// databases of the data directories, the most likely first.
+fn mime_types_of(extension: &str) -> Vec<String> {
+    let mut mime_types = Vec::new();
+    for dir in xdg_data_dirs() {
+        let Ok(globs2) = std::fs::read_to_string(dir.join("mime/globs2")) else {
+            continue;
+        };
+        for mime_type in mime_types_of_extension(&globs2, extension) {
+            if !mime_types.contains(&mime_type) {
+                mime_types.push(mime_type);
+            }
+        }
+    }
 
-    app_fields.exec?;
+    mime_types
+}
+
+/// The MIME types of the file at `path`, by its extension, the most likely
+/// first: `inode/directory` for directories.
+fn mime_types_of_path(path: &Path) -> Vec<String> {
+    if path.is_dir() {
+        return vec!["inode/directory".to_string()];
+    }
+
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(mime_types_of)
+        .unwrap_or_default()
+}
+
+/// `mime_types` and the types they are subclasses of in the shared
+/// MIME-info databases of the data directories, so that, e.g., text editors
+/// open `text/x-rust` files.
+fn with_ancestors(mime_types: Vec<String>) -> Vec<String> {
+    let subclasses: Vec<String> = xdg_data_dirs()
+        .iter()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("mime/subclasses")).ok())
+        .collect();
+
+    with_mime_ancestors(&subclasses, mime_types)
+}
+
+/// Whether `declared`, a MIME type of the `MimeType` of a desktop entry,
+/// which may be a `type/*` wildcard, is `mime_type`.
+fn mime_type_matches(declared: &str, mime_type: &str) -> bool {
+    match declared.strip_suffix("/*") {
+        Some(media_type) => mime_type
+            .split_once('/')
+            .is_some_and(|(mime_media_type, _)| mime_media_type.eq_ignore_ascii_case(media_type)),
+        None => declared.eq_ignore_ascii_case(mime_type),
+    }
+}
+
+/// Whether a `mimeapps.list` of `lists` removed the association of the
+/// desktop file ID `id` with `mime_type`.
+fn is_removed(lists: &[MimeApps], mime_type: &str, id: &str) -> bool {
+    lists.iter().any(|list| {
+        list.removed
+            .get(mime_type)
+            .is_some_and(|removed| removed.iter().any(|removed| removed == id))
+    })
+}
+
+/// Whether the `MimeType` of the desktop entry of `app`, or the
+/// `mimeapps.list` files, associate it with a MIME type of `target`, and
+/// no `mimeapps.list` removed that association.
+pub(crate) fn can_open(app: &App, target: &OpenTarget) -> bool {
+    let Ok(details) = read_desktop_entry_details(&app.app_desktop_path) else {
+        return false;
+    };
+    let mime_types = with_ancestors(match target {
+        OpenTarget::Path(path) => mime_types_of_path(path),
+        OpenTarget::MimeType(mime_type) => vec![mime_type.to_string()],
+    });
+    let id = desktop_file_id(&app.app_desktop_path);
+    let id = id.as_deref().unwrap_or_default();
+    let lists = read_mimeapps_lists();
+
+    mime_types.iter().any(|mime_type| {
+        let removed = is_removed(&lists, mime_type, id);
+        let declared = details
+            .mime_types
+            .iter()
+            .any(|declared| mime_type_matches(declared, mime_type));
+
+        !removed && (declared || associated_ids(&lists, mime_type).contains(&id))
+    })
+}
+
+/// `apps`, with the MIME types of their desktop entries, that open
+/// `mime_types`, for each the most likely first: the defaults and added
+/// associations of `lists`, in their order, then the apps that declare the
+/// type and `lists` did not remove it from.
+fn rank_handlers(
+    lists: &[MimeApps],
+    mime_types: &[String],
+    apps: &[(App, Vec<String>)],
+) -> Vec<App> {
+    let ids: Vec<Option<String>> = apps
+        .iter()
+        .map(|(app, _)| desktop_file_id(&app.app_desktop_path))
+        .collect();
+    let mut ranked = Vec::new();
+    for mime_type in mime_types {
+        for id in associated_ids(lists, mime_type) {
+            ranked.extend(ids.iter().position(|known| known.as_deref() == Some(id)));
+        }
+        ranked.extend((0..apps.len()).filter(|&index| {
+            let declared = apps[index]
+                .1
+                .iter()
+                .any(|declared| mime_type_matches(declared, mime_type));
+            let removed = ids[index]
+                .as_deref()
+                .is_some_and(|id| is_removed(lists, mime_type, id));
+            declared && !removed
+        }));
+    }
+
+    let mut seen = HashSet::new();
+    ranked
+        .into_iter()
+        .filter(|index| seen.insert(*index))
+        .map(|index| apps[index].0.clone())
+        .collect()
+}
+
+/// The apps found in the default search paths that open the file at
+/// `path`, ranked by the `mimeapps.list` files, see [`rank_handlers`].
+pub(crate) fn apps_for_file(path: &Path) -> Vec<App> {
+    let mime_types = with_ancestors(mime_types_of_path(path));
+    if mime_types.is_empty() {
+        return Vec::new();
+    }
+    let lists = MimeTypeLists::default();
+    let Ok(apps) = AppFinder::new().keep_mime_types(lists.clone()).find() else {
+        return Vec::new();
+    };
+    let mut lists = lists.take();
+    let apps: Vec<(App, Vec<String>)> = apps
+        .into_iter()
+        .map(|app| {
+            let mime_types = lists.remove(&app.app_desktop_path).unwrap_or_default();
+            (app, mime_types)
+        })
+        .collect();
+
+    rank_handlers(&read_mimeapps_lists(), &mime_types, &apps)
+}
+
+/// Make `app` the default of all the MIME types of `extension`, with
+/// `xdg-mime default`, which writes the `mimeapps.list` of the user.
+pub(crate) fn set_default_for_extension(extension: &str, app: &App) -> Result<()> {
+    let Some(id) = desktop_file_id(&app.app_desktop_path) else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "desktop file",
+        });
+    };
+    let mime_types = mime_types_of(extension);
+    if mime_types.is_empty() {
+        return Err(Error::Platform(
+            format!("no MIME type has the extension [{extension}]").into(),
+        ));
+    }
+
+    let mut args = vec!["default", id.as_str()];
+    args.extend(mime_types.iter().map(String::as_str));
+    run_command("xdg-mime", &args).map(drop)
+}
+
+/// The first installed terminal of the `xdg-terminals.list` files in the
+/// config and data directories, each a desktop file ID per line, which may
+/// name an action after a `:`.
+fn default_terminal() -> Option<App> {
+    let mut dirs = xdg_config_dirs();
+    dirs.extend(
+        xdg_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("xdg-terminal-exec")),
+    );
+    let lists: Vec<String> = desktop_config_files(&dirs, "xdg-terminals.list")
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+
+    first_installed(lists.iter().flat_map(|list| terminal_ids(list)))
+}
+
+/// The desktop file IDs of an `xdg-terminals.list` file, without the
+/// actions and those excluded with a leading `-`.
+fn terminal_ids(list: &str) -> impl Iterator<Item = &str> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+        .map(|line| {
+            let id = line.strip_prefix('+').unwrap_or(line);
+            id.split_once(':').map_or(id, |(id, _action)| id)
+        })
+}
+
+/// Get the applications that are started when the user logs in, i.e., the
+/// XDG autostart entries.
+pub fn get_autostart_apps() -> Result<Vec<App>> {
+    let mut seen_file_names = HashSet::new();
+    let mut apps = Vec::new();
+
+    for dir in get_autostart_paths() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("desktop")) {
+                continue;
+            }
+            // Shadowed by an entry with higher priority, which may be one
+            // with `Hidden=true` that disables this one.
+            if !seen_file_names.insert(entry.file_name()) {
+                continue;
+            }
+
+            if let Ok(app) = App::from_path(&path) {
+                apps.push(app);
+            }
+        }
+    }
+
+    Ok(apps)
+}
+
+/// What we know about a process from `/proc/<pid>`.
+#[derive(Debug, Default)]
+struct ProcessInfo {
+    pid: u32,
+    exe: Option<PathBuf>,
+    cmdline: Vec<String>,
+    flatpak_id: Option<String>,
+}
+
+fn read_process(proc_dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let cmdline: Vec<String> = std::fs::read(proc_dir.join("cmdline"))
+        .ok()?
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    // Kernel threads have no command line
+    if cmdline.is_empty() {
+        return None;
+    }
+
+    // These are only readable for processes owned by the current user
+    let exe = std::fs::read_link(proc_dir.join("exe")).ok();
+    let flatpak_id = std::fs::read(proc_dir.join("environ"))
+        .ok()
+        .and_then(|environ| {
+            environ.split(|byte| *byte == 0).find_map(|var| {
+                var.strip_prefix(b"FLATPAK_ID=")
+                    .map(|id| String::from_utf8_lossy(id).into_owned())
+            })
+        });
+
+    Some(ProcessInfo {
+        pid,
+        exe,
+        cmdline,
+        flatpak_id,
+    })
+}
+
+fn get_processes() -> Result<Vec<ProcessInfo>> {
+    let mut processes = Vec::new();
+    for entry in std::fs::read_dir("/proc")?.filter_map(|entry| entry.ok()) {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        // The process may exit while we are reading it
+        if let Some(process) = read_process(&entry.path(), pid) {
+            processes.push(process);
+        }
+    }
+
+    Ok(processes)
+}
+
+/// How a process can be recognized as an instance of an app.
+#[derive(Debug)]
+struct AppMatcher {
+    program: Option<PathBuf>,
+    wm_class: Option<String>,
+    flatpak_id: Option<String>,
+    /// Lowercase desktop file ID without the `.desktop` suffix.
+    desktop_id: Option<String>,
+}
+
+impl AppMatcher {
+    fn new(app: &App) -> Self {
+        let details = std::fs::read_to_string(&app.app_desktop_path)
+            .ok()
+            .and_then(|content| parse_desktop_entry_details(&content))
+            .unwrap_or_default();
+        let program = details
+            .exec
+            .as_deref()
+            .and_then(exec_program)
+            .and_then(|program| find_in_path(&program))
+            .and_then(|program| program.canonicalize().ok());
+        let is_flatpak_app = app.app_desktop_path.starts_with(FLATPAK_GLOBAL_APP_PATH)
+            || FLATPAK_PERSONAL_APP_PATH
+                .as_ref()
+                .is_some_and(|path| app.app_desktop_path.starts_with(path));
+        let flatpak_id = if is_flatpak_app {
+            app.app_desktop_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_string)
+        } else {
+            None
+        };
+
+        let desktop_id = app
+            .app_desktop_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_lowercase);
+
+        Self {
+            program,
+            wm_class: details.startup_wm_class.map(|class| class.to_lowercase()),
+            flatpak_id,
+            desktop_id,
+        }
+    }
+
+    /// Whether a window with the given `WM_CLASS` (or Wayland app ID)
+    /// belongs to this app.
+    fn matches_window_class(&self, class: &str) -> bool {
+        let class = class.to_lowercase();
+        match &self.wm_class {
+            Some(wm_class) => *wm_class == class,
+            // Without `StartupWMClass`, window managers fall back to the
+            // desktop file ID.
+            None => self.desktop_id.as_ref() == Some(&class),
+        }
+    }
+
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        if let (Some(id), Some(process_id)) = (&self.flatpak_id, &process.flatpak_id) {
+            return id == process_id;
+        }
+
+        if let Some(program) = &self.program {
+            if process.exe.as_ref() == Some(program) {
+                return true;
+            }
+            // Scripts are run by an interpreter, e.g., `python3 /usr/bin/foo`
+            let runs_program = process.cmdline.iter().take(2).any(|arg| {
+                arg.starts_with('/') && Path::new(arg).canonicalize().ok().as_ref() == Some(program)
+            });
+            if runs_program {
+                return true;
+            }
+        }
+
+        // `StartupWMClass` is a window property, but for most apps it is the
+        // same as the name of the program. Not `comm`, which the kernel cuts
+        // to 15 bytes.
+        self.wm_class.as_deref().is_some_and(|class| {
+            process
+                .exe
+                .as_deref()
+                .into_iter()
+                .chain(process.cmdline.first().map(Path::new))
+                .filter_map(Path::file_name)
+                .any(|name| name.to_string_lossy().to_lowercase() == class)
+        })
+    }
+}
+
+/// Get the apps found in the default search paths that are currently
+/// running.
+///
+/// Processes are mapped to desktop entries by their executable, their
+/// Flatpak app ID, or the `StartupWMClass` of the entry. Window titles are
+/// those of the X11 windows, and of the XWayland windows on Wayland, read
+/// through the `xprop` command; native Wayland windows are not listed.
+pub fn get_running_apps() -> Result<Vec<RunningApp>> {
+    let apps = get_all_apps(&get_default_search_paths())?;
+    let matchers: Vec<AppMatcher> = apps.iter().map(AppMatcher::new).collect();
+
+    let mut pids_per_app: Vec<Vec<u32>> = vec![Vec::new(); apps.len()];
+    for process in get_processes()? {
+        if let Some(idx) = matchers
+            .iter()
+            .position(|matcher| matcher.matches(&process))
+        {
+            pids_per_app[idx].push(process.pid);
+        }
+    }
+
+    // Without an X server there are no windows to list
+    let windows = get_windows_x11().unwrap_or_default();
+    Ok(apps
+        .into_iter()
+        .zip(pids_per_app)
+        .filter(|(_app, pids)| !pids.is_empty())
+        .map(|(app, mut pids)| {
+            pids.sort_unstable();
+            let window_titles = windows
+                .iter()
+                .filter(|window| window.pid.is_some_and(|pid| pids.contains(&pid)))
+                .filter_map(|window| window.title.clone())
+                .collect();
+            RunningApp {
+                app,
+                pids,
+                window_titles,
+            }
+        })
+        .collect())
+}
+
+/// What we know about a window, the one that has the input focus or one
+/// of the top-level windows.
+#[derive(Debug, Default, PartialEq)]
+struct WindowInfo {
+    pid: Option<u32>,
+    /// `WM_CLASS` instance and class names, or the Wayland app ID.
+    classes: Vec<String>,
+    title: Option<String>,
+}
+
+/// Extract the window ID from the output of `xprop -root _NET_ACTIVE_WINDOW`:
+///
+/// ```text
+/// _NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007
+/// ```
+fn parse_xprop_active_window(output: &str) -> Option<String> {
+    let id = output.split('#').nth(1)?.split(',').next()?.trim();
+    // 0x0 means no window has the focus
+    if id.is_empty() || u64::from_str_radix(id.trim_start_matches("0x"), 16).ok()? == 0 {
+        return None;
+    }
+
+    Some(id.to_string())
+}
+
+/// Extract the window IDs from the output of `xprop -root _NET_CLIENT_LIST`:
+///
+/// ```text
+/// _NET_CLIENT_LIST(WINDOW): window id # 0x1a00003, 0x3a00007
+/// ```
+fn parse_xprop_client_list(output: &str) -> Vec<String> {
+    let Some((_, ids)) = output.split_once('#') else {
+        return Vec::new();
+    };
+
+    ids.split(',')
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Unquote a string value printed by `xprop`, e.g., `"say \"hi\""`.
+fn parse_xprop_string(value: &str) -> Option<String> {
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut string = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.extend(chars.next()),
+            c => string.push(c),
+        }
+    }
+
+    Some(string)
+}
+
+/// Parse the output of `xprop -id <window> WM_CLASS _NET_WM_PID
+/// _NET_WM_NAME`:
+///
+/// ```text
+/// WM_CLASS(STRING) = "code", "Code"
+/// _NET_WM_PID(CARDINAL) = 12345
+/// _NET_WM_NAME(UTF8_STRING) = "main.rs - Visual Studio Code"
+/// ```
+fn parse_xprop_window_properties(output: &str) -> WindowInfo {
+    let mut window = WindowInfo::default();
+    for line in output.lines() {
+        let Some((property, value)) = line.split_once('=') else {
+            continue;
+        };
+        if property.starts_with("WM_CLASS") {
+            window.classes = value
+                .split(',')
+                .map(|class| class.trim().trim_matches('"').to_string())
+                .filter(|class| !class.is_empty())
+                .collect();
+        } else if property.starts_with("_NET_WM_PID") {
+            window.pid = value.trim().parse().ok();
+        } else if property.starts_with("_NET_WM_NAME") {
+            window.title = parse_xprop_string(value.trim());
+        }
+    }
+
+    window
+}
+
+fn run_command(program: &str, args: &[&str]) -> Result<String> {
+    let output = crate::process::output(
+        std::process::Command::new(program).args(args),
+        crate::process::DEFAULT_TIMEOUT,
+        None,
+    )?;
+    if !output.status.success() {
+        return Err(Error::command(program, &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Focused window on X11, or an XWayland window on Wayland.
+fn get_focused_window_x11() -> Result<Option<WindowInfo>> {
+    let output = run_command("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+    let Some(window_id) = parse_xprop_active_window(&output) else {
+        return Ok(None);
+    };
+    let output = run_command("xprop", &["-id", &window_id, "WM_CLASS", "_NET_WM_PID"])?;
+
+    Ok(Some(parse_xprop_window_properties(&output)))
+}
+
+/// The top-level windows on X11, or the XWayland windows on Wayland, as
+/// the window manager lists them in `_NET_CLIENT_LIST`.
+fn get_windows_x11() -> Result<Vec<WindowInfo>> {
+    let output = run_command("xprop", &["-root", "_NET_CLIENT_LIST"])?;
+
+    Ok(parse_xprop_client_list(&output)
+        .iter()
+        // Windows may be closed while we are reading them
+        .filter_map(|id| {
+            let args = ["-id", id, "WM_CLASS", "_NET_WM_PID", "_NET_WM_NAME"];
+            run_command("xprop", &args).ok()
+        })
+        .map(|output| parse_xprop_window_properties(&output))
+        .collect())
+}
+
+/// Focused window on compositors with the wlroots foreign toplevel
+/// protocol, which tells the app ID but not the process.
+fn get_focused_window_wlroots() -> Result<Option<WindowInfo>> {
+    let socket = wayland_socket().ok_or(Error::UnsupportedPlatform)?;
+    let stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(crate::process::DEFAULT_TIMEOUT))?;
+
+    Ok(read_toplevels(stream)?
+        .into_iter()
+        .find(|toplevel| toplevel.activated)
+        .map(|toplevel| WindowInfo {
+            pid: None,
+            classes: vec![toplevel.app_id],
+            title: Some(toplevel.title),
+        }))
+}
+
+/// Focused window on GNOME Shell, which requires `Eval` to be allowed
+/// (unsafe mode or development builds).
+fn get_focused_window_gnome_shell() -> Result<Option<WindowInfo>> {
+    let output = run_command(
+        "gdbus",
+        &[
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Shell",
+            "--object-path",
+            "/org/gnome/Shell",
+            "--method",
+            "org.gnome.Shell.Eval",
+            "(() => { const w = global.display.focus_window; \
+             return w ? `${w.get_pid()} ${w.get_wm_class()}` : ''; })()",
+        ],
+    )?;
+
+    // The output looks like `(true, '"1234 org.gnome.Nautilus"')`
+    let Some(result) = output.trim().strip_prefix("(true, '") else {
+        return Ok(None);
+    };
+    let result = result.trim_end_matches("')").trim_matches('"');
+    let mut parts = result.split_whitespace();
+    let pid = parts.next().and_then(|pid| pid.parse().ok());
+    let classes: Vec<String> = parts.map(str::to_string).collect();
+    if pid.is_none() && classes.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(WindowInfo {
+        pid,
+        classes,
+        title: None,
+    }))
+}
+
+/// Get the app that owns the focused window.
+///
+/// This uses `_NET_ACTIVE_WINDOW` on X11, queried through the `xprop`
+/// command. On Wayland, it uses the wlroots foreign toplevel protocol of,
+/// e.g., Sway, Hyprland, niri or labwc, then GNOME Shell introspection
+/// through the `gdbus` command, which requires `Eval` to be allowed. Other
+/// compositors, e.g., KDE Plasma, only expose native Wayland windows to
+/// privileged clients, so only XWayland windows can be resolved there.
+///
+/// Returns `None` if no window has the focus or if it does not belong to an
+/// app found in the default search paths, and
+/// [`Error::UnsupportedPlatform`] on Wayland if neither can be queried.
+pub fn get_frontmost_application() -> Result<Option<App>> {
+    let is_wayland = std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+        || std::env::var_os("WAYLAND_DISPLAY").is_some();
+
+    let window = if is_wayland {
+        if let Ok(window) = get_focused_window_wlroots() {
+            window
+        } else if let Ok(Some(window)) = get_focused_window_gnome_shell() {
+            Some(window)
+        } else {
+            get_focused_window_x11().map_err(|_| Error::UnsupportedPlatform)?
+        }
+    } else {
+        get_focused_window_x11()?
+    };
+    let Some(window) = window else {
+        return Ok(None);
+    };
+
+    let apps = get_all_apps(&get_default_search_paths())?;
+    let matchers: Vec<AppMatcher> = apps.iter().map(AppMatcher::new).collect();
+
+    let process = window
+        .pid
+        .and_then(|pid| read_process(&Path::new("/proc").join(pid.to_string()), pid));
+    let by_process = process.and_then(|process| {
+        matchers
+            .iter()
+            .position(|matcher| matcher.matches(&process))
+    });
+    let idx = by_process.or_else(|| {
+        matchers.iter().position(|matcher| {
+            window
+                .classes
+                .iter()
+                .any(|class| matcher.matches_window_class(class))
+        })
+    });
+
+    Ok(idx.map(|idx| apps[idx].clone()))
+}
+
+/// Directories containing AppStream metainfo files or uncompressed catalogs.
+pub fn get_default_appstream_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = vec![
+        "/usr/share/metainfo".into(),
+        // Legacy location
+        "/usr/share/appdata".into(),
+        "/usr/local/share/metainfo".into(),
+        "/usr/share/swcatalog/xml".into(),
+        "/var/cache/swcatalog/xml".into(),
+    ];
+    if let Some(home_dir) = std::env::var_os("HOME") {
+        paths.push(PathBuf::from(home_dir).join(".local/share/metainfo"));
+    }
+
+    paths
+}
+
+/// The AppStream components found on the system, keyed by the ID of the
+/// desktop file they describe, so that they can be joined with the apps
+/// returned by [`get_all_apps`].
+///
+/// Compressed catalogs (`*.xml.gz`) and the binary libappstream cache are not
+/// supported.
+#[derive(Debug, Default)]
+pub struct AppStreamIndex {
+    components: HashMap<String, AppStreamMetadata>,
+    /// What [`AppStreamIndex::get`] found for the desktop files that are not
+    /// in `components`, `None` if nothing, so that the metainfo next to them
+    /// is read once.
+    looked_up: Mutex<HashMap<PathBuf, Option<AppStreamMetadata>>>,
+}
+
+impl Clone for AppStreamIndex {
+    fn clone(&self) -> Self {
+        Self {
+            components: self.components.clone(),
+            looked_up: Mutex::new(self.looked_up.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl AppStreamIndex {
+    /// Load the components from [`get_default_appstream_paths`].
+    pub fn load() -> Self {
+        Self::from_paths(&get_default_appstream_paths())
+    }
+
+    pub fn from_paths(paths: &[PathBuf]) -> Self {
+        let mut index = Self::default();
+        for path in paths {
+            index.extend_from_dir(path);
+        }
+
+        index
+    }
+
+    fn extend_from_dir(&mut self, dir: &Path) {
+        for (_path, component) in read_appstream_dir(dir) {
+            for desktop_id in component.desktop_ids.iter() {
+                // The first occurrence wins, paths are ordered by priority.
+                self.components
+                    .entry(desktop_id.clone())
+                    .or_insert_with(|| component.clone());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Look up the AppStream metadata of `app`.
+    ///
+    /// Apps that ship their own metainfo next to their desktop file, like
+    /// Flatpak apps (`<prefix>/share/applications/<id>.desktop` and
+    /// `<prefix>/share/metainfo`), are resolved even if they are not in the
+    /// index.
+    pub fn get(&self, app: &App) -> Option<AppStreamMetadata> {
+        let desktop_id = app.app_desktop_path.file_name()?.to_str()?;
+        if let Some(component) = self.components.get(desktop_id) {
+            return Some(component.clone());
+        }
+        if let Some(looked_up) = self.looked_up.lock().unwrap().get(&app.app_desktop_path) {
+            return looked_up.clone();
+        }
+
+        let share_dir = app.app_desktop_path.parent()?.parent()?;
+        let component = read_appstream_dir(&share_dir.join("metainfo"))
+            .into_iter()
+            .map(|(_path, component)| component)
+            .find(|component| component.desktop_ids.iter().any(|id| id == desktop_id));
+        self.looked_up
+            .lock()
+            .unwrap()
+            .insert(app.app_desktop_path.clone(), component.clone());
+
+        component
+    }
+}
+
+/// The D-Bus object path of an application, `org.example.App-Foo` =>
+/// `/org/example/App_Foo`.
+fn dbus_object_path(app_id: &str) -> String {
+    format!("/{}", app_id.replace('.', "/").replace('-', "_"))
+}
+
+/// Call a method of the `org.freedesktop.Application` interface with
+/// `gdbus`, `args` are GVariant text representations.
+fn call_freedesktop_application(app_id: &str, method: &str, args: &[&str]) -> Result<()> {
+    let output = crate::process::output(
+        std::process::Command::new("gdbus")
+            .args(["call", "--session", "--dest", app_id, "--object-path"])
+            .arg(dbus_object_path(app_id))
+            .arg("--method")
+            .arg(format!("org.freedesktop.Application.{}", method))
+            .args(args),
+        crate::process::DEFAULT_TIMEOUT,
+        None,
+    )?;
+    if !output.status.success() {
+        return Err(Error::command("gdbus", &output));
+    }
+
+    Ok(())
+}
+
+fn spawn_exec(exec: &str, details: &DesktopEntryDetails, desktop_file: &Path) -> Result<()> {
+    let context = ExecContext {
+        name: &details.name,
+        icon: details.icon.as_deref(),
+        desktop_file,
+    };
+    let args = expand_exec_line(exec, &context);
+    let Some((program, args)) = args.split_first() else {
+        return Err(Error::Missing {
+            app: details.name.clone(),
+            missing: "Exec",
+        });
+    };
+
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    if let Some(dir) = details.path.as_deref().filter(|dir| !dir.is_empty()) {
+        command.current_dir(dir);
+    }
+
+    spawn_detached(&mut command)
+}
+
+/// The `Exec` of the desktop entry of `app`, with its field codes but
+/// those of the files and URLs expanded, see [`spawn_exec`].
+pub(crate) fn launch_spec(app: &App) -> Result<LaunchSpec> {
+    let desktop_file = app.app_desktop_path.as_path();
+    let details = read_desktop_entry_details(desktop_file)?;
+    let exec = details.exec.as_deref().unwrap_or_default();
+    let context = ExecContext {
+        name: &details.name,
+        icon: details.icon.as_deref(),
+        desktop_file,
+    };
+    let mut args = exec_template(exec, &context).into_iter();
+    let Some(LaunchArg::Literal(program)) = args.next() else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "Exec",
+        });
+    };
+
+    Ok(LaunchSpec {
+        program: program.into(),
+        args: args.collect(),
+        working_dir: details
+            .path
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+        terminal: details.terminal,
+    })
+}
+
+fn read_desktop_entry_details(desktop_file: &Path) -> Result<DesktopEntryDetails> {
+    let content = std::fs::read_to_string(desktop_file)?;
+    parse_desktop_entry_details(&content)
+        .ok_or_else(|| Error::parse(desktop_file, "invalid desktop file"))
+}
+
+/// Run the desktop action `action` (a `[Desktop Action <action>]` group) of
+/// `app`.
+///
+/// Like [`AppTrait::launch`], D-Bus activatable apps are asked to run the
+/// action through `org.freedesktop.Application.ActivateAction`.
+pub fn launch_action(app: &App, action: &str) -> Result<()> {
+    let desktop_file = app.app_desktop_path.as_path();
+    let details = read_desktop_entry_details(desktop_file)?;
+    let Some(action_exec) = details.actions.get(action) else {
+        return Err(Error::ActionNotFound {
+            path: desktop_file.to_path_buf(),
+            action: action.to_string(),
+        });
+    };
+
+    if details.dbus_activatable
+        && let Some(app_id) = desktop_file.file_stem().and_then(|stem| stem.to_str())
+    {
+        let action_name = format!("'{}'", action.replace('\'', "\\'"));
+        let activated = call_freedesktop_application(
+            app_id,
+            "ActivateAction",
+            &[&action_name, "@av []", "@a{sv} {}"],
+        );
+        if activated.is_ok() || action_exec.is_none() {
+            return activated;
+        }
+    }
+
+    let Some(exec) = action_exec else {
+        return Err(Error::Missing {
+            app: details.name.clone(),
+            missing: "Exec for the action",
+        });
+    };
+    spawn_exec(exec, &details, desktop_file)
+}
+
+impl AppTrait for App {
+    /// Apps with `DBusActivatable=true` are activated through the
+    /// `org.freedesktop.Application` D-Bus interface and fall back to `Exec`
+    /// if that fails, other apps are started by running `Exec`.
+    fn launch(&self) -> Result<()> {
+        let desktop_file = self.app_desktop_path.as_path();
+        let details = read_desktop_entry_details(desktop_file)?;
+
+        if details.dbus_activatable
+            && let Some(app_id) = desktop_file.file_stem().and_then(|stem| stem.to_str())
+        {
+            let activated = call_freedesktop_application(app_id, "Activate", &["@a{sv} {}"]);
+            if activated.is_ok() || details.exec.is_none() {
+                return activated;
+            }
+        }
+
+        let Some(exec) = details.exec.as_deref() else {
+            return Err(Error::Missing {
+                app: self.name.clone(),
+                missing: "Exec",
+            });
+        };
+        spawn_exec(exec, &details, desktop_file)
+    }
+
+    fn from_path(path: &Path) -> Result<Self> {
+        let desktop_file_content = std::fs::read_to_string(path)?;
+        parse_app(path, &desktop_file_content, false)
+            .ok_or_else(|| Error::parse(path, "invalid desktop file"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_get_apps() {
+        let default_search_path = get_default_search_paths();
+        let apps = get_all_apps(&default_search_path).unwrap();
+        assert!(!apps.is_empty());
+    }
+
+    #[test]
+    fn test_get_running_apps() {
+        let running_apps = get_running_apps().unwrap();
+        for running_app in running_apps {
+            assert!(!running_app.pids.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_desktop_entry_details_dbus_activatable() {
+        let nautilus = r#"[Desktop Entry]
+Type=Application
+Name=Files
+Exec=nautilus --new-window %U
+Icon=org.gnome.Nautilus
+DBusActivatable=true
+Actions=new-window;
+
+[Desktop Action new-window]
+Name=New Window
+Exec=nautilus --new-window"#;
+
+        let details = parse_desktop_entry_details(nautilus).unwrap();
+        assert!(details.dbus_activatable);
+        assert_eq!(
+            details.actions.get("new-window"),
+            Some(&Some("nautilus --new-window".to_string()))
+        );
+        assert_eq!(
+            dbus_object_path("org.gnome.Nautilus"),
+            "/org/gnome/Nautilus"
+        );
+        assert_eq!(
+            dbus_object_path("org.example.App-Foo"),
+            "/org/example/App_Foo"
+        );
+    }
+
+    #[test]
+    fn test_appstream_index_get() {
+        let temp_dir = TempDir::new("appstream");
+        let share_dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(share_dir.join("metainfo")).unwrap();
+        let metainfo = |id: &str| {
+            format!(
+                "<component type=\"desktop-application\"><id>{id}</id><name>{id}</name>\
+                 <launchable type=\"desktop-id\">{id}.desktop</launchable></component>"
+            )
+        };
+        std::fs::write(share_dir.join("metainfo/foo.xml"), metainfo("foo")).unwrap();
+        let app = |id: &str| App {
+            app_desktop_path: share_dir.join(format!("applications/{id}.desktop")),
+            ..Default::default()
+        };
+
+        let index = AppStreamIndex::default();
+        assert!(index.get(&app("foo")).is_some());
+        assert!(index.get(&app("bar")).is_none());
+        // What was looked up, found or not, is not read again
+        std::fs::remove_file(share_dir.join("metainfo/foo.xml")).unwrap();
+        std::fs::write(share_dir.join("metainfo/bar.xml"), metainfo("bar")).unwrap();
+        assert!(index.get(&app("foo")).is_some());
+        assert!(index.get(&app("bar")).is_none());
+        assert!(index.clone().get(&app("bar")).is_none());
+        assert!(AppStreamIndex::default().get(&app("bar")).is_some());
+    }
 
-    let icon = desktop_file_entry.icon?;
+    #[test]
+    fn test_site_start_url() {
+        // `start_url` before `"01A"` is mentioned, and after a quoted quote
+        let config = r#"{
+            "profiles": {"00": {"sites": ["01A", "01B"]}},
+            "sites": {
+                "01B": {"manifest": {"start_url": "https://b.example/"}},
+                "01A": {
+                    "config": {"name": "Say \"hi\""},
+                    "manifest": {"name": "A", "start_url": "https:\/\/a.example\/?q=\"x\""}
+                }
+            }
+        }"#;
+        assert_eq!(
+            site_start_url(config, "01A").as_deref(),
+            Some(r#"https://a.example/?q="x""#)
+        );
+        assert_eq!(
+            site_start_url(config, "01B").as_deref(),
+            Some("https://b.example/")
+        );
+        assert_eq!(site_start_url(config, "00"), None);
+        assert_eq!(site_start_url("{\"sites\": ", "01A"), None);
+    }
 
-    let name = desktop_file_entry.name.default;
-    let localized_names = desktop_file_entry.name.variants.into_iter().collect();
+    #[test]
+    fn test_classify_web_app() {
+        let path = Path::new("/home/foo/.local/share/applications/app.desktop");
 
-    Some((name, localized_names, icon.get_icon_path()))
-}
+        let chrome_app = "/opt/google/chrome/google-chrome --profile-directory=Default --app=https://example.org/";
+        assert_eq!(
+            classify_web_app(path, "", Some(chrome_app)),
+            Some(AppKind::WebApp {
+                url: Some("https://example.org/".to_string())
+            })
+        );
 
-pub fn get_default_search_paths() -> Vec<PathBuf> {
-    let home_dir =
-        PathBuf::from(std::env::var_os("HOME").expect("environment variable $HOME not found"));
+        let chrome_pwa = "/opt/google/chrome/google-chrome --profile-directory=Default --app-id=agimnkijcaahngcdmfeangaknmldooml";
+        assert_eq!(
+            classify_web_app(path, "", Some(chrome_pwa)),
+            Some(AppKind::WebApp { url: None })
+        );
 
-    vec![
-        "/usr/share/applications".into(),
-        home_dir.join(".local/share/applications"),
-        // Snap
-        "/var/lib/snapd/desktop/applications".into(),
-        // Flatpak
-        FLATPAK_GLOBAL_APP_PATH.into(),
-        FLATPAK_PERSONAL_APP_PATH.to_path_buf(),
-    ]
-}
+        // `firefoxpwa_start_url` tolerates a missing config
+        let firefox_pwa =
+            "/usr/bin/firefoxpwa site launch 01HZZZZZZZZZZZZZZZZZZZZZZZ --protocol %u";
+        assert!(matches!(
+            classify_web_app(path, "", Some(firefox_pwa)),
+            Some(AppKind::WebApp { .. })
+        ));
 
-/// Specialized implementation for Flatpak
-///
-/// Flatpak Application desktop file path:
-///
-/// ```text
-/// <flatpak_app_path>/<app_identifer>/current/active/files/share/applications/<app_identifier>.desktop
-/// ```
-fn get_flatpak_applications(flatpak_app_path: &Path) -> Result<Vec<App>> {
-    let dir = std::fs::read_dir(flatpak_app_path)?;
-    let mut apps = Vec::new();
+        let epiphany = "epiphany --application-mode --profile=/home/foo/.local/share/org.gnome.Epiphany.WebApp_1 https://example.org";
+        assert_eq!(
+            classify_web_app(path, "", Some(epiphany)),
+            Some(AppKind::WebApp {
+                url: Some("https://example.org".to_string())
+            })
+        );
 
-    for res_entry in dir {
-        let entry = res_entry?;
-        let app_desktop_file_path = {
-            let mut path = entry.path();
-            path.push("current/active/files/share/applications");
-            let app_identifier = entry
-                .file_name()
-                .into_string()
-                .expect("flatpak app identifier should be UTF-8 encoded");
-            path.push(format!("{}.desktop", app_identifier));
+        let webapp_manager = r#"[Desktop Entry]
+Type=Application
+Name=Example
+Exec=webapp-browser --url https://example.org
+X-WebApp-URL=https://example.org
 
-            path
-        };
+[Desktop Action Foo]
+X-WebApp-URL=https://example.com"#;
+        assert_eq!(
+            classify_web_app(path, webapp_manager, Some("webapp-browser")),
+            Some(AppKind::WebApp {
+                url: Some("https://example.org".to_string())
+            })
+        );
 
-        if !app_desktop_file_path.try_exists()? {
-            continue;
+        assert_eq!(
+            classify_web_app(path, "", Some("/usr/bin/google-chrome-stable %U")),
+            None
+        );
+        for name in [
+            "chrome-agimnkijcaahngcdmfeangaknmldooml-Default.desktop",
+            "chrome-agimnkijcaahngcdmfeangaknmldooml-Profile_1.desktop",
+        ] {
+            let path = Path::new("/home/foo/.local/share/applications").join(name);
+            assert_eq!(
+                classify_web_app(&path, "", Some("/usr/bin/google-chrome-stable")),
+                Some(AppKind::WebApp { url: None })
+            );
+        }
+        for name in [
+            "chrome-agimnkijcaahngcdmfeangaknmldooml-.desktop",
+            "chrome-agimnkijcaahngcdmfeangaknmldoo-Default.desktop",
+            "chrome-remote-desktop.desktop",
+        ] {
+            let path = Path::new("/home/foo/.local/share/applications").join(name);
+            assert_eq!(
+                classify_web_app(&path, "", Some("/usr/bin/google-chrome-stable")),
+                None
+            );
         }
+        assert_eq!(classify_web_app(path, "", Some("firefox %u")), None);
+    }
 
-        let desktop_file_content = std::fs::read_to_string(&app_desktop_file_path)?;
-        let Some((app_name, localized_app_names, opt_icon_path)) =
-            parse_desktop_file_content(&desktop_file_content)
-        else {
-            continue;
+    #[test]
+    fn test_desktop_entry_is_stale() {
+        let entry = |keys: &str| {
+            parse_desktop_entry_details(&format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\n{}",
+                keys
+            ))
+            .unwrap()
         };
 
-        let app = App {
-            name: app_name,
-            localized_app_names,
-            icon_path: opt_icon_path,
-            app_path_exe: None,
-            app_desktop_path: app_desktop_file_path,
-        };
-        apps.push(app);
+        assert!(!entry("Exec=sh -c true").is_stale());
+        assert!(entry("Exec=this-program-does-not-exist %U").is_stale());
+        assert!(entry("TryExec=this-program-does-not-exist\nExec=sh").is_stale());
+        assert!(!entry("TryExec=sh\nExec=this-program-does-not-exist").is_stale());
+        assert!(!entry("DBusActivatable=true").is_stale());
+        assert!(entry("").is_stale());
     }
 
-    Ok(apps)
-}
-
-pub fn get_all_apps(search_paths: &[PathBuf]) -> Result<Vec<App>> {
-    let search_dirs: HashSet<&PathBuf> = search_paths.iter().filter(|dir| dir.exists()).collect();
+    #[test]
+    fn test_parse_app_scope() {
+        let content = "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon=foo";
+        let system = parse_app(
+            Path::new("/usr/share/applications/foo.desktop"),
+            content,
+            false,
+        );
+        assert_eq!(system.unwrap().scope, InstallScope::Machine);
 
-    // for each dir, search for .desktop files
-    let mut apps: HashSet<App> = HashSet::new();
-    for dir in search_dirs {
-        // Specialized impl for Flatpak
-        if dir == Path::new(FLATPAK_GLOBAL_APP_PATH) || dir == &*FLATPAK_PERSONAL_APP_PATH {
-            let flatpak_apps = get_flatpak_applications(dir.as_path())?;
-            apps.extend(flatpak_apps);
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        let user = parse_app(
+            &home.join(".local/share/applications/foo.desktop"),
+            content,
+            false,
+        );
+        assert_eq!(user.unwrap().scope, InstallScope::User);
+    }
 
-            continue;
-        }
+    #[test]
+    fn test_classify_kind() {
+        let parse = |content: &str| {
+            parse_app(
+                Path::new("/usr/share/applications/foo.desktop"),
+                content,
+                false,
+            )
+            .unwrap()
+            .kind
+        };
+        let entry = "[Desktop Entry]\nType=Application\nName=Foo\nIcon=foo\n";
 
-        if !dir.exists() {
-            continue;
-        }
-        for entry in WalkDir::new(dir.clone()) {
-            if entry.is_err() {
-                continue;
-            }
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.extension().is_none() {
-                continue;
+        assert_eq!(parse(&format!("{entry}Exec=foo\n")), AppKind::Gui);
+        assert_eq!(
+            parse(&format!("{entry}Exec=htop\nTerminal=true\n")),
+            AppKind::Cli
+        );
+        assert_eq!(
+            parse(&format!("{entry}Exec=steam steam://rungameid/570\n")),
+            AppKind::Game {
+                url: Some("steam://rungameid/570".to_string())
             }
+        );
+        assert_eq!(
+            parse(&format!(
+                "{entry}Exec=nethack\nTerminal=true\nCategories=Game;RolePlaying;\n"
+            )),
+            AppKind::Game { url: None }
+        );
+        assert_eq!(
+            parse(&format!(
+                "{entry}Exec=gnome-control-center wifi\nX-GNOME-Settings-Panel=wifi\n"
+            )),
+            AppKind::System
+        );
+        assert_eq!(
+            parse(&format!(
+                "{entry}Exec=systemsettings kcm_mouse\nX-KDE-ServiceTypes=KCModule\n"
+            )),
+            AppKind::System
+        );
+    }
 
-            if path.extension().unwrap() == "desktop" && path.is_file() {
-                let desktop_file_content = std::fs::read_to_string(path)?;
-                let Some((app_name, localized_app_names, opt_icon_path)) =
-                    parse_desktop_file_content(&desktop_file_content)
-                else {
-                    continue;
-                };
-
-                let app = App {
-                    name: app_name,
-                    localized_app_names,
-                    icon_path: opt_icon_path,
-                    app_path_exe: None,
-                    app_desktop_path: path.to_path_buf(),
-                };
-                apps.insert(app);
-            }
-        }
+    #[test]
+    fn test_classify_waydroid_app() {
+        let path =
+            Path::new("/home/foo/.local/share/applications/waydroid.org.fdroid.fdroid.desktop");
+        let android_app = Some(AppKind::AndroidApp {
+            package: "org.fdroid.fdroid".to_string(),
+        });
+
+        assert_eq!(
+            classify_waydroid_app(path, Some("waydroid app launch org.fdroid.fdroid")),
+            android_app
+        );
+        assert_eq!(classify_waydroid_app(path, None), android_app);
+        assert_eq!(
+            classify_waydroid_app(
+                Path::new("/usr/share/applications/Waydroid.desktop"),
+                Some("waydroid first-launch")
+            ),
+            None
+        );
+        assert_eq!(
+            classify_waydroid_app(
+                Path::new("/usr/share/applications/waydroid.market.desktop"),
+                Some("waydroid app intent android.intent.action.VIEW market://details?id=")
+            ),
+            None
+        );
     }
-    Ok(apps.iter().cloned().collect())
-}
 
-impl AppTrait for App {
-    fn from_path(path: &Path) -> Result<Self> {
-        let desktop_file_content = std::fs::read_to_string(&path)?;
-        let Some((app_name, localized_app_names, opt_icon_path)) =
-            parse_desktop_file_content(&desktop_file_content)
-        else {
-            return Err(anyhow::anyhow!("invalid desktop file"));
+    #[test]
+    fn test_app_matcher_wm_class() {
+        let matcher = AppMatcher {
+            program: None,
+            wm_class: Some("jetbrains-toolbox".to_string()),
+            flatpak_id: None,
+            desktop_id: None,
         };
-
-        Ok(App {
-            name: app_name,
-            localized_app_names,
-            icon_path: opt_icon_path,
-            app_path_exe: None,
-            app_desktop_path: path.to_path_buf(),
-        })
+        // Its `comm` would be `jetbrains-toolb`
+        let process = ProcessInfo {
+            pid: 1,
+            exe: Some(PathBuf::from("/opt/jetbrains-toolbox/jetbrains-toolbox")),
+            cmdline: vec!["jetbrains-toolbox".to_string()],
+            flatpak_id: None,
+        };
+        assert!(matcher.matches(&process));
+        // Unreadable `exe` of another user's process
+        let process = ProcessInfo {
+            exe: None,
+            cmdline: vec!["/opt/jetbrains-toolbox/jetbrains-toolbox".to_string()],
+            ..process
+        };
+        assert!(matcher.matches(&process));
+        let process = ProcessInfo {
+            cmdline: vec!["jetbrains-toolbox-helper".to_string()],
+            ..process
+        };
+        assert!(!matcher.matches(&process));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_parse_xprop_output() {
+        assert_eq!(
+            parse_xprop_active_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007\n")
+                .as_deref(),
+            Some("0x3a00007")
+        );
+        assert_eq!(
+            parse_xprop_active_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0\n"),
+            None
+        );
+
+        let window = parse_xprop_window_properties(
+            "WM_CLASS(STRING) = \"code\", \"Code\"\n_NET_WM_PID(CARDINAL) = 12345\n",
+        );
+        assert_eq!(
+            window,
+            WindowInfo {
+                pid: Some(12345),
+                classes: vec!["code".to_string(), "Code".to_string()],
+                title: None,
+            }
+        );
+
+        assert_eq!(
+            parse_xprop_client_list("_NET_CLIENT_LIST(WINDOW): window id # 0x1a00003, 0x3a00007\n"),
+            ["0x1a00003", "0x3a00007"]
+        );
+        assert!(parse_xprop_client_list("_NET_CLIENT_LIST:  not found.\n").is_empty());
+
+        let window = parse_xprop_window_properties(
+            "_NET_WM_PID(CARDINAL) = 42\n\
+             _NET_WM_NAME(UTF8_STRING) = \"say \\\"hi\\\" = \\\\o/, bye\"\n",
+        );
+        assert_eq!(window.pid, Some(42));
+        assert_eq!(window.title.as_deref(), Some("say \"hi\" = \\o/, bye"));
+        let window = parse_xprop_window_properties("_NET_WM_NAME:  not found.\n");
+        assert_eq!(window.title, None);
+    }
 
     #[test]
-    fn test_get_apps() {
-        let default_search_path = get_default_search_paths();
-        let apps = get_all_apps(&default_search_path).unwrap();
-        assert!(!apps.is_empty());
+    fn test_parse_desktop_entry_details() {
+        let spotify = r#"[Desktop Entry]
+Type=Application
+Name=Spotify
+TryExec=spotify
+Exec=spotify %U
+Icon=spotify-client
+Categories=Audio;Music;Player;AudioVideo;
+StartupWMClass=spotify"#;
+
+        let details = parse_desktop_entry_details(spotify).unwrap();
+        assert_eq!(details.name, "Spotify");
+        assert!(!details.dbus_activatable);
+        assert_eq!(details.exec.as_deref(), Some("spotify %U"));
+        assert_eq!(details.try_exec.as_deref(), Some("spotify"));
+        assert_eq!(details.startup_wm_class.as_deref(), Some("spotify"));
+        assert_eq!(
+            details.categories,
+            vec!["Audio", "Music", "Player", "AudioVideo"]
+        );
     }
 
     #[test]
@@ -303,6 +2470,19 @@ Name=Open a new workspace"#;
         assert!(parse_desktop_file_content(zed).is_none());
     }
 
+    #[test]
+    fn test_parse_desktop_file_content_hidden_is_set() {
+        let autostart = r#"[Desktop Entry]
+Type=Application
+Name=Zed
+Exec=/home/foo/.local/zed.app/libexec/zed-editor %U
+Icon=/home/foo/.local/zed.app/share/icons/hicolor/512x512/apps/zed.png
+Hidden=true"#;
+
+        assert!(parse_desktop_file_content(autostart).is_none());
+    }
+
+    #[cfg(feature = "localization")]
     #[test]
     fn test_parse_desktop_file_with_localized_names() {
         let zed = r#"[Desktop Entry]
@@ -330,8 +2510,639 @@ Name=Open a new workspace"#;
         assert_eq!(name, "Zed");
         assert_eq!(localized_names.len(), 1);
         assert_eq!(
-            localized_names.get("zh_CN"),
+            localized_names.get("zh-CN"),
             Some(&"代码编辑器".to_string())
         );
     }
+
+    #[test]
+    fn test_get_all_apps_with_report() {
+        let temp_dir = TempDir::new("report");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::write(dir.join("icon.png"), b"").unwrap();
+        std::fs::write(
+            dir.join("foo.desktop"),
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo\nIcon={}",
+                dir.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("latin1.desktop"),
+            b"[Desktop Entry]\nName=Caf\xe9\n",
+        )
+        .unwrap();
+
+        let apps = get_all_apps(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(apps.len(), 1);
+        let report = get_all_apps_with_report(std::slice::from_ref(&dir));
+        assert_eq!(report.apps, apps);
+        assert_eq!(report.apps[0].name, "Foo");
+        assert_eq!(report.apps[0].sources, [DiscoverySource::DesktopFile]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, Some(dir.join("latin1.desktop")));
+        assert!(matches!(report.errors[0].error, Error::Io(_)));
+    }
+
+    #[test]
+    fn test_find_app() {
+        let temp_dir = TempDir::new("find");
+        let dir = temp_dir.path().to_path_buf();
+        let flatpak = dir.join("flatpak/app");
+        let flatpak_files = flatpak.join("org.foo.Bar/current/active/files/share/applications");
+        std::fs::create_dir_all(dir.join("kde4")).unwrap();
+        std::fs::create_dir_all(dir.join("foo-bar")).unwrap();
+        std::fs::create_dir_all(dir.join("x/y-z")).unwrap();
+        std::fs::create_dir_all(&flatpak_files).unwrap();
+        std::fs::write(dir.join("icon.png"), b"").unwrap();
+        let entry = |name: &str| {
+            format!(
+                "[Desktop Entry]\nType=Application\nName={name}\nExec=foo\nIcon={}",
+                dir.join("icon.png").display()
+            )
+        };
+        std::fs::write(dir.join("foo.desktop"), entry("Foo")).unwrap();
+        std::fs::write(dir.join("kde4/kate.desktop"), entry("Kate")).unwrap();
+        std::fs::write(dir.join("foo-bar/baz.desktop"), entry("Baz")).unwrap();
+        std::fs::write(dir.join("x/y-z/w.desktop"), entry("W")).unwrap();
+        std::fs::write(flatpak_files.join("org.foo.Bar.desktop"), entry("Bar")).unwrap();
+        std::fs::write(dir.join("broken.desktop"), "[Desktop Entry]\n").unwrap();
+        let search_paths = [dir.clone(), flatpak];
+
+        let name = |path_or_id: &str| {
+            find_app_in(path_or_id, &search_paths)
+                .unwrap()
+                .map(|app| app.name)
+        };
+        assert_eq!(name("foo").as_deref(), Some("Foo"));
+        assert_eq!(name("foo.desktop").as_deref(), Some("Foo"));
+        assert_eq!(name("kde4-kate").as_deref(), Some("Kate"));
+        assert_eq!(name("foo-bar-baz").as_deref(), Some("Baz"));
+        assert_eq!(name("x-y-z-w").as_deref(), Some("W"));
+        assert_eq!(name("foo-bar-qux"), None);
+        assert_eq!(name("org.foo.Bar").as_deref(), Some("Bar"));
+        assert_eq!(
+            name(dir.join("foo.desktop").to_str().unwrap()).as_deref(),
+            Some("Foo")
+        );
+        assert_eq!(name("missing"), None);
+        assert_eq!(name("../foo"), None);
+        // Tests run in the package root, where `Cargo.toml` is a file but
+        // not a desktop file ID of the search paths
+        assert!(Path::new("Cargo.toml").is_file());
+        assert_eq!(name("Cargo.toml"), None);
+        assert!(find_app_in("broken", &search_paths).is_err());
+    }
+
+    #[test]
+    fn test_find_in_fixture() {
+        let entry = |name: &str| {
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec={name}\nIcon={name}\n")
+        };
+        let fs = crate::fs::MemoryFs::new([
+            ("/image/usr/share/applications/foo.desktop", entry("Foo")),
+            (
+                "/image/usr/share/applications/kde4/kate.desktop",
+                entry("Kate"),
+            ),
+            (
+                "/image/usr/share/applications/README",
+                "not an entry".to_string(),
+            ),
+            (
+                "/image/usr/share/applications/broken.desktop",
+                "[Desktop Entry]\n".to_string(),
+            ),
+            (
+                "/image/var/lib/flatpak/app/org.foo.Bar/current/active/files/share/applications/org.foo.Bar.desktop",
+                entry("Bar"),
+            ),
+            // Still being installed
+            (
+                "/image/var/lib/flatpak/app/org.foo.Baz/current/metadata",
+                String::new(),
+            ),
+        ]);
+        let report = AppFinder::with_paths([
+            "/image/usr/share/applications",
+            "/image/var/lib/flatpak/app",
+            "/image/missing",
+        ])
+        .file_system(fs)
+        .order(crate::AppOrder::Discovery)
+        .find_with_report();
+        let names: Vec<&str> = report.apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, ["Foo", "Kate", "Bar"]);
+        assert_eq!(report.apps[2].sources, [DiscoverySource::Flatpak]);
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_associated_ids() {
+        let lists = [
+            MimeApps::parse(
+                "[Added Associations]\nx-scheme-handler/http=chromium.desktop;\n\
+                 [Removed Associations]\nx-scheme-handler/http=epiphany.desktop;\n",
+            ),
+            MimeApps::parse(
+                "[Default Applications]\nx-scheme-handler/http=firefox.desktop;\n\
+                 [Added Associations]\nx-scheme-handler/http=epiphany.desktop;firefox.desktop;\n",
+            ),
+        ];
+        assert_eq!(
+            associated_ids(&lists, "x-scheme-handler/http"),
+            ["firefox.desktop", "chromium.desktop"]
+        );
+        assert!(associated_ids(&lists, "x-scheme-handler/mailto").is_empty());
+
+        let list = "# preferred first\n+kitty.desktop\n-xterm.desktop\n\
+                    org.gnome.Console.desktop:new-window\n";
+        assert_eq!(
+            terminal_ids(list).collect::<Vec<_>>(),
+            ["kitty.desktop", "org.gnome.Console.desktop"]
+        );
+    }
+
+    #[test]
+    fn test_launch_spec() {
+        let temp_dir = TempDir::new("launch-spec");
+        let dir = temp_dir.path().to_path_buf();
+        let path = dir.join("foo.desktop");
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo --title %c %U\n\
+             Icon=foo\nPath=/tmp\nTerminal=true\n",
+        )
+        .unwrap();
+        let app = App {
+            app_desktop_path: path.clone(),
+            ..Default::default()
+        };
+
+        let spec = app.launch_spec().unwrap();
+        assert_eq!(spec.program, Path::new("foo"));
+        assert_eq!(
+            spec.args,
+            [
+                LaunchArg::Literal("--title".to_string()),
+                LaunchArg::Literal("Foo".to_string()),
+                LaunchArg::Urls,
+            ]
+        );
+        assert_eq!(spec.working_dir.as_deref(), Some(Path::new("/tmp")));
+        assert!(spec.terminal);
+        assert_eq!(
+            spec.args_with(&["https://example.org"]),
+            ["--title", "Foo", "https://example.org"]
+        );
+
+        std::fs::write(&path, "[Desktop Entry]\nType=Application\nName=Foo\n").unwrap();
+        assert!(matches!(
+            app.launch_spec(),
+            Err(Error::Missing {
+                missing: "Exec",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_can_open() {
+        let temp_dir = TempDir::new("can-open");
+        let dir = temp_dir.path().to_path_buf();
+        let path = dir.join("foo.desktop");
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo %F\nIcon=foo\n\
+             MimeType=text/plain;image/*;\n",
+        )
+        .unwrap();
+        let app = App {
+            app_desktop_path: path,
+            ..Default::default()
+        };
+
+        assert!(app.can_open("text/plain"));
+        assert!(app.can_open("image/png"));
+        assert!(!app.can_open("video/mp4"));
+        assert!(!app.can_open(dir.to_str().unwrap()));
+        assert!(mime_type_matches("TEXT/Plain", "text/plain"));
+        assert!(!mime_type_matches("image/*", "imagemagick"));
+    }
+
+    #[test]
+    fn test_keep_mime_types() {
+        let temp_dir = TempDir::new("mime-types");
+        let dir = temp_dir.path().to_path_buf();
+        let path = dir.join("foo.desktop");
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo %F\nIcon=foo\n\
+             MimeType=text/plain;image/*;\n",
+        )
+        .unwrap();
+
+        let lists = MimeTypeLists::default();
+        let apps = AppFinder::with_paths([&dir])
+            .keep_mime_types(lists.clone())
+            .find()
+            .unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(lists.take().get(&path).unwrap(), &["text/plain", "image/*"]);
+    }
+
+    #[test]
+    fn test_rank_handlers() {
+        let app = |id: &str, mime_types: &[&str]| {
+            (
+                App {
+                    name: id.to_string(),
+                    app_desktop_path: PathBuf::from("/usr/share/applications").join(id),
+                    ..Default::default()
+                },
+                mime_types
+                    .iter()
+                    .map(|mime_type| mime_type.to_string())
+                    .collect(),
+            )
+        };
+        let apps = [
+            app("gimp.desktop", &["image/png"]),
+            app("eog.desktop", &["image/*"]),
+            app("shotwell.desktop", &["image/png"]),
+            app("gedit.desktop", &["text/plain"]),
+            app("krita.desktop", &[]),
+        ];
+        let lists = [MimeApps::parse(
+            "[Default Applications]\nimage/png=eog.desktop;\n\
+             [Added Associations]\nimage/png=krita.desktop;\n\
+             [Removed Associations]\nimage/png=shotwell.desktop;\n",
+        )];
+        let names = |apps: Vec<App>| apps.into_iter().map(|app| app.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(rank_handlers(&lists, &["image/png".to_string()], &apps)),
+            ["eog.desktop", "krita.desktop", "gimp.desktop"]
+        );
+        assert_eq!(
+            names(rank_handlers(
+                &lists,
+                &["text/plain".to_string(), "image/png".to_string()],
+                &apps
+            )),
+            [
+                "gedit.desktop",
+                "eog.desktop",
+                "krita.desktop",
+                "gimp.desktop"
+            ]
+        );
+        assert!(rank_handlers(&lists, &["video/mp4".to_string()], &apps).is_empty());
+    }
+
+    #[test]
+    fn test_desktop_file_id() {
+        assert_eq!(
+            desktop_file_id(Path::new("/usr/share/applications/kde4/kate.desktop")).as_deref(),
+            Some("kde4-kate.desktop")
+        );
+        assert_eq!(
+            desktop_file_id(Path::new(
+                "/var/lib/flatpak/app/org.foo.Bar/current/active/files/share/applications/org.foo.Bar.desktop"
+            ))
+            .as_deref(),
+            Some("org.foo.Bar.desktop")
+        );
+        assert_eq!(desktop_file_id(Path::new("/opt/foo/foo.desktop")), None);
+        assert_eq!(
+            desktop_file_id(Path::new("/usr/share/applications/foo")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_with_root() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let temp_dir = TempDir::new("root");
+        let root = temp_dir.path().to_path_buf();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "opt/foo/foo.desktop",
+            "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo %U\nIcon=foo\n",
+        );
+        write(
+            "home/alice/.local/share/applications/bar.desktop",
+            "[Desktop Entry]\nType=Application\nName=Bar\nExec=/opt/bar/bar\nIcon=/opt/bar/bar.png\n",
+        );
+        write("opt/foo/bin/foo", "");
+        write("opt/bar/bar", "");
+        write("opt/bar/bar.png", "");
+        write("usr/share/pixmaps/foo.png", "");
+        for program in ["opt/foo/bin/foo", "opt/bar/bar"] {
+            std::fs::set_permissions(root.join(program), PermissionsExt::from_mode(0o755)).unwrap();
+        }
+        // Absolute links point into the root, not to the machine
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        symlink("/opt/foo/bin/foo", root.join("usr/bin/foo")).unwrap();
+        std::fs::create_dir_all(root.join("usr/share/applications")).unwrap();
+        symlink(
+            "/opt/foo/foo.desktop",
+            root.join("usr/share/applications/foo.desktop"),
+        )
+        .unwrap();
+
+        let finder = AppFinder::with_root(&root);
+        assert!(
+            finder
+                .search_paths()
+                .contains(&root.join("home/alice/.local/share/applications").as_path())
+        );
+        let report = finder.find_with_report();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        let [bar, foo] = &report.apps[..] else {
+            panic!("{:?}", report.apps);
+        };
+        assert_eq!(
+            foo.app_desktop_path,
+            root.join("usr/share/applications/foo.desktop")
+        );
+        assert_eq!(foo.app_path_exe, Some(root.join("opt/foo/bin/foo")));
+        assert_eq!(foo.icon_path, Some(root.join("usr/share/pixmaps/foo.png")));
+        assert_eq!(foo.scope, InstallScope::Machine);
+        assert_eq!(bar.app_path_exe, Some(root.join("opt/bar/bar")));
+        assert_eq!(bar.icon_path, Some(root.join("opt/bar/bar.png")));
+        assert_eq!(bar.scope, InstallScope::User);
+    }
+
+    #[test]
+    fn test_uninstall_action() {
+        let app = |path: &str, scope| App {
+            name: "Foo".to_string(),
+            app_desktop_path: path.into(),
+            scope,
+            ..Default::default()
+        };
+        let command = |app: App| match app.uninstall_action().unwrap() {
+            UninstallAction::Command(command) => {
+                let mut line = vec![command.program.to_string_lossy().into_owned()];
+                line.extend(command.args);
+                line.join(" ")
+            }
+            action => panic!("{action:?}"),
+        };
+        assert_eq!(
+            command(app(
+                "/home/foo/.local/share/flatpak/app/org.foo.Bar/current/active/files/share/applications/org.foo.Bar.desktop",
+                InstallScope::User
+            )),
+            "flatpak uninstall --user --noninteractive org.foo.Bar"
+        );
+        assert_eq!(
+            command(app(
+                "/var/lib/flatpak/exports/share/applications/org.foo.Bar.desktop",
+                InstallScope::Machine
+            )),
+            "flatpak uninstall --system --noninteractive org.foo.Bar"
+        );
+        assert_eq!(
+            command(app(
+                "/var/lib/snapd/desktop/applications/foo-bar_foo.desktop",
+                InstallScope::Machine
+            )),
+            "snap remove foo-bar"
+        );
+        let error = app("/opt/foo/foo.desktop", InstallScope::Machine)
+            .uninstall_action()
+            .unwrap_err();
+        assert!(matches!(error, Error::NotUninstallable { ref app, .. } if app == "Foo"));
+
+        let temp_dir = TempDir::new("dpkg");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::write(
+            dir.join("foo:amd64.list"),
+            "/usr\n/usr/share/applications/foo.desktop\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("foo:amd64.md5sums"),
+            "/usr/share/applications/bar.desktop\n",
+        )
+        .unwrap();
+        let package = |path: &str| dpkg_package(&dir, Path::new(path));
+        assert_eq!(
+            package("/usr/share/applications/foo.desktop").as_deref(),
+            Some("foo:amd64")
+        );
+        assert_eq!(package("/usr/share/applications/bar.desktop"), None);
+    }
+
+    #[test]
+    fn test_app_finder() {
+        let temp_dir = TempDir::new("finder");
+        let dir = temp_dir.path().to_path_buf();
+        let write = |path: &str, extra: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let name = path.file_stem().unwrap().to_string_lossy();
+            std::fs::write(
+                &path,
+                format!("[Desktop Entry]\nType=Application\nName={name}\nExec={name}\nIcon={name}\n{extra}"),
+            )
+            .unwrap();
+        };
+        write("a.desktop", "");
+        write("sub/b.desktop", "");
+        write("sub/deeper/c.desktop", "");
+        write(".hidden/d.desktop", "");
+        write("e.desktop", "NoDisplay=true\n");
+        write("excluded/f.desktop", "");
+
+        let names = |finder: AppFinder| {
+            let mut names: Vec<String> = finder
+                .find()
+                .unwrap()
+                .into_iter()
+                .map(|app| app.name)
+                .collect();
+            names.sort();
+            names
+        };
+        let finder = AppFinder::with_paths([&dir]).exclude(dir.join("excluded"));
+        assert_eq!(names(finder.clone()), ["a", "b", "c"]);
+        assert_eq!(names(finder.clone().max_depth(2)), ["a", "b"]);
+        assert_eq!(
+            names(finder.clone().include_hidden(true)),
+            ["a", "b", "c", "d", "e"]
+        );
+        assert!(names(finder.clone().providers([Provider::Flatpak])).is_empty());
+        assert_eq!(names(finder.clone().exclude_pattern("A*")), ["b", "c"]);
+        assert_eq!(
+            names(finder.clone().exclude_pattern("**/sub/deeper")),
+            ["a", "b"]
+        );
+        assert_eq!(
+            names(
+                finder
+                    .clone()
+                    .include_pattern(&format!("{}/sub/**", dir.display()))
+            ),
+            ["b", "c"]
+        );
+        assert_eq!(
+            names(
+                finder
+                    .clone()
+                    .include_pattern("c")
+                    .include_pattern("a.desktop")
+            ),
+            ["a", "c"]
+        );
+
+        #[derive(Default)]
+        struct Progress(std::sync::Mutex<Vec<String>>);
+        impl crate::ScanProgress for std::sync::Arc<Progress> {
+            fn on_provider_start(&self, provider: Provider) {
+                self.0.lock().unwrap().push(format!("{provider:?}"));
+            }
+            fn on_path_scanned(&self, scanned: usize, total: usize) {
+                self.0.lock().unwrap().push(format!("{scanned}/{total}"));
+            }
+        }
+        let options = |max_depth, symlinks| crate::PathOptions {
+            max_depth,
+            symlinks,
+        };
+        assert_eq!(
+            names(finder.clone().path_options(&dir, options(Some(2), None))),
+            ["a", "b"]
+        );
+        assert_eq!(
+            names(
+                finder
+                    .clone()
+                    .max_depth(1)
+                    .path_options(&dir, options(None, None))
+            ),
+            ["a"]
+        );
+        assert_eq!(
+            names(
+                finder
+                    .clone()
+                    .path_options("/elsewhere", options(Some(1), None))
+            ),
+            ["a", "b", "c"]
+        );
+        // A link to an ancestor is reported once, not followed forever
+        std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+        let report = finder
+            .clone()
+            .path_options(&dir, options(None, Some(crate::SymlinkPolicy::Follow)))
+            .find_with_report();
+        assert_eq!(report.apps.len(), 3);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, Some(dir.join("sub/loop")));
+        std::fs::remove_file(dir.join("sub/loop")).unwrap();
+
+        let apps = finder.clone().sort_by_name("en").find().unwrap();
+        let names: Vec<&str> = apps.iter().map(|app| app.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        for order in [crate::AppOrder::Path, crate::AppOrder::Discovery] {
+            let paths: Vec<PathBuf> = finder
+                .clone()
+                .order(order)
+                .find()
+                .unwrap()
+                .into_iter()
+                .map(|app| app.app_desktop_path)
+                .collect();
+            assert_eq!(
+                paths,
+                [
+                    dir.join("a.desktop"),
+                    dir.join("sub/b.desktop"),
+                    dir.join("sub/deeper/c.desktop")
+                ]
+            );
+        }
+
+        let progress = std::sync::Arc::new(Progress::default());
+        finder.progress(progress.clone()).find().unwrap();
+        let mut events = progress.0.lock().unwrap().clone();
+        events.sort();
+        assert_eq!(events, ["1/4", "2/4", "3/4", "4/4", "SearchPaths"]);
+    }
+
+    #[cfg(feature = "localization")]
+    #[test]
+    fn test_metadata() {
+        let temp_dir = TempDir::new("metadata");
+        let dir = temp_dir.path().to_path_buf();
+        let path = dir.join("foo.desktop");
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Foo\nName[de]=Fu\nExec=foo\nIcon=foo\n",
+        )
+        .unwrap();
+        let mut app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: path.clone(),
+            ..Default::default()
+        };
+        assert_eq!(app.metadata().localized_app_names["de"], "Fu");
+        app.load_metadata();
+        assert_eq!(app.display_name("de_DE"), "Fu");
+
+        // Read again once modified, which is later than the coarsest
+        // modification times can tell
+        std::fs::write(
+            &path,
+            "[Desktop Entry]\nType=Application\nName=Foo\nName[de]=Fuu\nExec=foo\nIcon=foo\n",
+        )
+        .unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(app.metadata().localized_app_names["de"], "Fuu");
+    }
+
+    #[test]
+    fn test_errors() {
+        let temp_dir = TempDir::new("errors");
+        let dir = temp_dir.path().to_path_buf();
+        let broken = dir.join("broken.desktop");
+        std::fs::write(&broken, "[Desktop Entry]\nType=Application\n").unwrap();
+
+        assert!(matches!(
+            App::from_path(&dir.join("missing.desktop")),
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+        assert!(
+            matches!(App::from_path(&broken), Err(Error::Parse { path, .. }) if path == broken)
+        );
+        let foo = dir.join("foo.desktop");
+        std::fs::write(
+            &foo,
+            "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo\n",
+        )
+        .unwrap();
+        let app = App {
+            name: "Foo".to_string(),
+            app_desktop_path: foo,
+            ..Default::default()
+        };
+        assert!(matches!(
+            launch_action(&app, "New"),
+            Err(Error::ActionNotFound { .. })
+        ));
+    }
 }
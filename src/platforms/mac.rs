@@ -1,10 +1,31 @@
-use crate::common::{App, AppTrait};
-use crate::utils::mac::{MacAppPath, MacSystemProfilterAppInfo, run_mdfind_to_get_app_list};
-use anyhow::Result;
+#[cfg(feature = "icons")]
+use crate::common::Icon;
+use crate::common::{App, AppTrait, DiscoverySource, InstallScope, ScanReport};
+use crate::finder::{AppFinder, Provider, par_map};
+use crate::fs::{FileKind, FileSystem, RootedFs, subdirs};
+use crate::handlers::{DefaultHandlers, OpenTarget};
+#[cfg(feature = "icons")]
+use crate::icon_cache::{encode_png, resize};
+use crate::launch_spec::LaunchSpec;
+use crate::metadata::{AppMetadata, disk_size};
+use crate::platforms::bundles::{developer_tool_bundles, mounted_volumes, nested_bundles};
+use crate::trace::{debug, timed};
+use crate::uninstall::UninstallAction;
+pub(crate) use crate::utils::mac::trash_item as trash;
+use crate::utils::mac::{
+    DocumentType, MacAppPath, MacSystemProfilterAppInfo, app_bundles_for_file,
+    content_type_conforms_to, content_type_of_extension, content_type_of_mime_type,
+    default_app_for_content_type, default_app_for_url, read_spotlight_metadata,
+    run_mdfind_by_bundle_id, run_mdfind_to_get_app_list, set_default_handler_for_content_type,
+    system_localized_name, user_locale,
+};
+use crate::{Error, Result};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 pub fn get_all_apps_mdfind(search_paths: &[PathBuf]) -> Result<Vec<App>> {
-    let apps_list = run_mdfind_to_get_app_list(search_paths)?;
+    let apps_list = run_mdfind_to_get_app_list(search_paths, &AppFinder::new())?;
     Ok(apps_list
         .iter()
         .map(|app_path| MacAppPath::new(PathBuf::from(app_path)).to_app())
@@ -27,8 +48,738 @@ pub fn get_default_search_paths() -> Vec<PathBuf> {
     paths
 }
 
+/// [`get_default_search_paths`] of the system below `root`, with the
+/// `Applications` of each of its users, for [`AppFinder::with_root`].
+pub(crate) fn root_search_paths(root: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![
+        root.join("Applications"),
+        root.join("System/Applications"),
+        root.join("System/Library/CoreServices"),
+    ];
+    paths.extend(
+        subdirs(&RootedFs::new(root), &root.join("Users"))
+            .into_iter()
+            .map(|home| home.join("Applications")),
+    );
+
+    paths
+}
+
 pub fn get_all_apps(search_paths: &[PathBuf]) -> Result<Vec<App>> {
-    get_all_apps_mdfind(search_paths)
+    AppFinder::with_paths(search_paths).find()
+}
+
+/// Like [`get_all_apps`], but a search path Spotlight cannot query, or a
+/// bundle with a broken `Info.plist`, is skipped and reported.
+pub fn get_all_apps_with_report(search_paths: &[PathBuf]) -> ScanReport {
+    AppFinder::with_paths(search_paths).find_with_report()
+}
+
+/// The apps Launch Services opens `https://` and `mailto:` URLs and shell
+/// scripts with.
+pub(crate) fn default_handlers() -> Result<DefaultHandlers> {
+    let app = |bundle: Option<PathBuf>| bundle.and_then(|bundle| App::from_path(&bundle).ok());
+
+    Ok(DefaultHandlers {
+        browser: app(default_app_for_url("https://")),
+        mail: app(default_app_for_url("mailto:")),
+        terminal: app(default_app_for_content_type(
+            "com.apple.terminal.shell-script",
+        )),
+    })
+}
+
+/// The apps of the bundles Launch Services lists for `path`, in its order.
+pub(crate) fn apps_for_file(path: &Path) -> Vec<App> {
+    app_bundles_for_file(path)
+        .into_iter()
+        .filter_map(|bundle| App::from_path(&bundle).ok())
+        .collect()
+}
+
+/// What a document of a type is known by, for matching it with the
+/// `CFBundleDocumentTypes` of an app.
+#[derive(Debug, Default)]
+struct DocumentKeys<'a> {
+    /// Lowercase, without the dot.
+    extension: Option<String>,
+    mime_type: Option<&'a str>,
+    content_type: Option<String>,
+}
+
+/// Whether `document_type` is that of a document with `keys`, the uniform
+/// types conforming with `conforms_to`.
+fn opens_document(
+    document_type: &DocumentType,
+    keys: &DocumentKeys,
+    conforms_to: impl Fn(&str, &str) -> bool,
+) -> bool {
+    let by_extension = keys.extension.as_deref().is_some_and(|extension| {
+        document_type
+            .extensions
+            .iter()
+            .any(|declared| declared == "*" || declared.eq_ignore_ascii_case(extension))
+    });
+    let by_mime_type = keys.mime_type.is_some_and(|mime_type| {
+        document_type
+            .mime_types
+            .iter()
+            .any(|declared| declared.eq_ignore_ascii_case(mime_type))
+    });
+    let by_content_type = keys.content_type.as_deref().is_some_and(|content_type| {
+        document_type
+            .content_types
+            .iter()
+            .any(|declared| conforms_to(content_type, declared))
+    });
+
+    by_extension || by_mime_type || by_content_type
+}
+
+/// Whether a `CFBundleDocumentTypes` entry of the bundle of `app` is that
+/// of `target`: by extension, MIME type, or a uniform type its own
+/// conforms to, e.g., `public.text` for a `.txt` file.
+pub(crate) fn can_open(app: &App, target: &OpenTarget) -> bool {
+    let keys = match target {
+        OpenTarget::Path(path) if path.is_dir() => DocumentKeys {
+            content_type: Some("public.folder".to_string()),
+            ..Default::default()
+        },
+        OpenTarget::Path(path) => {
+            let extension = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(str::to_lowercase);
+            DocumentKeys {
+                content_type: extension.as_deref().and_then(content_type_of_extension),
+                extension,
+                mime_type: None,
+            }
+        }
+        OpenTarget::MimeType(mime_type) => DocumentKeys {
+            extension: None,
+            mime_type: Some(mime_type),
+            content_type: content_type_of_mime_type(mime_type),
+        },
+    };
+
+    MacAppPath::new(app.app_desktop_path.clone())
+        .get_document_types()
+        .iter()
+        .any(|document_type| opens_document(document_type, &keys, content_type_conforms_to))
+}
+
+/// Bundles are started by Launch Services, not by a command line.
+pub(crate) fn launch_spec(_app: &App) -> Result<LaunchSpec> {
+    Err(Error::UnsupportedPlatform)
+}
+
+/// Make the bundle of `app` the default of the uniform type of
+/// `extension`, by its bundle identifier.
+pub(crate) fn set_default_for_extension(extension: &str, app: &App) -> Result<()> {
+    let Some(bundle_id) = MacAppPath::new(app.app_desktop_path.clone()).get_bundle_identifier()
+    else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "bundle identifier",
+        });
+    };
+    let Some(content_type) = content_type_of_extension(extension) else {
+        return Err(Error::Platform(
+            format!("no uniform type has the extension [{extension}]").into(),
+        ));
+    };
+
+    set_default_handler_for_content_type(&content_type, &bundle_id)
+}
+
+/// Bundles are moved to the Trash, except for the ones of macOS, which
+/// System Integrity Protection keeps, and the ones inside other bundles,
+/// which go with them.
+pub(crate) fn uninstall_action(app: &App) -> Result<UninstallAction> {
+    let path = &app.app_desktop_path;
+    let reason = if is_protected(path) {
+        "it is part of macOS, which System Integrity Protection keeps"
+    } else if path.parent().and_then(find_enclosing_bundle).is_some() {
+        "it is part of another app"
+    } else if path.extension().is_none_or(|ext| ext != "app") {
+        "it is not an app bundle"
+    } else {
+        return Ok(UninstallAction::Trash {
+            path: path.clone(),
+            app_store: path.join("Contents/_MASReceipt/receipt").is_file(),
+        });
+    };
+
+    Err(Error::NotUninstallable {
+        app: app.name.clone(),
+        reason: reason.to_string(),
+    })
+}
+
+/// Whether System Integrity Protection keeps `path` from being modified:
+/// it is on the system volume, or has the `restricted` flag, like the
+/// apps of macOS in `/Applications`, e.g., Safari.
+fn is_protected(path: &Path) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const SF_RESTRICTED: u32 = 0x0008_0000;
+
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.starts_with("/System")
+        || std::fs::symlink_metadata(&path)
+            .is_ok_and(|metadata| metadata.st_flags() & SF_RESTRICTED != 0)
+}
+
+/// The app of a bundle, or of a bundle identifier, e.g.,
+/// `com.apple.Safari`. Spotlight is asked for the bundles with the
+/// identifier, the bundles of the default search paths are read if it
+/// cannot tell.
+pub fn find_app(path_or_id: &str) -> Result<Option<App>> {
+    let path = Path::new(path_or_id);
+    if path.exists() {
+        return App::from_path(path).map(Some);
+    }
+    let is_bundle_id = !path_or_id.is_empty()
+        && path_or_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !is_bundle_id {
+        return Ok(None);
+    }
+
+    let finder = AppFinder::new();
+    let bundles: Vec<PathBuf> = match run_mdfind_by_bundle_id(path_or_id, &finder) {
+        Ok(bundles) if !bundles.is_empty() => bundles.into_iter().map(PathBuf::from).collect(),
+        // Spotlight may be off, or not have indexed the app yet
+        _ => finder
+            .search_paths()
+            .into_iter()
+            .flat_map(|dir| find_bundles(dir, None, &finder, &mut ScanReport::default()))
+            .collect(),
+    };
+    let bundle = bundles.into_iter().find(|bundle| {
+        MacAppPath::new(bundle.clone())
+            .get_bundle_identifier()
+            .is_some_and(|id| id.eq_ignore_ascii_case(path_or_id))
+    });
+
+    bundle.map(|bundle| App::from_path(&bundle)).transpose()
+}
+
+/// The `.app` bundles in `dir`, without the ones inside other bundles.
+fn find_bundles(
+    dir: &Path,
+    default_depth: Option<usize>,
+    finder: &AppFinder,
+    report: &mut ScanReport,
+) -> Vec<PathBuf> {
+    let mut bundles = Vec::new();
+    for entry in finder.walk(dir, default_depth) {
+        match entry {
+            Ok(entry) if entry.path.extension().is_some_and(|ext| ext == "app") => {
+                bundles.push(entry.path);
+            }
+            Ok(_) => {}
+            Err((path, e)) => report.skip(Some(path), e),
+        }
+    }
+
+    bundles
+}
+
+/// Whether the bundle at `path` is one of macOS, in `/System` of the root
+/// of `finder`.
+fn is_system_path(finder: &AppFinder, path: &Path) -> bool {
+    let root = finder.fs().root().unwrap_or(Path::new("/"));
+    path.starts_with(root.join("System"))
+}
+
+/// The user in whose home `path` is, unless it is the current user or the
+/// scan is of another system, whose users are all alike.
+fn other_user_of(finder: &AppFinder, path: &Path) -> Option<String> {
+    if finder.fs().root().is_some() {
+        return None;
+    }
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    home_user_of(path, home.as_deref())
+}
+
+/// The user whose home folder in `/Users` `path` is in, unless it is
+/// `home`, e.g., `bob` for `/Users/bob/Applications/Foo.app`. `Shared` is
+/// of all users.
+fn home_user_of(path: &Path, home: Option<&Path>) -> Option<String> {
+    if home.is_some_and(|home| path.starts_with(home)) {
+        return None;
+    }
+    let user = path
+        .strip_prefix("/Users")
+        .ok()?
+        .components()
+        .next()?
+        .as_os_str()
+        .to_str()?;
+    let is_shared = user.eq_ignore_ascii_case("Shared") || user.eq_ignore_ascii_case("Public");
+
+    (!is_shared).then(|| user.to_string())
+}
+
+/// How deep below the `Applications` of a volume bundles may be.
+const VOLUME_DEPTH: usize = 2;
+
+/// Spotlight does not walk the search paths, so the symlink option does
+/// not apply, unless subprocesses are disabled and the search paths are
+/// walked instead.
+pub(crate) fn find(finder: &AppFinder) -> ScanReport {
+    let mut report = ScanReport::default();
+    let mut seen = HashSet::new();
+    if finder.uses(Provider::SearchPaths) {
+        finder.start(Provider::SearchPaths);
+        find_in_search_paths(finder, &mut seen, &mut report);
+    }
+    if finder.uses(Provider::DeveloperTools) && !finder.is_cancelled() {
+        finder.start(Provider::DeveloperTools);
+        let bundles = developer_tool_bundles(finder)
+            .into_iter()
+            .filter(|path| !finder.is_excluded(path) && seen.insert(path.clone()))
+            .map(|path| (path, None))
+            .collect();
+        parse_bundles(
+            finder,
+            bundles,
+            DiscoverySource::Xcode,
+            finder.reads_metadata(),
+            &mut report,
+        );
+    }
+    if finder.uses(Provider::Volumes) && !finder.is_cancelled() {
+        finder.start(Provider::Volumes);
+        find_on_volumes(finder, &mut seen, &mut report);
+    }
+
+    report
+}
+
+fn find_in_search_paths(finder: &AppFinder, seen: &mut HashSet<PathBuf>, report: &mut ScanReport) {
+    let queries = par_map(finder.search_paths(), |search_path| {
+        let search_path = search_path.to_path_buf();
+        let mut walk_report = ScanReport::default();
+        let app_paths = if finder.allows_subprocesses() {
+            timed("mdfind", || {
+                run_mdfind_to_get_app_list(std::slice::from_ref(&search_path), finder)
+            })
+            .map(|app_paths| {
+                app_paths
+                    .into_iter()
+                    .filter(|app_path| !app_path.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+        } else {
+            Ok(timed("bundles", || {
+                find_bundles(&search_path, None, finder, &mut walk_report)
+            }))
+        };
+        (search_path, app_paths, walk_report)
+    });
+    let source = if finder.allows_subprocesses() {
+        DiscoverySource::Mdfind
+    } else {
+        DiscoverySource::Bundle
+    };
+
+    let mut bundles = Vec::new();
+    for (search_path, app_paths, walk_report) in queries {
+        report.errors.extend(walk_report.errors);
+        let app_paths: Vec<PathBuf> = match app_paths {
+            Ok(app_paths) => app_paths,
+            Err(e) => {
+                report.skip(Some(search_path), e);
+                continue;
+            }
+        };
+        for path in app_paths {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            if !finder.accepts(&search_path, &path)
+                || finder.is_excluded(&path)
+                || (!finder.includes_system() && is_system_path(finder, &path))
+                || (!finder.includes_other_users() && other_user_of(finder, &path).is_some())
+            {
+                continue;
+            }
+            // Spotlight lists the apps of other users, which may be private
+            if !MacAppPath::new(path.clone()).is_readable() {
+                debug!(path = %path.display(), "not readable");
+                continue;
+            }
+            if finder.finds_nested_apps() {
+                let nested = nested_bundles(finder.fs(), &path);
+                bundles.push(path);
+                bundles.extend(
+                    nested.into_iter().filter(|nested| {
+                        !finder.is_excluded(nested) && seen.insert(nested.clone())
+                    }),
+                );
+            } else {
+                bundles.push(path);
+            }
+        }
+    }
+
+    let bundles = bundles.into_iter().map(|path| (path, None)).collect();
+    parse_bundles(finder, bundles, source, finder.reads_metadata(), report);
+}
+
+/// Walk the `Applications` of each mounted volume on its own thread, and
+/// give up on those that take longer than the command timeout, e.g., a
+/// network share that is gone, which may hang for minutes. Their bundles
+/// are read without their localized names, as with
+/// [`AppFinder::lazy_metadata`].
+fn find_on_volumes(finder: &AppFinder, seen: &mut HashSet<PathBuf>, report: &mut ScanReport) {
+    let walks: Vec<_> = mounted_volumes(finder)
+        .into_iter()
+        .map(|volume| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let walker = finder.clone();
+            let applications = volume.join("Applications");
+            // Left running if it hangs, there is no cancelling a read
+            std::thread::spawn(move || {
+                let mut walk_report = ScanReport::default();
+                let bundles =
+                    find_bundles(&applications, Some(VOLUME_DEPTH), &walker, &mut walk_report);
+                let _ = tx.send((bundles, walk_report));
+            });
+            (volume, rx)
+        })
+        .collect();
+
+    let deadline = Instant::now() + finder.timeout();
+    let mut bundles = Vec::new();
+    for (volume, rx) in walks {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok((found, walk_report)) => {
+                report.errors.extend(walk_report.errors);
+                bundles.extend(
+                    found
+                        .into_iter()
+                        .filter(|path| !finder.is_excluded(path) && seen.insert(path.clone()))
+                        .map(|path| (path, Some(volume.clone()))),
+                );
+            }
+            Err(_) => {
+                let e = Error::VolumeTimeout {
+                    volume: volume.clone(),
+                    timeout: finder.timeout(),
+                };
+                report.skip(Some(volume), e);
+            }
+        }
+    }
+
+    parse_bundles(finder, bundles, DiscoverySource::Volume, false, report);
+}
+
+/// Read the `Info.plist`, and the localized names if `localized_names`, of
+/// each of `bundles`, with the volume it is on.
+fn parse_bundles(
+    finder: &AppFinder,
+    bundles: Vec<(PathBuf, Option<PathBuf>)>,
+    source: DiscoverySource,
+    localized_names: bool,
+    report: &mut ScanReport,
+) {
+    let counter = finder.path_counter(bundles.len());
+    let parsed = par_map(bundles, |(path, volume)| {
+        if finder.is_cancelled() {
+            return Ok(None);
+        }
+        let bundle = MacAppPath::new(path.clone());
+        let app = bundle.to_app_with(localized_names);
+        counter.scanned();
+        match app {
+            Some(mut app) => {
+                // Still being copied, found once it is complete
+                if let Err(e) = bundle.check_complete() {
+                    return Err((path, e));
+                }
+                if localized_names && finder.uses_system_localized_names() {
+                    add_system_localized_name(&mut app);
+                }
+                app.sources = vec![source];
+                app.volume = volume;
+                app.other_user = other_user_of(finder, &app.app_desktop_path);
+                if let Some(root) = finder.fs().root() {
+                    app.scope = InstallScope::of_path_below(root, &app.app_desktop_path);
+                }
+                Ok(Some(app))
+            }
+            // mdfind also lists command line tools, which are no bundles
+            None if bundle.is_app() => {
+                let e = Error::parse(&path, "invalid Info.plist");
+                Err((path, e))
+            }
+            None => {
+                debug!(path = %path.display(), "not an app bundle");
+                Ok(None)
+            }
+        }
+    });
+    for result in parsed {
+        match result {
+            Ok(app) => report.apps.extend(app),
+            Err((path, e)) => report.skip(Some(path), e),
+        }
+    }
+}
+
+/// Add the name of `app` in the language of the user from CoreFoundation,
+/// the last resort, unless the `.lproj` directories have it.
+fn add_system_localized_name(app: &mut App) {
+    if !cfg!(feature = "localization") {
+        return;
+    }
+    let Some(locale) = user_locale() else {
+        return;
+    };
+    if app.localized_app_names.contains_key(&locale) {
+        return;
+    }
+    if let Some(name) = system_localized_name(&app.app_desktop_path, &app.name) {
+        app.localized_app_names.insert(locale, name);
+    }
+}
+
+/// Apps are only found in the search paths, Spotlight lists them too.
+#[cfg(feature = "cache")]
+pub(crate) fn cache_sources() -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    Vec::new()
+}
+
+/// The localized names are in the `.lproj` directories of the bundle, the
+/// Spotlight attributes in the index of Spotlight.
+pub(crate) fn read_metadata(app: &App) -> AppMetadata {
+    let bundle = MacAppPath::new(app.app_desktop_path.clone());
+    let localized_app_names = if bundle.is_app() {
+        bundle.get_localized_app_names()
+    } else {
+        app.localized_app_names.clone()
+    };
+
+    AppMetadata {
+        localized_app_names,
+        version: app.version.clone(),
+        publisher: app.publisher.clone(),
+        size: disk_size(&app.app_desktop_path),
+        signature: None,
+        spotlight: read_spotlight_metadata(&app.app_desktop_path),
+    }
+}
+
+/// Load the icon of `app`, the largest image in its `.icns` file.
+#[cfg(feature = "icons")]
+pub fn load_icon(app: &App) -> Result<Icon> {
+    let Some(icon_path) = app.icon_path.as_ref() else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "icon",
+        });
+    };
+    let file = std::io::BufReader::new(std::fs::File::open(icon_path)?);
+    let family = tauri_icns::IconFamily::read(file)?;
+    let Some(icon_type) = family
+        .available_icons()
+        .into_iter()
+        .max_by_key(|icon_type| icon_type.pixel_width())
+    else {
+        return Err(Error::parse(icon_path, "no icons"));
+    };
+    let image = family
+        .get_icon_with_type(icon_type)?
+        .convert_to(tauri_icns::PixelFormat::RGBA);
+
+    Ok(Icon {
+        width: image.width(),
+        height: image.height(),
+        rgba: image.data().to_vec(),
+    })
+}
+
+/// The icon of `app` as a PNG image for an [`IconCache`](crate::IconCache),
+/// PNG icons, e.g., of iOS apps, are scaled without decoding them twice.
+#[cfg(feature = "icons")]
+pub(crate) fn render_icon(app: &App, size: u32) -> Result<Vec<u8>> {
+    if let Some(path) = &app.icon_path
+        && path.extension().is_some_and(|ext| ext == "png")
+    {
+        return crate::icon_cache::scale_png(path, &std::fs::read(path)?, size);
+    }
+    let icon = load_icon(app)?;
+    Ok(encode_png(&resize(&icon, size)?))
+}
+
+/// Find the `.app` bundle that contains `path`, e.g.,
+/// `/Applications/Foo.app/Contents/MacOS/Foo` => `/Applications/Foo.app`.
+fn find_enclosing_bundle(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|ancestor| ancestor.extension().is_some_and(|ext| ext == "app"))
+        .map(Path::to_path_buf)
+}
+
+/// Programs started by the launch agents that are loaded at login.
+fn get_launch_agent_programs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("/Library/LaunchAgents")];
+    if let Ok(home) = std::env::var("HOME") {
+        dirs.push(PathBuf::from(home).join("Library/LaunchAgents"));
+    }
+
+    let mut programs = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "plist") {
+                continue;
+            }
+            let Ok(value) = plist::Value::from_file(&path) else {
+                continue;
+            };
+            let Some(dict) = value.as_dictionary() else {
+                continue;
+            };
+            let run_at_load = dict
+                .get("RunAtLoad")
+                .and_then(|v| v.as_boolean())
+                .unwrap_or(false);
+            let disabled = dict
+                .get("Disabled")
+                .and_then(|v| v.as_boolean())
+                .unwrap_or(false);
+            if !run_at_load || disabled {
+                continue;
+            }
+
+            let program = dict.get("Program").and_then(|v| v.as_string()).or_else(|| {
+                dict.get("ProgramArguments")
+                    .and_then(|v| v.as_array())
+                    .and_then(|args| args.first())
+                    .and_then(|v| v.as_string())
+            });
+            if let Some(program) = program {
+                programs.push(PathBuf::from(program));
+            }
+        }
+    }
+
+    programs
+}
+
+/// Login items registered through System Events, this is the list shown in
+/// "System Settings > General > Login Items".
+fn get_login_items() -> Result<Vec<PathBuf>> {
+    let output = crate::process::output(
+        std::process::Command::new("osascript")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get the path of every login item"),
+        crate::process::DEFAULT_TIMEOUT,
+        None,
+    )?;
+    if !output.status.success() {
+        return Err(Error::command("osascript", &output));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .trim()
+        .split(", ")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Get the applications that are started when the user logs in, i.e., the
+/// login items and the apps launched by launch agents.
+pub fn get_autostart_apps() -> Result<Vec<App>> {
+    // Querying System Events can fail when the automation permission is not
+    // granted, launch agents are still worth returning in that case.
+    let login_items = get_login_items().unwrap_or_default();
+
+    let mut seen = HashSet::new();
+    let mut apps = Vec::new();
+    for program in login_items.into_iter().chain(get_launch_agent_programs()) {
+        let Some(bundle) = find_enclosing_bundle(&program) else {
+            continue;
+        };
+        if !seen.insert(bundle.clone()) {
+            continue;
+        }
+        if let Some(app) = MacAppPath::new(bundle).to_app() {
+            apps.push(app);
+        }
+    }
+
+    Ok(apps)
+}
+
+/// `file:///Applications/Visual%20Studio%20Code.app/` =>
+/// `/Applications/Visual Studio Code.app`
+fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    let encoded = url.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut idx = 0;
+    while idx < encoded.len() {
+        if encoded[idx] == b'%'
+            && let Some(byte) = encoded
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            idx += 3;
+            continue;
+        }
+        decoded.push(encoded[idx]);
+        idx += 1;
+    }
+    let path = String::from_utf8(decoded).ok()?;
+
+    Some(PathBuf::from(path.trim_end_matches('/')))
+}
+
+/// Bundles of the apps kept in the Dock, the `persistent-apps` of
+/// `com.apple.dock.plist`, in the Dock's order.
+fn dock_app_paths(dock: &plist::Value) -> Vec<PathBuf> {
+    dock.as_dictionary()
+        .and_then(|dict| dict.get("persistent-apps"))
+        .and_then(|apps| apps.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tile| {
+            tile.as_dictionary()?
+                .get("tile-data")?
+                .as_dictionary()?
+                .get("file-data")?
+                .as_dictionary()?
+                .get("_CFURLString")?
+                .as_string()
+        })
+        .filter_map(file_url_to_path)
+        .collect()
+}
+
+/// Get the apps kept in the Dock, in the order they are shown.
+pub fn get_pinned_apps() -> Result<Vec<App>> {
+    let home = std::env::var("HOME")?;
+    let dock = plist::Value::from_file(
+        PathBuf::from(home).join("Library/Preferences/com.apple.dock.plist"),
+    )?;
+
+    Ok(dock_app_paths(&dock)
+        .into_iter()
+        .filter_map(|path| MacAppPath::new(path).to_app())
+        .collect())
 }
 
 impl From<MacSystemProfilterAppInfo> for Option<App> {
@@ -42,7 +793,20 @@ impl AppTrait for App {
     fn from_path(path: &Path) -> Result<Self> {
         MacAppPath::new(path.to_path_buf())
             .to_app()
-            .ok_or(anyhow::Error::msg("Failed to create App from path"))
+            .ok_or_else(|| Error::parse(path, "not an app bundle"))
+    }
+
+    /// Open the bundle with `open(1)`, which goes through Launch Services.
+    fn launch(&self) -> Result<()> {
+        let output = std::process::Command::new("open")
+            .arg("-a")
+            .arg(&self.app_desktop_path)
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::command("open", &output));
+        }
+
+        Ok(())
     }
 }
 
@@ -50,6 +814,7 @@ impl AppTrait for App {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::TempDir;
     use crate::utils::mac::MacAppPath;
 
     #[test]
@@ -65,6 +830,199 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_dock_app_paths() {
+        let dock = plist::Value::from_reader_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>persistent-apps</key>
+    <array>
+        <dict>
+            <key>tile-data</key>
+            <dict>
+                <key>file-data</key>
+                <dict>
+                    <key>_CFURLString</key>
+                    <string>file:///System/Applications/Mail.app/</string>
+                </dict>
+            </dict>
+        </dict>
+        <dict>
+            <key>tile-data</key>
+            <dict>
+                <key>file-data</key>
+                <dict>
+                    <key>_CFURLString</key>
+                    <string>file:///Applications/Visual%20Studio%20Code.app/</string>
+                </dict>
+            </dict>
+        </dict>
+    </array>
+</dict>
+</plist>"#
+                .as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            dock_app_paths(&dock),
+            vec![
+                PathBuf::from("/System/Applications/Mail.app"),
+                PathBuf::from("/Applications/Visual Studio Code.app"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_spotlight_metadata() {
+        assert_eq!(
+            read_spotlight_metadata(Path::new("/Applications/Missing.app")),
+            None
+        );
+        let finder = read_spotlight_metadata(Path::new("/System/Library/CoreServices/Finder.app"));
+        assert!(finder.is_some());
+    }
+
+    #[test]
+    fn test_check_complete() {
+        let temp_dir = TempDir::new("incomplete");
+        let bundle = temp_dir.path().join("Foo.app");
+        let contents = bundle.join("Contents");
+        std::fs::create_dir_all(contents.join("MacOS")).unwrap();
+        let app = MacAppPath::new(bundle.clone());
+        let missing = |app: &MacAppPath| match app.check_complete() {
+            Err(Error::Incomplete { missing, .. }) => Some(missing),
+            _ => None,
+        };
+        assert_eq!(missing(&app), Some("Info.plist"));
+
+        std::fs::write(
+            contents.join("Info.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleExecutable</key>
+    <string>Foo</string>
+</dict>
+</plist>"#,
+        )
+        .unwrap();
+        assert_eq!(missing(&app), Some("executable"));
+        std::fs::write(contents.join("MacOS/Foo"), b"").unwrap();
+        assert_eq!(missing(&app), Some("executable"));
+        std::fs::write(contents.join("MacOS/Foo"), b"\xcf\xfa\xed\xfe").unwrap();
+        assert!(app.check_complete().is_ok());
+
+        std::fs::create_dir_all(contents.join("_CodeSignature")).unwrap();
+        assert_eq!(missing(&app), Some("code signature"));
+    }
+
+    #[test]
+    fn test_system_localized_name() {
+        let temp_dir = TempDir::new("localizable");
+        let bundle = temp_dir.path().join("Foo.app");
+        let resources = bundle.join("Contents/Resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(
+            bundle.join("Contents/Info.plist"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>CFBundleName</key>
+    <string>Foo</string>
+    <key>CFBundleDevelopmentRegion</key>
+    <string>en</string>
+</dict>
+</plist>"#,
+        )
+        .unwrap();
+
+        // Only in the `Localizable.strings` of the language of the user
+        let locale = user_locale().unwrap_or_else(|| "en".to_string());
+        let language = locale.split('-').next().unwrap();
+        for lproj in ["en", language, locale.as_str()] {
+            let lproj = resources.join(format!("{lproj}.lproj"));
+            std::fs::create_dir_all(&lproj).unwrap();
+            std::fs::write(
+                lproj.join("Localizable.strings"),
+                "\"Foo\" = \"F\u{f6}\u{f6}\";\n",
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            system_localized_name(&bundle, "Foo").as_deref(),
+            Some("F\u{f6}\u{f6}")
+        );
+    }
+
+    #[test]
+    fn test_home_user_of() {
+        let home = Some(Path::new("/Users/alice"));
+        assert_eq!(
+            home_user_of(Path::new("/Users/bob/Applications/Foo.app"), home).as_deref(),
+            Some("bob")
+        );
+        assert_eq!(
+            home_user_of(Path::new("/Users/alice/Applications/Foo.app"), home),
+            None
+        );
+        assert_eq!(home_user_of(Path::new("/Users/Shared/Foo.app"), home), None);
+        assert_eq!(home_user_of(Path::new("/Applications/Foo.app"), home), None);
+        assert_eq!(
+            home_user_of(Path::new("/Users/bob/Foo.app"), None).as_deref(),
+            Some("bob")
+        );
+    }
+
+    #[test]
+    fn test_parent_bundle() {
+        let suite = Path::new("/Applications/Suite.app");
+        assert_eq!(
+            MacAppPath::new(suite.join("Contents/Applications/Tool.app")).get_parent_bundle(),
+            Some(suite.to_path_buf())
+        );
+        assert_eq!(
+            MacAppPath::new(suite.to_path_buf()).get_parent_bundle(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_opens_document() {
+        let text = DocumentType {
+            extensions: vec!["TXT".to_string()],
+            mime_types: vec!["text/plain".to_string()],
+            content_types: vec!["public.text".to_string()],
+        };
+        let conforms_to = |content_type: &str, declared: &str| {
+            content_type == declared
+                || (content_type == "public.plain-text" && declared == "public.text")
+        };
+        let extension = |extension: &str| DocumentKeys {
+            extension: Some(extension.to_string()),
+            ..Default::default()
+        };
+        assert!(opens_document(&text, &extension("txt"), conforms_to));
+        assert!(!opens_document(&text, &extension("png"), conforms_to));
+        let mime_type = DocumentKeys {
+            mime_type: Some("TEXT/plain"),
+            ..Default::default()
+        };
+        assert!(opens_document(&text, &mime_type, conforms_to));
+        let content_type = DocumentKeys {
+            content_type: Some("public.plain-text".to_string()),
+            ..Default::default()
+        };
+        assert!(opens_document(&text, &content_type, conforms_to));
+
+        let all = DocumentType {
+            extensions: vec!["*".to_string()],
+            ..Default::default()
+        };
+        assert!(opens_document(&all, &extension("png"), conforms_to));
+    }
+
     #[test]
     fn test_get_all_apps() {
         let apps = get_all_apps(&[PathBuf::from("/"), PathBuf::from("/Users/home/steve")]).unwrap();
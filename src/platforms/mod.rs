@@ -1,3 +1,8 @@
+#[cfg(any(target_os = "macos", test))]
+mod bundles;
+mod root;
+pub(crate) use root::{PLATFORM, root_platform};
+
 #[cfg(target_os = "macos")]
 mod mac;
 #[cfg(target_os = "macos")]
@@ -0,0 +1,55 @@
+//! The platform of the system below the root of
+//! [`AppFinder::with_root`](crate::AppFinder::with_root), told by the files
+//! only that platform has.
+
+use crate::fs::FileSystem;
+use std::path::Path;
+
+/// The running platform, as the errors name it.
+pub(crate) const PLATFORM: &str = if cfg!(target_os = "macos") {
+    "macOS"
+} else if cfg!(target_os = "windows") {
+    "Windows"
+} else {
+    "Linux"
+};
+
+/// What is below the root of a system of each platform, the first of them
+/// that is there tells the platform.
+const MARKERS: [(&str, &str); 4] = [
+    ("macOS", "System/Library/CoreServices/SystemVersion.plist"),
+    ("Windows", "Windows/System32"),
+    ("Linux", "etc/os-release"),
+    ("Linux", "usr/lib/os-release"),
+];
+
+/// The platform of the system below `root`, `None` if it cannot tell,
+/// e.g., for a directory of apps only.
+pub(crate) fn root_platform(fs: &dyn FileSystem, root: &Path) -> Option<&'static str> {
+    MARKERS
+        .iter()
+        .find(|(_, marker)| fs.exists(&root.join(marker)))
+        .map(|(platform, _)| *platform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MemoryFs;
+
+    #[test]
+    fn test_root_platform() {
+        let fs = MemoryFs::new([
+            ("/mac/System/Library/CoreServices/SystemVersion.plist", ""),
+            ("/mac/etc/hosts", ""),
+            ("/windows/Windows/System32/kernel32.dll", ""),
+            ("/linux/usr/lib/os-release", ""),
+            ("/apps/foo.desktop", ""),
+        ]);
+        let platform = |root: &str| root_platform(&fs, Path::new(root));
+        assert_eq!(platform("/mac"), Some("macOS"));
+        assert_eq!(platform("/windows"), Some("Windows"));
+        assert_eq!(platform("/linux"), Some("Linux"));
+        assert_eq!(platform("/apps"), None);
+    }
+}
@@ -1,124 +1,51 @@
 use crate::AppTrait;
-use crate::common::App;
-use anyhow::Ok;
-use anyhow::Result;
+#[cfg(feature = "icons")]
+use crate::common::Icon;
+use crate::common::{
+    App, AppKind, DiscoverySource, InstallScope, InstallSource, RunningApp, ScanReport,
+    SignatureInfo, UninstallCommand,
+};
+use crate::finder::{AppFinder, Provider, par_map};
+use crate::fs::{FileKind, RootedFs, rebase, subdirs};
+use crate::handlers::{DefaultHandlers, OpenTarget};
+#[cfg(feature = "icons")]
+use crate::icon_cache::{encode_png, resize};
+use crate::launch_spec::{LaunchArg, LaunchSpec};
+use crate::metadata::{AppMetadata, disk_size};
+use crate::process::spawn_detached;
+use crate::trace::{debug, timed};
+use crate::uninstall::UninstallAction;
+use crate::utils::windows::{
+    ComGuard, ProcessInfo, ProgIdHandler, activate_application, expand_environment_strings,
+    extension_of_mime_type, find_wsa_icon, find_wsl_desktop_entry, find_wslg_icon,
+    foreground_process, is_console_program, load_indirect_string, manifest_requires_elevation,
+    native_architecture, parse_internet_shortcut, parse_wsa_arguments, parse_wslg_arguments,
+    pe_architecture, read_apps_folder, read_chocolatey_apps, read_execution_aliases,
+    read_file_user_choice, read_localized_file_names, read_open_with, read_packaged_apps,
+    read_portable_apps, read_processes, read_prog_id, read_registry_apps, read_removable_drives,
+    read_scoop_apps, read_supported_types, read_taskbar_pins, read_url_user_choice,
+    read_version_info, read_windows, read_winget_packages, resolve_known_folder_path,
+    resolve_shell_link, resolve_shell_path, run_as_admin, select_package_logo, split_arguments,
+    split_icon_location, user_locale, verify_signature, windows_terminal_is_default,
+    winget_name_matches,
+};
+#[cfg(feature = "icons")]
+use crate::utils::windows::{extract_icon, load_apps_folder_icon};
+use crate::{Error, Result};
 use lnk::ShellLink;
 use parselnk::Lnk;
 use parselnk::string_data;
-use serde_derive::Deserialize;
-use serde_derive::Serialize;
 use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
-use windows_icons::get_icon_by_path;
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PowerShellLnkParseResult {
-    #[serde(rename = "IconLocation")]
-    pub icon_location: String,
-    #[serde(rename = "Description")]
-    pub description: String,
-    #[serde(rename = "WorkingDirectory")]
-    pub working_directory: String,
-    #[serde(rename = "Arguments")]
-    pub arguments: String,
-    #[serde(rename = "Hotkey")]
-    pub hotkey: String,
-    #[serde(rename = "WindowStyle")]
-    pub window_style: i64,
-    #[serde(rename = "TargetPath")]
-    pub target_path: String,
-}
-
-pub fn parse_lnk_with_powershell_1(lnk_path: PathBuf) -> anyhow::Result<PowerShellLnkParseResult> {
-    let lnk_path = "C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Docker Desktop.lnk";
-
-    let script = format!(
-        r#"
-        function Get-Shortcut {{
-            param (
-                [string]$Path
-            )
-            
-            $shell = New-Object -ComObject WScript.Shell
-            $shortcut = $shell.CreateShortcut($Path)
-            
-            $properties = @{{
-                TargetPath = $shortcut.TargetPath
-                Arguments  = $shortcut.Arguments
-                Description = $shortcut.Description
-                Hotkey = $shortcut.Hotkey
-                IconLocation = $shortcut.IconLocation
-                WindowStyle = $shortcut.WindowStyle
-                WorkingDirectory = $shortcut.WorkingDirectory
-            }}
-            
-            return [PSCustomObject]$properties
-        }}
-
-        Get-Shortcut -Path "{}" | ConvertTo-Json
-    "#,
-        lnk_path
-    );
-
-    let output = Command::new("powershell")
-        .arg("-Command")
-        .arg(script)
-        .output()
-        .unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
-    // let result: PowerShellLnkParseResult = serde_json::from_str(&output).unwrap();
-
-    let json: PowerShellLnkParseResult = serde_json::from_str(&output.to_string())?;
-    Ok(json)
-}
 
-pub fn parse_lnk_with_powershell_2(lnk_path: PathBuf) -> anyhow::Result<App> {
-    let parsed_json = parse_lnk_with_powershell_1(lnk_path)?;
-    let target_path = PathBuf::from(parsed_json.target_path);
-    let desktop_path = if parsed_json.working_directory.len() == 0 {
-        PathBuf::from(parsed_json.working_directory)
-    } else {
-        target_path.parent().unwrap().to_path_buf()
-    };
-    let icon_path = if parsed_json.icon_location.len() == 0 {
-        None
-    } else {
-        Some(PathBuf::from(parsed_json.icon_location))
-    };
-    let name = if parsed_json.description.len() == 0 {
-        target_path
-            .parent()
-            .unwrap()
-            .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
-    } else {
-        let desc = parsed_json.description.clone();
-        if desc.starts_with("Runs ") {
-            // edge case for Tauri apps
-            desc[5..].to_string()
-        } else {
-            desc
-        }
-    };
-    let app = App {
-        name: name,
-        localized_app_names: BTreeMap::new(),
-        icon_path: icon_path,
-        app_path_exe: Some(target_path),
-        app_desktop_path: desktop_path,
-    };
-    Ok(app)
-}
+pub use crate::utils::windows::{Architecture, ExecutionAlias, RegistryApp};
 
 fn parse_lnk(path: PathBuf) -> Option<App> {
-    let shortcut = ShellLink::open(&path).unwrap();
+    let shortcut = ShellLink::open(&path).ok()?;
     let exe: Option<PathBuf> = match shortcut.link_info() {
         Some(info) => match info.local_base_path() {
             Some(path) => Some(PathBuf::from(path)),
@@ -131,52 +58,38 @@ fn parse_lnk(path: PathBuf) -> Option<App> {
         None => {
             // if exe is not None, use the exe's parent directory
             match &exe {
-                Some(exe) => exe.parent().unwrap().to_path_buf(),
+                Some(exe) => exe.parent()?.to_path_buf(),
                 None => return None,
             }
         }
     };
     let icon_path: Option<PathBuf> = shortcut.icon_location().as_ref().map(PathBuf::from);
+    let scope = InstallScope::of_path(&work_dir);
 
     Some(App {
-        name: path.file_stem().unwrap().to_str().unwrap().to_string(),
-        localized_app_names: BTreeMap::new(),
+        name: path.file_stem()?.to_string_lossy().to_string(),
         icon_path,
         app_path_exe: exe,
         app_desktop_path: work_dir,
+        scope,
+        sources: vec![DiscoverySource::StartMenu],
+        ..Default::default()
     })
 }
 
-/// Windows have path like this "%windir%\\system32\\mstsc.exe"
-/// This function will translate the path to the real path
+/// Windows have path like this "%windir%\\system32\\mstsc.exe" or
+/// "shell:Programs\\App.lnk". This function will translate the path to
+/// the real path, expanding environment variables wherever they are.
 fn translate_path_alias(path: PathBuf) -> PathBuf {
-    let mut path_str = path.to_string_lossy().to_string().to_lowercase();
-
-    // Common Windows environment variables
-    let env_vars = vec![
-        "%windir%",
-        "%systemroot%",
-        "%programfiles%",
-        "%programfiles(x86)%",
-        "%programdata%",
-        "%userprofile%",
-        "%appdata%",
-        "%localappdata%",
-        "%public%",
-        "%systemdrive%",
-    ];
-
-    for var in env_vars {
-        if path_str.starts_with(var) {
-            let env_name = var.trim_matches('%').to_uppercase();
-            if let std::result::Result::Ok(value) = std::env::var(env_name) {
-                path_str = path_str.replace(var, &value);
-                return PathBuf::from(path_str);
-            }
-        }
+    let path_str = path.to_string_lossy();
+    if let Some(resolved) = resolve_shell_path(&path_str) {
+        return resolved;
+    }
+    if !path_str.contains('%') {
+        return path;
     }
 
-    path
+    PathBuf::from(expand_environment_strings(&path_str))
 }
 
 fn strip_extended_prefix(path: PathBuf) -> PathBuf {
@@ -193,13 +106,13 @@ pub(crate) fn parse_lnk2(path: PathBuf) -> Option<App> {
         return None;
     };
 
-    let icon = lnk.string_data.icon_location.clone().map(|icon| {
-        if icon.to_string_lossy().starts_with("%") {
-            translate_path_alias(PathBuf::from(icon))
-        } else {
-            icon
+    let (icon, icon_index) = match lnk.string_data.icon_location.clone() {
+        Some(icon) => {
+            let (icon, icon_index) = split_icon_location(&icon.to_string_lossy());
+            (Some(translate_path_alias(icon)), icon_index)
         }
-    });
+        None => (None, None),
+    };
     let mut app_exe_path: Option<PathBuf> = match lnk.link_info.local_base_path {
         Some(path) => Some(PathBuf::from(path)),
         None => lnk.string_data.relative_path.clone(),
@@ -229,18 +142,13 @@ pub(crate) fn parse_lnk2(path: PathBuf) -> Option<App> {
     let app_exe_path = translate_path_alias(app_exe_path);
     let exe_abs_path = match app_exe_path.exists() {
         true => app_exe_path,
-        false => path.parent().unwrap().join(&app_exe_path),
+        false => path.parent()?.join(&app_exe_path),
     };
     if !exe_abs_path.exists() {
         return None;
     }
 
-    let exe_abs_path = std::fs::canonicalize(exe_abs_path);
-    let exe_path = if exe_abs_path.is_ok() {
-        strip_extended_prefix(exe_abs_path.unwrap())
-    } else {
-        return None;
-    };
+    let exe_path = strip_extended_prefix(std::fs::canonicalize(exe_abs_path).ok()?);
 
     let work_dir = lnk.string_data.working_dir;
     let work_dir = match work_dir {
@@ -251,111 +159,2351 @@ pub(crate) fn parse_lnk2(path: PathBuf) -> Option<App> {
                 dir
             }
         }
-        None => exe_path.parent().unwrap().to_path_buf(),
+        None => exe_path.parent()?.to_path_buf(),
     };
 
-    let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+    let name = path.file_stem()?.to_string_lossy().to_string();
+    let scope = InstallScope::of_path(&exe_path);
     Some(App {
         name,
-        localized_app_names: BTreeMap::new(),
         icon_path: icon,
+        icon_index,
         app_path_exe: Some(exe_path),
         app_desktop_path: work_dir,
+        scope,
+        sources: vec![DiscoverySource::StartMenu],
+        ..Default::default()
+    })
+}
+
+/// Resolve a shortcut through the Shell's own `IShellLink` implementation,
+/// which also handles MSI advertised shortcuts whose target is a
+/// component rather than a path.
+fn parse_lnk_with_shell_link(path: &Path) -> Option<App> {
+    let link = resolve_shell_link(path).ok()?;
+    let target = link.target?;
+    let exe = translate_path_alias(target);
+    let is_exe = exe
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"));
+    // Execution aliases, e.g., `WsaClient.exe`, cannot be followed
+    if !is_exe || !(exe.exists() || std::fs::symlink_metadata(&exe).is_ok()) {
+        return None;
+    }
+
+    let work_dir = match link.working_dir {
+        Some(dir) => translate_path_alias(dir),
+        None => exe.parent()?.to_path_buf(),
+    };
+    let (mut icon_path, icon_index) = match link.icon_location {
+        Some((icon, index)) => (Some(translate_path_alias(icon)), Some(index)),
+        None => (None, None),
+    };
+    let name = path.file_stem()?.to_str()?.to_string();
+    let scope = InstallScope::of_path(&exe);
+    let launch_args = link
+        .arguments
+        .as_deref()
+        .map(split_arguments)
+        .unwrap_or_default();
+
+    let file_name = exe
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let arguments = link.arguments.as_deref().unwrap_or_default();
+    let has_icon = icon_path.as_ref().is_some_and(|icon| icon.exists());
+    let kind = if file_name == "wslg.exe"
+        && let Some((distro, command)) = parse_wslg_arguments(arguments)
+    {
+        if !has_icon {
+            icon_path = find_wslg_icon(&distro, &name).or(icon_path);
+        }
+        AppKind::WslApp { distro, command }
+    } else if file_name == "wsaclient.exe"
+        && let Some(package) = parse_wsa_arguments(arguments)
+    {
+        if !has_icon {
+            icon_path = find_wsa_icon(&package).or(icon_path);
+        }
+        AppKind::AndroidApp { package }
+    } else {
+        AppKind::Gui
+    };
+
+    Some(App {
+        name,
+        icon_path,
+        icon_index,
+        app_path_exe: Some(exe),
+        app_desktop_path: work_dir,
+        kind,
+        launch_args,
+        requires_elevation: link.run_as_admin,
+        scope,
+        sources: vec![DiscoverySource::StartMenu],
+        ..Default::default()
     })
 }
 
-pub fn open_file_with(file_path: PathBuf, app: App) {
-    let mut command = Command::new(app.app_path_exe.unwrap());
-    command.arg(file_path);
-    command
-        .spawn()
-        .expect("Failed to open file with the specified application.");
+/// Add the display name Explorer shows for the shortcut, as given by the
+/// `desktop.ini` next to it, to `localized_app_names`.
+fn localize_shortcut_name(app: &mut App, lnk_path: &Path) {
+    if !cfg!(feature = "localization") {
+        return;
+    }
+    let Some(file_name) = lnk_path.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    let Some(dir) = lnk_path.parent() else {
+        return;
+    };
+    let names = read_localized_file_names(dir);
+    let Some(display_name) = names.get(&file_name.to_lowercase()) else {
+        return;
+    };
+    let display_name = if display_name.starts_with('@') {
+        match load_indirect_string(display_name) {
+            Some(display_name) => display_name,
+            None => return,
+        }
+    } else {
+        display_name.clone()
+    };
+    if let Some(locale) = user_locale() {
+        app.localized_app_names.insert(locale, display_name);
+    }
+}
+
+/// Strip the ` (2)` Explorer appends to a copied file's name.
+fn strip_copy_suffix(name: &str) -> &str {
+    if let Some(stripped) = name.strip_suffix(')')
+        && let Some((base, number)) = stripped.rsplit_once(" (")
+        && !base.is_empty()
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+    {
+        return base;
+    }
+
+    name
+}
+
+/// Flag the app when the manifest of its executable asks for elevation.
+fn apply_elevation(app: &mut App) {
+    if let Some(exe) = app.app_path_exe.as_ref()
+        && !app.requires_elevation
+    {
+        app.requires_elevation = manifest_requires_elevation(exe);
+    }
+}
+
+/// Fill in the version and publisher from the executable's `VERSIONINFO`,
+/// and replace names of copied shortcuts like "app (1)" with the product's.
+fn apply_version_info(app: &mut App) {
+    let Some(exe) = app.app_path_exe.as_ref() else {
+        return;
+    };
+    let Some(info) = read_version_info(exe) else {
+        return;
+    };
+
+    if app.version.is_none() {
+        app.version = info.product_version.or(info.file_version);
+    }
+    if app.publisher.is_none() {
+        app.publisher = info.company_name;
+    }
+    let base_name = strip_copy_suffix(&app.name);
+    if base_name.len() != app.name.len() {
+        app.name = info
+            .file_description
+            .or(info.product_name)
+            .unwrap_or_else(|| base_name.to_string());
+    }
+}
+
+/// Tell command line programs and system tools, e.g., the consoles of
+/// Windows Tools, from GUI apps by the executable a shortcut starts.
+fn apply_kind(app: &mut App) {
+    let Some(exe) = app
+        .app_path_exe
+        .as_ref()
+        .filter(|_| app.kind == AppKind::Gui)
+    else {
+        return;
+    };
+    let file_name = exe
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = exe
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if matches!(file_name.as_str(), "mmc.exe" | "control.exe")
+        || matches!(extension.as_str(), "msc" | "cpl")
+    {
+        app.kind = AppKind::System;
+    } else if matches!(extension.as_str(), "bat" | "cmd" | "ps1") || is_console_program(exe) {
+        app.kind = AppKind::Cli;
+    }
+}
+
+/// Load the icon of `app`, icons inside EXE and DLL files are extracted by
+/// their [`App::icon_index`].
+#[cfg(feature = "icons")]
+pub fn load_icon(app: &App) -> Result<Icon> {
+    if app.icon_path.is_none()
+        && let Some(aumid) = app.aumid.as_deref()
+    {
+        return load_apps_folder_icon(aumid, 256);
+    }
+    let Some(icon_path) = app.icon_path.as_ref().or(app.app_path_exe.as_ref()) else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "icon",
+        });
+    };
+
+    extract_icon(icon_path, app.icon_index.unwrap_or(0))
+}
+
+/// The icon of `app` as a PNG image for an [`IconCache`](crate::IconCache).
+#[cfg(feature = "icons")]
+pub(crate) fn render_icon(app: &App, size: u32) -> Result<Vec<u8>> {
+    let icon = load_icon(app)?;
+    Ok(encode_png(&resize(&icon, size)?))
+}
+
+/// The architecture `exe` is compiled for, `None` if it is no PE image.
+/// An ARM64 Windows runs [`Architecture::X86`] and [`Architecture::X64`]
+/// executables emulated.
+pub fn get_exe_architecture(exe: &Path) -> Option<Architecture> {
+    pe_architecture(&mut std::fs::File::open(exe).ok()?)
+}
+
+/// The architecture of Windows, rather than of the current process, e.g.,
+/// [`Architecture::Arm64`] for an x64 build running emulated on ARM64.
+pub fn get_native_architecture() -> Option<Architecture> {
+    native_architecture()
+}
+
+/// Check the Authenticode signature of `app`'s executable.
+pub fn get_signature_info(app: &App) -> Result<SignatureInfo> {
+    let Some(exe) = app.app_path_exe.as_ref() else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "executable",
+        });
+    };
+
+    Ok(verify_signature(exe))
+}
+
+pub fn open_file_with(file_path: PathBuf, app: App) -> Result<()> {
+    let Some(exe) = app.app_path_exe else {
+        return Err(Error::Missing {
+            app: app.name,
+            missing: "executable",
+        });
+    };
+    spawn_detached(Command::new(exe).arg(file_path))
 }
 
 pub fn get_default_search_paths() -> Vec<PathBuf> {
-    vec![
-        format!(
-            "{}\\Microsoft\\Windows\\Start Menu\\Programs",
-            std::env::var("APPDATA").unwrap()
-        )
-        .into(),
-        "C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs".into(),
-    ]
+    let mut paths: Vec<PathBuf> = Vec::new();
+    if let std::result::Result::Ok(app_data) = std::env::var("APPDATA") {
+        paths.push(PathBuf::from(app_data).join("Microsoft\\Windows\\Start Menu\\Programs"));
+    }
+    paths.push("C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs".into());
+    // Some installers only create desktop shortcuts
+    if let std::result::Result::Ok(profile) = std::env::var("USERPROFILE") {
+        paths.push(PathBuf::from(profile).join("Desktop"));
+    }
+    let public = std::env::var("PUBLIC").unwrap_or_else(|_| "C:\\Users\\Public".to_string());
+    paths.push(PathBuf::from(public).join("Desktop"));
+
+    paths
 }
 
-pub fn get_all_apps(search_paths: &[PathBuf]) -> Result<Vec<App>> {
-    // Create a HashSet of search paths starting with the default Windows paths
-    let mut path_set: HashSet<&PathBuf> = HashSet::new();
+/// [`get_default_search_paths`] of the system below `root`, with the
+/// Start Menu and desktop of each of its users, for
+/// [`AppFinder::with_root`].
+pub(crate) fn root_search_paths(root: &Path) -> Vec<PathBuf> {
+    let users = subdirs(&RootedFs::new(root), &root.join("Users"));
+    let mut paths: Vec<PathBuf> = users
+        .iter()
+        .filter(|home| !home.ends_with("Public"))
+        .map(|home| home.join("AppData\\Roaming\\Microsoft\\Windows\\Start Menu\\Programs"))
+        .collect();
+    paths.push(root.join("ProgramData\\Microsoft\\Windows\\Start Menu\\Programs"));
+    // The `Desktop` of `Public` too
+    paths.extend(users.iter().map(|home| home.join("Desktop")));
+
+    paths
+}
+
+/// The Start Menu folders, e.g., `Accessories` or a vendor's, between
+/// `search_path` and the shortcut at `path`, outermost first.
+fn shortcut_categories(search_path: &Path, path: &Path) -> Vec<String> {
+    path.parent()
+        .and_then(|dir| dir.strip_prefix(search_path).ok())
+        .map(|dir| {
+            dir.components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Links the shortcuts Steam and the Epic Games Launcher create for the
+/// games they install start them with.
+const GAME_URLS: &[&str] = &["steam://rungameid/", "com.epicgames.launcher://apps/"];
+
+/// Turn an Internet Shortcut to a website into a web app, and one to a game
+/// of a store client into a game; shortcuts to other URL schemes are not
+/// apps we can describe.
+fn parse_url_shortcut(path: &Path) -> Option<App> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let shortcut = parse_internet_shortcut(&content)?;
+    let url = shortcut.url.to_lowercase();
+    let kind = if url.starts_with("http://") || url.starts_with("https://") {
+        AppKind::WebApp {
+            url: Some(shortcut.url),
+        }
+    } else if GAME_URLS.iter().any(|prefix| url.starts_with(prefix)) {
+        AppKind::Game {
+            url: Some(shortcut.url),
+        }
+    } else {
+        return None;
+    };
+
+    Some(App {
+        name: path.file_stem()?.to_str()?.to_string(),
+        icon_path: shortcut.icon_file.map(translate_path_alias),
+        icon_index: shortcut.icon_index,
+        app_desktop_path: path.parent()?.to_path_buf(),
+        kind,
+        scope: InstallScope::of_path(path),
+        sources: vec![DiscoverySource::StartMenu],
+        ..Default::default()
+    })
+}
+
+impl RegistryApp {
+    /// The executable, guessed from `DisplayIcon`, which usually points to
+    /// the main executable of the app.
+    pub fn exe_path(&self) -> Option<PathBuf> {
+        let (icon_path, _index) = split_icon_location(self.display_icon.as_deref()?);
+        let icon_path = translate_path_alias(icon_path);
+        let is_exe = icon_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"));
+        let is_uninstaller = icon_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.to_lowercase().starts_with("unins"));
+
+        (is_exe && !is_uninstaller && icon_path.exists()).then_some(icon_path)
+    }
+
+    pub fn to_app(&self) -> Option<App> {
+        let app_path_exe = self.exe_path();
+        let app_desktop_path = match &self.install_location {
+            Some(location) => location.clone(),
+            None => app_path_exe.as_ref()?.parent()?.to_path_buf(),
+        };
+        let (icon_path, icon_index) = match self.display_icon.as_deref() {
+            Some(icon) => {
+                let (icon_path, icon_index) = split_icon_location(icon);
+                (Some(translate_path_alias(icon_path)), icon_index)
+            }
+            None => (None, None),
+        };
+
+        // System components may name themselves with a resource string
+        let mut localized_app_names = BTreeMap::new();
+        let name = match load_indirect_string(&self.display_name) {
+            Some(name) => {
+                if cfg!(feature = "localization")
+                    && let Some(locale) = user_locale()
+                {
+                    localized_app_names.insert(locale, name.clone());
+                }
+                name
+            }
+            None => self.display_name.clone(),
+        };
 
-    // Add extra search paths
-    for path in search_paths.iter() {
-        path_set.insert(path);
+        Some(App {
+            name,
+            localized_app_names,
+            icon_path,
+            icon_index,
+            app_path_exe,
+            app_desktop_path,
+            version: self.display_version.clone(),
+            publisher: self.publisher.clone(),
+            product_code: self.product_code.clone(),
+            uninstaller: self.uninstaller(),
+            scope: if self.per_user {
+                InstallScope::User
+            } else {
+                InstallScope::Machine
+            },
+            sources: vec![DiscoverySource::Registry],
+            ..Default::default()
+        })
     }
+}
+
+/// Get the programs listed in "Add/Remove Programs", read from the
+/// `Uninstall` registry keys. Entries hidden by `SystemComponent` are
+/// skipped.
+pub fn get_registry_apps() -> Vec<RegistryApp> {
+    read_registry_apps()
+        .into_iter()
+        .filter(|app| !app.system_component)
+        .collect()
+}
 
-    let mut apps = vec![];
-    for search_path in path_set {
-        if !search_path.exists() {
+/// Get the apps installed through Scoop.
+pub fn get_scoop_apps() -> Vec<App> {
+    read_scoop_apps()
+}
+
+/// Get the apps installed through Chocolatey.
+pub fn get_chocolatey_apps() -> Vec<App> {
+    read_chocolatey_apps()
+}
+
+/// Get the apps pinned to the taskbar, in the order they are pinned. Pins
+/// of packaged apps have no target path and are resolved through
+/// `shell:AppsFolder`.
+pub fn get_pinned_apps() -> Result<Vec<App>> {
+    let mut apps_folder_apps: Option<Vec<App>> = None;
+    let mut apps = Vec::new();
+    for link in read_taskbar_pins() {
+        if let std::result::Result::Ok(app) = App::from_path(&link) {
+            apps.push(app);
+            continue;
+        }
+
+        // Matched by name, which is that of the app when it was pinned
+        let Some(name) = link
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+        else {
             continue;
+        };
+        let apps_folder_apps =
+            apps_folder_apps.get_or_insert_with(|| get_apps_folder_apps().unwrap_or_default());
+        if let Some(app) = apps_folder_apps
+            .iter()
+            .find(|app| app.name.eq_ignore_ascii_case(&name))
+        {
+            apps.push(app.clone());
         }
+    }
+
+    Ok(apps)
+}
+
+/// Get the Android apps of the Windows Subsystem for Android, found
+/// through the Start Menu shortcuts WSA creates for them.
+pub fn get_wsa_apps() -> Vec<App> {
+    let Some(app_data) = std::env::var_os("APPDATA") else {
+        return Vec::new();
+    };
+    let programs = PathBuf::from(app_data).join("Microsoft\\Windows\\Start Menu\\Programs");
+
+    WalkDir::new(programs)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"))
+        })
+        .filter_map(|entry| App::from_path(entry.path()).ok())
+        .filter(|app| matches!(app.kind, AppKind::AndroidApp { .. }))
+        .collect()
+}
+
+/// Get the portable apps on removable drives, such as PortableApps.com
+/// apps on a USB stick. Only part of an [`AppFinder`] scan with
+/// [`Provider::Portable`].
+pub fn get_portable_apps() -> Vec<App> {
+    read_removable_drives()
+        .iter()
+        .flat_map(|root| read_portable_apps(root))
+        .collect()
+}
 
-        for entry in WalkDir::new(search_path)
-            .max_depth(2)
-            .into_iter()
-            .filter_map(|e| e.ok())
+/// Set [`App::winget_id`] of the apps winget lists as installed from its
+/// source, matched by name. It runs `winget list`, which takes a few
+/// seconds, hence it is not part of [`get_all_apps`].
+pub fn apply_winget_ids(apps: &mut [App]) -> Result<()> {
+    let packages = read_winget_packages()?;
+    for app in apps.iter_mut() {
+        if let Some(package) = packages
+            .iter()
+            .find(|package| winget_name_matches(&app.name, &package.name))
         {
-            let path = entry.path();
-            if path.is_file() {
+            app.winget_id = Some(package.id.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the App Execution Aliases of the current user, e.g., `wt.exe` or
+/// `python.exe`, with the packaged apps they start.
+pub fn get_execution_aliases() -> Vec<ExecutionAlias> {
+    read_execution_aliases()
+}
+
+/// Get the apps `shell:AppsFolder` lists, which is exactly what the Start
+/// menu shows, with the names it shows. Apps without an executable path
+/// get the identifier Windows knows them by as their [`App::aumid`], their
+/// icons come from [`load_icon`].
+pub fn get_apps_folder_apps() -> Result<Vec<App>> {
+    let apps = read_apps_folder()?
+        .into_iter()
+        .map(|item| {
+            let exe = resolve_known_folder_path(&item.parsing_name)
+                .or_else(|| Some(PathBuf::from(&item.parsing_name)))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+                        && path.exists()
+                });
+            let app_desktop_path = exe
+                .as_ref()
+                .and_then(|exe| exe.parent())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            let aumid = exe.is_none().then(|| item.parsing_name.clone());
+            let scope = exe
+                .as_deref()
+                .map(InstallScope::of_path)
+                .unwrap_or_default();
+
+            App {
+                name: item.display_name,
+                icon_path: exe.clone(),
+                app_path_exe: exe,
+                app_desktop_path,
+                aumid,
+                scope,
+                sources: vec![DiscoverySource::AppsFolder],
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    Ok(apps)
+}
+
+/// The desktop entry inside the WSL distribution a WSLg app comes from,
+/// e.g., `\\wsl.localhost\Ubuntu\usr\share\applications\org.gnome.gedit.desktop`.
+/// The distribution needs to be running.
+pub fn get_wsl_desktop_entry(app: &App) -> Option<PathBuf> {
+    match &app.kind {
+        AppKind::WslApp { distro, command } => find_wsl_desktop_entry(distro, command),
+        _ => None,
+    }
+}
+
+/// The logo of a packaged app that fits `size` (in pixels) best, as
+/// declared by its `AppxManifest.xml`.
+pub fn get_uwp_logo(app: &App, size: u32) -> Option<PathBuf> {
+    let (_, app_id) = app.aumid.as_deref()?.split_once('!')?;
+    select_package_logo(&app.app_desktop_path, app_id, size)
+}
+
+/// Get the packaged (MSIX/UWP) apps of the current user through the
+/// `PackageManager` API.
+pub fn get_uwp_apps() -> Result<Vec<App>> {
+    read_packaged_apps()
+}
+
+/// Names, in lowercase, of shortcuts and programs that are not apps a user
+/// would launch.
+const NOISE_NAME_PATTERNS: &[&str] = &[
+    "uninstall",
+    "redistributable",
+    "readme",
+    "read me",
+    "release notes",
+    "changelog",
+    "license",
+    "eula",
+    "check for updates",
+];
+
+/// Executable stems, in lowercase, of installers and uninstallers.
+const NOISE_EXE_PREFIXES: &[&str] = &["unins", "setup", "vcredist", "vc_redist", "dotnetfx"];
+
+/// Start Menu folders, in lowercase, that only hold leftovers.
+const NOISE_FOLDERS: &[&str] = &["uninstall", "uninstallers", "old", "archive", "archived"];
+
+/// Whether `app` is an uninstaller, an installer, a redistributable or a
+/// document rather than an app.
+pub fn is_noise_app(app: &App) -> bool {
+    let name = app.name.to_lowercase();
+    if NOISE_NAME_PATTERNS
+        .iter()
+        .any(|pattern| name.contains(pattern))
+    {
+        return true;
+    }
+
+    app.app_path_exe
+        .as_ref()
+        .and_then(|exe| exe.file_stem())
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .is_some_and(|stem| {
+            NOISE_EXE_PREFIXES
+                .iter()
+                .any(|prefix| stem.starts_with(prefix))
+        })
+}
+
+fn is_noise_folder(dir: &Path) -> bool {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .is_some_and(|name| NOISE_FOLDERS.contains(&name.as_str()))
+}
+
+pub fn get_all_apps(search_paths: &[PathBuf]) -> Result<Vec<App>> {
+    AppFinder::with_paths(search_paths).find()
+}
+
+/// Like [`get_all_apps`], with the directories that could not be read and
+/// the sources that failed, e.g., the package catalog.
+pub fn get_all_apps_with_report(search_paths: &[PathBuf]) -> ScanReport {
+    AppFinder::with_paths(search_paths).find_with_report()
+}
+
+/// The app of a shortcut, or of an ID: the AppUserModelID of a packaged
+/// app, e.g., `Microsoft.WindowsCalculator_8wekyb3d8bbwe!App`, or the
+/// `Uninstall` registry key or product code of an installed program.
+/// Only the packages or the registry are read, not the Start menu.
+pub fn find_app(path_or_id: &str) -> Result<Option<App>> {
+    let path = Path::new(path_or_id);
+    if path.is_file() {
+        return App::from_path(path).map(Some);
+    }
+    if path_or_id.contains('!') {
+        return Ok(get_uwp_apps()?.into_iter().find(|app| {
+            app.aumid
+                .as_deref()
+                .is_some_and(|aumid| aumid.eq_ignore_ascii_case(path_or_id))
+        }));
+    }
+
+    Ok(get_registry_apps()
+        .into_iter()
+        .find(|app| {
+            app.key_name.eq_ignore_ascii_case(path_or_id)
+                || app
+                    .product_code
+                    .as_deref()
+                    .is_some_and(|code| code.eq_ignore_ascii_case(path_or_id))
+        })
+        .and_then(|app| app.to_app()))
+}
+
+pub(crate) fn find(finder: &AppFinder) -> ScanReport {
+    let mut report = ScanReport::default();
+    let search_paths = if finder.uses(Provider::SearchPaths) {
+        finder.start(Provider::SearchPaths);
+        finder.search_paths()
+    } else {
+        Vec::new()
+    };
+
+    let mut shortcuts = Vec::new();
+    for search_path in search_paths {
+        for entry in finder.walk(search_path, Some(2)) {
+            let entry = match entry {
+                std::result::Result::Ok(entry) => entry,
+                Err((path, e)) => {
+                    report.skip(Some(path), e);
+                    continue;
+                }
+            };
+            let path = &entry.path;
+            if finder.excludes_noise() && path.parent().is_some_and(is_noise_folder) {
+                continue;
+            }
+            if entry.kind == Some(FileKind::File) {
                 if let Some(extension) = path.extension() {
-                    if extension == "lnk" {
-                        let result = App::from_path(&path);
-                        if let Some(app) = result.ok() {
-                            apps.push(app);
-                        } else {
-                        }
+                    if extension == "lnk" || extension == "url" {
+                        shortcuts.push((search_path, path.to_path_buf()));
                     }
                 }
             }
         }
     }
-    Ok(apps)
-}
 
-impl AppTrait for App {
-    fn from_path(path: &Path) -> Result<Self> {
-        if let Some(extension) = path.extension() {
-            if extension == "lnk" {
-                if let Some(app) = parse_lnk2(path.to_path_buf()) {
-                    return Ok(app);
+    debug!(shortcuts = shortcuts.len(), "walked the search paths");
+
+    // Resolving shortcuts is what takes time on a large Start Menu
+    let counter = finder.path_counter(shortcuts.len());
+    let mut apps: Vec<App> = timed("start menu", || {
+        par_map(shortcuts, |(search_path, path)| {
+            if finder.is_cancelled() {
+                return None;
+            }
+            // Keeps COM up on the worker for all the lookups of one shortcut
+            let _com = ComGuard::new();
+            let app = read_shortcut(&path, finder.reads_metadata());
+            counter.scanned();
+            let mut app = app.ok()?;
+            app.categories = shortcut_categories(search_path, &path);
+            // The targets are paths of the system below the root
+            if let Some(root) = finder.fs().root() {
+                app.app_path_exe = app.app_path_exe.map(|exe| rebase(root, &exe));
+                app.icon_path = app.icon_path.map(|icon| rebase(root, &icon));
+                app.scope = InstallScope::of_path_below(root, &path);
+            }
+            Some(app)
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    });
+
+    // Package managers install into their own directories; tag what the
+    // Start Menu already yielded, add the rest
+    let package_manager_apps: Vec<App> =
+        if finder.uses(Provider::PackageManagers) && !finder.is_cancelled() {
+            finder.start(Provider::PackageManagers);
+            timed("package managers", || {
+                get_scoop_apps()
+                    .into_iter()
+                    .chain(get_chocolatey_apps())
+                    .collect()
+            })
+        } else {
+            Vec::new()
+        };
+    let portable_apps = if finder.uses(Provider::Portable) && !finder.is_cancelled() {
+        finder.start(Provider::Portable);
+        timed("portable", get_portable_apps)
+    } else {
+        Vec::new()
+    };
+    // Each executable is resolved once, it reads the file system
+    let mut by_exe: HashMap<String, usize> = HashMap::new();
+    for (index, app) in apps.iter().enumerate() {
+        if let Some(exe) = app.app_path_exe.as_deref() {
+            by_exe.entry(exe_key(exe)).or_insert(index);
+        }
+    }
+    for app in package_manager_apps.into_iter().chain(portable_apps) {
+        let Some(exe) = app.app_path_exe.as_ref() else {
+            continue;
+        };
+        match by_exe.get(&exe_key(exe)).map(|&index| &mut apps[index]) {
+            Some(known) => {
+                known.sources.extend(app.sources);
+                known.install_source = app.install_source;
+                if app.version.is_some() {
+                    known.version = app.version;
                 }
             }
+            None => {
+                by_exe.insert(exe_key(exe), apps.len());
+                apps.push(app);
+            }
         }
-        Err(anyhow::anyhow!(
-            "Failed to create App from path: {:?}",
-            path
-        ))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Programs that never created a Start Menu shortcut
+    let known_exes: HashSet<String> = apps
+        .iter()
+        .filter_map(|app| app.app_path_exe.as_ref())
+        .map(|exe| exe.to_string_lossy().to_lowercase())
+        .collect();
+    let registry_apps = if finder.uses(Provider::Registry) && !finder.is_cancelled() {
+        finder.start(Provider::Registry);
+        timed("registry", get_registry_apps)
+    } else {
+        Vec::new()
+    };
+    for registry_app in registry_apps {
+        let Some(mut app) = registry_app.to_app() else {
+            continue;
+        };
+        let Some(exe) = app.app_path_exe.as_ref() else {
+            continue;
+        };
+        if !known_exes.contains(&exe.to_string_lossy().to_lowercase()) {
+            if finder.reads_metadata() {
+                apply_version_info(&mut app);
+            }
+            apply_elevation(&mut app);
+            apps.push(app);
+        }
+    }
 
-    #[test]
-    fn test_get_all_apps() {
-        let search_paths = vec![PathBuf::from(
-            "C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs",
-        )];
-        let apps = get_all_apps(&search_paths).unwrap();
-        assert!(!apps.is_empty());
+    // Store apps have neither shortcuts nor executables we could launch
+    // directly, a failure here should not hide the other apps.
+    if finder.uses(Provider::Uwp) && !finder.is_cancelled() {
+        finder.start(Provider::Uwp);
+        match timed("uwp", get_uwp_apps) {
+            std::result::Result::Ok(uwp_apps) => apps.extend(uwp_apps),
+            Err(e) => report.skip(None, e),
+        }
     }
 
-    #[test]
-    fn test_path_alias() {
-        let path = PathBuf::from("%windir%\\system32\\mstsc.exe");
-        let path = translate_path_alias(path);
-        assert_eq!(
-            path.to_string_lossy().to_lowercase(),
-            "c:\\windows\\system32\\mstsc.exe"
+    // Aliases are 0-byte stubs; they give packaged apps a command to run,
+    // or stand for apps whose package has no app list entry
+    let aliases = if finder.uses(Provider::ExecutionAliases) && !finder.is_cancelled() {
+        finder.start(Provider::ExecutionAliases);
+        timed("execution aliases", get_execution_aliases)
+    } else {
+        Vec::new()
+    };
+    for alias in aliases {
+        match apps
+            .iter_mut()
+            .find(|app| app.aumid.as_deref() == Some(alias.aumid.as_str()))
+        {
+            Some(app) => {
+                if app.app_path_exe.is_none() {
+                    app.app_path_exe = Some(alias.alias_path);
+                }
+                app.sources.push(DiscoverySource::ExecutionAlias);
+            }
+            None => {
+                let Some(name) = alias.alias_path.file_stem() else {
+                    continue;
+                };
+                let scope = InstallScope::of_path(&alias.alias_path);
+                apps.push(App {
+                    name: name.to_string_lossy().to_string(),
+                    icon_path: Some(alias.target.clone()),
+                    app_path_exe: Some(alias.alias_path.clone()),
+                    app_desktop_path: alias
+                        .target
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or(alias.alias_path),
+                    // Only on `PATH`, e.g., `python.exe` or `winget.exe`
+                    kind: AppKind::Cli,
+                    aumid: Some(alias.aumid),
+                    scope,
+                    sources: vec![DiscoverySource::ExecutionAlias],
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    if finder.excludes_noise() {
+        apps.retain(|app| !is_noise_app(app));
+    }
+    if !finder.includes_system() {
+        apps.retain(|app| !is_system_app(app));
+    }
+    report.apps = apps;
+
+    report
+}
+
+/// Whether `app` ships with Windows, i.e., its executable is in `%WINDIR%`.
+fn is_system_app(app: &App) -> bool {
+    let Some(windir) = std::env::var_os("WINDIR") else {
+        return false;
+    };
+    let windir = windir.to_string_lossy().to_lowercase();
+    app.app_path_exe
+        .as_ref()
+        .is_some_and(|exe| exe.to_string_lossy().to_lowercase().starts_with(&windir))
+}
+
+/// Compare executables by their real path, Scoop's `current` directory is
+/// a junction to the installed version.
+fn exe_key(exe: &Path) -> String {
+    std::fs::canonicalize(exe)
+        .map(strip_extended_prefix)
+        .unwrap_or_else(|_| exe.to_path_buf())
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+/// The apps of the `UserChoice` of `https` and `mailto`, and Windows
+/// Terminal if it is the default terminal application, among the apps
+/// found in the default search paths.
+pub(crate) fn default_handlers() -> Result<DefaultHandlers> {
+    let apps = get_all_apps(&get_default_search_paths())?;
+    let by_aumid = |aumid: &str| {
+        apps.iter()
+            .find(|app| {
+                app.aumid
+                    .as_deref()
+                    .is_some_and(|known| known.eq_ignore_ascii_case(aumid))
+            })
+            .cloned()
+    };
+    let handler = |scheme: &str| {
+        let handler = read_prog_id(&read_url_user_choice(scheme)?)?;
+        if let Some(aumid) = handler.aumid.as_deref() {
+            return by_aumid(aumid);
+        }
+        let exe = exe_key(handler.exe.as_ref()?);
+        apps.iter()
+            .find(|app| {
+                app.app_path_exe
+                    .as_deref()
+                    .is_some_and(|known| exe_key(known) == exe)
+            })
+            .cloned()
+    };
+    let terminal = if windows_terminal_is_default() {
+        apps.iter()
+            .find(|app| {
+                app.aumid
+                    .as_deref()
+                    .is_some_and(|aumid| aumid.starts_with("Microsoft.WindowsTerminal"))
+            })
+            .cloned()
+    } else {
+        None
+    };
+
+    Ok(DefaultHandlers {
+        browser: handler("https").or_else(|| handler("http")),
+        mail: handler("mailto"),
+        terminal,
+    })
+}
+
+/// The target of the shortcut with its arguments, started in the folder
+/// of the shortcut, as [`AppTrait::launch`] runs it.
+pub(crate) fn launch_spec(app: &App) -> Result<LaunchSpec> {
+    let Some(exe) = app.app_path_exe.as_ref() else {
+        return Err(Error::Missing {
+            app: app.name.clone(),
+            missing: "executable",
+        });
+    };
+
+    Ok(LaunchSpec {
+        program: exe.clone(),
+        args: app
+            .launch_args
+            .iter()
+            .cloned()
+            .map(LaunchArg::Literal)
+            .collect(),
+        working_dir: app
+            .app_desktop_path
+            .is_dir()
+            .then(|| app.app_desktop_path.clone()),
+        terminal: false,
+    })
+}
+
+/// The lowercase extension of `target`, with the dot, as the registry keys
+/// of file types are named. `None` for folders, which Explorer opens.
+fn target_extension(target: &OpenTarget) -> Option<String> {
+    match target {
+        OpenTarget::Path(path) if path.is_dir() => None,
+        OpenTarget::Path(path) => path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| format!(".{}", extension.to_lowercase())),
+        OpenTarget::MimeType(mime_type) => {
+            extension_of_mime_type(mime_type).map(|extension| extension.to_lowercase())
+        }
+    }
+}
+
+/// The file name of the executable of `app`, lowercase, as the
+/// `OpenWithList`s and `HKEY_CLASSES_ROOT\Applications` know it.
+fn exe_name(app: &App) -> Option<String> {
+    app.app_path_exe
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().to_lowercase())
+}
+
+/// Whether `handler` is the app with `aumid` and the [`exe_key`] `app_exe`.
+fn is_handler(handler: &ProgIdHandler, aumid: Option<&str>, app_exe: Option<&str>) -> bool {
+    let by_aumid = handler
+        .aumid
+        .as_deref()
+        .zip(aumid)
+        .is_some_and(|(aumid, known)| known.eq_ignore_ascii_case(aumid));
+    let by_exe = handler
+        .exe
+        .as_deref()
+        .zip(app_exe)
+        .is_some_and(|(exe, known)| exe_key(exe) == known);
+
+    by_aumid || by_exe
+}
+
+/// Whether the executable of `app` is registered for the extension of
+/// `target`: in its `SupportedTypes`, in an `OpenWithList` of the
+/// extension, or as the program of one of its ProgIDs. Folders are opened
+/// by Explorer.
+pub(crate) fn can_open(app: &App, target: &OpenTarget) -> bool {
+    let Some(extension) = target_extension(target) else {
+        return false;
+    };
+    let exe_name = exe_name(app);
+
+    if let Some(exe_name) = exe_name.as_deref()
+        && read_supported_types(exe_name).contains(&extension)
+    {
+        return true;
+    }
+    let open_with = read_open_with(&extension);
+    if let Some(exe_name) = exe_name.as_deref()
+        && open_with.exe_names.iter().any(|name| name == exe_name)
+    {
+        return true;
+    }
+    let app_exe = app.app_path_exe.as_deref().map(exe_key);
+    open_with
+        .prog_ids
+        .iter()
+        .filter_map(|prog_id| read_prog_id(prog_id))
+        .any(|handler| is_handler(&handler, app.aumid.as_deref(), app_exe.as_deref()))
+}
+
+/// The apps found in the default search paths that are registered for the
+/// extension of `path`, in the order of the "Open with" menu of Explorer:
+/// the `UserChoice`, the ProgIDs of the extension, its `OpenWithList`s,
+/// the most recently used first, then the apps that list it in their
+/// `SupportedTypes`.
+pub(crate) fn apps_for_file(path: &Path) -> Vec<App> {
+    let Some(extension) = target_extension(&OpenTarget::Path(path)) else {
+        return Vec::new();
+    };
+    let Ok(apps) = get_all_apps(&get_default_search_paths()) else {
+        return Vec::new();
+    };
+    let app_exes: Vec<Option<String>> = apps
+        .iter()
+        .map(|app| app.app_path_exe.as_deref().map(exe_key))
+        .collect();
+    let exe_names: Vec<Option<String>> = apps.iter().map(exe_name).collect();
+    let open_with = read_open_with(&extension);
+
+    let mut ranked: Vec<usize> = Vec::new();
+    for handler in read_file_user_choice(&extension)
+        .into_iter()
+        .chain(open_with.prog_ids)
+        .filter_map(|prog_id| read_prog_id(&prog_id))
+    {
+        ranked.extend((0..apps.len()).find(|&index| {
+            is_handler(
+                &handler,
+                apps[index].aumid.as_deref(),
+                app_exes[index].as_deref(),
+            )
+        }));
+    }
+    for name in &open_with.exe_names {
+        ranked.extend(
+            exe_names
+                .iter()
+                .position(|exe_name| exe_name.as_ref() == Some(name)),
+        );
+    }
+    ranked.extend((0..apps.len()).filter(|&index| {
+        exe_names[index]
+            .as_deref()
+            .is_some_and(|exe_name| read_supported_types(exe_name).contains(&extension))
+    }));
+
+    let mut seen = HashSet::new();
+    ranked
+        .into_iter()
+        .filter(|index| seen.insert(*index))
+        .map(|index| apps[index].clone())
+        .collect()
+}
+
+/// Windows keeps the `UserChoice` of each type with a hash of the user and
+/// the app, so that only the user can change it, in the Settings or the
+/// "Open with" dialog, not a program.
+pub(crate) fn set_default_for_extension(_extension: &str, _app: &App) -> Result<()> {
+    Err(Error::UnsupportedPlatform)
+}
+
+/// The index of the app `process` belongs to, by its AppUserModelID or
+/// executable. `app_exes` are the [`exe_key`]s of `apps`.
+fn find_process_app(
+    apps: &[App],
+    app_exes: &[Option<String>],
+    process: &ProcessInfo,
+) -> Option<usize> {
+    let by_aumid = process.aumid.as_deref().and_then(|aumid| {
+        apps.iter()
+            .position(|app| app.aumid.as_deref() == Some(aumid))
+    });
+
+    by_aumid.or_else(|| {
+        let exe = exe_key(process.exe.as_ref()?);
+        app_exes
+            .iter()
+            .position(|app_exe| app_exe.as_ref() == Some(&exe))
+    })
+}
+
+/// Get the app that owns the foreground window. UWP apps are resolved
+/// through the `ApplicationFrameHost.exe` frame around their windows.
+///
+/// Returns `None` if no window has the focus or if it does not belong to an
+/// app found in the default search paths.
+pub fn get_frontmost_application() -> Result<Option<App>> {
+    let Some(process) = foreground_process() else {
+        return Ok(None);
+    };
+
+    let apps = get_all_apps(&get_default_search_paths())?;
+    let app_exes: Vec<Option<String>> = apps
+        .iter()
+        .map(|app| app.app_path_exe.as_deref().map(exe_key))
+        .collect();
+
+    Ok(find_process_app(&apps, &app_exes, &process).map(|idx| apps[idx].clone()))
+}
+
+/// Get the apps found in the default search paths that are currently
+/// running, with their processes and window titles.
+///
+/// Processes are mapped to apps by their executable or, for packaged apps,
+/// their AppUserModelID.
+pub fn get_running_apps() -> Result<Vec<RunningApp>> {
+    let apps = get_all_apps(&get_default_search_paths())?;
+    let app_exes: Vec<Option<String>> = apps
+        .iter()
+        .map(|app| app.app_path_exe.as_deref().map(exe_key))
+        .collect();
+
+    let mut pids_per_app: Vec<Vec<u32>> = vec![Vec::new(); apps.len()];
+    for process in read_processes()? {
+        if let Some(idx) = find_process_app(&apps, &app_exes, &process) {
+            pids_per_app[idx].push(process.pid);
+        }
+    }
+
+    let windows = read_windows();
+    Ok(apps
+        .into_iter()
+        .zip(pids_per_app)
+        .filter(|(_app, pids)| !pids.is_empty())
+        .map(|(app, mut pids)| {
+            pids.sort_unstable();
+            let window_titles = windows
+                .iter()
+                .filter(|window| pids.contains(&window.pid))
+                .map(|window| window.title.clone())
+                .collect();
+            RunningApp {
+                app,
+                pids,
+                window_titles,
+            }
+        })
+        .collect())
+}
+
+/// Split the executable out of a command line like
+/// `"C:\Program Files\Foo\foo.exe" --minimized` or `C:\foo.exe /background`.
+fn parse_command_line_exe(command_line: &str) -> Option<PathBuf> {
+    let command_line = command_line.trim();
+    let exe = if let Some(quoted) = command_line.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        &quoted[..end]
+    } else {
+        // Unquoted paths may contain spaces, cut right after the extension
+        // if there is one. Only ASCII is lowercased, so that the offsets
+        // are the same in both
+        let lowercase = command_line.to_ascii_lowercase();
+        match lowercase.find(".exe") {
+            Some(pos) => &command_line[..pos + 4],
+            None => command_line.split_whitespace().next()?,
+        }
+    };
+
+    if exe.is_empty() {
+        None
+    } else {
+        Some(translate_path_alias(PathBuf::from(exe)))
+    }
+}
+
+/// Programs registered in the `Run` registry keys.
+fn get_run_key_apps() -> Vec<App> {
+    use winreg::RegKey;
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_32KEY};
+
+    const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+    let keys = [
+        RegKey::predef(HKEY_CURRENT_USER).open_subkey(RUN_KEY),
+        RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(RUN_KEY),
+        RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey_with_flags(RUN_KEY, KEY_READ | KEY_WOW64_32KEY),
+    ];
+
+    let mut apps = Vec::new();
+    for key in keys.into_iter().filter_map(|key| key.ok()) {
+        for (value_name, _) in key.enum_values().filter_map(|value| value.ok()) {
+            let Some(command_line) = key.get_value::<String, _>(&value_name).ok() else {
+                continue;
+            };
+            let Some(exe) = parse_command_line_exe(&command_line) else {
+                continue;
+            };
+            if !exe.exists() {
+                continue;
+            }
+            let Some(work_dir) = exe.parent().map(Path::to_path_buf) else {
+                continue;
+            };
+            let scope = InstallScope::of_path(&work_dir);
+
+            apps.push(App {
+                name: value_name,
+                app_path_exe: Some(exe),
+                app_desktop_path: work_dir,
+                scope,
+                sources: vec![DiscoverySource::Registry],
+                ..Default::default()
+            });
+        }
+    }
+
+    apps
+}
+
+/// Get the applications that are started when the user logs in, i.e., the
+/// programs in the `Run` registry keys and the shortcuts in the Startup
+/// folders.
+pub fn get_autostart_apps() -> Result<Vec<App>> {
+    let mut startup_folders: Vec<PathBuf> =
+        vec!["C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs\\StartUp".into()];
+    if let std::result::Result::Ok(appdata) = std::env::var("APPDATA") {
+        startup_folders.push(
+            format!(
+                "{}\\Microsoft\\Windows\\Start Menu\\Programs\\Startup",
+                appdata
+            )
+            .into(),
+        );
+    }
+
+    let mut apps = get_run_key_apps();
+    for folder in startup_folders {
+        let Some(entries) = std::fs::read_dir(&folder).ok() else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if let Some(app) = App::from_path(&entry.path()).ok() {
+                apps.push(app);
+            }
+        }
+    }
+
+    Ok(apps)
+}
+
+/// The app a `.lnk` or `.url` shortcut starts, without its localized
+/// name and the version information of its executable unless `metadata`.
+fn read_shortcut(path: &Path, metadata: bool) -> Result<App> {
+    if let Some(extension) = path.extension() {
+        if extension == "lnk" {
+            if let Some(mut app) =
+                parse_lnk_with_shell_link(path).or_else(|| parse_lnk2(path.to_path_buf()))
+            {
+                if metadata {
+                    localize_shortcut_name(&mut app, path);
+                }
+                // It would describe wslg.exe or WsaClient.exe
+                if !matches!(
+                    app.kind,
+                    AppKind::WslApp { .. } | AppKind::AndroidApp { .. }
+                ) {
+                    if metadata {
+                        apply_version_info(&mut app);
+                    }
+                    apply_elevation(&mut app);
+                    apply_kind(&mut app);
+                }
+                return Ok(app);
+            }
+        }
+        if extension == "url" {
+            if let Some(mut app) = parse_url_shortcut(path) {
+                if metadata {
+                    localize_shortcut_name(&mut app, path);
+                }
+                return Ok(app);
+            }
+        }
+    }
+    Err(Error::parse(path, "not a shortcut to an app"))
+}
+
+/// The uninstaller the installer registered, `msiexec /x` for Windows
+/// Installer products, `Remove-AppxPackage` for packaged apps, or the
+/// package manager that installed the app.
+pub(crate) fn uninstall_action(app: &App) -> Result<UninstallAction> {
+    let command = |program: &str, args: &[&str]| {
+        Ok(UninstallAction::Command(UninstallCommand {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            quiet: true,
+        }))
+    };
+    if let Some(uninstaller) = &app.uninstaller {
+        return Ok(UninstallAction::Command(uninstaller.clone()));
+    }
+    if let Some(product_code) = &app.product_code {
+        return command("msiexec.exe", &["/x", product_code, "/passive"]);
+    }
+    // Package family names are letters, digits, `.`, `-` and `_`
+    if let Some((family, _)) = app.aumid.as_deref().and_then(|aumid| aumid.split_once('!'))
+        && family
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+    {
+        let script = format!("Get-AppxPackage -PackageFamilyName '{family}' | Remove-AppxPackage");
+        return command(
+            "powershell.exe",
+            &["-NoProfile", "-NonInteractive", "-Command", &script],
+        );
+    }
+    match &app.install_source {
+        // Installed in `<root>\apps\<name>\current`
+        Some(InstallSource::Scoop { .. }) => {
+            if let Some(name) = app
+                .app_desktop_path
+                .parent()
+                .and_then(Path::file_name)
+                .and_then(|name| name.to_str())
+            {
+                return command("scoop.cmd", &["uninstall", name]);
+            }
+        }
+        Some(InstallSource::Chocolatey { package }) => {
+            return command("choco.exe", &["uninstall", package, "-y"]);
+        }
+        Some(InstallSource::Portable) => {
+            return Err(Error::NotUninstallable {
+                app: app.name.clone(),
+                reason: "it is not installed, delete the folder it runs from".to_string(),
+            });
+        }
+        None => {}
+    }
+    if let Some(winget_id) = &app.winget_id {
+        return command(
+            "winget.exe",
+            &["uninstall", "--id", winget_id, "--exact", "--silent"],
+        );
+    }
+
+    Err(Error::NotUninstallable {
+        app: app.name.clone(),
+        reason: "no uninstaller is registered for it".to_string(),
+    })
+}
+
+/// The registry keys the registry and packaged apps are found in.
+#[cfg(feature = "cache")]
+pub(crate) fn cache_sources() -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    crate::utils::windows::registry_sources()
+}
+
+/// The localized name of a shortcut and the version information and
+/// signature of the executable.
+pub(crate) fn read_metadata(app: &App) -> AppMetadata {
+    let mut read = App {
+        localized_app_names: BTreeMap::new(),
+        version: None,
+        publisher: None,
+        ..app.clone()
+    };
+    if matches!(
+        app.app_desktop_path
+            .extension()
+            .and_then(|ext| ext.to_str()),
+        Some("lnk" | "url")
+    ) {
+        localize_shortcut_name(&mut read, &app.app_desktop_path);
+    }
+    apply_version_info(&mut read);
+    let mut localized_app_names = app.localized_app_names.clone();
+    localized_app_names.extend(read.localized_app_names);
+
+    AppMetadata {
+        localized_app_names,
+        version: app.version.clone().or(read.version),
+        publisher: app.publisher.clone().or(read.publisher),
+        size: app.app_path_exe.as_deref().and_then(disk_size),
+        signature: get_signature_info(app).ok(),
+        spotlight: None,
+    }
+}
+
+impl AppTrait for App {
+    fn from_path(path: &Path) -> Result<Self> {
+        read_shortcut(path, true)
+    }
+
+    fn launch(&self) -> Result<()> {
+        if let Some(aumid) = self.aumid.as_deref() {
+            // Only packaged apps can be activated, the Shell starts the
+            // others by their AppUserModelID
+            if activate_application(aumid).is_err() {
+                spawn_detached(
+                    Command::new("explorer").arg(format!("shell:AppsFolder\\{}", aumid)),
+                )?;
+            }
+            return Ok(());
+        }
+        if let AppKind::WebApp { url: Some(url) } | AppKind::Game { url: Some(url) } = &self.kind
+            && self.app_path_exe.is_none()
+        {
+            // Opens the URL in the default browser, or the store client
+            spawn_detached(Command::new("explorer").arg(url))?;
+            return Ok(());
+        }
+
+        let Some(exe) = self.app_path_exe.as_ref() else {
+            return Err(Error::Missing {
+                app: self.name.clone(),
+                missing: "executable",
+            });
+        };
+
+        if self.requires_elevation {
+            return run_as_admin(exe, &self.launch_args, &self.app_desktop_path);
+        }
+
+        let mut command = Command::new(exe);
+        command.args(&self.launch_args);
+        if self.app_desktop_path.is_dir() {
+            command.current_dir(&self.app_desktop_path);
+        }
+        spawn_detached(&mut command)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_get_all_apps() {
+        let search_paths = vec![PathBuf::from(
+            "C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs",
+        )];
+        let apps = get_all_apps(&search_paths).unwrap();
+        assert!(!apps.is_empty());
+    }
+
+    #[test]
+    fn test_parse_command_line_exe() {
+        assert_eq!(
+            parse_command_line_exe("\"C:\\Program Files\\Foo\\foo.exe\" --minimized"),
+            Some(PathBuf::from("C:\\Program Files\\Foo\\foo.exe"))
+        );
+        assert_eq!(
+            parse_command_line_exe("C:\\Program Files\\Foo\\foo.EXE /background"),
+            Some(PathBuf::from("C:\\Program Files\\Foo\\foo.EXE"))
+        );
+        // `İ` is longer in lowercase
+        assert_eq!(
+            parse_command_line_exe("C:\\İSTANBUL Apps\\foo.exe /background"),
+            Some(PathBuf::from("C:\\İSTANBUL Apps\\foo.exe"))
+        );
+        assert_eq!(parse_command_line_exe("   "), None);
+    }
+
+    #[test]
+    fn test_split_icon_location() {
+        assert_eq!(
+            split_icon_location("\"C:\\Program Files\\Foo\\foo.exe\",0"),
+            (PathBuf::from("C:\\Program Files\\Foo\\foo.exe"), Some(0))
+        );
+        assert_eq!(
+            split_icon_location("C:\\Windows\\System32\\shell32.dll,-22067"),
+            (
+                PathBuf::from("C:\\Windows\\System32\\shell32.dll"),
+                Some(-22067)
+            )
+        );
+        assert_eq!(
+            split_icon_location("C:\\Foo, Inc\\foo.ico"),
+            (PathBuf::from("C:\\Foo, Inc\\foo.ico"), None)
+        );
+    }
+
+    #[test]
+    fn test_file_uri_to_path() {
+        assert_eq!(
+            crate::utils::windows::file_uri_to_path(
+                "file:///C:/Program%20Files/WindowsApps/Foo/Assets/StoreLogo.png"
+            ),
+            Some(PathBuf::from(
+                "C:\\Program Files\\WindowsApps\\Foo\\Assets\\StoreLogo.png"
+            ))
+        );
+        assert_eq!(
+            crate::utils::windows::file_uri_to_path("ms-appx:///Assets/Logo.png"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_uwp_apps() {
+        let apps = get_uwp_apps().unwrap();
+        assert!(apps.iter().all(|app| app.app_path_exe.is_none()));
+        assert!(apps.iter().any(|app| app.aumid.is_some()));
+    }
+
+    #[test]
+    fn test_parse_app_exec_link() {
+        let strings = [
+            "Microsoft.WindowsTerminal_8wekyb3d8bbwe",
+            "Microsoft.WindowsTerminal_8wekyb3d8bbwe!App",
+            "C:\\Program Files\\WindowsApps\\Microsoft.WindowsTerminal\\wt.exe",
+            "0",
+        ];
+        let mut data = Vec::new();
+        data.extend_from_slice(&0x8000_001Bu32.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        for string in strings {
+            for unit in string.encode_utf16().chain([0]) {
+                data.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+
+        let (package_family_name, aumid, target) =
+            crate::utils::windows::parse_app_exec_link(&data).unwrap();
+        assert_eq!(package_family_name, strings[0]);
+        assert_eq!(aumid, strings[1]);
+        assert_eq!(target, PathBuf::from(strings[2]));
+
+        data[0] = 0;
+        assert_eq!(crate::utils::windows::parse_app_exec_link(&data), None);
+    }
+
+    #[test]
+    fn test_parse_localized_file_names() {
+        let content = "\
+[.ShellClassInfo]
+LocalizedResourceName=@%SystemRoot%\\system32\\shell32.dll,-21787
+
+[LocalizedFileNames]
+Command Prompt.lnk=@%SystemRoot%\\system32\\shell32.dll,-22022
+Notepad.lnk=Notepad
+";
+        let names = crate::utils::windows::parse_localized_file_names(content);
+        assert_eq!(names.len(), 2);
+        assert_eq!(
+            names.get("command prompt.lnk").map(String::as_str),
+            Some("@%SystemRoot%\\system32\\shell32.dll,-22022")
+        );
+        assert_eq!(
+            names.get("notepad.lnk").map(String::as_str),
+            Some("Notepad")
+        );
+    }
+
+    #[test]
+    fn test_load_indirect_string() {
+        assert_eq!(
+            crate::utils::windows::load_indirect_string("Calculator"),
+            None
+        );
+        assert!(
+            crate::utils::windows::load_indirect_string(
+                "@%SystemRoot%\\system32\\shell32.dll,-22022"
+            )
+            .is_some()
+        );
+    }
+
+    #[cfg(feature = "icons")]
+    #[test]
+    fn test_load_icon() {
+        let windir = std::env::var("WINDIR").unwrap();
+        let app = App {
+            name: "Explorer".to_string(),
+            icon_path: Some(PathBuf::from(format!("{}\\explorer.exe", windir))),
+            icon_index: Some(0),
+            ..Default::default()
+        };
+        let icon = load_icon(&app).unwrap();
+        assert!(icon.width > 0);
+        assert_eq!(icon.rgba.len(), (icon.width * icon.height * 4) as usize);
+    }
+
+    #[test]
+    fn test_get_signature_info() {
+        let exe = std::env::current_exe().unwrap();
+        let app = App {
+            name: "test".to_string(),
+            app_path_exe: Some(exe),
+            ..Default::default()
+        };
+        let info = get_signature_info(&app).unwrap();
+        assert_eq!(info.status, crate::SignatureStatus::Unsigned);
+        assert_eq!(info.subject, None);
+    }
+
+    #[test]
+    fn test_strip_copy_suffix() {
+        assert_eq!(strip_copy_suffix("app (1)"), "app");
+        assert_eq!(strip_copy_suffix("Foo Bar (12)"), "Foo Bar");
+        assert_eq!(
+            strip_copy_suffix("Python 3.12 (64-bit)"),
+            "Python 3.12 (64-bit)"
+        );
+        assert_eq!(strip_copy_suffix("(1)"), "(1)");
+        assert_eq!(strip_copy_suffix("app"), "app");
+    }
+
+    #[test]
+    fn test_read_version_info() {
+        let windir = std::env::var("WINDIR").unwrap();
+        let info = crate::utils::windows::read_version_info(&PathBuf::from(format!(
+            "{}\\explorer.exe",
+            windir
+        )))
+        .unwrap();
+        assert!(info.file_version.is_some());
+        assert_eq!(info.company_name.as_deref(), Some("Microsoft Corporation"));
+    }
+
+    #[test]
+    fn test_parse_scoop_app() {
+        let temp_dir = TempDir::new("scoop");
+        let root = temp_dir.path().to_path_buf();
+        let current = root.join("apps\\foo\\current");
+        std::fs::create_dir_all(current.join("bin")).unwrap();
+        std::fs::write(current.join("foo.exe"), b"").unwrap();
+        std::fs::write(current.join("bin\\foo-cli.exe"), b"").unwrap();
+        std::fs::write(
+            current.join("manifest.json"),
+            r#"{
+                "version": "1.2.3",
+                "bin": [["bin\\foo-cli.exe", "foo"]],
+                "shortcuts": [["foo.exe", "Foo"]]
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(current.join("install.json"), r#"{"bucket": "extras"}"#).unwrap();
+
+        let apps = crate::utils::windows::parse_scoop_app("foo", &current);
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].name, "Foo");
+        assert_eq!(apps[0].app_path_exe, Some(current.join("foo.exe")));
+        assert_eq!(apps[0].version.as_deref(), Some("1.2.3"));
+        assert_eq!(
+            apps[0].install_source,
+            Some(crate::InstallSource::Scoop {
+                bucket: Some("extras".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_chocolatey_package() {
+        let temp_dir = TempDir::new("choco");
+        let root = temp_dir.path().to_path_buf();
+        let package = root.join("lib\\foo");
+        std::fs::create_dir_all(package.join("tools")).unwrap();
+        std::fs::write(package.join("tools\\foo.exe"), b"").unwrap();
+        std::fs::write(package.join("tools\\helper.exe"), b"").unwrap();
+        std::fs::write(package.join("tools\\helper.exe.ignore"), b"").unwrap();
+        std::fs::write(
+            package.join("foo.nuspec"),
+            "<package><metadata><id>foo</id><version>4.5.6</version>\
+             <title>Foo Tool</title><authors>Foo Inc.</authors></metadata></package>",
+        )
+        .unwrap();
+
+        let apps = crate::utils::windows::parse_chocolatey_package(&package);
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].name, "Foo Tool");
+        assert_eq!(apps[0].version.as_deref(), Some("4.5.6"));
+        assert_eq!(apps[0].publisher.as_deref(), Some("Foo Inc."));
+        assert_eq!(
+            apps[0].install_source,
+            Some(crate::InstallSource::Chocolatey {
+                package: "foo".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_portable_apps() {
+        let temp_dir = TempDir::new("portable");
+        let root = temp_dir.path().to_path_buf();
+        let foo = root.join("PortableApps\\FooPortable");
+        std::fs::create_dir_all(foo.join("App\\AppInfo")).unwrap();
+        std::fs::write(foo.join("FooPortable.exe"), b"").unwrap();
+        std::fs::write(foo.join("App\\AppInfo\\appicon_128.png"), b"").unwrap();
+        std::fs::write(
+            foo.join("App\\AppInfo\\appinfo.ini"),
+            "[Format]\r\nType=PortableApps.comFormat\r\n\
+             [Details]\r\nName=Foo Portable\r\nPublisher=Foo Inc.\r\n\
+             [Version]\r\nDisplayVersion=1.2\r\n\
+             [Control]\r\nStart=FooPortable.exe\r\n",
+        )
+        .unwrap();
+        let bar = root.join("Bar-2.0");
+        std::fs::create_dir_all(&bar).unwrap();
+        std::fs::write(bar.join("bar.exe"), b"").unwrap();
+        std::fs::write(bar.join("helper.exe"), b"").unwrap();
+
+        let mut apps = crate::utils::windows::read_portable_apps(&root);
+        apps.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].name, "bar");
+        assert_eq!(apps[0].app_path_exe, Some(bar.join("bar.exe")));
+        assert_eq!(apps[1].name, "Foo Portable");
+        assert_eq!(apps[1].version.as_deref(), Some("1.2"));
+        assert_eq!(apps[1].publisher.as_deref(), Some("Foo Inc."));
+        assert_eq!(
+            apps[1].icon_path,
+            Some(foo.join("App\\AppInfo\\appicon_128.png"))
+        );
+        assert!(
+            apps.iter()
+                .all(|app| app.install_source == Some(crate::InstallSource::Portable))
+        );
+    }
+
+    #[test]
+    fn test_parse_winget_list() {
+        let output = "\r   - \r   \\ \r\
+Name                                  Id                           Version      Available Source
+-----------------------------------------------------------------------------------------------
+Microsoft Visual Studio Code (User)   Microsoft.VisualStudioCode   1.90.0       1.91.1    winget
+7-Zip 23.01 (x64)                     7zip.7zip                    23.01                  winget
+Mozilla Firefox (x64 en-US)           Mozilla.Firefox              127.0.2                winget
+Some Really Long Application Name Th… Foo.LongName                 2.0                    winget
+";
+        let packages = crate::utils::windows::parse_winget_list(output);
+        assert_eq!(packages.len(), 4);
+        assert_eq!(packages[0].name, "Microsoft Visual Studio Code (User)");
+        assert_eq!(packages[0].id, "Microsoft.VisualStudioCode");
+        assert_eq!(packages[0].version.as_deref(), Some("1.90.0"));
+        assert_eq!(packages[0].available.as_deref(), Some("1.91.1"));
+        assert_eq!(packages[1].id, "7zip.7zip");
+        assert_eq!(packages[1].available, None);
+
+        assert!(crate::utils::windows::winget_name_matches(
+            "Some Really Long Application Name That Goes On",
+            &packages[3].name
+        ));
+        assert!(!crate::utils::windows::winget_name_matches(
+            "Mozilla Firefox",
+            &packages[2].name
+        ));
+    }
+
+    #[test]
+    fn test_parse_internet_shortcut() {
+        let content = "\
+[{000214A0-0000-0000-C000-000000000046}]
+Prop3=19,11
+[InternetShortcut]
+IDList=
+URL=https://github.com/
+IconIndex=0
+IconFile=C:\\Users\\foo\\AppData\\Local\\Mozilla\\github.ico
+";
+        let shortcut = crate::utils::windows::parse_internet_shortcut(content).unwrap();
+        assert_eq!(shortcut.url, "https://github.com/");
+        assert_eq!(shortcut.icon_index, Some(0));
+        assert_eq!(
+            shortcut.icon_file,
+            Some(PathBuf::from(
+                "C:\\Users\\foo\\AppData\\Local\\Mozilla\\github.ico"
+            ))
+        );
+
+        assert_eq!(
+            crate::utils::windows::parse_internet_shortcut("[InternetShortcut]\nIconIndex=0\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_url_shortcut() {
+        let temp_dir = TempDir::new("url-shortcut");
+        let dir = temp_dir.path().to_path_buf();
+        let shortcut = |name: &str, url: &str| {
+            let path = dir.join(format!("{name}.url"));
+            std::fs::write(&path, format!("[InternetShortcut]\nURL={url}\n")).unwrap();
+            parse_url_shortcut(&path).map(|app| app.kind)
+        };
+
+        assert_eq!(
+            shortcut("GitHub", "https://github.com/"),
+            Some(AppKind::WebApp {
+                url: Some("https://github.com/".to_string())
+            })
+        );
+        assert_eq!(
+            shortcut("Dota 2", "steam://rungameid/570"),
+            Some(AppKind::Game {
+                url: Some("steam://rungameid/570".to_string())
+            })
+        );
+        assert_eq!(shortcut("Mail", "mailto:foo@example.com"), None);
+    }
+
+    #[test]
+    fn test_pe_subsystem() {
+        use crate::utils::windows::pe_subsystem;
+
+        let mut image = vec![0u8; 0x200];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        image[0x80 + 24 + 68] = 3;
+        assert_eq!(pe_subsystem(&image), Some(3));
+        assert_eq!(pe_subsystem(&image[..0x90]), None);
+        assert_eq!(pe_subsystem(b"#!/bin/sh"), None);
+        // Offsets past the end, or where they overflow
+        for pe_offset in [0x1f0, 0x1000, u32::MAX] {
+            let mut hostile = image.clone();
+            hostile[0x3c..0x40].copy_from_slice(&pe_offset.to_le_bytes());
+            hostile[0x1f0..0x1f4].copy_from_slice(b"PE\0\0");
+            assert_eq!(pe_subsystem(&hostile), None);
+        }
+
+        let cmd = PathBuf::from(std::env::var_os("WINDIR").unwrap()).join("System32\\cmd.exe");
+        assert!(is_console_program(&cmd));
+    }
+
+    #[test]
+    fn test_get_apps_folder_apps() {
+        let apps = get_apps_folder_apps().unwrap();
+        assert!(!apps.is_empty());
+        assert!(
+            apps.iter()
+                .all(|app| app.app_path_exe.is_some() || app.aumid.is_some())
+        );
+    }
+
+    #[test]
+    fn test_is_noise_app() {
+        let app = |name: &str, exe: &str| App {
+            name: name.to_string(),
+            app_path_exe: Some(PathBuf::from(exe)),
+            ..Default::default()
+        };
+        assert!(is_noise_app(&app("Uninstall Foo", "C:\\Foo\\foo.exe")));
+        assert!(is_noise_app(&app("Foo", "C:\\Foo\\unins000.exe")));
+        assert!(is_noise_app(&app(
+            "Microsoft Visual C++ 2015 Redistributable",
+            "C:\\Temp\\vc_redist.x64.exe"
+        )));
+        assert!(is_noise_app(&app("Foo Readme", "C:\\Foo\\foo.exe")));
+        // Squirrel apps start through their updater
+        assert!(!is_noise_app(&app("Discord", "C:\\Discord\\Update.exe")));
+        assert!(!is_noise_app(&app("Foo", "C:\\Foo\\foo.exe")));
+
+        assert!(is_noise_folder(Path::new(
+            "C:\\Start Menu\\Programs\\Uninstallers"
+        )));
+        assert!(!is_noise_folder(Path::new("C:\\Start Menu\\Programs\\Foo")));
+    }
+
+    #[test]
+    fn test_select_qualified_asset() {
+        let base = Path::new("C:\\Foo\\Assets\\Square44x44Logo.png");
+        let files: Vec<String> = [
+            "Square44x44Logo.scale-100.png",
+            "Square44x44Logo.scale-200.png",
+            "Square44x44Logo.targetsize-48.png",
+            "Square44x44Logo.targetsize-48_altform-unplated.png",
+            "Square44x44Logo.targetsize-256_altform-unplated.png",
+            "Square44x44Logo.targetsize-256_altform-lightunplated.png",
+            "Square44x44Logo.contrast-white_targetsize-256.png",
+            "Wide310x150Logo.scale-200.png",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+        let select = |size| crate::utils::windows::select_qualified_asset(base, 44, size, &files);
+
+        assert_eq!(
+            select(48).as_deref(),
+            Some("Square44x44Logo.targetsize-48_altform-unplated.png")
+        );
+        assert_eq!(select(64).as_deref(), Some("Square44x44Logo.scale-200.png"));
+        assert_eq!(
+            select(512).as_deref(),
+            Some("Square44x44Logo.targetsize-256_altform-unplated.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest_logos() {
+        let manifest = r#"<Package>
+  <Applications>
+    <Application Id="Other" Executable="other.exe">
+      <uap:VisualElements Square150x150Logo="Other150.png" Square44x44Logo="Other44.png" />
+    </Application>
+    <Application Id="App" Executable="foo.exe" EntryPoint="Windows.FullTrustApplication">
+      <uap:VisualElements DisplayName="Foo" Square150x150Logo="Assets\Square150x150Logo.png"
+        Square44x44Logo="Assets\Square44x44Logo.png" BackgroundColor="transparent">
+        <uap:DefaultTile Wide310x150Logo="Assets\Wide310x150Logo.png" />
+      </uap:VisualElements>
+    </Application>
+  </Applications>
+</Package>"#;
+        assert_eq!(
+            crate::utils::windows::parse_manifest_logos(manifest, "App"),
+            vec![
+                (PathBuf::from("Assets\\Square150x150Logo.png"), 150),
+                (PathBuf::from("Assets\\Square44x44Logo.png"), 44),
+            ]
+        );
+        assert!(crate::utils::windows::parse_manifest_logos(manifest, "Missing").is_empty());
+    }
+
+    #[test]
+    fn test_parse_wslg_arguments() {
+        use crate::utils::windows::parse_wslg_arguments;
+
+        assert_eq!(
+            parse_wslg_arguments("-d Ubuntu --cd \"~\" -- gedit"),
+            Some(("Ubuntu".to_string(), "gedit".to_string()))
+        );
+        assert_eq!(
+            parse_wslg_arguments("~ -d Ubuntu-22.04 /usr/bin/gimp --new-instance"),
+            Some((
+                "Ubuntu-22.04".to_string(),
+                "/usr/bin/gimp --new-instance".to_string()
+            ))
+        );
+        assert_eq!(parse_wslg_arguments("-d Ubuntu"), None);
+        assert_eq!(parse_wslg_arguments("gedit"), None);
+    }
+
+    #[test]
+    fn test_desktop_entry_runs() {
+        let content = "[Desktop Entry]\nName=Text Editor\nExec=/usr/bin/gedit %U\n";
+        assert!(crate::utils::windows::desktop_entry_runs(content, "gedit"));
+        assert!(crate::utils::windows::desktop_entry_runs(
+            content,
+            "/usr/bin/gedit"
+        ));
+        assert!(!crate::utils::windows::desktop_entry_runs(content, "gimp"));
+    }
+
+    #[test]
+    fn test_get_running_apps() {
+        let running_apps = get_running_apps().unwrap();
+        assert!(
+            running_apps
+                .iter()
+                .all(|running_app| !running_app.pids.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_find_process_app() {
+        let apps = vec![
+            App {
+                name: "Foo".to_string(),
+                app_path_exe: Some(PathBuf::from("C:\\Foo\\foo.exe")),
+                ..Default::default()
+            },
+            App {
+                name: "Calculator".to_string(),
+                aumid: Some("Microsoft.WindowsCalculator_8wekyb3d8bbwe!App".to_string()),
+                ..Default::default()
+            },
+        ];
+        let app_exes: Vec<Option<String>> = apps
+            .iter()
+            .map(|app| app.app_path_exe.as_deref().map(exe_key))
+            .collect();
+
+        let process = |exe: Option<&str>, aumid: Option<&str>| ProcessInfo {
+            pid: 42,
+            exe: exe.map(PathBuf::from),
+            aumid: aumid.map(str::to_string),
+        };
+        assert_eq!(
+            find_process_app(&apps, &app_exes, &process(Some("c:\\foo\\FOO.exe"), None)),
+            Some(0)
+        );
+        assert_eq!(
+            find_process_app(
+                &apps,
+                &app_exes,
+                &process(
+                    Some("C:\\Program Files\\WindowsApps\\Calculator.exe"),
+                    Some("Microsoft.WindowsCalculator_8wekyb3d8bbwe!App")
+                )
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            find_process_app(&apps, &app_exes, &process(Some("C:\\bar.exe"), None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        use crate::utils::windows::split_arguments;
+
+        assert_eq!(
+            split_arguments("--processStart \"Discord.exe\""),
+            vec!["--processStart", "Discord.exe"]
+        );
+        assert_eq!(
+            split_arguments("--profile-directory=\"Profile 1\"  --app-id=abc"),
+            vec!["--profile-directory=Profile 1", "--app-id=abc"]
+        );
+        assert_eq!(split_arguments("\"\""), vec![""]);
+        assert!(split_arguments("  ").is_empty());
+    }
+
+    #[test]
+    fn test_command_program() {
+        use crate::utils::windows::command_program;
+
+        assert_eq!(
+            command_program("\"C:\\Program Files\\Foo\\foo.exe\" -osint -url \"%1\""),
+            Some(PathBuf::from("C:\\Program Files\\Foo\\foo.exe"))
+        );
+        assert_eq!(
+            command_program("%SystemRoot%\\notepad.exe %1"),
+            Some(PathBuf::from(std::env::var("SystemRoot").unwrap()).join("notepad.exe"))
+        );
+        assert_eq!(command_program(""), None);
+    }
+
+    #[test]
+    fn test_order_taskbar_pins() {
+        use crate::utils::windows::order_taskbar_pins;
+
+        let favorites: Vec<u8> = "\0\0Firefox.lnk\0\0File Explorer.lnk\0"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        let links = vec![
+            PathBuf::from("TaskBar\\File Explorer.lnk"),
+            PathBuf::from("TaskBar\\Zed.lnk"),
+            PathBuf::from("TaskBar\\Firefox.lnk"),
+        ];
+        assert_eq!(
+            order_taskbar_pins(links, &favorites),
+            vec![
+                PathBuf::from("TaskBar\\Firefox.lnk"),
+                PathBuf::from("TaskBar\\File Explorer.lnk"),
+                PathBuf::from("TaskBar\\Zed.lnk"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shortcut_categories() {
+        let programs = Path::new("C:\\ProgramData\\Microsoft\\Windows\\Start Menu\\Programs");
+        assert_eq!(
+            shortcut_categories(
+                programs,
+                &programs.join("Accessories\\System Tools\\Character Map.lnk")
+            ),
+            vec!["Accessories", "System Tools"]
+        );
+        assert!(shortcut_categories(programs, &programs.join("Foo.lnk")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_wsa_arguments() {
+        use crate::utils::windows::parse_wsa_arguments;
+
+        assert_eq!(
+            parse_wsa_arguments("/launch wsa://com.amazon.venezia").as_deref(),
+            Some("com.amazon.venezia")
+        );
+        assert_eq!(
+            parse_wsa_arguments("/launch \"wsa://org.mozilla.firefox/\"").as_deref(),
+            Some("org.mozilla.firefox")
+        );
+        assert_eq!(parse_wsa_arguments("/launch"), None);
+        assert_eq!(parse_wsa_arguments("wsa://"), None);
+    }
+
+    #[test]
+    fn test_join_arguments() {
+        use crate::utils::windows::{join_arguments, split_arguments};
+
+        let args = vec![
+            "--profile-directory=Profile 1".to_string(),
+            "--app-id=abc".to_string(),
+            String::new(),
+        ];
+        assert_eq!(
+            join_arguments(&args),
+            "\"--profile-directory=Profile 1\" --app-id=abc \"\""
+        );
+        assert_eq!(split_arguments(&join_arguments(&args)), args);
+    }
+
+    #[test]
+    fn test_parse_execution_level() {
+        use crate::utils::windows::parse_execution_level;
+
+        let manifest = r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    <security>
+      <requestedPrivileges>
+        <requestedExecutionLevel level='requireAdministrator' uiAccess='false'/>
+      </requestedPrivileges>
+    </security>
+  </trustInfo>
+</assembly>"#;
+        assert_eq!(
+            parse_execution_level(manifest).as_deref(),
+            Some("requireAdministrator")
+        );
+        assert_eq!(parse_execution_level("<assembly/>"), None);
+    }
+
+    #[test]
+    fn test_get_registry_apps() {
+        let apps = get_registry_apps();
+        assert!(apps.iter().all(|app| !app.display_name.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_uninstall_string() {
+        use crate::utils::windows::parse_uninstall_string;
+
+        let command =
+            parse_uninstall_string("\"C:\\Program Files\\Foo\\uninst.exe\" /S", true).unwrap();
+        assert_eq!(
+            command.program,
+            PathBuf::from("C:\\Program Files\\Foo\\uninst.exe")
+        );
+        assert_eq!(command.args, vec!["/S"]);
+        assert!(command.quiet);
+
+        let command =
+            parse_uninstall_string("C:\\Program Files\\Bar\\unins000.exe /SILENT", false).unwrap();
+        assert_eq!(
+            command.program,
+            PathBuf::from("C:\\Program Files\\Bar\\unins000.exe")
+        );
+        assert_eq!(command.args, vec!["/SILENT"]);
+
+        let command = parse_uninstall_string(
+            "rundll32.exe dfshim.dll,ShArpMaintain App.application",
+            false,
+        )
+        .unwrap();
+        assert_eq!(command.program, PathBuf::from("rundll32.exe"));
+        assert_eq!(
+            command.args,
+            vec!["dfshim.dll,ShArpMaintain", "App.application"]
+        );
+        // `İ` is longer in lowercase
+        let command =
+            parse_uninstall_string("C:\\Program Files\\İzmir\\unins000.exe /SILENT", false)
+                .unwrap();
+        assert_eq!(
+            command.program,
+            PathBuf::from("C:\\Program Files\\İzmir\\unins000.exe")
+        );
+        assert_eq!(command.args, vec!["/SILENT"]);
+        assert!(parse_uninstall_string("  ", false).is_none());
+    }
+
+    #[test]
+    fn test_registry_app_uninstaller() {
+        let registry_app = RegistryApp {
+            key_name: "{23170F69-40C1-2702-2409-000001000000}".to_string(),
+            display_name: "7-Zip".to_string(),
+            uninstall_string: Some(
+                "MsiExec.exe /I{23170F69-40C1-2702-2409-000001000000}".to_string(),
+            ),
+            product_code: Some("{23170F69-40C1-2702-2409-000001000000}".to_string()),
+            ..Default::default()
+        };
+        let uninstaller = registry_app.uninstaller().unwrap();
+        assert_eq!(uninstaller.program, PathBuf::from("msiexec.exe"));
+        assert_eq!(
+            uninstaller.args,
+            vec!["/x", "{23170F69-40C1-2702-2409-000001000000}", "/passive"]
+        );
+
+        let registry_app = RegistryApp {
+            uninstall_string: Some("\"C:\\Foo\\uninstall.exe\"".to_string()),
+            quiet_uninstall_string: Some("\"C:\\Foo\\uninstall.exe\" /S".to_string()),
+            ..Default::default()
+        };
+        let uninstaller = registry_app.uninstaller().unwrap();
+        assert_eq!(uninstaller.args, vec!["/S"]);
+        assert!(uninstaller.quiet);
+    }
+
+    /// A PE32+ image for `machine`, with one section holding a load
+    /// configuration that points to CHPE metadata at `chpe`.
+    fn pe_image(machine: u16, chpe: u64) -> Vec<u8> {
+        let mut image = vec![0; 0x400];
+        let mut put = |at: usize, bytes: &[u8]| image[at..at + bytes.len()].copy_from_slice(bytes);
+        put(0, b"MZ");
+        put(0x3c, &0x80u32.to_le_bytes());
+        put(0x80, b"PE\0\0");
+        put(0x84, &machine.to_le_bytes());
+        put(0x86, &1u16.to_le_bytes());
+        put(0x94, &0xf0u16.to_le_bytes());
+        // The optional header, and its load configuration directory
+        put(0x98, &0x20bu16.to_le_bytes());
+        put(0x98 + 192, &0x1000u32.to_le_bytes());
+        put(0x98 + 196, &0x140u32.to_le_bytes());
+        // The section table
+        put(0x188 + 8, &0x200u32.to_le_bytes());
+        put(0x188 + 12, &0x1000u32.to_le_bytes());
+        put(0x188 + 16, &0x200u32.to_le_bytes());
+        put(0x188 + 20, &0x200u32.to_le_bytes());
+        // The load configuration
+        put(0x200, &0x140u32.to_le_bytes());
+        put(0x200 + 0xc8, &chpe.to_le_bytes());
+
+        image
+    }
+
+    #[test]
+    fn test_pe_architecture() {
+        let architecture =
+            |machine, chpe| pe_architecture(&mut std::io::Cursor::new(pe_image(machine, chpe)));
+        assert_eq!(architecture(0x8664, 0), Some(Architecture::X64));
+        assert_eq!(architecture(0xaa64, 0), Some(Architecture::Arm64));
+        assert_eq!(
+            architecture(0x8664, 0x1_4000_3000),
+            Some(Architecture::Arm64Ec)
+        );
+        assert_eq!(
+            architecture(0xaa64, 0x1_4000_3000),
+            Some(Architecture::Arm64X)
+        );
+        assert_eq!(architecture(0x014c, 0x1_4000_3000), Some(Architecture::X86));
+        assert_eq!(architecture(0x0200, 0), None);
+
+        let mut truncated = pe_image(0xaa64, 0x1_4000_3000);
+        truncated.truncate(0x200);
+        assert_eq!(
+            pe_architecture(&mut std::io::Cursor::new(truncated)),
+            Some(Architecture::Arm64)
+        );
+        assert_eq!(
+            pe_architecture(&mut std::io::Cursor::new(b"#!/bin/sh")),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "aarch64")]
+    fn test_arm64() {
+        assert_eq!(get_native_architecture(), Some(Architecture::Arm64));
+        let windir = PathBuf::from(std::env::var_os("WINDIR").unwrap());
+        assert!(matches!(
+            get_exe_architecture(&windir.join("System32\\cmd.exe")),
+            Some(Architecture::Arm64 | Architecture::Arm64X)
+        ));
+        // x86 programs are emulated with the system of SysWOW64
+        assert_eq!(
+            get_exe_architecture(&windir.join("SysWOW64\\cmd.exe")),
+            Some(Architecture::X86)
+        );
+    }
+
+    #[test]
+    fn test_uninstall_action() {
+        let args = |app: App| match app.uninstall_action().unwrap() {
+            UninstallAction::Command(command) => command.args,
+            action => panic!("{action:?}"),
+        };
+        let packaged = App {
+            aumid: Some("Microsoft.WindowsCalculator_8wekyb3d8bbwe!App".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            args(packaged)[3],
+            "Get-AppxPackage -PackageFamilyName 'Microsoft.WindowsCalculator_8wekyb3d8bbwe' | Remove-AppxPackage"
+        );
+        let scoop = App {
+            app_desktop_path: "C:\\Users\\foo\\scoop\\apps\\firefox\\current".into(),
+            install_source: Some(InstallSource::Scoop { bucket: None }),
+            ..Default::default()
+        };
+        assert_eq!(args(scoop), ["uninstall", "firefox"]);
+        let winget = App {
+            winget_id: Some("Foo.Bar".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            args(winget),
+            ["uninstall", "--id", "Foo.Bar", "--exact", "--silent"]
+        );
+
+        let portable = App {
+            install_source: Some(InstallSource::Portable),
+            ..Default::default()
+        };
+        assert!(matches!(
+            portable.uninstall_action(),
+            Err(Error::NotUninstallable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_path_alias() {
+        let path = PathBuf::from("%windir%\\system32\\mstsc.exe");
+        let path = translate_path_alias(path);
+        assert_eq!(
+            path.to_string_lossy().to_lowercase(),
+            "c:\\windows\\system32\\mstsc.exe"
+        );
+
+        let path =
+            translate_path_alias(PathBuf::from("%SystemDrive%\\Windows\\%Unknown%\\app.exe"));
+        assert_eq!(
+            path.to_string_lossy().to_lowercase(),
+            "c:\\windows\\%unknown%\\app.exe"
+        );
+        let path = translate_path_alias(PathBuf::from("shell:Windows\\notepad.exe"));
+        assert_eq!(
+            path.to_string_lossy().to_lowercase(),
+            "c:\\windows\\notepad.exe"
         );
     }
 }
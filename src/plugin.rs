@@ -0,0 +1,94 @@
+//! A Tauri plugin with the commands a launcher needs: listing the installed
+//! apps, launching them and rendering their icons, and the changes to the
+//! apps as events.
+//!
+//! ```ignore
+//! tauri::Builder::default()
+//!     .plugin(applications::plugin::init())
+//!     .run(tauri::generate_context!())
+//!     .expect("error while running tauri application");
+//! ```
+//!
+//! The capabilities of the app have to grant `applications:default`, then
+//! the frontend calls:
+//!
+//! * `invoke("plugin:applications|list_apps")`, the installed apps
+//! * `invoke("plugin:applications|launch_app", { path })`, to start the app
+//!   whose `app_desktop_path` is `path`
+//! * `invoke("plugin:applications|app_icon", { path, size })`, the path of a
+//!   PNG file of its icon, `size` pixels wide and high, for
+//!   `convertFileSrc`
+//!
+//! and listens to [`CHANGED_EVENT`], whose payload is an
+//! [`IndexChange`](crate::IndexChange).
+
+use crate::{App, AppIndex, AppTrait, Error, IconCache, Result, get_default_search_paths};
+use std::path::{Path, PathBuf};
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{Emitter, Manager, Runtime, State};
+
+/// The event emitted for each change to the installed apps.
+pub const CHANGED_EVENT: &str = "applications://changed";
+
+/// The size of the icons of `app_icon` if the frontend does not ask for one.
+const DEFAULT_ICON_SIZE: u32 = 64;
+
+/// The state of the plugin, managed by the app.
+struct Apps {
+    index: AppIndex,
+    icons: IconCache,
+}
+
+impl Apps {
+    fn get(&self, path: &Path) -> Result<App> {
+        self.index
+            .get_by_id(path)
+            .ok_or_else(|| Error::AppNotFound(path.to_path_buf()))
+    }
+}
+
+#[tauri::command]
+fn list_apps(apps: State<'_, Apps>) -> Vec<App> {
+    apps.index.snapshot()
+}
+
+#[tauri::command]
+fn launch_app(apps: State<'_, Apps>, path: PathBuf) -> Result<()> {
+    apps.get(&path)?.launch()
+}
+
+// Async, to not render icons on the main thread
+#[tauri::command]
+async fn app_icon(apps: State<'_, Apps>, path: PathBuf, size: Option<u32>) -> Result<PathBuf> {
+    apps.icons
+        .get(&apps.get(&path)?, size.unwrap_or(DEFAULT_ICON_SIZE))
+}
+
+/// The plugin for the apps in the default search paths.
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    init_with_paths(get_default_search_paths())
+}
+
+/// The plugin for the apps in `search_paths`, which are scanned when the
+/// plugin is set up, then watched. Icons are cached in the cache directory
+/// of the app.
+pub fn init_with_paths<R: Runtime>(search_paths: Vec<PathBuf>) -> TauriPlugin<R> {
+    Builder::new("applications")
+        .invoke_handler(tauri::generate_handler![list_apps, launch_app, app_icon])
+        .setup(move |app, _api| {
+            let index = AppIndex::new(&search_paths)?;
+            let changes = index.subscribe();
+            let emitter = app.clone();
+            // Ends once the index is dropped with the app
+            std::thread::spawn(move || {
+                for change in changes {
+                    let _ = emitter.emit(CHANGED_EVENT, change);
+                }
+            });
+
+            let icons = IconCache::new(app.path().app_cache_dir()?.join("icons"));
+            app.manage(Apps { index, icons });
+            Ok(())
+        })
+        .build()
+}
@@ -0,0 +1,157 @@
+//! External commands, e.g., `mdfind` or `winget`, run with a timeout so
+//! that a hung one does not hang the caller too, and the apps launched.
+
+use crate::{Error, Result};
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long a command may run unless an [`AppFinder`](crate::AppFinder)
+/// says otherwise.
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running command is checked for having exited, timed out or
+/// been cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Cancels a scan from another thread, see
+/// [`AppFinder::cancel_token`](crate::AppFinder::cancel_token). Clones
+/// share their state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop the scans using this token: running commands are killed, and
+    /// what has not been read yet is skipped.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tokens are equal when they are clones of each other.
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancelToken {}
+
+/// Like [`Command::output`], but the command is killed once it has run for
+/// `timeout`, or `cancel` is cancelled. With the `no-subprocess` feature,
+/// nothing is run.
+pub(crate) fn output(
+    command: &mut Command,
+    timeout: Duration,
+    cancel: Option<&CancelToken>,
+) -> Result<Output> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    if cfg!(feature = "no-subprocess") {
+        return Err(Error::SubprocessDisabled(program));
+    }
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read on threads, a command filling a pipe would wait for us forever
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut content = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut content);
+            }
+            content
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let error = if cancel.is_some_and(CancelToken::is_cancelled) {
+            Error::Cancelled
+        } else if Instant::now() >= deadline {
+            Error::Timeout { program, timeout }
+        } else {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        // Its own children may keep the pipes open, the reading threads
+        // are left to finish on their own
+        let _ = child.kill();
+        let _ = child.wait();
+        crate::trace::debug!(error = %error, "killed");
+        return Err(error);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Start `command` without waiting for it to exit, e.g., an app being
+/// launched. A thread waits for it instead, so that it does not linger as a
+/// zombie once it exits.
+pub(crate) fn spawn_detached(command: &mut Command) -> Result<()> {
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+#[cfg(all(test, unix, not(feature = "no-subprocess")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output() {
+        let out = output(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            DEFAULT_TIMEOUT,
+            None,
+        )
+        .unwrap();
+        assert!(out.status.success());
+        assert_eq!(out.stdout, b"out\n");
+        assert_eq!(out.stderr, b"err\n");
+
+        let start = Instant::now();
+        let e = output(
+            Command::new("sleep").arg("5"),
+            Duration::from_millis(100),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(e, Error::Timeout { ref program, .. } if program == "sleep"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        let cancel = CancelToken::new();
+        cancel.clone().cancel();
+        let e = output(
+            Command::new("sleep").arg("5"),
+            DEFAULT_TIMEOUT,
+            Some(&cancel),
+        )
+        .unwrap_err();
+        assert!(matches!(e, Error::Cancelled));
+    }
+}
@@ -0,0 +1,261 @@
+//! [`AppSearch`], matching what a user types in a launcher against the
+//! names of apps.
+
+use crate::App;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// How well a name matches a query, the best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Rank {
+    /// The whole name, e.g., `code` for `Code`.
+    Exact,
+    /// The start of the name, e.g., `vis` for `Visual Studio Code`.
+    Prefix,
+    /// The start of a word of the name, e.g., `stu` for `Visual Studio Code`.
+    WordPrefix,
+    /// The initials of the words of the name, e.g., `vsc` for
+    /// `Visual Studio Code`.
+    Initials,
+    /// Anywhere in the name, e.g., `dio` for `Visual Studio Code`.
+    Substring,
+}
+
+type Transliterate = Arc<dyn Fn(char) -> Option<String> + Send + Sync>;
+
+/// A search of apps by [`App::name`] and [`App::localized_app_names`],
+/// ignoring case:
+///
+/// ```no_run
+/// use applications::{AppSearch, get_all_apps, get_default_search_paths};
+///
+/// let apps = get_all_apps(&get_default_search_paths()).unwrap();
+/// for app in AppSearch::new().search(&apps, "vsc") {
+///     println!("{}", app.name);
+/// }
+/// ```
+///
+/// Names in scripts that users do not type directly are matched by their
+/// romanization too, given a [transliteration](AppSearch::transliterate)
+/// of their characters. With Pinyin, `fangda` and its initials `fd` find
+/// Finder by its Chinese name 访达. The crate does not ship the tables,
+/// crates like `pinyin` do:
+///
+/// ```ignore
+/// use pinyin::ToPinyin;
+///
+/// let search = AppSearch::new().transliterate(|c| Some(c.to_pinyin()?.plain().to_string()));
+/// ```
+#[derive(Clone, Default)]
+pub struct AppSearch {
+    transliterate: Option<Transliterate>,
+}
+
+impl fmt::Debug for AppSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppSearch")
+            .field("transliterate", &self.transliterate.is_some())
+            .finish()
+    }
+}
+
+impl AppSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Romanize the characters `transliterate` returns `Some` for, each of
+    /// them counts as a word of its own.
+    pub fn transliterate(
+        mut self,
+        transliterate: impl Fn(char) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.transliterate = Some(Arc::new(transliterate));
+        self
+    }
+
+    /// Whether one of the names of `app` matches `query`. A query without
+    /// letters or digits, e.g., an empty one or `-`, matches no app.
+    pub fn matches(&self, app: &App, query: &str) -> bool {
+        let query = Query::new(query);
+        !query.compact.is_empty() && self.rank(app, &query).is_some()
+    }
+
+    /// The apps that match `query`, the best matches first, and the apps
+    /// that match equally well sorted by name. None if `query` has no
+    /// letters or digits.
+    pub fn search<'a>(&self, apps: impl IntoIterator<Item = &'a App>, query: &str) -> Vec<&'a App> {
+        let query = Query::new(query);
+        if query.compact.is_empty() {
+            return Vec::new();
+        }
+        let mut found: Vec<(Rank, &App)> = apps
+            .into_iter()
+            .filter_map(|app| Some((self.rank(app, &query)?, app)))
+            .collect();
+        found.sort_by(|(rank, app), (other_rank, other)| {
+            rank.cmp(other_rank).then_with(|| compare_names(app, other))
+        });
+
+        found.into_iter().map(|(_, app)| app).collect()
+    }
+
+    fn rank(&self, app: &App, query: &Query) -> Option<Rank> {
+        std::iter::once(&app.name)
+            .chain(app.localized_app_names.values())
+            .filter_map(|name| self.rank_name(name, query))
+            .min()
+    }
+
+    fn rank_name(&self, name: &str, query: &Query) -> Option<Rank> {
+        let text = name.to_lowercase();
+        let words = self.words(name);
+        let joined: String = words.concat();
+        let initials: String = words
+            .iter()
+            .filter_map(|word| word.chars().next())
+            .collect();
+
+        if text == query.text || joined == query.compact {
+            return Some(Rank::Exact);
+        }
+        if text.starts_with(&query.text) || joined.starts_with(&query.compact) {
+            return Some(Rank::Prefix);
+        }
+        // Where each word starts in `joined`, so that queries spanning
+        // words match too, e.g., `studiocode`
+        let mut start = 0;
+        for word in &words {
+            if joined[start..].starts_with(&query.compact) {
+                return Some(Rank::WordPrefix);
+            }
+            start += word.len();
+        }
+        if initials.starts_with(&query.compact) {
+            return Some(Rank::Initials);
+        }
+        if text.contains(&query.text) || joined.contains(&query.compact) {
+            return Some(Rank::Substring);
+        }
+
+        None
+    }
+
+    /// The lowercase words of `name`: runs of letters and digits, and the
+    /// romanization of each transliterated character.
+    fn words(&self, name: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        for c in name.chars() {
+            let romanized = self.transliterate.as_ref().and_then(|f| f(c));
+            if let Some(romanized) = romanized {
+                words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+                words.push(romanized.to_lowercase());
+            } else if c.is_alphanumeric() {
+                word.extend(c.to_lowercase());
+            } else {
+                words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            }
+        }
+        words.extend((!word.is_empty()).then_some(word));
+
+        words
+    }
+}
+
+struct Query {
+    /// Lowercase and trimmed.
+    text: String,
+    /// Without anything but letters and digits, e.g., `fangda` for
+    /// `fang da`.
+    compact: String,
+}
+
+impl Query {
+    fn new(query: &str) -> Self {
+        let text = query.trim().to_lowercase();
+        let compact = text.chars().filter(|c| c.is_alphanumeric()).collect();
+        Self { text, compact }
+    }
+}
+
+fn compare_names(app: &App, other: &App) -> Ordering {
+    app.name
+        .to_lowercase()
+        .cmp(&other.name.to_lowercase())
+        .then_with(|| app.name.cmp(&other.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, localized_names: &[(&str, &str)]) -> App {
+        App {
+            name: name.to_string(),
+            localized_app_names: localized_names
+                .iter()
+                .map(|(locale, name)| (locale.to_string(), name.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(apps: Vec<&App>) -> Vec<&str> {
+        apps.into_iter().map(|app| app.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_search() {
+        let apps = vec![
+            app("Visual Studio Code", &[]),
+            app("Code", &[]),
+            app("Audio Recorder", &[]),
+            app("Finder", &[("zh-CN", "访达")]),
+        ];
+        let search = AppSearch::new();
+        assert_eq!(
+            names(search.search(&apps, "code")),
+            ["Code", "Visual Studio Code"]
+        );
+        assert_eq!(names(search.search(&apps, "vsc")), ["Visual Studio Code"]);
+        assert_eq!(
+            names(search.search(&apps, "studio code")),
+            ["Visual Studio Code"]
+        );
+        assert_eq!(
+            names(search.search(&apps, "dio")),
+            ["Audio Recorder", "Visual Studio Code"]
+        );
+        assert_eq!(names(search.search(&apps, "访达")), ["Finder"]);
+        assert!(search.search(&apps, "fangda").is_empty());
+        assert!(search.search(&apps, "").is_empty());
+        assert!(search.search(&apps, " ").is_empty());
+        assert!(search.search(&apps, "- !").is_empty());
+        assert!(!search.matches(&apps[0], "-"));
+        assert!(search.matches(&apps[0], "VISUAL"));
+        assert!(!search.matches(&apps[0], "xcode"));
+    }
+
+    #[test]
+    fn test_transliterate() {
+        let apps = vec![app("Finder", &[("zh-CN", "访达")]), app("QQ音乐", &[])];
+        let search = AppSearch::new().transliterate(|c| {
+            let romanized = match c {
+                '访' => "Fang",
+                '达' => "da",
+                '音' => "yin",
+                '乐' => "yue",
+                _ => return None,
+            };
+            Some(romanized.to_string())
+        });
+        assert_eq!(names(search.search(&apps, "fangda")), ["Finder"]);
+        assert_eq!(names(search.search(&apps, "fang da")), ["Finder"]);
+        assert_eq!(names(search.search(&apps, "fd")), ["Finder"]);
+        assert_eq!(names(search.search(&apps, "yinyue")), ["QQ音乐"]);
+        assert_eq!(names(search.search(&apps, "qyy")), ["QQ音乐"]);
+        assert_eq!(names(search.search(&apps, "音乐")), ["QQ音乐"]);
+    }
+}
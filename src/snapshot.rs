@@ -0,0 +1,212 @@
+//! [`AppSnapshot`], the result of a scan saved to a file, to look at
+//! somewhere else.
+
+use crate::finder::{AppFinder, Provider, ScanProgress};
+use crate::{App, Error, Result, ScanError, ScanReport};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Bumped whenever the layout of the file changes in a way older versions
+/// of the crate cannot read.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// When a provider of the scan started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderStart {
+    pub provider: Provider,
+    /// Since the scan started.
+    pub after: Duration,
+}
+
+/// What a scan could not read, see [`ScanError`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotError {
+    pub path: Option<PathBuf>,
+    /// The error, as it was displayed.
+    pub message: String,
+}
+
+/// Everything a scan found and skipped, with where and when it ran, saved
+/// as JSON, e.g., for a user to attach to a report of an app that is
+/// missing, and for maintainers to replay in a test:
+///
+/// ```no_run
+/// use applications::{AppFinder, AppSnapshot};
+///
+/// AppSnapshot::capture(&AppFinder::new())
+///     .save("apps-snapshot.json")
+///     .unwrap();
+///
+/// let snapshot = AppSnapshot::load("apps-snapshot.json").unwrap();
+/// for error in &snapshot.errors {
+///     println!("{:?}: {}", error.path, error.message);
+/// }
+/// ```
+///
+/// Where each app was found is in its [`App::sources`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppSnapshot {
+    schema_version: u32,
+    /// When the scan started.
+    pub taken_at: SystemTime,
+    /// How long it took.
+    pub duration: Duration,
+    /// The version of the crate that took it.
+    pub crate_version: String,
+    /// [`std::env::consts::OS`] and [`std::env::consts::ARCH`] of the
+    /// machine it was taken on.
+    pub os: String,
+    pub arch: String,
+    /// The search paths that were scanned: those that exist and are not
+    /// excluded.
+    pub search_paths: Vec<PathBuf>,
+    /// In the order they started.
+    pub providers: Vec<ProviderStart>,
+    pub apps: Vec<App>,
+    pub errors: Vec<SnapshotError>,
+}
+
+/// Remembers when the providers of a scan start.
+struct Recorder {
+    started: Instant,
+    providers: Mutex<Vec<ProviderStart>>,
+}
+
+impl ScanProgress for Recorder {
+    fn on_provider_start(&self, provider: Provider) {
+        self.providers.lock().unwrap().push(ProviderStart {
+            provider,
+            after: self.started.elapsed(),
+        });
+    }
+}
+
+impl AppSnapshot {
+    /// Scan with `finder` and keep all of the result.
+    pub fn capture(finder: &AppFinder) -> Self {
+        let taken_at = SystemTime::now();
+        let recorder = Arc::new(Recorder {
+            started: Instant::now(),
+            providers: Mutex::new(Vec::new()),
+        });
+        let report = finder.observed_by(recorder.clone()).find_with_report();
+        let duration = recorder.started.elapsed();
+        let providers = std::mem::take(&mut *recorder.providers.lock().unwrap());
+
+        Self {
+            schema_version: SNAPSHOT_VERSION,
+            taken_at,
+            duration,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            search_paths: finder
+                .search_paths()
+                .into_iter()
+                .map(Path::to_path_buf)
+                .collect(),
+            providers,
+            apps: report.apps,
+            errors: report
+                .errors
+                .into_iter()
+                .map(|skipped| SnapshotError {
+                    path: skipped.path,
+                    message: skipped.error.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Save the snapshot as JSON at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|e| Error::parse(path, e.to_string()))?;
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    /// A snapshot saved by [`AppSnapshot::save`]. Snapshots of a newer
+    /// version of the file are rejected with
+    /// [`Error::UnsupportedSchemaVersion`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read(path)?;
+        let snapshot: Self =
+            serde_json::from_slice(&content).map_err(|e| Error::parse(path, e.to_string()))?;
+        if snapshot.schema_version > SNAPSHOT_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(snapshot.schema_version));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// The scan as [`AppFinder::find_with_report`] returned it, with the
+    /// errors as [`Error::Platform`] of their message.
+    pub fn into_report(self) -> ScanReport {
+        ScanReport {
+            apps: self.apps,
+            errors: self
+                .errors
+                .into_iter()
+                .map(|skipped| ScanError {
+                    path: skipped.path,
+                    error: Error::Platform(skipped.message.into()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiscoverySource;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_snapshot() {
+        let temp_dir = TempDir::new("snapshot");
+        let dir = temp_dir.path().to_path_buf();
+        let mut snapshot = AppSnapshot::capture(&AppFinder::with_paths([&dir]));
+        assert_eq!(snapshot.search_paths, std::slice::from_ref(&dir));
+        assert!(snapshot.apps.is_empty());
+        assert!(
+            snapshot
+                .providers
+                .iter()
+                .any(|start| start.provider == Provider::SearchPaths)
+        );
+
+        snapshot.apps.push(App {
+            name: "Foo".to_string(),
+            app_desktop_path: dir.join("foo.desktop"),
+            sources: vec![DiscoverySource::DesktopFile],
+            ..Default::default()
+        });
+        snapshot.errors.push(SnapshotError {
+            path: Some(dir.join("bar.desktop")),
+            message: "permission denied".to_string(),
+        });
+        let path = dir.join("snapshot.json");
+        snapshot.save(&path).unwrap();
+        let loaded = AppSnapshot::load(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+        let report = loaded.into_report();
+        assert_eq!(report.apps, snapshot.apps);
+        assert_eq!(report.errors[0].error.to_string(), "permission denied");
+
+        let newer = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"schema_version\": 1", "\"schema_version\": 2");
+        std::fs::write(&path, newer).unwrap();
+        assert!(matches!(
+            AppSnapshot::load(&path),
+            Err(Error::UnsupportedSchemaVersion(2))
+        ));
+    }
+}
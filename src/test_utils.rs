@@ -0,0 +1,30 @@
+//! Helpers shared by the unit tests.
+
+use std::path::{Path, PathBuf};
+
+/// `applications-rs-<name>-<process ID>` in the temporary directory,
+/// removed once it is dropped, including when an assertion fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create the directory, empty, in case a run with the same process ID
+    /// was killed before it removed it.
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("applications-rs-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,32 @@
+//! [`tracing`](https://docs.rs/tracing) events of scans and watchers, with
+//! the `tracing` feature. Without it, nothing is compiled in.
+
+/// A `tracing::debug!` event.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::debug!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use debug;
+
+/// Run `f` in a `name` span, e.g., a provider of a scan, and report how
+/// long it took.
+pub(crate) fn timed<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "tracing")]
+    {
+        let _span = tracing::debug_span!("applications", provider = name).entered();
+        let start = std::time::Instant::now();
+        let result = f();
+        tracing::debug!(elapsed = ?start.elapsed(), "done");
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = name;
+        f()
+    }
+}
@@ -0,0 +1,84 @@
+//! [`App::uninstall`], removing an app the way its platform does.
+
+use crate::common::{App, UninstallCommand};
+#[cfg(target_os = "macos")]
+use crate::platforms::trash;
+use crate::platforms::uninstall_action;
+use crate::{Error, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// What [`App::uninstall`] does to remove an app.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UninstallAction {
+    /// Run an uninstaller: the one the installer registered on Windows,
+    /// `msiexec /x` for Windows Installer products, `Remove-AppxPackage`
+    /// for packaged apps, `winget`, Scoop or Chocolatey, or `flatpak
+    /// uninstall` or `snap remove` on Linux.
+    Command(UninstallCommand),
+    /// Move the bundle at `path` to the Trash, on macOS. `app_store` if it
+    /// was installed from the App Store, which offers to install it again.
+    Trash { path: PathBuf, app_store: bool },
+}
+
+impl App {
+    /// How to uninstall the app, or [`Error::NotUninstallable`] with the
+    /// reason, e.g., that it belongs to a package of the system package
+    /// manager, which is for the user to remove.
+    pub fn uninstall_action(&self) -> Result<UninstallAction> {
+        uninstall_action(self)
+    }
+
+    /// A command that uninstalls the app, ready to be spawned, if that is
+    /// how it is uninstalled, see [`App::uninstall_action`].
+    pub fn uninstall_command(&self) -> Option<Command> {
+        match self.uninstall_action() {
+            Ok(UninstallAction::Command(uninstaller)) => Some(uninstaller.to_command()),
+            _ => None,
+        }
+    }
+
+    /// Move the bundle of the app to the Trash, and return where it is in
+    /// the Trash. The apps of macOS, which System Integrity Protection
+    /// keeps, are refused with [`Error::NotUninstallable`]. A watcher of
+    /// the search path reports the app deleted.
+    #[cfg(target_os = "macos")]
+    pub fn move_to_trash(&self) -> Result<PathBuf> {
+        match self.uninstall_action()? {
+            UninstallAction::Trash { path, .. } => trash(&path),
+            UninstallAction::Command(_) => Err(Error::UnsupportedPlatform),
+        }
+    }
+
+    /// Uninstall the app, waiting for the uninstaller to exit, which may
+    /// ask the user to confirm or for admin rights first.
+    pub fn uninstall(&self) -> Result<()> {
+        match self.uninstall_action()? {
+            UninstallAction::Command(uninstaller) => {
+                let program = uninstaller.program.to_string_lossy().into_owned();
+                if cfg!(feature = "no-subprocess") {
+                    return Err(Error::SubprocessDisabled(program));
+                }
+                let output = uninstaller.to_command().output()?;
+                if !output.status.success() {
+                    return Err(Error::command(&program, &output));
+                }
+
+                Ok(())
+            }
+            #[cfg(target_os = "macos")]
+            UninstallAction::Trash { path, .. } => trash(&path).map(drop),
+            #[cfg(not(target_os = "macos"))]
+            UninstallAction::Trash { .. } => Err(Error::UnsupportedPlatform),
+        }
+    }
+}
+
+impl UninstallCommand {
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+
+        command
+    }
+}
@@ -0,0 +1,1287 @@
+//! Linux helpers that do not belong to the desktop entry scanner itself.
+
+use crate::launch_spec::LaunchArg;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Split the value of an `Exec` key into arguments, following the quoting
+/// rules of the Desktop Entry Specification: arguments containing reserved
+/// characters are quoted with double quotes, inside which `"`, `` ` ``, `$`
+/// and `\` are escaped with a backslash.
+///
+/// Field codes (`%f`, `%U`, ...) are kept as-is.
+pub fn split_exec_line(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_arg = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+/// Values used to expand the field codes of an `Exec` line.
+pub struct ExecContext<'a> {
+    /// `%c`, the translated name of the app.
+    pub name: &'a str,
+    /// `%i`, the `Icon` key.
+    pub icon: Option<&'a str>,
+    /// `%k`, the location of the desktop file.
+    pub desktop_file: &'a Path,
+}
+
+/// Split an `Exec` line and expand its field codes, for a launch without
+/// files or URLs: `%f`, `%F`, `%u` and `%U` are removed, as are the
+/// deprecated ones.
+pub fn expand_exec_line(exec: &str, context: &ExecContext) -> Vec<String> {
+    exec_template(exec, context)
+        .into_iter()
+        .filter_map(|arg| match arg {
+            LaunchArg::Literal(arg) => Some(arg),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split an `Exec` line and expand its field codes, but those of the files
+/// and URLs, `%f`, `%F`, `%u` and `%U`, which are left in their places.
+pub fn exec_template(exec: &str, context: &ExecContext) -> Vec<LaunchArg> {
+    let mut expanded = Vec::new();
+    for arg in split_exec_line(exec) {
+        match arg.as_str() {
+            // Field codes that must stand alone
+            "%f" => expanded.push(LaunchArg::File),
+            "%F" => expanded.push(LaunchArg::Files),
+            "%u" => expanded.push(LaunchArg::Url),
+            "%U" => expanded.push(LaunchArg::Urls),
+            "%i" => {
+                if let Some(icon) = context.icon {
+                    expanded.push(LaunchArg::Literal("--icon".to_string()));
+                    expanded.push(LaunchArg::Literal(icon.to_string()));
+                }
+            }
+            _ => expanded.extend(expand_field_codes(&arg, context).map(LaunchArg::Literal)),
+        }
+    }
+
+    expanded
+}
+
+/// `arg` with its field codes expanded, `None` if it only contained
+/// dropped ones.
+fn expand_field_codes(arg: &str, context: &ExecContext) -> Option<String> {
+    let mut buf = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            buf.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => buf.push('%'),
+            Some('c') => buf.push_str(context.name),
+            Some('k') => buf.push_str(&context.desktop_file.to_string_lossy()),
+            // Deprecated or misplaced ones are dropped
+            Some(_) | None => {}
+        }
+    }
+    if buf.is_empty() && !arg.is_empty() {
+        // It only contained dropped field codes
+        return None;
+    }
+
+    Some(buf)
+}
+
+/// The program an `Exec` line runs, skipping an `env VAR=value` prefix.
+pub fn exec_program(exec: &str) -> Option<String> {
+    let args = split_exec_line(exec);
+    let mut args = args.into_iter().peekable();
+    if args
+        .peek()
+        .is_some_and(|arg| arg == "env" || arg == "/usr/bin/env")
+    {
+        args.next();
+        while args
+            .peek()
+            .is_some_and(|arg| arg.contains('=') && !arg.starts_with('/'))
+        {
+            args.next();
+        }
+    }
+
+    args.next()
+}
+
+pub(crate) fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Resolve `program` to an executable file like a shell would: names
+/// containing a slash are used as-is, others are searched in `$PATH`.
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable_file(&path).then_some(path);
+    }
+
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable_file(candidate))
+}
+
+/// A minimal XML element tree, just enough for reading AppStream metainfo
+/// files. Namespaces, DTDs and processing instructions are ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+    pub children: Vec<XmlNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+impl XmlElement {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Child elements with the given tag name.
+    pub fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter_map(move |child| match child {
+            XmlNode::Element(element) if element.name == name => Some(element),
+            _ => None,
+        })
+    }
+
+    /// The first child element with the given tag name that is not a
+    /// translation, i.e., has no `xml:lang` attribute.
+    pub fn untranslated(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find_map(|child| match child {
+            XmlNode::Element(element)
+                if element.name == name && element.attr("xml:lang").is_none() =>
+            {
+                Some(element)
+            }
+            _ => None,
+        })
+    }
+
+    /// Concatenated text of this element and all its descendants, with
+    /// whitespace collapsed.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn collect_text(&self, buf: &mut String) {
+        for child in self.children.iter() {
+            match child {
+                XmlNode::Element(element) => element.collect_text(buf),
+                XmlNode::Text(text) => buf.push_str(text),
+            }
+        }
+    }
+}
+
+fn decode_entities(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semicolon];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse::<u32>().ok().and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+
+        match replacement {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                // Not an entity we know, keep it verbatim
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn parse_attributes(raw: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = raw.trim();
+
+    while let Some(eq_pos) = rest.find('=') {
+        let name = rest[..eq_pos].trim().to_string();
+        let after_eq = rest[eq_pos + 1..].trim_start();
+        let Some(quote) = after_eq.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(end) = after_eq[1..].find(quote) else {
+            break;
+        };
+        attributes.insert(name, decode_entities(&after_eq[1..1 + end]));
+        rest = after_eq[end + 2..].trim_start();
+    }
+
+    attributes
+}
+
+/// Parse `content` into its root element.
+///
+/// Returns `None` if the document is not well-formed enough to build a tree.
+pub fn parse_xml(content: &str) -> Option<XmlElement> {
+    // Stack of open elements, the bottom one is a synthetic document node.
+    let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+    let mut rest = content;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            let text = decode_entities(rest);
+            stack.last_mut()?.children.push(XmlNode::Text(text));
+            break;
+        };
+        if lt > 0 {
+            let text = decode_entities(&rest[..lt]);
+            stack.last_mut()?.children.push(XmlNode::Text(text));
+        }
+        rest = &rest[lt..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->")?;
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>")?;
+            stack
+                .last_mut()?
+                .children
+                .push(XmlNode::Text(after[..end].to_string()));
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>')?;
+            rest = &rest[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>')?;
+            let name = after[..end].trim();
+            let element = stack.pop()?;
+            if element.name != name || stack.is_empty() {
+                return None;
+            }
+            stack.last_mut()?.children.push(XmlNode::Element(element));
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find('>')?;
+            let mut tag = &rest[1..end];
+            let self_closing = tag.ends_with('/');
+            if self_closing {
+                tag = &tag[..tag.len() - 1];
+            }
+            let (name, raw_attributes) = match tag.find(char::is_whitespace) {
+                Some(pos) => (&tag[..pos], &tag[pos..]),
+                None => (tag, ""),
+            };
+            let element = XmlElement {
+                name: name.to_string(),
+                attributes: parse_attributes(raw_attributes),
+                children: Vec::new(),
+            };
+            if self_closing {
+                stack.last_mut()?.children.push(XmlNode::Element(element));
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    if stack.len() != 1 {
+        return None;
+    }
+    let document = stack.pop()?;
+    document.children.into_iter().find_map(|node| match node {
+        XmlNode::Element(element) => Some(element),
+        XmlNode::Text(_) => None,
+    })
+}
+
+/// Metadata of an application taken from its AppStream component.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AppStreamMetadata {
+    /// Component ID, e.g., `org.gnome.Calculator`.
+    pub id: String,
+    /// One-line summary of the application.
+    pub summary: Option<String>,
+    /// Long description, paragraphs are separated by an empty line and list
+    /// items are prefixed with "- ".
+    pub description: Option<String>,
+    pub developer_name: Option<String>,
+    /// URL of the project homepage.
+    pub homepage: Option<String>,
+    /// URLs of the screenshots, the default screenshot comes first.
+    pub screenshots: Vec<String>,
+    /// Desktop file IDs (`<launchable type="desktop-id">`) of this component.
+    pub desktop_ids: Vec<String>,
+}
+
+fn description_text(description: &XmlElement) -> String {
+    let mut blocks = Vec::new();
+    for child in description.children.iter() {
+        let XmlNode::Element(element) = child else {
+            continue;
+        };
+        if element.attr("xml:lang").is_some() {
+            continue;
+        }
+        match element.name.as_str() {
+            "p" => blocks.push(element.text()),
+            "ul" | "ol" => {
+                let items: Vec<String> = element
+                    .elements("li")
+                    .filter(|li| li.attr("xml:lang").is_none())
+                    .map(|li| format!("- {}", li.text()))
+                    .collect();
+                blocks.push(items.join("\n"));
+            }
+            _ => {}
+        }
+    }
+
+    blocks.retain(|block| !block.is_empty());
+    blocks.join("\n\n")
+}
+
+fn parse_component(component: &XmlElement) -> Option<AppStreamMetadata> {
+    let id = component.untranslated("id")?.text();
+    if id.is_empty() {
+        return None;
+    }
+
+    let non_empty = |text: String| (!text.is_empty()).then_some(text);
+
+    let summary = component
+        .untranslated("summary")
+        .map(XmlElement::text)
+        .and_then(non_empty);
+    let description = component
+        .untranslated("description")
+        .map(description_text)
+        .and_then(non_empty);
+    // `<developer_name>` is deprecated in favor of `<developer><name>`
+    let developer_name = component
+        .untranslated("developer")
+        .and_then(|developer| developer.untranslated("name"))
+        .or_else(|| component.untranslated("developer_name"))
+        .map(XmlElement::text)
+        .and_then(non_empty);
+    let homepage = component
+        .elements("url")
+        .find(|url| url.attr("type") == Some("homepage"))
+        .map(XmlElement::text)
+        .and_then(non_empty);
+
+    let mut screenshots = Vec::new();
+    if let Some(container) = component.elements("screenshots").next() {
+        let mut all: Vec<&XmlElement> = container.elements("screenshot").collect();
+        // Stable sort, so the default one goes first and the rest keep their order
+        all.sort_by_key(|screenshot| screenshot.attr("type") != Some("default"));
+        for screenshot in all {
+            let images: Vec<&XmlElement> = screenshot
+                .elements("image")
+                .filter(|image| image.attr("xml:lang").is_none())
+                .collect();
+            let source = images
+                .iter()
+                .find(|image| image.attr("type") == Some("source"))
+                .or_else(|| images.first())
+                .copied();
+            if let Some(url) = source.map(XmlElement::text).and_then(non_empty) {
+                screenshots.push(url);
+            }
+        }
+    }
+
+    let mut desktop_ids: Vec<String> = component
+        .elements("launchable")
+        .filter(|launchable| launchable.attr("type") == Some("desktop-id"))
+        .map(XmlElement::text)
+        .filter(|desktop_id| !desktop_id.is_empty())
+        .collect();
+    if desktop_ids.is_empty() {
+        // Legacy metainfo files use the desktop file ID as the component ID
+        if id.ends_with(".desktop") {
+            desktop_ids.push(id.clone());
+        } else {
+            desktop_ids.push(format!("{}.desktop", id));
+        }
+    }
+
+    Some(AppStreamMetadata {
+        id,
+        summary,
+        description,
+        developer_name,
+        homepage,
+        screenshots,
+        desktop_ids,
+    })
+}
+
+/// Parse a metainfo file or an AppStream catalog (a `<components>` document),
+/// only desktop applications are returned.
+pub fn parse_appstream(content: &str) -> Vec<AppStreamMetadata> {
+    let Some(root) = parse_xml(content) else {
+        return Vec::new();
+    };
+
+    let is_desktop_app = |component: &XmlElement| {
+        matches!(
+            component.attr("type"),
+            Some("desktop-application") | Some("desktop")
+        )
+    };
+
+    match root.name.as_str() {
+        "component" => {
+            if is_desktop_app(&root) {
+                parse_component(&root).into_iter().collect()
+            } else {
+                Vec::new()
+            }
+        }
+        "components" => root
+            .elements("component")
+            .filter(|component| is_desktop_app(component))
+            .filter_map(parse_component)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Read all the `.xml` files (metainfo files or uncompressed catalogs)
+/// directly under `dir`. Unreadable or malformed files are skipped.
+pub fn read_appstream_dir(dir: &Path) -> Vec<(PathBuf, AppStreamMetadata)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut components = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "xml") {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for component in parse_appstream(&content) {
+            components.push((path.clone(), component));
+        }
+    }
+
+    components
+}
+
+/// The associations of MIME types with apps in a `mimeapps.list` file,
+/// each a list of desktop file IDs, e.g., `firefox.desktop`, in the order
+/// of the file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MimeApps {
+    /// `[Default Applications]`, the apps that open a type.
+    pub default: HashMap<String, Vec<String>>,
+    /// `[Added Associations]`, the apps that can open a type, those the
+    /// user picked first.
+    pub added: HashMap<String, Vec<String>>,
+    /// `[Removed Associations]`, the apps that must not be offered for a
+    /// type, though they or a file of lower priority claim it.
+    pub removed: HashMap<String, Vec<String>>,
+}
+
+impl MimeApps {
+    /// Parse a `mimeapps.list` file. Other groups and malformed lines are
+    /// skipped.
+    pub fn parse(content: &str) -> Self {
+        let mut mime_apps = Self::default();
+        let mut group = "";
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                group = name;
+                continue;
+            }
+            let associations = match group {
+                "Default Applications" => &mut mime_apps.default,
+                "Added Associations" => &mut mime_apps.added,
+                "Removed Associations" => &mut mime_apps.removed,
+                _ => continue,
+            };
+            let Some((mime_type, ids)) = line.split_once('=') else {
+                continue;
+            };
+            let ids = ids
+                .split(';')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string);
+            associations
+                .entry(mime_type.trim().to_string())
+                .or_default()
+                .extend(ids);
+        }
+
+        mime_apps
+    }
+}
+
+/// The MIME types of the files with `extension`, e.g., `png`, in a
+/// `globs2` file of the shared MIME-info database, the most likely first.
+/// Each line is `<weight>:<MIME type>:<glob>`, with `:cs` after globs that
+/// are case-sensitive.
+pub fn mime_types_of_extension(globs2: &str, extension: &str) -> Vec<String> {
+    let glob = format!("*.{extension}");
+    let mut mime_types: Vec<(u32, &str)> = globs2
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let weight = fields.next()?.parse().ok()?;
+            let mime_type = fields.next()?;
+            let pattern = fields.next()?;
+            let case_sensitive = fields.any(|flag| flag == "cs");
+            let matches = if case_sensitive {
+                pattern == glob
+            } else {
+                pattern.eq_ignore_ascii_case(&glob)
+            };
+            matches.then_some((weight, mime_type))
+        })
+        .collect();
+    mime_types.sort_by_key(|(weight, _)| std::cmp::Reverse(*weight));
+
+    mime_types
+        .into_iter()
+        .map(|(_, mime_type)| mime_type.to_string())
+        .collect()
+}
+
+/// `mime_types` followed by the types they are subclasses of, nearest
+/// first, e.g., `text/x-rust` => `text/plain`, in `subclasses` files of the
+/// shared MIME-info database. Each line is a MIME type and one of its
+/// parents.
+pub fn with_mime_ancestors(subclasses: &[String], mut mime_types: Vec<String>) -> Vec<String> {
+    let parents: Vec<(&str, &str)> = subclasses
+        .iter()
+        .flat_map(|subclasses| subclasses.lines())
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(' '))
+        .collect();
+    let mut i = 0;
+    while i < mime_types.len() {
+        for (mime_type, parent) in &parents {
+            if mime_type.eq_ignore_ascii_case(&mime_types[i])
+                && !mime_types
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(parent))
+            {
+                mime_types.push(parent.to_string());
+            }
+        }
+        i += 1;
+    }
+
+    mime_types
+}
+
+/// A toplevel window of a Wayland compositor, as the wlroots foreign
+/// toplevel protocol describes it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Toplevel {
+    pub app_id: String,
+    pub title: String,
+    /// Whether it has the input focus.
+    pub activated: bool,
+}
+
+/// The global that lists the toplevels of all clients.
+const FOREIGN_TOPLEVEL_MANAGER: &str = "zwlr_foreign_toplevel_manager_v1";
+
+/// `zwlr_foreign_toplevel_handle_v1.state` value of the focused toplevel.
+const TOPLEVEL_STATE_ACTIVATED: u32 = 2;
+
+/// The socket of the compositor, `$WAYLAND_DISPLAY` relative to
+/// `$XDG_RUNTIME_DIR` unless it is absolute.
+pub fn wayland_socket() -> Option<PathBuf> {
+    let display = PathBuf::from(std::env::var_os("WAYLAND_DISPLAY")?);
+    if display.is_absolute() {
+        return Some(display);
+    }
+
+    Some(PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?).join(display))
+}
+
+/// A message of the Wayland wire protocol, to or from `object`.
+#[derive(Debug, PartialEq, Eq)]
+struct WaylandMessage {
+    object: u32,
+    opcode: u16,
+    args: Vec<u8>,
+}
+
+impl WaylandMessage {
+    fn new(object: u32, opcode: u16) -> Self {
+        Self {
+            object,
+            opcode,
+            args: Vec::new(),
+        }
+    }
+
+    fn uint(mut self, value: u32) -> Self {
+        self.args.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn string(self, value: &str) -> Self {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        self.array(&bytes)
+    }
+
+    fn array(mut self, value: &[u8]) -> Self {
+        self.args
+            .extend_from_slice(&(value.len() as u32).to_ne_bytes());
+        self.args.extend_from_slice(value);
+        self.args.resize(self.args.len().next_multiple_of(4), 0);
+        self
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let size = (8 + self.args.len()) as u32;
+        let mut bytes = Vec::with_capacity(size as usize);
+        bytes.extend_from_slice(&self.object.to_ne_bytes());
+        bytes.extend_from_slice(&((size << 16) | u32::from(self.opcode)).to_ne_bytes());
+        bytes.extend_from_slice(&self.args);
+        bytes
+    }
+}
+
+/// Reads the arguments of a [`WaylandMessage`] in order, `None` once they
+/// run out.
+struct WaylandArgs<'a>(&'a [u8]);
+
+impl<'a> WaylandArgs<'a> {
+    fn uint(&mut self) -> Option<u32> {
+        let (value, rest) = self.0.split_first_chunk::<4>()?;
+        self.0 = rest;
+        Some(u32::from_ne_bytes(*value))
+    }
+
+    fn array(&mut self) -> Option<&'a [u8]> {
+        let len = self.uint()? as usize;
+        let value = self.0.get(..len)?;
+        self.0 = self.0.get(len.next_multiple_of(4)..)?;
+        Some(value)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let value = self.array()?;
+        // Without the terminating NUL, a null string is empty
+        let value = value.strip_suffix(&[0]).unwrap_or(value);
+        Some(String::from_utf8_lossy(value).into_owned())
+    }
+}
+
+/// Take the complete messages off the front of `buf`.
+fn take_wayland_messages(buf: &mut Vec<u8>) -> std::io::Result<Vec<WaylandMessage>> {
+    let mut messages = Vec::new();
+    let mut at = 0;
+    while let Some(header) = buf.get(at..at + 8) {
+        let object = u32::from_ne_bytes(header[..4].try_into().unwrap());
+        let word = u32::from_ne_bytes(header[4..].try_into().unwrap());
+        let size = (word >> 16) as usize;
+        if size < 8 || !size.is_multiple_of(4) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid Wayland message size {size}"),
+            ));
+        }
+        let Some(args) = buf.get(at + 8..at + size) else {
+            break;
+        };
+        messages.push(WaylandMessage {
+            object,
+            opcode: word as u16,
+            args: args.to_vec(),
+        });
+        at += size;
+    }
+    buf.drain(..at);
+
+    Ok(messages)
+}
+
+/// List the toplevels through the Wayland connection `stream`, with the
+/// `zwlr_foreign_toplevel_manager_v1` global that wlroots based
+/// compositors, e.g., Sway, Hyprland, niri or labwc, provide. Fails with
+/// [`std::io::ErrorKind::Unsupported`] on other compositors.
+pub fn read_toplevels(
+    mut stream: impl std::io::Read + std::io::Write,
+) -> std::io::Result<Vec<Toplevel>> {
+    use std::io::{Error, ErrorKind};
+
+    const DISPLAY: u32 = 1;
+    const REGISTRY: u32 = 2;
+    const REGISTRY_DONE: u32 = 3;
+    const MANAGER: u32 = 4;
+    const MANAGER_DONE: u32 = 5;
+
+    // `wl_display.get_registry` and `wl_display.sync`, whose callback is
+    // done once the globals are announced
+    let mut requests = WaylandMessage::new(DISPLAY, 1).uint(REGISTRY).encode();
+    requests.extend(WaylandMessage::new(DISPLAY, 0).uint(REGISTRY_DONE).encode());
+    stream.write_all(&requests)?;
+
+    let mut toplevels: Vec<(u32, Toplevel)> = Vec::new();
+    let mut manager_bound = false;
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..read]);
+
+        for message in take_wayland_messages(&mut buf)? {
+            let mut args = WaylandArgs(&message.args);
+            match (message.object, message.opcode) {
+                (DISPLAY, 0) => {
+                    let reason = (args.uint(), args.uint(), args.string());
+                    return Err(Error::other(format!("Wayland error {reason:?}")));
+                }
+                // `wl_registry.global`
+                (REGISTRY, 0) if !manager_bound => {
+                    let (Some(name), Some(interface), Some(_version)) =
+                        (args.uint(), args.string(), args.uint())
+                    else {
+                        continue;
+                    };
+                    if interface == FOREIGN_TOPLEVEL_MANAGER {
+                        // `wl_registry.bind` to version 1, and wait for
+                        // the toplevels it sends right away
+                        let mut requests = WaylandMessage::new(REGISTRY, 0)
+                            .uint(name)
+                            .string(&interface)
+                            .uint(1)
+                            .uint(MANAGER)
+                            .encode();
+                        requests
+                            .extend(WaylandMessage::new(DISPLAY, 0).uint(MANAGER_DONE).encode());
+                        stream.write_all(&requests)?;
+                        manager_bound = true;
+                    }
+                }
+                (REGISTRY_DONE, 0) if !manager_bound => {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("the compositor has no {FOREIGN_TOPLEVEL_MANAGER}"),
+                    ));
+                }
+                (MANAGER_DONE, 0) => {
+                    return Ok(toplevels
+                        .into_iter()
+                        .map(|(_, toplevel)| toplevel)
+                        .collect());
+                }
+                // `zwlr_foreign_toplevel_manager_v1.toplevel`
+                (MANAGER, 0) => {
+                    if let Some(handle) = args.uint() {
+                        toplevels.push((handle, Toplevel::default()));
+                    }
+                }
+                (object, opcode) => {
+                    let Some((_, toplevel)) =
+                        toplevels.iter_mut().find(|(handle, _)| *handle == object)
+                    else {
+                        continue;
+                    };
+                    match opcode {
+                        0 => toplevel.title = args.string().unwrap_or_default(),
+                        1 => toplevel.app_id = args.string().unwrap_or_default(),
+                        4 => {
+                            let states = args.array().unwrap_or_default();
+                            toplevel.activated = states
+                                .chunks_exact(4)
+                                .any(|state| state == TOPLEVEL_STATE_ACTIVATED.to_ne_bytes());
+                        }
+                        // `closed`
+                        6 => toplevels.retain(|(handle, _)| *handle != object),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_split_exec_line() {
+        assert_eq!(
+            split_exec_line(r#"/usr/bin/foo --name "Foo \"Bar\"" %U"#),
+            vec!["/usr/bin/foo", "--name", "Foo \"Bar\"", "%U"]
+        );
+        assert_eq!(
+            split_exec_line(r#""/opt/My App/app"  "" arg"#),
+            vec!["/opt/My App/app", "", "arg"]
+        );
+        assert!(split_exec_line("   ").is_empty());
+    }
+
+    #[test]
+    fn test_find_in_path() {
+        assert!(find_in_path("sh").is_some());
+        assert!(find_in_path("this-program-does-not-exist").is_none());
+
+        let temp_dir = TempDir::new("not-executable");
+        let not_executable = temp_dir.path().join("foo");
+        std::fs::write(&not_executable, "#!/bin/sh\n").unwrap();
+        assert!(find_in_path(not_executable.to_str().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_expand_exec_line() {
+        let context = ExecContext {
+            name: "Foo",
+            icon: Some("foo"),
+            desktop_file: Path::new("/usr/share/applications/foo.desktop"),
+        };
+
+        assert_eq!(
+            expand_exec_line("foo %U", &context),
+            vec!["foo".to_string()]
+        );
+        assert_eq!(
+            expand_exec_line("foo %i --title=%c --desktop %k 100%% %d", &context),
+            vec![
+                "foo",
+                "--icon",
+                "foo",
+                "--title=Foo",
+                "--desktop",
+                "/usr/share/applications/foo.desktop",
+                "100%",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_template() {
+        let context = ExecContext {
+            name: "Foo",
+            icon: None,
+            desktop_file: Path::new("/usr/share/applications/foo.desktop"),
+        };
+
+        assert_eq!(
+            exec_template("foo --name=%c %i %F -- %u %d", &context),
+            vec![
+                LaunchArg::Literal("foo".to_string()),
+                LaunchArg::Literal("--name=Foo".to_string()),
+                LaunchArg::Files,
+                LaunchArg::Literal("--".to_string()),
+                LaunchArg::Url,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_program() {
+        assert_eq!(exec_program("firefox %u").as_deref(), Some("firefox"));
+        assert_eq!(
+            exec_program("env GDK_BACKEND=x11 BAMF_DESKTOP_FILE_HINT=foo /usr/bin/foo %F")
+                .as_deref(),
+            Some("/usr/bin/foo")
+        );
+        assert_eq!(exec_program(""), None);
+    }
+
+    #[test]
+    fn test_parse_xml_entities_and_cdata() {
+        let root = parse_xml(
+            r#"<?xml version="1.0"?>
+<!-- comment -->
+<a x='1' y="&quot;2&quot;"><b>Tom &amp; Jerry &#x263A;</b><c/><![CDATA[<raw>]]></a>"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.name, "a");
+        assert_eq!(root.attr("x"), Some("1"));
+        assert_eq!(root.attr("y"), Some("\"2\""));
+        assert_eq!(root.elements("b").next().unwrap().text(), "Tom & Jerry ☺");
+        assert!(root.elements("c").next().is_some());
+        assert_eq!(root.text(), "Tom & Jerry ☺<raw>");
+    }
+
+    #[test]
+    fn test_parse_xml_malformed() {
+        assert!(parse_xml("<a><b></a>").is_none());
+        assert!(parse_xml("<a>").is_none());
+        assert!(parse_xml("").is_none());
+    }
+
+    #[test]
+    fn test_parse_metainfo() {
+        let metainfo = r#"<?xml version="1.0" encoding="UTF-8"?>
+<component type="desktop-application">
+  <id>org.gnome.Calculator</id>
+  <name>Calculator</name>
+  <summary>Perform arithmetic, scientific or financial calculations</summary>
+  <summary xml:lang="de">Arithmetische, wissenschaftliche Berechnungen</summary>
+  <description>
+    <p>Calculator is an application that solves
+       mathematical equations.</p>
+    <p xml:lang="de">Rechner ist eine Anwendung.</p>
+    <ul>
+      <li>Basic</li>
+      <li>Advanced</li>
+    </ul>
+  </description>
+  <launchable type="desktop-id">org.gnome.Calculator.desktop</launchable>
+  <url type="bugtracker">https://gitlab.gnome.org/GNOME/gnome-calculator/issues</url>
+  <url type="homepage">https://apps.gnome.org/Calculator</url>
+  <developer id="org.gnome">
+    <name>The GNOME Project</name>
+  </developer>
+  <screenshots>
+    <screenshot>
+      <image>https://example.org/advanced.png</image>
+    </screenshot>
+    <screenshot type="default">
+      <image type="thumbnail" width="224" height="126">https://example.org/basic-small.png</image>
+      <image type="source">https://example.org/basic.png</image>
+    </screenshot>
+  </screenshots>
+</component>"#;
+
+        let components = parse_appstream(metainfo);
+        assert_eq!(components.len(), 1);
+        let component = &components[0];
+
+        assert_eq!(component.id, "org.gnome.Calculator");
+        assert_eq!(
+            component.summary.as_deref(),
+            Some("Perform arithmetic, scientific or financial calculations")
+        );
+        assert_eq!(
+            component.description.as_deref(),
+            Some(
+                "Calculator is an application that solves mathematical equations.\n\n- Basic\n- Advanced"
+            )
+        );
+        assert_eq!(
+            component.developer_name.as_deref(),
+            Some("The GNOME Project")
+        );
+        assert_eq!(
+            component.homepage.as_deref(),
+            Some("https://apps.gnome.org/Calculator")
+        );
+        assert_eq!(
+            component.screenshots,
+            vec![
+                "https://example.org/basic.png".to_string(),
+                "https://example.org/advanced.png".to_string()
+            ]
+        );
+        assert_eq!(component.desktop_ids, vec!["org.gnome.Calculator.desktop"]);
+    }
+
+    #[test]
+    fn test_parse_catalog_skips_non_desktop_components() {
+        let catalog = r#"<components version="0.14">
+  <component type="desktop">
+    <id>gimp.desktop</id>
+    <developer_name>The GIMP Team</developer_name>
+  </component>
+  <component type="addon">
+    <id>org.gimp.plugin</id>
+  </component>
+  <component type="desktop-application">
+    <id>org.inkscape.Inkscape</id>
+  </component>
+</components>"#;
+
+        let components = parse_appstream(catalog);
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0].desktop_ids, vec!["gimp.desktop"]);
+        assert_eq!(
+            components[0].developer_name.as_deref(),
+            Some("The GIMP Team")
+        );
+        assert_eq!(
+            components[1].desktop_ids,
+            vec!["org.inkscape.Inkscape.desktop"]
+        );
+    }
+
+    #[test]
+    fn test_parse_mimeapps() {
+        let mime_apps = MimeApps::parse(
+            "# comment\n\
+             [Default Applications]\n\
+             x-scheme-handler/http=firefox.desktop;chromium.desktop\n\
+             [Added Associations]\n\
+             text/plain = org.gnome.TextEditor.desktop; vim.desktop;\n\
+             [Removed Associations]\n\
+             text/plain=nano.desktop;\n\
+             [Other]\n\
+             text/html=ignored.desktop\n\
+             not an association\n",
+        );
+        assert_eq!(
+            mime_apps.default["x-scheme-handler/http"],
+            ["firefox.desktop", "chromium.desktop"]
+        );
+        assert_eq!(
+            mime_apps.added["text/plain"],
+            ["org.gnome.TextEditor.desktop", "vim.desktop"]
+        );
+        assert_eq!(mime_apps.removed["text/plain"], ["nano.desktop"]);
+        assert!(!mime_apps.default.contains_key("text/html"));
+    }
+
+    #[test]
+    fn test_mime_types_of_extension() {
+        let globs2 = "# shared-mime-info\n\
+                      50:text/markdown:*.md\n\
+                      80:text/x-markdown:*.MD:cs\n\
+                      60:text/x-markdown-extra:*.md\n\
+                      50:image/png:*.png\n";
+        assert_eq!(
+            mime_types_of_extension(globs2, "md"),
+            ["text/x-markdown-extra", "text/markdown"]
+        );
+        assert_eq!(mime_types_of_extension(globs2, "PNG"), ["image/png"]);
+        assert!(mime_types_of_extension(globs2, "txt").is_empty());
+    }
+
+    #[test]
+    fn test_with_mime_ancestors() {
+        let subclasses = [
+            "text/x-csrc text/plain\ntext/x-c++src text/x-csrc\n".to_string(),
+            "# other data directory\ntext/x-rust text/plain\nimage/svg+xml application/xml\n\
+             application/xml text/plain\n"
+                .to_string(),
+        ];
+        let ancestors =
+            |mime_type: &str| with_mime_ancestors(&subclasses, vec![mime_type.to_string()]);
+
+        assert_eq!(ancestors("text/x-rust"), ["text/x-rust", "text/plain"]);
+        assert_eq!(
+            ancestors("text/x-c++src"),
+            ["text/x-c++src", "text/x-csrc", "text/plain"]
+        );
+        assert_eq!(
+            ancestors("image/svg+xml"),
+            ["image/svg+xml", "application/xml", "text/plain"]
+        );
+        assert_eq!(ancestors("image/png"), ["image/png"]);
+    }
+
+    /// Answer the requests of [`read_toplevels`] with `globals`, then with
+    /// `toplevels` once it binds the manager.
+    fn fake_compositor(
+        globals: Vec<WaylandMessage>,
+        toplevels: Vec<WaylandMessage>,
+    ) -> Vec<Toplevel> {
+        use std::io::{Read, Write};
+
+        let (client, mut server) = std::os::unix::net::UnixStream::pair().unwrap();
+        let compositor = std::thread::spawn(move || {
+            let mut requests = [0; 256];
+            let _ = server.read(&mut requests).unwrap();
+            let mut events: Vec<u8> = globals.iter().flat_map(WaylandMessage::encode).collect();
+            // `wl_callback.done` of the first `wl_display.sync`
+            events.extend(WaylandMessage::new(3, 0).uint(1).encode());
+            server.write_all(&events).unwrap();
+
+            let bind = server.read(&mut requests).unwrap();
+            if bind > 0 {
+                let mut events: Vec<u8> =
+                    toplevels.iter().flat_map(WaylandMessage::encode).collect();
+                events.extend(WaylandMessage::new(5, 0).uint(2).encode());
+                // One byte at a time, messages come in pieces
+                for byte in events {
+                    server.write_all(&[byte]).unwrap();
+                }
+            }
+        });
+
+        let toplevels = read_toplevels(&client);
+        drop(client);
+        compositor.join().unwrap();
+        toplevels.unwrap()
+    }
+
+    #[test]
+    fn test_read_toplevels() {
+        let globals = vec![
+            WaylandMessage::new(2, 0)
+                .uint(1)
+                .string("wl_compositor")
+                .uint(6),
+            WaylandMessage::new(2, 0)
+                .uint(7)
+                .string(FOREIGN_TOPLEVEL_MANAGER)
+                .uint(3),
+        ];
+        let states = |states: &[u32]| -> Vec<u8> {
+            states
+                .iter()
+                .flat_map(|state| state.to_ne_bytes())
+                .collect()
+        };
+        let toplevels = vec![
+            WaylandMessage::new(4, 0).uint(0xff000000),
+            WaylandMessage::new(0xff000000, 0).string("Terminal"),
+            WaylandMessage::new(0xff000000, 1).string("foot"),
+            WaylandMessage::new(0xff000000, 4).array(&states(&[0])),
+            WaylandMessage::new(0xff000000, 5),
+            WaylandMessage::new(4, 0).uint(0xff000001),
+            WaylandMessage::new(0xff000001, 0).string("main.rs - Code"),
+            WaylandMessage::new(0xff000001, 1).string("code"),
+            WaylandMessage::new(0xff000001, 4).array(&states(&[1, 2])),
+            WaylandMessage::new(0xff000001, 5),
+            WaylandMessage::new(4, 0).uint(0xff000002),
+            WaylandMessage::new(0xff000002, 6),
+        ];
+
+        assert_eq!(
+            fake_compositor(globals, toplevels),
+            [
+                Toplevel {
+                    app_id: "foot".to_string(),
+                    title: "Terminal".to_string(),
+                    activated: false,
+                },
+                Toplevel {
+                    app_id: "code".to_string(),
+                    title: "main.rs - Code".to_string(),
+                    activated: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_toplevels_unsupported() {
+        use std::io::{Read, Write};
+
+        let (client, mut server) = std::os::unix::net::UnixStream::pair().unwrap();
+        let compositor = std::thread::spawn(move || {
+            let mut requests = [0; 256];
+            let _ = server.read(&mut requests).unwrap();
+            let mut events = WaylandMessage::new(2, 0)
+                .uint(1)
+                .string("wl_compositor")
+                .uint(6)
+                .encode();
+            events.extend(WaylandMessage::new(3, 0).uint(1).encode());
+            server.write_all(&events).unwrap();
+        });
+        let error = read_toplevels(&client).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+        compositor.join().unwrap();
+    }
+
+    #[test]
+    fn test_take_wayland_messages() {
+        let message = WaylandMessage::new(2, 0).uint(1).string("wl_seat").uint(9);
+        let mut buf = message.encode();
+        assert_eq!(buf.len(), 8 + 4 + 12 + 4);
+        buf.extend_from_slice(&[1, 0]);
+        assert_eq!(take_wayland_messages(&mut buf).unwrap(), [message]);
+        // The start of the next message is kept
+        assert_eq!(buf, [1, 0]);
+
+        let bytes = [&3u32.to_ne_bytes()[..], b"ab\0"].concat();
+        let mut args = WaylandArgs(&bytes);
+        assert_eq!(args.uint(), Some(3));
+        // Cut short
+        assert_eq!(args.string(), None);
+
+        // A size below the header would never advance
+        let mut buf = [1u32.to_ne_bytes(), (4u32 << 16).to_ne_bytes()].concat();
+        assert!(take_wayland_messages(&mut buf).is_err());
+    }
+}
@@ -1,6 +1,6 @@
-use crate::common::App;
-use anyhow::Result;
-use anyhow::anyhow;
+use crate::common::{App, AppKind, DiscoverySource, InstallScope, normalize_locale};
+use crate::metadata::SpotlightMetadata;
+use crate::{AppFinder, Error, Result};
 use glob::glob;
 use plist::Value as PlistValue;
 use serde_derive::Deserialize;
@@ -80,55 +80,219 @@ pub struct InfoPlist {
     cf_bundle_version: Option<String>,
     #[serde(rename = "CFBundleDisplayName")]
     cf_bundle_display_name: Option<String>,
+    /// E.g., `public.app-category.games`.
+    #[serde(rename = "LSApplicationCategoryType")]
+    ls_application_category_type: Option<String>,
+    /// A boolean, but often written as a string, e.g., `"1"`.
+    #[serde(rename = "LSUIElement")]
+    ls_ui_element: Option<PlistValue>,
+    #[serde(rename = "LSBackgroundOnly")]
+    ls_background_only: Option<PlistValue>,
+}
+
+/// A `CFBundleDocumentTypes` entry of an `Info.plist`, documents an app
+/// opens.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DocumentType {
+    /// `CFBundleTypeExtensions`, without the dots, `*` for all.
+    pub extensions: Vec<String>,
+    /// `CFBundleTypeMIMETypes`.
+    pub mime_types: Vec<String>,
+    /// `LSItemContentTypes`, uniform types, e.g., `public.plain-text`.
+    pub content_types: Vec<String>,
+}
+
+impl DocumentType {
+    /// Read from the value, so that an entry with keys of another type
+    /// than documented, which are common, does not fail all of the
+    /// `Info.plist`.
+    pub fn from_value(value: &PlistValue) -> Option<Self> {
+        let entry = value.as_dictionary()?;
+        let strings = |key: &str| -> Vec<String> {
+            entry
+                .get(key)
+                .and_then(PlistValue::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(PlistValue::as_string)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Some(Self {
+            extensions: strings("CFBundleTypeExtensions"),
+            mime_types: strings("CFBundleTypeMIMETypes"),
+            content_types: strings("LSItemContentTypes"),
+        })
+    }
 }
 
 impl InfoPlist {
+    /// A game by its App Store category, a menu bar or background agent
+    /// without a Dock icon.
+    fn kind(&self) -> AppKind {
+        let is_game = self
+            .ls_application_category_type
+            .as_deref()
+            .is_some_and(|category| category.ends_with("games"));
+        if is_game {
+            return AppKind::Game { url: None };
+        }
+        if is_true(self.ls_ui_element.as_ref()) || is_true(self.ls_background_only.as_ref()) {
+            return AppKind::System;
+        }
+
+        AppKind::Gui
+    }
+
+    /// Keys of another type than expected, e.g., a `CFBundleName` that is
+    /// a number, are left out rather than failing all of the `Info.plist`.
     pub fn from_value(value: &plist::Value) -> Result<InfoPlist> {
-        let info_plist = plist::from_value(value).unwrap();
-        Ok(info_plist)
+        let err = match plist::from_value(value) {
+            Ok(info_plist) => return Ok(info_plist),
+            Err(err) => err,
+        };
+        let Some(dict) = value.as_dictionary() else {
+            return Err(err.into());
+        };
+        let mut valid = plist::Dictionary::new();
+        for (key, value) in dict {
+            let mut entry = plist::Dictionary::new();
+            entry.insert(key.clone(), value.clone());
+            if plist::from_value::<InfoPlist>(&PlistValue::Dictionary(entry)).is_ok() {
+                valid.insert(key.clone(), value.clone());
+            }
+        }
+
+        Ok(plist::from_value(&PlistValue::Dictionary(valid))?)
     }
 
     pub fn from_file(path: &PathBuf) -> Result<InfoPlist> {
-        match plist::from_file(path) {
-            Ok(info_plist) => Ok(info_plist),
-            Err(_) => match plist::Value::from_file(path) {
-                // using plist::Value is a workaround for the error "duplicate key: CFBundleShortVersionString"
-                Ok(value) => Ok(InfoPlist::from_value(&value).unwrap()),
-                Err(err) => Err(anyhow::Error::msg(format!("Fail to parse plist: {}", err))),
-            },
+        InfoPlist::from_value(&read_plist(path)?)
+    }
+}
+
+/// Read the property list at `path`, see [`parse_plist`].
+pub(crate) fn read_plist(path: &Path) -> Result<PlistValue> {
+    let bytes = std::fs::read(path)?;
+
+    parse_plist(&bytes).map_err(|message| Error::parse(path, message))
+}
+
+/// Parse a binary or XML property list, leniently, as older apps ship
+/// `Info.plist` files the plist crate rejects: of a duplicate key the last
+/// one wins, XML in UTF-16 or with bytes that are not UTF-8 is read with
+/// those replaced, and characters XML does not allow are left out.
+pub(crate) fn parse_plist(bytes: &[u8]) -> std::result::Result<PlistValue, String> {
+    // A panic of the parser on a malformed file fails that file only
+    let parse = |bytes: &[u8]| -> std::result::Result<PlistValue, String> {
+        std::panic::catch_unwind(|| PlistValue::from_reader(std::io::Cursor::new(bytes)))
+            .map_err(|_| "the plist parser panicked".to_string())?
+            .map_err(|err| err.to_string())
+    };
+    let err = match parse(bytes) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    if bytes.starts_with(b"bplist") {
+        return Err(err);
+    }
+
+    let text = decode_text(bytes);
+    let text: String = match text.strip_prefix("<?xml") {
+        // The encoding it declares is not that of the text any more
+        Some(rest) => match rest.split_once("?>") {
+            Some((_, rest)) => format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{rest}"),
+            None => text,
+        },
+        None => text,
+    }
+    .chars()
+    .filter(|&c| is_xml_char(c))
+    .collect();
+
+    parse(text.as_bytes()).map_err(|_| err)
+}
+
+/// The text of a file in UTF-16, by its byte order mark or the zero byte
+/// of its first character, which is ASCII, or else in UTF-8, lossily.
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|chunk| from_bytes([chunk[0], chunk[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0, first, ..] if *first != 0 => utf16(bytes, u16::from_be_bytes),
+        [first, 0, ..] if *first != 0 => utf16(bytes, u16::from_le_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Whether XML 1.0 allows the character, rather than, e.g., a control
+/// character of a name pasted into an `Info.plist`.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// Whether a boolean of an Info.plist is set, which may be a string or a
+/// number too.
+fn is_true(value: Option<&PlistValue>) -> bool {
+    match value {
+        Some(PlistValue::Boolean(value)) => *value,
+        Some(PlistValue::Integer(value)) => value.as_signed().is_some_and(|value| value != 0),
+        Some(PlistValue::String(value)) => {
+            value == "1" || value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("yes")
         }
+        _ => false,
+    }
+}
+
+/// Run `mdfind` with the timeout and cancel token of `finder`, it not
+/// starting at all means there is no Spotlight.
+fn run_mdfind(finder: &AppFinder, args: &[&str]) -> Result<std::process::Output> {
+    match finder.output(std::process::Command::new("mdfind").args(args)) {
+        Err(Error::Io(e)) => Err(Error::SpotlightUnavailable(e.to_string())),
+        result => result,
     }
 }
 
-fn run_mdfind_only_in(dir: &Path) -> Result<Vec<String>> {
-    let output = std::process::Command::new("mdfind")
-        .arg("-onlyin")
-        .arg(format!("{}", dir.display()))
-        .arg("kMDItemKind == 'Application'")
-        .output()?;
+fn run_mdfind_only_in(dir: &Path, finder: &AppFinder) -> Result<Vec<String>> {
+    let dir = format!("{}", dir.display());
+    let output = run_mdfind(finder, &["-onlyin", &dir, "kMDItemKind == 'Application'"])?;
 
     if !output.status.success() {
-        return Err(anyhow!(
-            "failed to spawn mdfind, stderr [{}]",
-            String::from_utf8_lossy(&output.stdout)
+        return Err(Error::SpotlightUnavailable(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
         ));
     }
 
-    let stdout = String::from_utf8(output.stdout)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
     let lines1: Vec<String> = stdout.split("\n").map(|line| line.to_string()).collect();
 
-    let output = std::process::Command::new("mdfind")
-        .arg("kMDItemContentType = 'com.apple.application-bundle'")
-        .arg("-onlyin")
-        .arg(format!("{}", dir.display()))
-        .output()?;
+    let output = run_mdfind(
+        finder,
+        &[
+            "kMDItemContentType = 'com.apple.application-bundle'",
+            "-onlyin",
+            &dir,
+        ],
+    )?;
     if !output.status.success() {
-        return Err(anyhow!(
-            "failed to spawn mdfind, stderr [{}]",
-            String::from_utf8_lossy(&output.stdout)
+        return Err(Error::SpotlightUnavailable(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
         ));
     }
-    let stdout = String::from_utf8(output.stdout)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
     let lines2: Vec<String> = stdout.split("\n").map(|line| line.to_string()).collect();
 
     Ok(lines1
@@ -139,17 +303,497 @@ fn run_mdfind_only_in(dir: &Path) -> Result<Vec<String>> {
         .collect())
 }
 
-pub fn run_mdfind_to_get_app_list(search_paths: &[PathBuf]) -> Result<Vec<String>> {
+pub fn run_mdfind_to_get_app_list(
+    search_paths: &[PathBuf],
+    finder: &AppFinder,
+) -> Result<Vec<String>> {
     let mut set = HashSet::new();
 
     for search_path in search_paths {
-        let apps = run_mdfind_only_in(search_path)?;
+        let apps = run_mdfind_only_in(search_path, finder)?;
         set.extend(apps);
     }
 
     Ok(set.into_iter().collect())
 }
 
+/// The bundles Spotlight knows with the bundle identifier `id`, which must
+/// not need quoting.
+pub fn run_mdfind_by_bundle_id(id: &str, finder: &AppFinder) -> Result<Vec<String>> {
+    let output = run_mdfind(finder, &[&format!("kMDItemCFBundleIdentifier == '{id}'c")])?;
+    if !output.status.success() {
+        return Err(Error::SpotlightUnavailable(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Move `path` to the Trash the way the Finder does, with
+/// `-[NSFileManager trashItemAtURL:resultingItemURL:error:]`, and return
+/// where it is in the Trash.
+pub fn trash_item(path: &Path) -> Result<PathBuf> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::{CFString, CFStringRef};
+    use core_foundation::url::{CFURL, CFURLRef};
+    use objc::{Id, Sel, msg_send};
+
+    let url = CFURL::from_path(path, path.is_dir())
+        .ok_or_else(|| Error::parse(path, "not a file URL"))?;
+    unsafe {
+        let pool = objc::objc_autoreleasePoolPush();
+        let send: unsafe extern "C" fn(Id, Sel) -> Id = msg_send();
+        let trash: unsafe extern "C" fn(Id, Sel, Id, *mut Id, *mut Id) -> i8 = msg_send();
+
+        let manager = send(
+            objc::objc_getClass(c"NSFileManager".as_ptr()),
+            objc::sel_registerName(c"defaultManager".as_ptr()),
+        );
+        let mut trashed: Id = std::ptr::null_mut();
+        let mut error: Id = std::ptr::null_mut();
+        // NSURL and CFURL are toll-free bridged, as are NSString and CFString
+        let result = if trash(
+            manager,
+            objc::sel_registerName(c"trashItemAtURL:resultingItemURL:error:".as_ptr()),
+            url.as_concrete_TypeRef() as Id,
+            &mut trashed,
+            &mut error,
+        ) != 0
+            && !trashed.is_null()
+        {
+            CFURL::wrap_under_get_rule(trashed as CFURLRef)
+                .to_path()
+                .ok_or_else(|| Error::parse(path, "trashed to no file URL"))
+        } else if error.is_null() {
+            Err(Error::Platform("trashItemAtURL failed".into()))
+        } else {
+            let description = send(
+                error,
+                objc::sel_registerName(c"localizedDescription".as_ptr()),
+            );
+            Err(Error::Platform(
+                CFString::wrap_under_get_rule(description as CFStringRef)
+                    .to_string()
+                    .into(),
+            ))
+        };
+        objc::objc_autoreleasePoolPop(pool);
+
+        result
+    }
+}
+
+/// The bundle of the app Launch Services opens `url` with, e.g., that of
+/// the default browser for `https://`.
+pub fn default_app_for_url(url: &str) -> Option<PathBuf> {
+    use core_foundation::base::{TCFType, kCFAllocatorDefault};
+    use core_foundation::string::CFString;
+    use core_foundation::url::{CFURL, CFURLCreateWithString};
+
+    let url = CFString::new(url);
+    unsafe {
+        let url = CFURLCreateWithString(
+            kCFAllocatorDefault,
+            url.as_concrete_TypeRef(),
+            std::ptr::null(),
+        );
+        if url.is_null() {
+            return None;
+        }
+        let url = CFURL::wrap_under_create_rule(url);
+        let app = launch_services::LSCopyDefaultApplicationURLForURL(
+            url.as_concrete_TypeRef(),
+            launch_services::ROLES_ALL,
+            std::ptr::null_mut(),
+        );
+        launch_services::bundle_path(app)
+    }
+}
+
+/// The bundle of the app Launch Services opens files of the uniform type
+/// `content_type` with, e.g., `public.plain-text`.
+pub fn default_app_for_content_type(content_type: &str) -> Option<PathBuf> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let content_type = CFString::new(content_type);
+    unsafe {
+        let app = launch_services::LSCopyDefaultApplicationURLForContentType(
+            content_type.as_concrete_TypeRef(),
+            launch_services::ROLES_ALL,
+            std::ptr::null_mut(),
+        );
+        launch_services::bundle_path(app)
+    }
+}
+
+/// The bundles of all apps Launch Services opens the file at `path` with,
+/// the default first, then in the order of its "Open With" menu.
+pub fn app_bundles_for_file(path: &Path) -> Vec<PathBuf> {
+    use core_foundation::array::CFArray;
+    use core_foundation::base::TCFType;
+    use core_foundation::url::CFURL;
+
+    let Some(url) = CFURL::from_path(path, path.is_dir()) else {
+        return Vec::new();
+    };
+    unsafe {
+        let apps = launch_services::LSCopyApplicationURLsForURL(
+            url.as_concrete_TypeRef(),
+            launch_services::ROLES_ALL,
+        );
+        if apps.is_null() {
+            return Vec::new();
+        }
+        CFArray::<CFURL>::wrap_under_create_rule(apps)
+            .iter()
+            .filter_map(|app| app.to_path())
+            .collect()
+    }
+}
+
+/// The uniform type of the files with `extension`, e.g.,
+/// `net.daringfireball.markdown` for `md`. `None` if no app or the system
+/// declares one, Launch Services makes up a dynamic `dyn.` type then.
+pub fn content_type_of_extension(extension: &str) -> Option<String> {
+    content_type_of_tag("public.filename-extension", extension)
+}
+
+/// The uniform type of the MIME type `mime_type`, like
+/// [`content_type_of_extension`].
+pub fn content_type_of_mime_type(mime_type: &str) -> Option<String> {
+    content_type_of_tag("public.mime-type", mime_type)
+}
+
+/// Whether the uniform type `content_type` is, or conforms to,
+/// `conforms_to`, e.g., `public.plain-text` to `public.text`.
+pub fn content_type_conforms_to(content_type: &str, conforms_to: &str) -> bool {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let content_type = CFString::new(content_type);
+    let conforms_to = CFString::new(conforms_to);
+    unsafe {
+        launch_services::UTTypeConformsTo(
+            content_type.as_concrete_TypeRef(),
+            conforms_to.as_concrete_TypeRef(),
+        ) != 0
+    }
+}
+
+/// The preferred uniform type of `tag` of `tag_class`.
+fn content_type_of_tag(tag_class: &str, tag: &str) -> Option<String> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let tag_class = CFString::new(tag_class);
+    let tag = CFString::new(tag);
+    let content_type = unsafe {
+        let content_type = launch_services::UTTypeCreatePreferredIdentifierForTag(
+            tag_class.as_concrete_TypeRef(),
+            tag.as_concrete_TypeRef(),
+            std::ptr::null(),
+        );
+        if content_type.is_null() {
+            return None;
+        }
+        CFString::wrap_under_create_rule(content_type).to_string()
+    };
+
+    (!content_type.starts_with("dyn.")).then_some(content_type)
+}
+
+/// Make the app with `bundle_id` the default of `content_type`, in all of
+/// its roles.
+pub fn set_default_handler_for_content_type(content_type: &str, bundle_id: &str) -> Result<()> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let content_type = CFString::new(content_type);
+    let bundle_id = CFString::new(bundle_id);
+    let status = unsafe {
+        launch_services::LSSetDefaultRoleHandlerForContentType(
+            content_type.as_concrete_TypeRef(),
+            launch_services::ROLES_ALL,
+            bundle_id.as_concrete_TypeRef(),
+        )
+    };
+    if status != 0 {
+        return Err(Error::Platform(
+            format!("LSSetDefaultRoleHandlerForContentType failed with {status}").into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The Spotlight attributes of the bundle at `path`, read with the MDItem
+/// API of Core Services. `None` if Spotlight has no item for it.
+pub fn read_spotlight_metadata(path: &Path) -> Option<SpotlightMetadata> {
+    use core_foundation::array::CFArray;
+    use core_foundation::base::{CFType, TCFType, kCFAllocatorDefault};
+    use core_foundation::string::CFString;
+
+    let path = CFString::new(path.to_str()?);
+    let item = unsafe {
+        let item = spotlight::MDItemCreate(kCFAllocatorDefault, path.as_concrete_TypeRef());
+        if item.is_null() {
+            return None;
+        }
+        CFType::wrap_under_create_rule(item)
+    };
+    let attribute = |name: &str| {
+        let name = CFString::new(name);
+        unsafe {
+            let value =
+                spotlight::MDItemCopyAttribute(item.as_CFTypeRef(), name.as_concrete_TypeRef());
+            (!value.is_null()).then(|| CFType::wrap_under_create_rule(value))
+        }
+    };
+    let string = |name: &str| {
+        attribute(name)?
+            .downcast::<CFString>()
+            .map(|value| value.to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let keywords = attribute("kMDItemKeywords")
+        .filter(|value| value.instance_of::<CFArray>())
+        .map(|value| {
+            let keywords: CFArray<CFType> =
+                unsafe { CFArray::wrap_under_get_rule(value.as_CFTypeRef() as _) };
+            keywords
+                .iter()
+                .filter_map(|keyword| keyword.downcast::<CFString>())
+                .map(|keyword| keyword.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(SpotlightMetadata {
+        keywords,
+        comment: string("kMDItemFinderComment"),
+        app_store_category: string("kMDItemAppStoreCategory"),
+        copyright: string("kMDItemCopyright"),
+    })
+}
+
+/// The language the user prefers most, in the `zh-CN` form the keys of
+/// `App::localized_app_names` use.
+pub fn user_locale() -> Option<String> {
+    use core_foundation::array::CFArray;
+    use core_foundation::base::TCFType;
+    use core_foundation::string::CFString;
+
+    let languages = unsafe {
+        let languages = bundle::CFLocaleCopyPreferredLanguages();
+        if languages.is_null() {
+            return None;
+        }
+        CFArray::<CFString>::wrap_under_create_rule(languages)
+    };
+    let language = languages.iter().next()?.to_string();
+
+    normalize_locale(&language)
+}
+
+/// The name of the bundle at `path` in the language of the user, if it is
+/// another one than `name`, its name in the `Info.plist`: as
+/// `CFBundleCopyLocalizedString` reads it from the `InfoPlist` or the
+/// `Localizable` strings of the bundle, or else as the Finder displays it,
+/// by the display name Launch Services keeps.
+pub fn system_localized_name(path: &Path, name: &str) -> Option<String> {
+    use core_foundation::base::{CFType, TCFType, kCFAllocatorDefault};
+    use core_foundation::string::CFString;
+    use core_foundation::url::CFURL;
+
+    let url = CFURL::from_path(path, true)?;
+    let bundle = unsafe {
+        let bundle = bundle::CFBundleCreate(kCFAllocatorDefault, url.as_concrete_TypeRef());
+        (!bundle.is_null()).then(|| CFType::wrap_under_create_rule(bundle))
+    };
+    // The key itself if the table has no string for it
+    let localized = |key: &str, table: &str| {
+        let bundle = bundle.as_ref()?;
+        let cf_key = CFString::new(key);
+        let table = CFString::new(table);
+        let value = unsafe {
+            let value = bundle::CFBundleCopyLocalizedString(
+                bundle.as_CFTypeRef(),
+                cf_key.as_concrete_TypeRef(),
+                std::ptr::null(),
+                table.as_concrete_TypeRef(),
+            );
+            if value.is_null() {
+                return None;
+            }
+            CFString::wrap_under_create_rule(value).to_string()
+        };
+
+        Some(value).filter(|value| value != key)
+    };
+    let file_stem = path.file_stem().and_then(|stem| stem.to_str());
+
+    localized("CFBundleDisplayName", "InfoPlist")
+        .or_else(|| localized("CFBundleName", "InfoPlist"))
+        .or_else(|| localized(name, "Localizable"))
+        // The Finder displays the file name of a bundle it has no localized
+        // name for
+        .or_else(|| finder_display_name(path).filter(|display| Some(display.as_str()) != file_stem))
+        .filter(|localized| !localized.is_empty() && localized != name)
+}
+
+/// `-[NSFileManager displayNameAtPath:]`, without the `.app` the Finder
+/// shows if the user has it show all extensions.
+fn finder_display_name(path: &Path) -> Option<String> {
+    use core_foundation::base::TCFType;
+    use core_foundation::string::{CFString, CFStringRef};
+    use objc::{Id, Sel, msg_send};
+
+    let path = CFString::new(path.to_str()?);
+    let name = unsafe {
+        let pool = objc::objc_autoreleasePoolPush();
+        let send: unsafe extern "C" fn(Id, Sel) -> Id = msg_send();
+        let display_name: unsafe extern "C" fn(Id, Sel, Id) -> Id = msg_send();
+
+        let manager = send(
+            objc::objc_getClass(c"NSFileManager".as_ptr()),
+            objc::sel_registerName(c"defaultManager".as_ptr()),
+        );
+        // NSString and CFString are toll-free bridged
+        let name = display_name(
+            manager,
+            objc::sel_registerName(c"displayNameAtPath:".as_ptr()),
+            path.as_concrete_TypeRef() as Id,
+        );
+        let name = (!name.is_null())
+            .then(|| CFString::wrap_under_get_rule(name as CFStringRef).to_string());
+        objc::objc_autoreleasePoolPop(pool);
+
+        name
+    }?;
+
+    Some(
+        name.strip_suffix(".app")
+            .map(str::to_string)
+            .unwrap_or(name),
+    )
+}
+
+/// Bundles and the preferred languages, in CoreFoundation.
+mod bundle {
+    use core_foundation::array::CFArrayRef;
+    use core_foundation::base::{CFAllocatorRef, CFTypeRef};
+    use core_foundation::string::CFStringRef;
+    use core_foundation::url::CFURLRef;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        pub fn CFBundleCreate(allocator: CFAllocatorRef, url: CFURLRef) -> CFTypeRef;
+        pub fn CFBundleCopyLocalizedString(
+            bundle: CFTypeRef,
+            key: CFStringRef,
+            value: CFStringRef,
+            table: CFStringRef,
+        ) -> CFStringRef;
+        pub fn CFLocaleCopyPreferredLanguages() -> CFArrayRef;
+    }
+}
+
+/// The metadata items of Spotlight, in Core Services.
+mod spotlight {
+    use core_foundation::base::{CFAllocatorRef, CFTypeRef};
+    use core_foundation::string::CFStringRef;
+
+    #[link(name = "CoreServices", kind = "framework")]
+    unsafe extern "C" {
+        pub fn MDItemCreate(allocator: CFAllocatorRef, path: CFStringRef) -> CFTypeRef;
+        pub fn MDItemCopyAttribute(item: CFTypeRef, name: CFStringRef) -> CFTypeRef;
+    }
+}
+
+/// Launch Services of Core Services, which knows the apps that open each
+/// URL scheme and content type.
+mod launch_services {
+    use core_foundation::array::CFArrayRef;
+    use core_foundation::base::TCFType;
+    use core_foundation::error::CFErrorRef;
+    use core_foundation::string::CFStringRef;
+    use core_foundation::url::{CFURL, CFURLRef};
+    use std::path::PathBuf;
+
+    /// `kLSRolesAll`, the apps that view, edit or run what they open.
+    pub const ROLES_ALL: u32 = 0xFFFF_FFFF;
+
+    #[link(name = "CoreServices", kind = "framework")]
+    unsafe extern "C" {
+        pub fn LSCopyDefaultApplicationURLForURL(
+            url: CFURLRef,
+            role: u32,
+            error: *mut CFErrorRef,
+        ) -> CFURLRef;
+        pub fn LSCopyApplicationURLsForURL(url: CFURLRef, role: u32) -> CFArrayRef;
+        pub fn LSCopyDefaultApplicationURLForContentType(
+            content_type: CFStringRef,
+            role: u32,
+            error: *mut CFErrorRef,
+        ) -> CFURLRef;
+        pub fn LSSetDefaultRoleHandlerForContentType(
+            content_type: CFStringRef,
+            role: u32,
+            bundle_id: CFStringRef,
+        ) -> i32;
+        pub fn UTTypeCreatePreferredIdentifierForTag(
+            tag_class: CFStringRef,
+            tag: CFStringRef,
+            conforming_to: CFStringRef,
+        ) -> CFStringRef;
+        pub fn UTTypeConformsTo(content_type: CFStringRef, conforms_to: CFStringRef) -> u8;
+    }
+
+    /// The path of `app`, a URL Launch Services returned with the create
+    /// rule, or `None` if it is null.
+    pub unsafe fn bundle_path(app: CFURLRef) -> Option<PathBuf> {
+        if app.is_null() {
+            return None;
+        }
+
+        unsafe { CFURL::wrap_under_create_rule(app) }.to_path()
+    }
+}
+
+/// The Objective-C runtime, for the Foundation APIs that Core Foundation
+/// has no counterpart of.
+mod objc {
+    use std::ffi::{c_char, c_void};
+
+    pub type Id = *mut c_void;
+    pub type Sel = *mut c_void;
+
+    #[link(name = "Foundation", kind = "framework")]
+    unsafe extern "C" {}
+
+    #[link(name = "objc")]
+    unsafe extern "C" {
+        pub fn objc_getClass(name: *const c_char) -> Id;
+        pub fn sel_registerName(name: *const c_char) -> Sel;
+        fn objc_msgSend();
+        pub fn objc_autoreleasePoolPush() -> *mut c_void;
+        pub fn objc_autoreleasePoolPop(pool: *mut c_void);
+    }
+
+    /// `objc_msgSend`, as the function type `F` of the method it calls,
+    /// which its caller must get right.
+    pub unsafe fn msg_send<F: Copy>() -> F {
+        assert_eq!(size_of::<F>(), size_of::<unsafe extern "C" fn()>());
+        unsafe { std::mem::transmute_copy(&(objc_msgSend as unsafe extern "C" fn())) }
+    }
+}
+
 /// Mac App folder is very complicated, I made this struct with some helper functions to make it easier to work with
 pub struct MacAppPath(PathBuf);
 
@@ -200,6 +844,16 @@ impl MacAppPath {
         }
     }
 
+    /// The bundle this one is embedded in, e.g., `/Applications/Foo.app`
+    /// for `/Applications/Foo.app/Contents/Applications/Bar.app`.
+    pub fn get_parent_bundle(&self) -> Option<PathBuf> {
+        self.0
+            .parent()?
+            .ancestors()
+            .find(|ancestor| ancestor.extension().is_some_and(|ext| ext == "app"))
+            .map(Path::to_path_buf)
+    }
+
     /// Get the path to the first inner .app folder in the Wrapper, if it exists
     /// iPad apps are wrapped in a Wrapper folder
     /// Here we assume there is only one inner .app folder, otherwise the logic will get too complicated
@@ -208,13 +862,7 @@ impl MacAppPath {
         let wrapper_path_str = wrapper_path.to_str()?;
         // search for .app in the wrapper
         let glob_path = format!("{}/*.app", wrapper_path_str);
-        if let Some(e) = glob(&glob_path)
-            .expect("Failed to read glob pattern")
-            .next()
-        {
-            return Some(e.unwrap());
-        }
-        None
+        glob(&glob_path).ok()?.next()?.ok()
     }
 
     pub fn has_info_plist(&self) -> bool {
@@ -238,17 +886,100 @@ impl MacAppPath {
         }
     }
 
+    /// Whether the user may read the bundle: its `Info.plist` and the
+    /// resources its icon is in, which they may not in the home folder of
+    /// another user.
+    pub(crate) fn is_readable(&self) -> bool {
+        let resources = match self.get_app_path_in_wrapper() {
+            Some(inner_app_path) if self.has_wrapper() => inner_app_path,
+            _ => self.0.join("Contents/Resources"),
+        };
+
+        self.get_info_plist_path()
+            .is_some_and(|path| std::fs::File::open(path).is_ok())
+            && std::fs::read_dir(resources).is_ok()
+    }
+
+    /// Whether the bundle is completely installed, rather than still being
+    /// copied: it has an `Info.plist`, the executable it names, which is
+    /// not empty, and a code signature that reads, if it has one.
+    pub(crate) fn check_complete(&self) -> Result<()> {
+        let incomplete = |missing| {
+            Err(Error::Incomplete {
+                path: self.0.clone(),
+                missing,
+            })
+        };
+        let Some(info_plist) = self
+            .get_info_plist_path()
+            .and_then(|path| InfoPlist::from_file(&path).ok())
+        else {
+            return incomplete("Info.plist");
+        };
+        let (executables, signature) = match self.get_app_path_in_wrapper() {
+            // iOS apps have no Contents folder
+            Some(inner_app_path) if self.has_wrapper() => (
+                inner_app_path.clone(),
+                inner_app_path.join("_CodeSignature"),
+            ),
+            _ => (
+                self.0.join("Contents/MacOS"),
+                self.0.join("Contents/_CodeSignature"),
+            ),
+        };
+
+        if let Some(executable) = &info_plist.cf_bundle_executable
+            && !std::fs::metadata(executables.join(executable))
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() > 0)
+        {
+            return incomplete("executable");
+        }
+        if signature.is_dir() && PlistValue::from_file(signature.join("CodeResources")).is_err() {
+            return incomplete("code signature");
+        }
+
+        Ok(())
+    }
+
     /// Convert the MacAppPath to an App struct
     ///
     /// This function will return None if the path is not an app
     pub fn to_app(&self) -> Option<App> {
+        self.to_app_with(true)
+    }
+
+    /// The `CFBundleDocumentTypes` of the `Info.plist`.
+    pub(crate) fn get_document_types(&self) -> Vec<DocumentType> {
+        let Some(info) = self
+            .get_info_plist_path()
+            .and_then(|path| read_plist(&path).ok())
+        else {
+            return Vec::new();
+        };
+
+        info.as_dictionary()
+            .and_then(|info| info.get("CFBundleDocumentTypes"))
+            .and_then(PlistValue::as_array)
+            .map(|types| types.iter().filter_map(DocumentType::from_value).collect())
+            .unwrap_or_default()
+    }
+
+    /// The `CFBundleIdentifier` of the `Info.plist`.
+    pub(crate) fn get_bundle_identifier(&self) -> Option<String> {
+        InfoPlist::from_file(&self.get_info_plist_path()?)
+            .ok()?
+            .cf_bundle_identifier
+    }
+
+    /// Like [`MacAppPath::to_app`], without reading the localized names
+    /// unless `localized_names`.
+    pub fn to_app_with(&self, localized_names: bool) -> Option<App> {
         // Validate it
         if !self.is_app() {
             return None;
         }
-        let info_plist_path = self
-            .get_info_plist_path()
-            .expect("is_app() ensures that there is an Info.plist file");
+        // Gone since is_app() if the bundle is being deleted
+        let info_plist_path = self.get_info_plist_path()?;
         // If the Info.plist file is invalid, this is not an app, return None.
         let info_plist = InfoPlist::from_file(&info_plist_path).ok()?;
 
@@ -266,7 +997,11 @@ impl MacAppPath {
                 self.0.file_stem()?.to_str()?.to_string()
             }
         };
-        let localized_app_names = self.get_localized_app_names();
+        let localized_app_names = if localized_names {
+            self.get_localized_app_names()
+        } else {
+            BTreeMap::new()
+        };
 
         /* Executable file */
         let is_ios_app = self.has_wrapper();
@@ -306,6 +1041,15 @@ impl MacAppPath {
             icon_path,
             app_path_exe,
             app_desktop_path: self.0.clone(),
+            kind: info_plist.kind(),
+            version: info_plist
+                .cf_bundle_short_version_string
+                .clone()
+                .or_else(|| info_plist.cf_bundle_version.clone()),
+            scope: InstallScope::of_path(&self.0),
+            sources: vec![DiscoverySource::Bundle],
+            parent: self.get_parent_bundle(),
+            ..Default::default()
         })
     }
 
@@ -455,9 +1199,9 @@ impl MacAppPath {
         None
     }
 
-    fn get_localized_app_names(&self) -> BTreeMap<String, String> {
+    pub(crate) fn get_localized_app_names(&self) -> BTreeMap<String, String> {
         // support for iOS apps has not be implemented
-        if self.has_wrapper() {
+        if !cfg!(feature = "localization") || self.has_wrapper() {
             return BTreeMap::new();
         }
 
@@ -473,7 +1217,7 @@ impl MacAppPath {
         }
 
         // Try to read from all lproj directories
-        extract_from_all_lproj_dirs(&resources_path, &mut names).unwrap();
+        extract_from_all_lproj_dirs(&resources_path, &mut names);
 
         names
     }
@@ -490,13 +1234,16 @@ fn extract_names_from_loctable(loctable: &PlistValue, names: &mut BTreeMap<Strin
     if let Some(dict) = loctable.as_dictionary() {
         for (locale, value) in dict {
             if let Some(locale_dict) = value.as_dictionary() {
+                let Some(locale) = normalize_locale(locale) else {
+                    continue;
+                };
                 if let Some(display_name) = locale_dict.get("CFBundleDisplayName") {
                     if let Some(name) = display_name.as_string() {
-                        names.insert(locale.to_string(), name.to_string());
+                        names.insert(locale, name.to_string());
                     }
                 } else if let Some(bundle_name) = locale_dict.get("CFBundleName") {
                     if let Some(name) = bundle_name.as_string() {
-                        names.insert(locale.to_string(), name.to_string());
+                        names.insert(locale, name.to_string());
                     }
                 }
             }
@@ -507,12 +1254,17 @@ fn extract_names_from_loctable(loctable: &PlistValue, names: &mut BTreeMap<Strin
 /// InfoPlist.strings can be in:
 ///
 /// * Apple binary property list
-/// * Plain text key-value pairs, which can be in UTF-8 and UTF-16 encoded
+/// * Plain text key-value pairs, which can be in UTF-8 and UTF-16 encoded,
+///   of either byte order, with or without a byte order mark
 fn infoplist_strings_parser(path: &Path) -> HashMap<String, String> {
     let mut result = HashMap::new();
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return result,
+    };
 
     // Try to parse as binary plist first
-    if let Ok(plist) = plist::from_file::<_, PlistValue>(path) {
+    if let Ok(plist) = PlistValue::from_reader(std::io::Cursor::new(&bytes)) {
         if let Some(dict) = plist.as_dictionary() {
             for (key, value) in dict {
                 if let Some(val_str) = value.as_string() {
@@ -522,66 +1274,127 @@ fn infoplist_strings_parser(path: &Path) -> HashMap<String, String> {
             return result;
         }
     }
+    if bytes.starts_with(b"bplist") {
+        return result;
+    }
 
     // Fall back to text parsing for UTF-16 and UTF-8 formats
-    let bytes = match std::fs::read(path) {
-        Ok(bytes) => bytes,
-        Err(_) => return result,
-    };
-
-    let content = if bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] == 0xFE {
-        // UTF-16 little-endian BOM detected
-        let utf16: Vec<u16> = bytes[2..]
-            .chunks_exact(2)
-            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-            .collect();
-        String::from_utf16_lossy(&utf16)
-    } else {
-        // Try UTF-8
-        String::from_utf8_lossy(&bytes).into_owned()
-    };
+    parse_strings(&decode_text(&bytes))
+}
 
-    // Parse the property list format line by line
-    for line in content.lines() {
-        let line = line.trim();
+/// A string, quoted or not, or a `=` or `;` of a `.strings` file.
+enum StringsToken {
+    Text(String),
+    Punct(char),
+}
 
-        // Skip empty lines and comments
-        if line.is_empty()
-            || line.starts_with("/*")
-            || line.starts_with("//")
-            || line.ends_with("*/")
-        {
-            continue;
+/// The `"key" = "value";` pairs of the text of a `.strings` file, which
+/// may span lines, with the escapes of the strings, e.g., `\"` and
+/// `\U00E9`, replaced, and the comments left out.
+fn parse_strings(text: &str) -> HashMap<String, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let is_unquoted =
+        |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '$' | ':' | '/');
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => tokens.push(StringsToken::Text(quoted_string(&mut chars))),
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c if is_unquoted(c) => {
+                let mut word = String::from(c);
+                while let Some(&c) = chars.peek()
+                    && is_unquoted(c)
+                {
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(StringsToken::Text(word));
+            }
+            c => tokens.push(StringsToken::Punct(c)),
         }
+    }
 
-        if let Some(eq_pos) = line.find('=') {
-            let mut key = line[..eq_pos].trim();
-            let mut value = line[eq_pos + 1..].trim();
+    let mut result = HashMap::new();
+    let mut rest = tokens.as_slice();
+    loop {
+        rest = match rest {
+            [
+                StringsToken::Text(key),
+                StringsToken::Punct('='),
+                StringsToken::Text(value),
+                rest @ ..,
+            ] => {
+                result.insert(key.clone(), value.clone());
+                rest
+            }
+            // A key that is its own value
+            [StringsToken::Text(key), StringsToken::Punct(';'), rest @ ..] => {
+                result.insert(key.clone(), key.clone());
+                rest
+            }
+            [_, rest @ ..] => rest,
+            [] => break,
+        };
+    }
 
-            // key has optional double quotes
-            key = key.trim_matches('"');
-            // Value has optional tailing semicolon
-            value = value.trim_end_matches(';');
-            // Value has surrounding double-quotes
-            value = value.trim_matches('"');
+    result
+}
 
-            result.insert(key.to_string(), value.to_string());
+/// The rest of a string of a `.strings` file after its opening quote.
+fn quoted_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut string = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some('U' | 'u') => {
+                    let mut code = 0;
+                    for _ in 0..4 {
+                        let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) else {
+                            break;
+                        };
+                        code = code * 16 + digit;
+                        chars.next();
+                    }
+                    string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                // `\"`, `\\` and `\'`
+                Some(c) => string.push(c),
+                None => break,
+            },
+            c => string.push(c),
         }
     }
 
-    result
+    string
 }
 
-fn extract_from_all_lproj_dirs(
-    resources_path: &Path,
-    names: &mut BTreeMap<String, String>,
-) -> Result<(), String> {
+fn extract_from_all_lproj_dirs(resources_path: &Path, names: &mut BTreeMap<String, String>) {
     const LPROJ: &str = ".lproj";
 
     // Find all .lproj directories
     if let Ok(entries) = std::fs::read_dir(resources_path) {
-        for res_entry in entries {
-            let entry = res_entry.unwrap();
+        for entry in entries.filter_map(|entry| entry.ok()) {
             let file_path = entry.path();
             let Some(file_name_os_str) = file_path.file_name() else {
                 continue;
@@ -593,26 +1406,177 @@ fn extract_from_all_lproj_dirs(
 
             if file_path.is_dir() && file_name.ends_with(LPROJ) {
                 let localized_info_plist_path = file_path.join("InfoPlist.strings");
-                if !localized_info_plist_path.try_exists().expect("TODO") {
+                if !localized_info_plist_path.is_file() {
                     continue;
                 }
                 let info_plist_kvs: HashMap<String, String> =
                     infoplist_strings_parser(&localized_info_plist_path);
 
-                let locale = file_name.trim_end_matches(LPROJ);
+                let Some(locale) = normalize_locale(file_name.trim_end_matches(LPROJ)) else {
+                    continue;
+                };
 
                 if let Some(display_name) = info_plist_kvs.get("CFBundleDisplayName") {
-                    names.insert(locale.to_string(), display_name.clone());
+                    names.insert(locale, display_name.clone());
                     continue;
                 }
 
                 if let Some(display_name) = info_plist_kvs.get("CFBundleName") {
-                    names.insert(locale.to_string(), display_name.clone());
+                    names.insert(locale, display_name.clone());
                     continue;
                 }
             }
         }
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn xml(dict: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\">\n<dict>\n{dict}\n</dict>\n</plist>\n"
+        )
+    }
+
+    fn utf16(text: &str, to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(to_bytes).collect()
+    }
+
+    /// `Info.plist` files of apps that broke discovery, with the name and
+    /// version they are read with, or `None` if they cannot be read.
+    #[test]
+    fn test_malformed_info_plists() {
+        let name = "<key>CFBundleName</key><string>Foo</string>";
+        let utf16_xml = xml(name).replace("UTF-8", "UTF-16");
+        let corpus: Vec<(&str, Vec<u8>, Option<(&str, Option<&str>)>)> = vec![
+            ("well-formed", xml(name).into_bytes(), Some(("Foo", None))),
+            (
+                "duplicate version",
+                xml("<key>CFBundleName</key><string>Foo</string>\
+                     <key>CFBundleShortVersionString</key><string>1.0</string>\
+                     <key>CFBundleShortVersionString</key><string>1.1</string>")
+                .into_bytes(),
+                Some(("Foo", Some("1.1"))),
+            ),
+            (
+                "duplicate name",
+                xml("<key>CFBundleName</key><string>Foo</string>\
+                     <key>CFBundleName</key><string>Bar</string>")
+                .into_bytes(),
+                Some(("Bar", None)),
+            ),
+            (
+                "invalid UTF-8",
+                // Latin-1
+                xml("<key>CFBundleName</key><string>Caf#</string>")
+                    .replace('#', "\u{0}")
+                    .into_bytes()
+                    .into_iter()
+                    .map(|byte| if byte == 0 { 0xE9 } else { byte })
+                    .collect(),
+                Some(("Caf\u{FFFD}", None)),
+            ),
+            (
+                "UTF-8 byte order mark",
+                [&[0xEF, 0xBB, 0xBF][..], xml(name).as_bytes()].concat(),
+                Some(("Foo", None)),
+            ),
+            (
+                "UTF-16 big-endian",
+                [&[0xFE, 0xFF][..], &utf16(&utf16_xml, u16::to_be_bytes)].concat(),
+                Some(("Foo", None)),
+            ),
+            (
+                "UTF-16 little-endian without byte order mark",
+                utf16(&utf16_xml, u16::to_le_bytes),
+                Some(("Foo", None)),
+            ),
+            (
+                "name of another type",
+                xml("<key>CFBundleName</key><integer>1</integer>\
+                     <key>CFBundleShortVersionString</key><string>1.0</string>")
+                .into_bytes(),
+                Some(("", Some("1.0"))),
+            ),
+            (
+                "truncated",
+                xml(name).split_at(80).0.as_bytes().to_vec(),
+                None,
+            ),
+            ("truncated binary", b"bplist00\xd1\x01\x02".to_vec(), None),
+            ("empty", Vec::new(), None),
+        ];
+
+        for (case, bytes, expected) in corpus {
+            let info_plist = parse_plist(&bytes)
+                .ok()
+                .and_then(|value| InfoPlist::from_value(&value).ok());
+            let read = info_plist.as_ref().map(|info_plist| {
+                (
+                    info_plist.cf_bundle_name.as_deref().unwrap_or_default(),
+                    info_plist.cf_bundle_short_version_string.as_deref(),
+                )
+            });
+            assert_eq!(read, expected, "{case}");
+        }
+    }
+
+    /// `InfoPlist.strings` files as apps ship them, in the encodings they
+    /// ship them in.
+    #[test]
+    fn test_infoplist_strings_parser() {
+        let xcode = "/* Localized versions of Info.plist keys */\n\n\
+                     CFBundleName = \"Cafe\\u00E9\";\n\
+                     \"CFBundleDisplayName\" = \"Caf\u{e9} \\\"Pro\\\"\";\n\
+                     // A trailing comment\n\
+                     NSHumanReadableCopyright = \"Copyright \u{a9} 2020\\nFoo, Inc.\";\n";
+        let multi_line = "\"CFBundleName\"\n    =\n    \"Foo; Bar\" /* = \"Baz\"; */ ;\n\"NSHumanReadableCopyright\";";
+        let temp_dir = TempDir::new("strings");
+        let dir = temp_dir.path().to_path_buf();
+
+        let corpus: Vec<(&str, Vec<u8>)> = vec![
+            ("UTF-8", xcode.as_bytes().to_vec()),
+            (
+                "UTF-16 little-endian",
+                [&[0xFF, 0xFE][..], &utf16(xcode, u16::to_le_bytes)].concat(),
+            ),
+            (
+                "UTF-16 big-endian",
+                [&[0xFE, 0xFF][..], &utf16(xcode, u16::to_be_bytes)].concat(),
+            ),
+            (
+                "UTF-16 little-endian without byte order mark",
+                utf16(xcode, u16::to_le_bytes),
+            ),
+            (
+                "UTF-16 big-endian without byte order mark",
+                utf16(xcode, u16::to_be_bytes),
+            ),
+        ];
+        for (case, bytes) in corpus {
+            let path = dir.join("InfoPlist.strings");
+            std::fs::write(&path, bytes).unwrap();
+            let strings = infoplist_strings_parser(&path);
+            assert_eq!(strings["CFBundleName"], "Cafe\u{e9}", "{case}");
+            assert_eq!(
+                strings["CFBundleDisplayName"], "Caf\u{e9} \"Pro\"",
+                "{case}"
+            );
+            assert_eq!(
+                strings["NSHumanReadableCopyright"], "Copyright \u{a9} 2020\nFoo, Inc.",
+                "{case}"
+            );
+        }
+
+        let strings = parse_strings(multi_line);
+        assert_eq!(strings["CFBundleName"], "Foo; Bar");
+        assert_eq!(
+            strings["NSHumanReadableCopyright"],
+            "NSHumanReadableCopyright"
+        );
+        assert_eq!(strings.len(), 2);
+    }
 }
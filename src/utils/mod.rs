@@ -1,2 +1,8 @@
 #[cfg(target_os = "macos")]
 pub mod mac;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+#[cfg(target_os = "windows")]
+pub mod windows;
@@ -0,0 +1,2794 @@
+//! Windows helpers that do not belong to the Start Menu scanner itself.
+
+#[cfg(feature = "icons")]
+use crate::common::Icon;
+use crate::common::{
+    App, DiscoverySource, InstallScope, InstallSource, SignatureInfo, SignatureStatus,
+    UninstallCommand, normalize_locale,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use winreg::enums::{
+    HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_32KEY, KEY_WOW64_64KEY,
+};
+use winreg::{HKEY, RegKey};
+
+/// The registry key "Add/Remove Programs" lists.
+pub const UNINSTALL_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+
+/// An "Add/Remove Programs" entry, i.e., a subkey of an `Uninstall` registry
+/// key.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RegistryApp {
+    /// Name of the subkey, which is the product code (`{...}`) for MSI
+    /// installs.
+    pub key_name: String,
+    /// Whether it was found under `HKEY_CURRENT_USER` rather than
+    /// `HKEY_LOCAL_MACHINE`.
+    pub per_user: bool,
+    /// The `Uninstall` key it was found in, named with its view, e.g.,
+    /// `HKEY_LOCAL_MACHINE\Software\...\Uninstall (32-bit)`. The 32-bit
+    /// and 64-bit views may both have a subkey named `key_name`.
+    pub uninstall_key: String,
+    pub display_name: String,
+    pub display_version: Option<String>,
+    pub publisher: Option<String>,
+    /// Installation date in the `YYYYMMDD` format.
+    pub install_date: Option<String>,
+    pub install_location: Option<PathBuf>,
+    /// Raw `DisplayIcon` value, a path with an optional `,<index>` suffix.
+    pub display_icon: Option<String>,
+    pub uninstall_string: Option<String>,
+    /// Uninstalls without asking anything, not every installer registers
+    /// one.
+    pub quiet_uninstall_string: Option<String>,
+    /// Set for Windows Installer products, whose subkey is the product
+    /// code.
+    pub product_code: Option<String>,
+    /// `SystemComponent=1`, which hides the entry from "Add/Remove Programs".
+    pub system_component: bool,
+}
+
+/// Split a resource location like `"C:\foo\app.exe",0` into its path and
+/// icon index.
+pub fn split_icon_location(location: &str) -> (PathBuf, Option<i32>) {
+    let location = location.trim();
+    let (path, index) = match location.rsplit_once(',') {
+        Some((path, index)) if index.trim().parse::<i32>().is_ok() => {
+            (path, index.trim().parse::<i32>().ok())
+        }
+        _ => (location, None),
+    };
+
+    (PathBuf::from(path.trim().trim_matches('"')), index)
+}
+
+/// Whether `value` is a GUID in braces, `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}`.
+fn is_guid(value: &str) -> bool {
+    let Some(guid) = value
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+    else {
+        return false;
+    };
+    let groups: Vec<&str> = guid.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Parse the command line of an `UninstallString` into the program and its
+/// arguments.
+pub fn parse_uninstall_string(command_line: &str, quiet: bool) -> Option<UninstallCommand> {
+    let command_line = command_line.trim();
+    let (program, rest) = if let Some(quoted) = command_line.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (&quoted[..end], &quoted[end + 1..])
+    } else {
+        // Unquoted paths may contain spaces, cut right after the extension
+        // if there is one. Only ASCII is lowercased, so that the offsets
+        // are the same in both
+        let lowercase = command_line.to_ascii_lowercase();
+        let end = match lowercase.find(".exe") {
+            Some(pos) => pos + 4,
+            None => command_line
+                .find(char::is_whitespace)
+                .unwrap_or(command_line.len()),
+        };
+        command_line.split_at(end)
+    };
+    if program.is_empty() {
+        return None;
+    }
+
+    Some(UninstallCommand {
+        program: PathBuf::from(expand_environment_strings(program)),
+        args: split_arguments(rest),
+        quiet,
+    })
+}
+
+impl RegistryApp {
+    /// How to uninstall the entry, quietly if possible. Windows Installer
+    /// products are removed by `msiexec /x` with a progress bar only, as
+    /// their `UninstallString` usually opens the maintenance dialog.
+    pub fn uninstaller(&self) -> Option<UninstallCommand> {
+        if let Some(quiet) = self.quiet_uninstall_string.as_deref()
+            && let Some(command) = parse_uninstall_string(quiet, true)
+        {
+            return Some(command);
+        }
+        if let Some(product_code) = self.product_code.as_ref() {
+            return Some(UninstallCommand {
+                program: PathBuf::from("msiexec.exe"),
+                args: vec![
+                    "/x".to_string(),
+                    product_code.clone(),
+                    "/passive".to_string(),
+                ],
+                quiet: true,
+            });
+        }
+        parse_uninstall_string(self.uninstall_string.as_deref()?, false)
+    }
+}
+
+fn read_string(key: &RegKey, name: &str) -> Option<String> {
+    key.get_value::<String, _>(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn read_registry_app(
+    key: &RegKey,
+    key_name: String,
+    per_user: bool,
+    uninstall_key: String,
+) -> Option<RegistryApp> {
+    let display_name = read_string(key, "DisplayName")?;
+    // Updates and patches point to the product they belong to
+    if read_string(key, "ParentKeyName").is_some() {
+        return None;
+    }
+    let is_msi = key
+        .get_value::<u32, _>("WindowsInstaller")
+        .is_ok_and(|value| value == 1);
+    let product_code = (is_msi && is_guid(&key_name)).then(|| key_name.to_uppercase());
+
+    Some(RegistryApp {
+        key_name,
+        per_user,
+        uninstall_key,
+        display_name,
+        display_version: read_string(key, "DisplayVersion"),
+        publisher: read_string(key, "Publisher"),
+        install_date: read_string(key, "InstallDate"),
+        install_location: read_string(key, "InstallLocation")
+            .map(|location| PathBuf::from(location.trim_matches('"'))),
+        display_icon: read_string(key, "DisplayIcon"),
+        uninstall_string: read_string(key, "UninstallString"),
+        quiet_uninstall_string: read_string(key, "QuietUninstallString"),
+        product_code,
+        system_component: key
+            .get_value::<u32, _>("SystemComponent")
+            .is_ok_and(|value| value == 1),
+    })
+}
+
+/// The `Uninstall` key of 32-bit ARM programs on ARM64, which the 64-bit
+/// view has, there is no flag for a view of its own like for x86 ones.
+pub const ARM32_UNINSTALL_KEY: &str =
+    "Software\\WowAA32Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+
+/// The `Uninstall` keys and views of them to read and watch: the 64-bit
+/// and 32-bit views of `HKEY_LOCAL_MACHINE`, which x64 and ARM64 programs
+/// share on ARM64, the key of 32-bit ARM programs, and the key of
+/// `HKEY_CURRENT_USER`, which is not redirected.
+pub fn uninstall_keys(access: u32) -> [(HKEY, &'static str, u32); 4] {
+    [
+        (HKEY_LOCAL_MACHINE, UNINSTALL_KEY, access | KEY_WOW64_64KEY),
+        (HKEY_LOCAL_MACHINE, UNINSTALL_KEY, access | KEY_WOW64_32KEY),
+        (
+            HKEY_LOCAL_MACHINE,
+            ARM32_UNINSTALL_KEY,
+            access | KEY_WOW64_64KEY,
+        ),
+        (HKEY_CURRENT_USER, UNINSTALL_KEY, access),
+    ]
+}
+
+/// The key with a subkey for each package installed for the current user.
+pub const PACKAGES_KEY: &str = "Software\\Classes\\Local Settings\\Software\\Microsoft\\Windows\\CurrentVersion\\AppModel\\Repository\\Packages";
+
+/// The `Uninstall` keys of [`uninstall_keys`] and [`PACKAGES_KEY`], named
+/// with their view, with when each was last written, which is when a
+/// subkey was added or removed: an app was installed or uninstalled.
+pub fn registry_sources() -> Vec<(PathBuf, Option<std::time::SystemTime>)> {
+    uninstall_keys(KEY_READ)
+        .into_iter()
+        .chain([(HKEY_CURRENT_USER, PACKAGES_KEY, KEY_READ)])
+        .map(|(hive, path, flags)| {
+            (
+                PathBuf::from(registry_key_name(hive, path, flags)),
+                registry_key_modified(hive, path, flags),
+            )
+        })
+        .collect()
+}
+
+/// The key at `path` of `hive`, named with the view `flags` open.
+fn registry_key_name(hive: HKEY, path: &str, flags: u32) -> String {
+    let hive = if hive == HKEY_CURRENT_USER {
+        "HKEY_CURRENT_USER"
+    } else {
+        "HKEY_LOCAL_MACHINE"
+    };
+    let view = if flags & KEY_WOW64_32KEY != 0 {
+        " (32-bit)"
+    } else {
+        ""
+    };
+
+    format!("{hive}\\{path}{view}")
+}
+
+/// When the key at `path` of `hive` was last written, `None` if there is
+/// none.
+fn registry_key_modified(hive: HKEY, path: &str, flags: u32) -> Option<std::time::SystemTime> {
+    let key = RegKey::predef(hive)
+        .open_subkey_with_flags(path, flags)
+        .ok()?;
+    let written = key.query_info().ok()?.last_write_time;
+    // In 100 ns since 1601
+    let intervals = (u64::from(written.dwHighDateTime) << 32) | u64::from(written.dwLowDateTime);
+    let since_unix = intervals.checked_sub(116_444_736_000_000_000)?;
+
+    Some(std::time::UNIX_EPOCH + std::time::Duration::from_nanos(since_unix * 100))
+}
+
+/// Read the entries of the `Uninstall` keys of [`uninstall_keys`].
+pub fn read_registry_apps() -> Vec<RegistryApp> {
+    // 32-bit Windows ignores the view flags, both views are the same key
+    let has_views = !matches!(
+        native_architecture(),
+        Some(Architecture::X86 | Architecture::Arm)
+    );
+    let mut apps = Vec::new();
+    for (hive, path, flags) in uninstall_keys(KEY_READ) {
+        if flags & KEY_WOW64_32KEY != 0 && !has_views {
+            continue;
+        }
+        let per_user = hive == HKEY_CURRENT_USER;
+        let Ok(uninstall_key) = RegKey::predef(hive).open_subkey_with_flags(path, flags) else {
+            continue;
+        };
+        let name = registry_key_name(hive, path, flags);
+        for key_name in uninstall_key.enum_keys().filter_map(|name| name.ok()) {
+            let Ok(key) = uninstall_key.open_subkey_with_flags(&key_name, flags) else {
+                continue;
+            };
+            apps.extend(read_registry_app(&key, key_name, per_user, name.clone()));
+        }
+    }
+
+    apps
+}
+
+/// Decode the `%XX` escapes of a URI component.
+fn percent_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(byte) = encoded
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            idx += 3;
+            continue;
+        }
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `file:///C:/Program%20Files/Foo/logo.png` => `C:\Program Files\Foo\logo.png`
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file:///")?;
+    Some(PathBuf::from(percent_decode(path).replace('/', "\\")))
+}
+
+/// Parse the qualifiers of a package asset's file name, e.g.,
+/// `Logo.targetsize-48_altform-unplated.png` => `[("targetsize", "48"),
+/// ("altform", "unplated")]`, `None` if it is not a variant of `base`,
+/// e.g., `Logo.png`.
+fn asset_qualifiers(base: &Path, file_name: &str) -> Option<Vec<(String, String)>> {
+    let stem = base.file_stem()?.to_str()?.to_lowercase();
+    let ext = base.extension()?.to_str()?.to_lowercase();
+    let file_name = file_name.to_lowercase();
+    let rest = file_name.strip_suffix(&format!(".{}", ext))?;
+    if rest == stem {
+        return Some(Vec::new());
+    }
+    let qualifiers = rest.strip_prefix(&format!("{}.", stem))?;
+
+    Some(
+        qualifiers
+            .split('_')
+            .filter_map(|qualifier| qualifier.split_once('-'))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+/// Pick the variant of a package asset that fits `size` best among the
+/// `file_names` in its directory. `base_size` is the size the unqualified
+/// asset has, e.g., 44 for `Square44x44Logo`.
+///
+/// `targetsize-*` variants are sized in pixels, `scale-*` ones relative to
+/// `base_size`. The smallest variant not smaller than `size` wins, or the
+/// largest one. High contrast variants are skipped and plated ones, which
+/// come with the tile's background, lose against `altform-unplated`.
+pub fn select_qualified_asset(
+    base: &Path,
+    base_size: u32,
+    size: u32,
+    file_names: &[String],
+) -> Option<String> {
+    let mut candidates: Vec<(u32, bool, &String)> = file_names
+        .iter()
+        .filter_map(|file_name| {
+            let qualifiers = asset_qualifiers(base, file_name)?;
+            let mut effective_size = base_size;
+            let mut unplated = false;
+            for (name, value) in &qualifiers {
+                match (name.as_str(), value.as_str()) {
+                    ("contrast", _) => return None,
+                    ("altform", "unplated") => unplated = true,
+                    // For light taskbars only
+                    ("altform", _) => return None,
+                    ("targetsize", value) => effective_size = value.parse().ok()?,
+                    ("scale", value) => {
+                        effective_size = base_size * value.parse::<u32>().ok()? / 100
+                    }
+                    _ => {}
+                }
+            }
+
+            Some((effective_size, unplated, file_name))
+        })
+        .collect();
+    // Large enough ones first, smallest of them first, then the largest of
+    // the rest; unplated ones first among equally sized ones
+    candidates.sort_by_key(|&(effective_size, unplated, file_name)| {
+        let too_small = effective_size < size;
+        let distance = if too_small {
+            u32::MAX - effective_size
+        } else {
+            effective_size
+        };
+        (too_small, distance, !unplated, file_name.clone())
+    });
+
+    candidates
+        .first()
+        .map(|(_, _, file_name)| file_name.to_string())
+}
+
+/// Resolve `asset`, relative to the package directory, to the file that
+/// fits `size` best, see [`select_qualified_asset`].
+pub fn resolve_package_asset(
+    installed_path: &Path,
+    asset: &Path,
+    base_size: u32,
+    size: u32,
+) -> Option<PathBuf> {
+    let path = installed_path.join(asset);
+    let dir = path.parent()?;
+    let file_names: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+
+    select_qualified_asset(&path, base_size, size, &file_names).map(|file_name| dir.join(file_name))
+}
+
+/// The value of attribute `name` in the start tag `tag`.
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = start + tag[start..].find('"')?;
+
+    Some(tag[start..end].to_string())
+}
+
+/// Find the logos the `VisualElements` of application `app_id` declare in
+/// an `AppxManifest.xml`, with the size each has unqualified, largest
+/// first.
+pub fn parse_manifest_logos(manifest: &str, app_id: &str) -> Vec<(PathBuf, u32)> {
+    let id_pattern = format!("Id=\"{}\"", app_id);
+    let Some(application) = manifest
+        .match_indices("<Application ")
+        .map(|(start, _)| &manifest[start..])
+        .find(|application| {
+            application
+                .find('>')
+                .is_some_and(|end| application[..end].contains(&id_pattern))
+        })
+    else {
+        return Vec::new();
+    };
+    let application = match application.find("</Application>") {
+        Some(end) => &application[..end],
+        None => application,
+    };
+    let Some(start) = application.find("VisualElements ") else {
+        return Vec::new();
+    };
+    let Some(end) = application[start..].find('>') else {
+        return Vec::new();
+    };
+    let tag = &application[start..start + end];
+
+    [("Square150x150Logo", 150), ("Square44x44Logo", 44)]
+        .into_iter()
+        .filter_map(|(attribute, size)| Some((PathBuf::from(xml_attribute(tag, attribute)?), size)))
+        .collect()
+}
+
+/// The logo of application `app_id` of the package installed at
+/// `installed_path` that fits `size` best. Small sizes prefer the
+/// 44x44 logo, whose variants are made for small sizes, large sizes the
+/// 150x150 one.
+pub fn select_package_logo(installed_path: &Path, app_id: &str, size: u32) -> Option<PathBuf> {
+    let manifest = std::fs::read_to_string(installed_path.join("AppxManifest.xml")).ok()?;
+    let mut logos = parse_manifest_logos(&manifest, app_id);
+    if size <= 64 {
+        logos.reverse();
+    }
+
+    logos.into_iter().find_map(|(asset, base_size)| {
+        resolve_package_asset(installed_path, &asset, base_size, size)
+    })
+}
+
+/// Packaged (MSIX/UWP) apps of the current user, one per app list entry,
+/// so a package with several apps yields several apps, like the Start menu
+/// shows them.
+pub fn read_packaged_apps() -> crate::Result<Vec<App>> {
+    use windows::Management::Deployment::PackageManager;
+    use windows::core::HSTRING;
+
+    let package_manager = PackageManager::new()?;
+    // An empty security ID means the current user
+    let packages = package_manager.FindPackagesByUserSecurityId(&HSTRING::new())?;
+
+    let mut apps = Vec::new();
+    for package in packages {
+        if package.IsFramework().unwrap_or(false) || package.IsResourcePackage().unwrap_or(false) {
+            continue;
+        }
+        let Ok(installed_path) = package.InstalledPath() else {
+            continue;
+        };
+        let installed_path = PathBuf::from(installed_path.to_string_lossy());
+
+        let version = package
+            .Id()
+            .and_then(|id| id.Version())
+            .ok()
+            .map(|version| {
+                format!(
+                    "{}.{}.{}.{}",
+                    version.Major, version.Minor, version.Build, version.Revision
+                )
+            });
+        let publisher = package
+            .PublisherDisplayName()
+            .ok()
+            .map(|publisher| publisher.to_string_lossy())
+            .filter(|publisher| !publisher.is_empty());
+        // The store logo, for apps whose manifest we cannot read
+        let store_logo = package
+            .Logo()
+            .and_then(|uri| uri.RawUri())
+            .ok()
+            .and_then(|uri| file_uri_to_path(&uri.to_string_lossy()))
+            .and_then(|path| {
+                let asset = path.strip_prefix(&installed_path).ok()?.to_path_buf();
+                resolve_package_asset(&installed_path, &asset, 50, 256)
+            });
+
+        let Ok(entries) = package.GetAppListEntries() else {
+            continue;
+        };
+        for entry in entries {
+            let Ok(name) = entry.DisplayInfo().and_then(|info| info.DisplayName()) else {
+                continue;
+            };
+            let name = name.to_string_lossy();
+            if name.is_empty() {
+                continue;
+            }
+            let aumid = entry
+                .AppUserModelId()
+                .ok()
+                .map(|aumid| aumid.to_string_lossy())
+                .filter(|aumid| !aumid.is_empty());
+            let icon_path = aumid
+                .as_deref()
+                .and_then(|aumid| aumid.split_once('!'))
+                .and_then(|(_, app_id)| select_package_logo(&installed_path, app_id, 256))
+                .or_else(|| store_logo.clone());
+
+            apps.push(App {
+                name,
+                icon_path,
+                app_desktop_path: installed_path.clone(),
+                version: version.clone(),
+                publisher: publisher.clone(),
+                aumid,
+                scope: InstallScope::of_path(&installed_path),
+                sources: vec![DiscoverySource::Uwp],
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(apps)
+}
+
+/// COM initialized for the calling thread until the guard is dropped.
+///
+/// Initializing fails with `RPC_E_CHANGED_MODE` if the thread already
+/// initialized COM differently, which is fine for the objects created here,
+/// so only a successful initialization is undone. Declare the guard before
+/// any COM object so the objects are released first.
+pub(crate) struct ComGuard {
+    initialized: bool,
+}
+
+impl ComGuard {
+    pub(crate) fn new() -> Self {
+        use windows::Win32::System::Com::{COINIT_APARTMENTTHREADED, CoInitializeEx};
+
+        let initialized = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.is_ok();
+        Self { initialized }
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { windows::Win32::System::Com::CoUninitialize() };
+        }
+    }
+}
+
+/// Start a packaged app by its Application User Model ID, returns the
+/// process ID of the started app.
+pub fn activate_application(aumid: &str) -> crate::Result<u32> {
+    use windows::Win32::System::Com::{CLSCTX_LOCAL_SERVER, CoCreateInstance};
+    use windows::Win32::UI::Shell::{
+        AO_NONE, ApplicationActivationManager, IApplicationActivationManager,
+    };
+    use windows::core::{HSTRING, PCWSTR};
+
+    unsafe {
+        let _com = ComGuard::new();
+        let manager: IApplicationActivationManager =
+            CoCreateInstance(&ApplicationActivationManager, None, CLSCTX_LOCAL_SERVER)?;
+        let pid = manager.ActivateApplication(&HSTRING::from(aumid), PCWSTR::null(), AO_NONE)?;
+
+        Ok(pid)
+    }
+}
+
+/// What a `.lnk` shortcut points to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShellLinkInfo {
+    /// The target, for advertised shortcuts this is the installed path of
+    /// the advertised component.
+    pub target: Option<PathBuf>,
+    pub arguments: Option<String>,
+    pub working_dir: Option<PathBuf>,
+    pub description: Option<String>,
+    /// Icon path and index.
+    pub icon_location: Option<(PathBuf, i32)>,
+    /// "Run as administrator" is checked in the shortcut's properties.
+    pub run_as_admin: bool,
+}
+
+/// Buffer size for the strings read from a shell link, `INFOTIPSIZE`,
+/// which is also how long arguments can be.
+const SHELL_LINK_BUFFER_LEN: usize = 1024;
+
+fn from_wide(buffer: &[u16]) -> Option<String> {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    let string = String::from_utf16_lossy(&buffer[..len]);
+    let string = string.trim();
+
+    (!string.is_empty()).then(|| string.to_string())
+}
+
+/// The installed path of the component an MSI advertised shortcut points
+/// to, `None` for regular shortcuts or if the component is not installed
+/// locally.
+fn resolve_msi_shortcut(path: &Path) -> Option<PathBuf> {
+    use windows::Win32::System::ApplicationInstallationAndServicing::{
+        INSTALLSTATE_LOCAL, MsiGetComponentPathW, MsiGetShortcutTargetW,
+    };
+    use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+    // GUIDs in their "{...}" form are 38 characters long
+    let mut product_code = [0u16; 39];
+    let mut feature_id = [0u16; 39];
+    let mut component_code = [0u16; 39];
+    let mut path_buffer = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut path_len = path_buffer.len() as u32;
+
+    unsafe {
+        let result = MsiGetShortcutTargetW(
+            &HSTRING::from(path.as_os_str()),
+            PWSTR(product_code.as_mut_ptr()),
+            PWSTR(feature_id.as_mut_ptr()),
+            PWSTR(component_code.as_mut_ptr()),
+        );
+        if result != 0 {
+            return None;
+        }
+
+        let state = MsiGetComponentPathW(
+            PCWSTR(product_code.as_ptr()),
+            PCWSTR(component_code.as_ptr()),
+            PWSTR(path_buffer.as_mut_ptr()),
+            Some(&mut path_len),
+        );
+        if state != INSTALLSTATE_LOCAL {
+            return None;
+        }
+    }
+
+    from_wide(&path_buffer).map(PathBuf::from)
+}
+
+/// Read a `.lnk` shortcut with `IShellLinkW`/`IPersistFile`.
+pub fn resolve_shell_link(path: &Path) -> crate::Result<ShellLinkInfo> {
+    use windows::Win32::System::Com::{
+        CLSCTX_INPROC_SERVER, CoCreateInstance, IPersistFile, STGM_READ,
+    };
+    use windows::Win32::UI::Shell::{IShellLinkDataList, IShellLinkW, ShellLink};
+    use windows::core::{HSTRING, Interface};
+
+    // `SLDF_RUNAS_USER` of `SHELL_LINK_DATA_FLAGS`
+    const RUN_AS_USER: u32 = 0x2000;
+
+    let mut target = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut arguments = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut working_dir = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut description = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut icon = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut icon_index = 0;
+    let mut flags = 0;
+
+    unsafe {
+        let _com = ComGuard::new();
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.cast::<IPersistFile>()?
+            .Load(&HSTRING::from(path.as_os_str()), STGM_READ)?;
+
+        // Shortcuts to shell items, e.g., "This PC", have no path
+        let _ = link.GetPath(&mut target, std::ptr::null_mut(), 0);
+        let _ = link.GetArguments(&mut arguments);
+        let _ = link.GetWorkingDirectory(&mut working_dir);
+        let _ = link.GetDescription(&mut description);
+        let _ = link.GetIconLocation(&mut icon, &mut icon_index);
+        if let Ok(data_list) = link.cast::<IShellLinkDataList>() {
+            flags = data_list.GetFlags().unwrap_or_default();
+        }
+    }
+
+    // The path of an advertised shortcut is an icon in the Installer cache
+    let target = resolve_msi_shortcut(path).or_else(|| from_wide(&target).map(PathBuf::from));
+
+    Ok(ShellLinkInfo {
+        target,
+        arguments: from_wide(&arguments),
+        working_dir: from_wide(&working_dir).map(PathBuf::from),
+        description: from_wide(&description),
+        icon_location: from_wide(&icon).map(|icon| (PathBuf::from(icon), icon_index)),
+        run_as_admin: flags & RUN_AS_USER != 0,
+    })
+}
+
+/// The `level` of the `requestedExecutionLevel` in an application
+/// manifest, e.g., `requireAdministrator`.
+pub fn parse_execution_level(manifest: &str) -> Option<String> {
+    let start = manifest.find("requestedExecutionLevel")?;
+    let end = start + manifest[start..].find('>')?;
+    let tag = manifest[start..end].replace('\'', "\"");
+
+    xml_attribute(&tag, "level")
+}
+
+/// Read the application manifest embedded in an executable, the
+/// `RT_MANIFEST` resource with ID 1.
+pub fn read_embedded_manifest(path: &Path) -> Option<String> {
+    use windows::Win32::System::LibraryLoader::{
+        FindResourceW, FreeLibrary, LOAD_LIBRARY_AS_DATAFILE, LOAD_LIBRARY_AS_IMAGE_RESOURCE,
+        LoadLibraryExW, LoadResource, LockResource, SizeofResource,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::RT_MANIFEST;
+    use windows::core::{HSTRING, PCWSTR};
+
+    unsafe {
+        // Maps the resources only, nothing of the executable runs
+        let module = LoadLibraryExW(
+            &HSTRING::from(path.as_os_str()),
+            None,
+            LOAD_LIBRARY_AS_DATAFILE | LOAD_LIBRARY_AS_IMAGE_RESOURCE,
+        )
+        .ok()?;
+
+        let manifest = (|| {
+            // MAKEINTRESOURCE(1)
+            let resource = FindResourceW(Some(module), PCWSTR(1 as *const u16), RT_MANIFEST);
+            if resource.is_invalid() {
+                return None;
+            }
+            let data = LockResource(LoadResource(Some(module), resource).ok()?);
+            let len = SizeofResource(Some(module), resource) as usize;
+            if data.is_null() || len == 0 {
+                return None;
+            }
+            let bytes = std::slice::from_raw_parts(data as *const u8, len);
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        })();
+
+        let _ = FreeLibrary(module);
+        manifest
+    }
+}
+
+/// Whether the manifest of the executable makes starting it prompt for
+/// elevation. `highestAvailable` only does so for administrators.
+pub fn manifest_requires_elevation(path: &Path) -> bool {
+    use windows::Win32::UI::Shell::IsUserAnAdmin;
+
+    match read_embedded_manifest(path)
+        .and_then(|manifest| parse_execution_level(&manifest))
+        .as_deref()
+    {
+        Some("requireAdministrator") => true,
+        Some("highestAvailable") => unsafe { IsUserAnAdmin().as_bool() },
+        _ => false,
+    }
+}
+
+/// The `Subsystem` field of the PE image that starts with `image`, e.g., 2
+/// for GUI programs and 3 for console programs.
+pub fn pe_subsystem(image: &[u8]) -> Option<u16> {
+    if !image.starts_with(b"MZ") {
+        return None;
+    }
+    // The offset is read from the file, it may point anywhere
+    let pe_offset = usize::try_from(le_u32(image, 0x3c)?).ok()?;
+    if image.get(pe_offset..pe_offset.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+    // After the signature and the COFF header, at the same offset in the
+    // optional headers of PE32 and PE32+ images
+    le_u16(image, pe_offset.checked_add(4 + 20 + 68)?)
+}
+
+/// Whether the executable at `path` is a console program, which opens a
+/// console window when it is not started from one.
+pub fn is_console_program(path: &Path) -> bool {
+    use std::io::Read;
+
+    const IMAGE_SUBSYSTEM_WINDOWS_CUI: u16 = 3;
+    let mut header = Vec::with_capacity(4096);
+    let read = std::fs::File::open(path).and_then(|file| file.take(4096).read_to_end(&mut header));
+
+    read.is_ok() && pe_subsystem(&header) == Some(IMAGE_SUBSYSTEM_WINDOWS_CUI)
+}
+
+/// What a PE image is compiled for, see [`pe_architecture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Architecture {
+    X86,
+    X64,
+    /// 32-bit ARM, which Windows 11 24H2 no longer runs.
+    Arm,
+    Arm64,
+    /// x64 compatible code that runs natively on ARM64, loading x64 code
+    /// that is emulated.
+    Arm64Ec,
+    /// Both ARM64 and ARM64EC code in one image, which loads natively into
+    /// ARM64 and into emulated x64 processes.
+    Arm64X,
+}
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+const IMAGE_FILE_MACHINE_ARMNT: u16 = 0x01c4;
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
+impl Architecture {
+    pub(crate) fn from_machine(machine: u16) -> Option<Self> {
+        match machine {
+            IMAGE_FILE_MACHINE_I386 => Some(Self::X86),
+            IMAGE_FILE_MACHINE_ARMNT => Some(Self::Arm),
+            IMAGE_FILE_MACHINE_AMD64 => Some(Self::X64),
+            IMAGE_FILE_MACHINE_ARM64 => Some(Self::Arm64),
+            _ => None,
+        }
+    }
+}
+
+/// The architecture of the PE image `file`, from the machine of its COFF
+/// header. ARM64X images say ARM64 there and ARM64EC ones x64, they are
+/// told apart by the CHPE metadata of their load configuration.
+pub fn pe_architecture(file: &mut (impl std::io::Read + std::io::Seek)) -> Option<Architecture> {
+    let dos = read_at(file, 0, 0x40)?;
+    if !dos.starts_with(b"MZ") {
+        return None;
+    }
+    let pe_offset = u64::from(le_u32(&dos, 0x3c)?);
+    let coff = read_at(file, pe_offset, 24)?;
+    if !coff.starts_with(b"PE\0\0") {
+        return None;
+    }
+    let architecture = Architecture::from_machine(le_u16(&coff, 4)?)?;
+    let hybrid = matches!(architecture, Architecture::X64 | Architecture::Arm64)
+        && has_chpe_metadata(file, pe_offset, &coff).unwrap_or(false);
+
+    Some(match architecture {
+        Architecture::X64 if hybrid => Architecture::Arm64Ec,
+        Architecture::Arm64 if hybrid => Architecture::Arm64X,
+        architecture => architecture,
+    })
+}
+
+/// Whether the load configuration of the PE32+ image with the COFF header
+/// `coff` at `pe_offset` points to CHPE metadata.
+fn has_chpe_metadata(
+    file: &mut (impl std::io::Read + std::io::Seek),
+    pe_offset: u64,
+    coff: &[u8],
+) -> Option<bool> {
+    const PE32_PLUS: u16 = 0x20b;
+    /// The data directories follow the standard and Windows fields of the
+    /// optional header, the load configuration is the 11th.
+    const LOAD_CONFIG_DIRECTORY: usize = 112 + 10 * 8;
+    /// `CHPEMetadataPointer` of `IMAGE_LOAD_CONFIG_DIRECTORY64`.
+    const CHPE_METADATA_POINTER: usize = 0xc8;
+
+    let sections = usize::from(le_u16(coff, 6)?);
+    let optional_size = usize::from(le_u16(coff, 20)?);
+    let optional = read_at(file, pe_offset + 24, optional_size)?;
+    if le_u16(&optional, 0)? != PE32_PLUS {
+        return Some(false);
+    }
+    let rva = le_u32(&optional, LOAD_CONFIG_DIRECTORY)?;
+    if rva == 0 {
+        return Some(false);
+    }
+
+    let section_table = read_at(file, pe_offset + 24 + optional_size as u64, sections * 40)?;
+    let offset = section_table.chunks_exact(40).find_map(|section| {
+        let virtual_size = le_u32(section, 8)?;
+        let virtual_address = le_u32(section, 12)?;
+        let raw_size = le_u32(section, 16)?;
+        let raw_offset = le_u32(section, 20)?;
+        let end = virtual_address.checked_add(virtual_size.max(raw_size))?;
+        (virtual_address..end)
+            .contains(&rva)
+            .then(|| u64::from(raw_offset) + u64::from(rva - virtual_address))
+    })?;
+    let load_config = read_at(file, offset, CHPE_METADATA_POINTER + 8)?;
+    // Older images have shorter load configurations
+    if (le_u32(&load_config, 0)? as usize) < CHPE_METADATA_POINTER + 8 {
+        return Some(false);
+    }
+
+    Some(le_u64(&load_config, CHPE_METADATA_POINTER)? != 0)
+}
+
+fn read_at(
+    file: &mut (impl std::io::Read + std::io::Seek),
+    offset: u64,
+    len: usize,
+) -> Option<Vec<u8>> {
+    file.seek(std::io::SeekFrom::Start(offset)).ok()?;
+    let mut bytes = vec![0; len];
+    file.read_exact(&mut bytes).ok()?;
+
+    Some(bytes)
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(at..at.checked_add(2)?)?.try_into().ok()?,
+    ))
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(at..at.checked_add(4)?)?.try_into().ok()?,
+    ))
+}
+
+fn le_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(at..at.checked_add(8)?)?.try_into().ok()?,
+    ))
+}
+
+/// The architecture of Windows, which may not be the one of the current
+/// process, e.g., an x64 process emulated on ARM64.
+pub fn native_architecture() -> Option<Architecture> {
+    use windows::Win32::System::SystemInformation::IMAGE_FILE_MACHINE;
+    use windows::Win32::System::Threading::{GetCurrentProcess, IsWow64Process2};
+
+    let mut process = IMAGE_FILE_MACHINE::default();
+    let mut native = IMAGE_FILE_MACHINE::default();
+    unsafe { IsWow64Process2(GetCurrentProcess(), &mut process, Some(&mut native)) }.ok()?;
+
+    Architecture::from_machine(native.0)
+}
+
+/// Extract an icon from an EXE, DLL or ICO file at the largest size icons
+/// come in (256x256); the shell picks the frame closest to it. A negative
+/// `index` is a resource ID.
+#[cfg(feature = "icons")]
+pub fn extract_icon(path: &Path, index: i32) -> crate::Result<Icon> {
+    use windows::Win32::UI::Shell::SHDefExtractIconW;
+    use windows::Win32::UI::WindowsAndMessaging::{DestroyIcon, HICON};
+    use windows::core::HSTRING;
+
+    const SIZE: u32 = 256;
+
+    let mut icon = HICON::default();
+    unsafe {
+        SHDefExtractIconW(
+            &HSTRING::from(path.as_os_str()),
+            index,
+            0,
+            Some(&mut icon),
+            None,
+            SIZE,
+        )?;
+        let result = icon_to_rgba(icon);
+        let _ = DestroyIcon(icon);
+
+        result
+    }
+}
+
+#[cfg(feature = "icons")]
+unsafe fn icon_to_rgba(
+    icon: windows::Win32::UI::WindowsAndMessaging::HICON,
+) -> crate::Result<Icon> {
+    use windows::Win32::Graphics::Gdi::DeleteObject;
+    use windows::Win32::UI::WindowsAndMessaging::{GetIconInfo, ICONINFO};
+
+    unsafe {
+        let mut info = ICONINFO::default();
+        GetIconInfo(icon, &mut info)?;
+        let result = bitmap_to_rgba(info.hbmColor);
+        let _ = DeleteObject(info.hbmColor.into());
+        let _ = DeleteObject(info.hbmMask.into());
+
+        result
+    }
+}
+
+/// Read a 32-bit bitmap, it is not deleted.
+#[cfg(feature = "icons")]
+unsafe fn bitmap_to_rgba(hbitmap: windows::Win32::Graphics::Gdi::HBITMAP) -> crate::Result<Icon> {
+    use windows::Win32::Graphics::Gdi::{
+        BI_RGB, BITMAP, BITMAPINFO, BITMAPINFOHEADER, DIB_RGB_COLORS, GetDC, GetDIBits, GetObjectW,
+        ReleaseDC,
+    };
+
+    unsafe {
+        let mut bitmap = BITMAP::default();
+        let read = GetObjectW(
+            hbitmap.into(),
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut bitmap as *mut BITMAP as *mut _),
+        );
+        if read == 0 {
+            return Err(crate::Error::Platform("icon has no color bitmap".into()));
+        }
+
+        let width = bitmap.bmWidth as u32;
+        let height = bitmap.bmHeight as u32;
+        let mut bitmap_info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width as i32,
+                // Negative for rows from the top
+                biHeight: -(height as i32),
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let dc = GetDC(None);
+        let lines = GetDIBits(
+            dc,
+            hbitmap,
+            0,
+            height,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut bitmap_info,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(None, dc);
+        if lines == 0 {
+            return Err(crate::Error::Platform(
+                "failed to read the icon bitmap".into(),
+            ));
+        }
+
+        // BGRA => RGBA
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        // Icons without an alpha channel rely on their mask
+        if pixels.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+        }
+
+        Ok(Icon {
+            width,
+            height,
+            rgba: pixels,
+        })
+    }
+}
+
+/// Resolve an indirect string like `@shell32.dll,-22067` or
+/// `@{Microsoft.WindowsCalculator_...?ms-resource://...}` with
+/// `SHLoadIndirectString`, `None` if `source` is not an indirect string or
+/// cannot be resolved.
+pub fn load_indirect_string(source: &str) -> Option<String> {
+    use windows::Win32::UI::Shell::SHLoadIndirectString;
+    use windows::core::HSTRING;
+
+    if !source.starts_with('@') {
+        return None;
+    }
+
+    let mut buffer = [0u16; SHELL_LINK_BUFFER_LEN];
+    unsafe {
+        SHLoadIndirectString(&HSTRING::from(source), &mut buffer, None).ok()?;
+    }
+
+    from_wide(&buffer)
+}
+
+/// The locale of the current user, in the `zh-CN` form the keys of
+/// `App::localized_app_names` use.
+pub fn user_locale() -> Option<String> {
+    use windows::Win32::Globalization::GetUserDefaultLocaleName;
+
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buffer = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(&mut buffer) };
+    if len == 0 {
+        return None;
+    }
+
+    from_wide(&buffer).and_then(|locale| normalize_locale(&locale))
+}
+
+/// Parse the `[LocalizedFileNames]` section of a `desktop.ini`, which maps
+/// file names in its folder to display names, which are usually indirect
+/// strings.
+pub fn parse_localized_file_names(content: &str) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line.eq_ignore_ascii_case("[LocalizedFileNames]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((file_name, display_name)) = line.split_once('=') {
+            names.insert(
+                file_name.trim().to_lowercase(),
+                display_name.trim().to_string(),
+            );
+        }
+    }
+
+    names
+}
+
+/// Read the `desktop.ini` of `dir`, they are usually UTF-16LE.
+pub fn read_localized_file_names(dir: &Path) -> HashMap<String, String> {
+    let Ok(bytes) = std::fs::read(dir.join("desktop.ini")) else {
+        return HashMap::new();
+    };
+    let content = match bytes.strip_prefix(&[0xFF, 0xFE]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => String::from_utf8_lossy(&bytes).into_owned(),
+    };
+
+    parse_localized_file_names(&content)
+}
+
+/// Subject of the first signer's certificate of a verified file.
+unsafe fn signer_subject(state: windows::Win32::Foundation::HANDLE) -> Option<String> {
+    use windows::Win32::Security::Cryptography::{
+        CERT_NAME_SIMPLE_DISPLAY_TYPE, CertGetNameStringW,
+    };
+    use windows::Win32::Security::WinTrust::{
+        WTHelperGetProvSignerFromChain, WTHelperProvDataFromStateData,
+    };
+
+    unsafe {
+        let provider = WTHelperProvDataFromStateData(state);
+        if provider.is_null() {
+            return None;
+        }
+        let signer = WTHelperGetProvSignerFromChain(provider, 0, false.into(), 0);
+        if signer.is_null() || (*signer).csCertChain == 0 || (*signer).pasCertChain.is_null() {
+            return None;
+        }
+        let cert = (*(*signer).pasCertChain).pCert;
+        if cert.is_null() {
+            return None;
+        }
+
+        let mut buffer = [0u16; SHELL_LINK_BUFFER_LEN];
+        CertGetNameStringW(
+            cert,
+            CERT_NAME_SIMPLE_DISPLAY_TYPE,
+            0,
+            None,
+            Some(&mut buffer),
+        );
+
+        from_wide(&buffer)
+    }
+}
+
+/// Verify the Authenticode signature embedded in `path` with
+/// `WinVerifyTrust`. Revocation is not checked, so this works offline.
+///
+/// System binaries signed through a catalog rather than an embedded
+/// signature are reported as unsigned.
+pub fn verify_signature(path: &Path) -> SignatureInfo {
+    use windows::Win32::Foundation::{HWND, TRUST_E_NOSIGNATURE, TRUST_E_SUBJECT_FORM_UNKNOWN};
+    use windows::Win32::Security::WinTrust::{
+        WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_DATA, WINTRUST_DATA_0, WINTRUST_FILE_INFO,
+        WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE, WTD_STATEACTION_VERIFY,
+        WTD_UI_NONE, WinVerifyTrust,
+    };
+    use windows::core::{HSTRING, PCWSTR};
+
+    let path = HSTRING::from(path.as_os_str());
+    let mut file_info = WINTRUST_FILE_INFO {
+        cbStruct: std::mem::size_of::<WINTRUST_FILE_INFO>() as u32,
+        pcwszFilePath: PCWSTR(path.as_ptr()),
+        ..Default::default()
+    };
+    let mut data = WINTRUST_DATA {
+        cbStruct: std::mem::size_of::<WINTRUST_DATA>() as u32,
+        dwUIChoice: WTD_UI_NONE,
+        fdwRevocationChecks: WTD_REVOKE_NONE,
+        dwUnionChoice: WTD_CHOICE_FILE,
+        Anonymous: WINTRUST_DATA_0 {
+            pFile: &mut file_info,
+        },
+        dwStateAction: WTD_STATEACTION_VERIFY,
+        ..Default::default()
+    };
+    let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+
+    unsafe {
+        let result = WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            &mut data as *mut WINTRUST_DATA as *mut _,
+        );
+        let status = match result {
+            0 => SignatureStatus::Valid,
+            code if code == TRUST_E_NOSIGNATURE.0 || code == TRUST_E_SUBJECT_FORM_UNKNOWN.0 => {
+                SignatureStatus::Unsigned
+            }
+            _ => SignatureStatus::Invalid,
+        };
+        let subject = match status {
+            SignatureStatus::Unsigned => None,
+            _ => signer_subject(data.hWVTStateData),
+        };
+
+        data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(
+            HWND::default(),
+            &mut action,
+            &mut data as *mut WINTRUST_DATA as *mut _,
+        );
+
+        SignatureInfo { status, subject }
+    }
+}
+
+/// The string values of an executable's `VERSIONINFO` resource.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub file_version: Option<String>,
+    pub product_version: Option<String>,
+    pub product_name: Option<String>,
+    pub file_description: Option<String>,
+    pub company_name: Option<String>,
+}
+
+/// Read the `VERSIONINFO` resource of `path`, using its first translation.
+pub fn read_version_info(path: &Path) -> Option<VersionInfo> {
+    use windows::Win32::Storage::FileSystem::{
+        GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW,
+    };
+    use windows::core::HSTRING;
+
+    let path = HSTRING::from(path.as_os_str());
+    unsafe {
+        let size = GetFileVersionInfoSizeW(&path, None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(&path, None, size, data.as_mut_ptr() as *mut _).ok()?;
+
+        // Pairs of language and code page
+        let mut translations = std::ptr::null_mut();
+        let mut len = 0;
+        let translation = if VerQueryValueW(
+            data.as_ptr() as *const _,
+            &HSTRING::from("\\VarFileInfo\\Translation"),
+            &mut translations,
+            &mut len,
+        )
+        .as_bool()
+            && len >= 4
+        {
+            let pair = translations as *const u16;
+            format!("{:04x}{:04x}", *pair, *pair.add(1))
+        } else {
+            // US English, Unicode
+            "040904b0".to_string()
+        };
+
+        let query = |name: &str| -> Option<String> {
+            let mut value = std::ptr::null_mut();
+            let mut len = 0;
+            let sub_block = format!("\\StringFileInfo\\{}\\{}", translation, name);
+            if !VerQueryValueW(
+                data.as_ptr() as *const _,
+                &HSTRING::from(sub_block),
+                &mut value,
+                &mut len,
+            )
+            .as_bool()
+                || len == 0
+            {
+                return None;
+            }
+            from_wide(std::slice::from_raw_parts(
+                value as *const u16,
+                len as usize,
+            ))
+        };
+
+        Some(VersionInfo {
+            file_version: query("FileVersion"),
+            product_version: query("ProductVersion"),
+            product_name: query("ProductName"),
+            file_description: query("FileDescription"),
+            company_name: query("CompanyName"),
+        })
+    }
+}
+
+/// Scoop roots, `$SCOOP` (`~/scoop` by default) and
+/// `$SCOOP_GLOBAL` (`C:\ProgramData\scoop` by default).
+pub fn scoop_roots() -> Vec<PathBuf> {
+    let user = std::env::var_os("SCOOP")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("USERPROFILE").map(|home| PathBuf::from(home).join("scoop")));
+    let global = std::env::var_os("SCOOP_GLOBAL")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("scoop")));
+
+    user.into_iter().chain(global).collect()
+}
+
+/// The executables a Scoop manifest's `bin` field lists, which is a path,
+/// or a list of paths or `[path, alias, args...]` lists.
+fn scoop_bins(bin: &serde_json::Value) -> Vec<String> {
+    match bin {
+        serde_json::Value::String(path) => vec![path.clone()],
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                serde_json::Value::String(path) => Some(path.clone()),
+                serde_json::Value::Array(parts) => parts.first()?.as_str().map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Turn an installed Scoop app, i.e., `<root>\apps\<name>\current`, into
+/// apps: one per Start Menu shortcut it declares, or one for its first
+/// executable if it only puts commands on the `PATH`.
+pub fn parse_scoop_app(name: &str, current_dir: &Path) -> Vec<App> {
+    let Ok(manifest) = std::fs::read_to_string(current_dir.join("manifest.json")) else {
+        return Vec::new();
+    };
+    let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&manifest) else {
+        return Vec::new();
+    };
+    let bucket = std::fs::read_to_string(current_dir.join("install.json"))
+        .ok()
+        .and_then(|install| serde_json::from_str::<serde_json::Value>(&install).ok())
+        .and_then(|install| install.get("bucket")?.as_str().map(str::to_string));
+    let version = manifest
+        .get("version")
+        .and_then(|version| version.as_str())
+        .map(str::to_string);
+
+    let app = |name: String, exe: PathBuf, icon: Option<PathBuf>| App {
+        name,
+        icon_path: icon.or_else(|| Some(exe.clone())),
+        app_path_exe: Some(exe),
+        app_desktop_path: current_dir.to_path_buf(),
+        version: version.clone(),
+        install_source: Some(InstallSource::Scoop {
+            bucket: bucket.clone(),
+        }),
+        scope: InstallScope::of_path(current_dir),
+        sources: vec![DiscoverySource::Scoop],
+        ..Default::default()
+    };
+
+    // [exe, name, args, icon]
+    let shortcuts: Vec<App> = manifest
+        .get("shortcuts")
+        .and_then(|shortcuts| shortcuts.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|shortcut| {
+            let shortcut = shortcut.as_array()?;
+            let exe = current_dir.join(shortcut.first()?.as_str()?);
+            let name = shortcut.get(1)?.as_str()?.to_string();
+            let icon = shortcut
+                .get(3)
+                .and_then(|icon| icon.as_str())
+                .map(|icon| current_dir.join(icon));
+            let args = shortcut
+                .get(2)
+                .and_then(|args| args.as_str())
+                .map(split_arguments)
+                .unwrap_or_default();
+
+            exe.exists().then(|| App {
+                launch_args: args,
+                ..app(name, exe, icon)
+            })
+        })
+        .collect();
+    if !shortcuts.is_empty() {
+        return shortcuts;
+    }
+
+    manifest
+        .get("bin")
+        .map(scoop_bins)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|bin| current_dir.join(bin))
+        .find(|exe| {
+            exe.exists()
+                && exe
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        .map(|exe| vec![app(name.to_string(), exe, None)])
+        .unwrap_or_default()
+}
+
+/// The apps installed by Scoop, both per-user and global ones.
+pub fn read_scoop_apps() -> Vec<App> {
+    let mut apps = Vec::new();
+    for root in scoop_roots() {
+        let Ok(entries) = std::fs::read_dir(root.join("apps")) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Scoop itself
+            if name == "scoop" {
+                continue;
+            }
+            apps.extend(parse_scoop_app(&name, &entry.path().join("current")));
+        }
+    }
+
+    apps
+}
+
+/// The text of the first `<tag>` element of a `.nuspec`.
+fn nuspec_value(content: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = content.find(&open)? + open.len();
+    let end = start + content[start..].find(&close)?;
+    let value = content[start..end].trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Turn a Chocolatey package directory, i.e., `<root>\lib\<id>`, into
+/// apps, one per executable Chocolatey shims. Packages wrapping installers
+/// contain no executables, their apps are found through the Start Menu
+/// or the registry.
+pub fn parse_chocolatey_package(package_dir: &Path) -> Vec<App> {
+    let Some(id) = package_dir.file_name().and_then(|id| id.to_str()) else {
+        return Vec::new();
+    };
+    let Ok(nuspec) = std::fs::read_to_string(package_dir.join(format!("{}.nuspec", id))) else {
+        return Vec::new();
+    };
+    let version = nuspec_value(&nuspec, "version");
+    let title = nuspec_value(&nuspec, "title");
+    let authors = nuspec_value(&nuspec, "authors");
+
+    let exes: Vec<PathBuf> = walkdir::WalkDir::new(package_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        // `foo.exe.ignore` tells Chocolatey not to shim `foo.exe`
+        .filter(|exe| !PathBuf::from(format!("{}.ignore", exe.display())).exists())
+        .collect();
+    let single = exes.len() == 1;
+
+    exes.into_iter()
+        .map(|exe| {
+            let stem = exe
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| id.to_string());
+            let name = match &title {
+                Some(title) if single => title.clone(),
+                _ => stem,
+            };
+            let dir = exe.parent().unwrap_or(package_dir).to_path_buf();
+
+            App {
+                name,
+                icon_path: Some(exe.clone()),
+                app_path_exe: Some(exe),
+                app_desktop_path: dir,
+                version: version.clone(),
+                publisher: authors.clone(),
+                install_source: Some(InstallSource::Chocolatey {
+                    package: id.to_string(),
+                }),
+                scope: InstallScope::of_path(package_dir),
+                sources: vec![DiscoverySource::Chocolatey],
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The apps installed by Chocolatey, under `$ChocolateyInstall`
+/// (`C:\ProgramData\chocolatey` by default).
+pub fn read_chocolatey_apps() -> Vec<App> {
+    let root = std::env::var_os("ChocolateyInstall")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("chocolatey"))
+        });
+    let Some(root) = root else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(root.join("lib")) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        // Chocolatey itself and its extensions
+        .filter(|entry| {
+            !entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("chocolatey")
+        })
+        .flat_map(|entry| parse_chocolatey_package(&entry.path()))
+        .collect()
+}
+
+/// A row of `winget list`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WingetPackage {
+    /// Display name, winget truncates long ones with `…`.
+    pub name: String,
+    pub id: String,
+    pub version: Option<String>,
+    /// The newer version `winget upgrade` would install.
+    pub available: Option<String>,
+}
+
+/// Parse the table `winget list` prints. Columns are located by the
+/// offsets of their headers, the header line is the one above the `---`
+/// separator, after the progress spinner winget draws with `\r`.
+pub fn parse_winget_list(output: &str) -> Vec<WingetPackage> {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or(line))
+        .collect();
+    let Some(separator) = lines
+        .iter()
+        .position(|line| line.len() > 3 && line.trim().chars().all(|c| c == '-'))
+    else {
+        return Vec::new();
+    };
+    let Some(header) = separator.checked_sub(1).map(|idx| lines[idx]) else {
+        return Vec::new();
+    };
+
+    // Offsets in chars, since names are not ASCII-only
+    let header: Vec<char> = header.chars().collect();
+    let mut columns = Vec::new();
+    for (idx, c) in header.iter().enumerate() {
+        if !c.is_whitespace() && (idx == 0 || header[idx - 1].is_whitespace()) {
+            columns.push(idx);
+        }
+    }
+    // Name, Id, Version, then Available and Source, whichever are present
+    if columns.len() < 3 {
+        return Vec::new();
+    }
+    let header_names: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, &start)| {
+            let end = columns.get(idx + 1).copied().unwrap_or(header.len());
+            header[start..end]
+                .iter()
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .collect();
+    let available_column = header_names.iter().position(|name| name == "Available");
+
+    lines[separator + 1..]
+        .iter()
+        .filter_map(|line| {
+            let line: Vec<char> = line.chars().collect();
+            let cell = |column: usize| -> Option<String> {
+                let start = *columns.get(column)?;
+                let end = columns.get(column + 1).copied().unwrap_or(line.len());
+                let value = line.get(start..end.min(line.len()))?;
+                let value = value.iter().collect::<String>().trim().to_string();
+                (!value.is_empty()).then_some(value)
+            };
+
+            Some(WingetPackage {
+                name: cell(0)?,
+                id: cell(1)?,
+                version: cell(2),
+                available: available_column.and_then(cell),
+            })
+        })
+        .collect()
+}
+
+/// Run `winget list` for the packages winget knows from its own source.
+pub fn read_winget_packages() -> crate::Result<Vec<WingetPackage>> {
+    let output = crate::process::output(
+        std::process::Command::new("winget").args([
+            "list",
+            "--source",
+            "winget",
+            "--accept-source-agreements",
+            "--disable-interactivity",
+        ]),
+        crate::process::DEFAULT_TIMEOUT,
+        None,
+    )?;
+
+    Ok(parse_winget_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Whether `name` of an app is the (possibly truncated) `package_name`.
+pub fn winget_name_matches(name: &str, package_name: &str) -> bool {
+    let name = name.to_lowercase();
+    let package_name = package_name.to_lowercase();
+    match package_name.strip_suffix('…') {
+        Some(prefix) => name.starts_with(prefix.trim_end()),
+        None => name == package_name,
+    }
+}
+
+/// An App Execution Alias, one of the 0-byte `.exe` files in
+/// `%LOCALAPPDATA%\Microsoft\WindowsApps` that start a packaged app.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ExecutionAlias {
+    /// The alias itself, e.g., `...\WindowsApps\wt.exe`.
+    pub alias_path: PathBuf,
+    pub package_family_name: String,
+    pub aumid: String,
+    /// The executable inside the package it starts.
+    pub target: PathBuf,
+}
+
+/// `IO_REPARSE_TAG_APPEXECLINK`
+const IO_REPARSE_TAG_APPEXECLINK: u32 = 0x8000_001B;
+
+/// Parse the data of an `IO_REPARSE_TAG_APPEXECLINK` reparse point: the
+/// tag, the data length, a reserved field, a version, then NUL-terminated
+/// UTF-16 strings, the package family name, the AUMID and the target.
+pub fn parse_app_exec_link(data: &[u8]) -> Option<(String, String, PathBuf)> {
+    let tag = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    if tag != IO_REPARSE_TAG_APPEXECLINK {
+        return None;
+    }
+    let units: Vec<u16> = data
+        .get(12..)?
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let mut strings = units.split(|&unit| unit == 0).map(String::from_utf16_lossy);
+
+    let package_family_name = strings.next()?;
+    let aumid = strings.next()?;
+    let target = strings.next()?;
+    if aumid.is_empty() || target.is_empty() {
+        return None;
+    }
+
+    Some((package_family_name, aumid, PathBuf::from(target)))
+}
+
+/// Read the reparse point data of `path` without following it.
+fn read_reparse_point(path: &Path) -> Option<Vec<u8>> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_SHARE_DELETE,
+        FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+    use windows::Win32::System::Ioctl::FSCTL_GET_REPARSE_POINT;
+    use windows::core::HSTRING;
+
+    // MAXIMUM_REPARSE_DATA_BUFFER_SIZE
+    let mut buffer = vec![0u8; 16 * 1024];
+    let mut returned = 0u32;
+    unsafe {
+        let handle = CreateFileW(
+            &HSTRING::from(path.as_os_str()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_OPEN_REPARSE_POINT | FILE_FLAG_BACKUP_SEMANTICS,
+            None,
+        )
+        .ok()?;
+        let result = DeviceIoControl(
+            handle,
+            FSCTL_GET_REPARSE_POINT,
+            None,
+            0,
+            Some(buffer.as_mut_ptr() as *mut _),
+            buffer.len() as u32,
+            Some(&mut returned),
+            None,
+        );
+        let _ = CloseHandle(handle);
+        result.ok()?;
+    }
+    buffer.truncate(returned as usize);
+
+    Some(buffer)
+}
+
+/// Read the App Execution Aliases of the current user.
+pub fn read_execution_aliases() -> Vec<ExecutionAlias> {
+    let Some(dir) = std::env::var_os("LOCALAPPDATA")
+        .map(|dir| PathBuf::from(dir).join("Microsoft").join("WindowsApps"))
+    else {
+        return Vec::new();
+    };
+
+    // Aliases also live in per-package subdirectories, these are the
+    // ones on the PATH
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        .filter_map(|alias_path| {
+            let data = read_reparse_point(&alias_path)?;
+            let (package_family_name, aumid, target) = parse_app_exec_link(&data)?;
+
+            Some(ExecutionAlias {
+                alias_path,
+                package_family_name,
+                aumid,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// The value of `key` in `[section]` of an INI file, both matched
+/// case-insensitively.
+fn ini_value(content: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim().eq_ignore_ascii_case(section);
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case(key) {
+            let value = value.trim();
+            return (!value.is_empty()).then(|| value.to_string());
+        }
+    }
+
+    None
+}
+
+/// Icons in `App\AppInfo` of a PortableApps.com app, largest first.
+const PORTABLE_APP_ICONS: &[&str] = &[
+    "appicon_256.png",
+    "appicon_128.png",
+    "appicon_75.png",
+    "appicon_32.png",
+    "appicon.ico",
+];
+
+fn portable_app(name: String, exe: PathBuf, icon_path: Option<PathBuf>) -> App {
+    App {
+        name,
+        icon_path: icon_path.or_else(|| Some(exe.clone())),
+        app_desktop_path: exe.parent().map(Path::to_path_buf).unwrap_or_default(),
+        app_path_exe: Some(exe),
+        install_source: Some(InstallSource::Portable),
+        // Wherever they are, no admin rights are needed to modify them
+        scope: InstallScope::User,
+        sources: vec![DiscoverySource::Portable],
+        ..Default::default()
+    }
+}
+
+/// Parse an app in the PortableApps.com format, a directory with an
+/// `App\AppInfo\appinfo.ini` that names the launcher to start.
+pub fn parse_portable_app(app_dir: &Path) -> Option<App> {
+    let app_info_dir = app_dir.join("App").join("AppInfo");
+    let appinfo = std::fs::read_to_string(app_info_dir.join("appinfo.ini")).ok()?;
+    let exe = app_dir.join(ini_value(&appinfo, "Control", "Start")?);
+    if !exe.is_file() {
+        return None;
+    }
+    let name = ini_value(&appinfo, "Details", "Name")
+        .or_else(|| Some(app_dir.file_name()?.to_string_lossy().to_string()))?;
+    let icon_path = PORTABLE_APP_ICONS
+        .iter()
+        .map(|icon| app_info_dir.join(icon))
+        .find(|icon| icon.is_file());
+
+    Some(App {
+        version: ini_value(&appinfo, "Version", "DisplayVersion")
+            .or_else(|| ini_value(&appinfo, "Version", "PackageVersion")),
+        publisher: ini_value(&appinfo, "Details", "Publisher"),
+        ..portable_app(name, exe, icon_path)
+    })
+}
+
+/// Lowercase letters and digits of `name`, to compare folder and file
+/// names like "Foo Bar" and "foo-bar".
+fn normalize_portable_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// The executable of a folder an app was unpacked to: the one named like
+/// the folder, e.g., `Foo\foo.exe` or `Foo-1.2\Foo.exe`, or the only one.
+pub fn find_portable_exe(dir: &Path) -> Option<PathBuf> {
+    let exes: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+        })
+        .collect();
+    let dir_name = normalize_portable_name(&dir.file_name()?.to_string_lossy());
+    let named_like_dir = exes.iter().find(|exe| {
+        exe.file_stem()
+            .map(|stem| normalize_portable_name(&stem.to_string_lossy()))
+            .is_some_and(|stem| !stem.is_empty() && dir_name.starts_with(&stem))
+    });
+
+    match named_like_dir {
+        Some(exe) => Some(exe.clone()),
+        None if exes.len() == 1 => exes.into_iter().next(),
+        None => None,
+    }
+}
+
+/// Find the portable apps under `root`, e.g., a USB stick: apps in the
+/// PortableApps.com format, either in `root\PortableApps` or right in
+/// `root`, and folders that hold the executable of an app.
+pub fn read_portable_apps(root: &Path) -> Vec<App> {
+    let portable_apps_dir = root.join("PortableApps");
+    let mut apps = Vec::new();
+    for dir in [root, portable_apps_dir.as_path()] {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if !path.is_dir() || path == portable_apps_dir {
+                continue;
+            }
+            if let Some(app) = parse_portable_app(&path) {
+                apps.push(app);
+            } else if let Some(exe) = find_portable_exe(&path) {
+                let name = exe
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                apps.push(portable_app(name, exe, None));
+            }
+        }
+    }
+
+    apps
+}
+
+/// Root directories of the removable drives, e.g., `E:\`.
+pub fn read_removable_drives() -> Vec<PathBuf> {
+    use windows::Win32::Storage::FileSystem::{GetDriveTypeW, GetLogicalDrives};
+    use windows::core::HSTRING;
+
+    // `DRIVE_REMOVABLE` of `GetDriveTypeW()`
+    const DRIVE_REMOVABLE: u32 = 2;
+
+    let drives = unsafe { GetLogicalDrives() };
+    (0..26u8)
+        .filter(|idx| drives & (1 << idx) != 0)
+        .map(|idx| PathBuf::from(format!("{}:\\", (b'A' + idx) as char)))
+        .filter(
+            |root| unsafe { GetDriveTypeW(&HSTRING::from(root.as_os_str())) } == DRIVE_REMOVABLE,
+        )
+        .collect()
+}
+
+/// What an Internet Shortcut (`.url` file) points to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InternetShortcut {
+    pub url: String,
+    /// Usually the site's favicon, cached by the browser that created the
+    /// shortcut.
+    pub icon_file: Option<PathBuf>,
+    pub icon_index: Option<i32>,
+}
+
+/// Parse the `[InternetShortcut]` section of a `.url` file.
+pub fn parse_internet_shortcut(content: &str) -> Option<InternetShortcut> {
+    let mut in_section = false;
+    let mut url = None;
+    let mut icon_file = None;
+    let mut icon_index = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line.eq_ignore_ascii_case("[InternetShortcut]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "URL" if !value.is_empty() => url = Some(value.to_string()),
+            "IconFile" if !value.is_empty() => icon_file = Some(PathBuf::from(value)),
+            "IconIndex" => icon_index = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Some(InternetShortcut {
+        url: url?,
+        icon_file,
+        icon_index,
+    })
+}
+
+/// An item of `shell:AppsFolder`, the list the Start menu's "All apps"
+/// shows.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AppsFolderItem {
+    pub display_name: String,
+    /// The AppUserModelID of packaged apps and of shortcuts that set one,
+    /// the path to the target otherwise, which might start with a known
+    /// folder GUID like `{6D809377-6AF0-444B-8957-A3773F02200E}\Foo\foo.exe`.
+    pub parsing_name: String,
+}
+
+/// Read a string the Shell allocated and free it.
+unsafe fn take_co_task_string(string: windows::core::PWSTR) -> Option<String> {
+    use windows::Win32::System::Com::CoTaskMemFree;
+
+    unsafe {
+        let value = string.to_string().ok();
+        CoTaskMemFree(Some(string.0 as *const _));
+
+        value.filter(|value| !value.is_empty())
+    }
+}
+
+/// Enumerate `shell:AppsFolder` with `IShellItem`, which covers Win32
+/// apps, packaged apps and the apps shortcuts point to just like the
+/// Start menu does.
+pub fn read_apps_folder() -> crate::Result<Vec<AppsFolderItem>> {
+    use windows::Win32::System::Com::IBindCtx;
+    use windows::Win32::UI::Shell::{
+        BHID_EnumItems, FOLDERID_AppsFolder, IEnumShellItems, IShellItem, KF_FLAG_DEFAULT,
+        SHGetKnownFolderItem, SIGDN_NORMALDISPLAY, SIGDN_PARSINGNAME,
+    };
+
+    let mut items = Vec::new();
+    unsafe {
+        let _com = ComGuard::new();
+        let folder: IShellItem = SHGetKnownFolderItem(&FOLDERID_AppsFolder, KF_FLAG_DEFAULT, None)?;
+        let children: IEnumShellItems = folder.BindToHandler(None::<&IBindCtx>, &BHID_EnumItems)?;
+
+        loop {
+            let mut batch = [None];
+            let mut fetched = 0;
+            if children.Next(&mut batch, Some(&mut fetched)).is_err() || fetched == 0 {
+                break;
+            }
+            let Some(child) = batch[0].take() else {
+                break;
+            };
+            let display_name = child
+                .GetDisplayName(SIGDN_NORMALDISPLAY)
+                .ok()
+                .and_then(|name| take_co_task_string(name));
+            let parsing_name = child
+                .GetDisplayName(SIGDN_PARSINGNAME)
+                .ok()
+                .and_then(|name| take_co_task_string(name));
+            if let (Some(display_name), Some(parsing_name)) = (display_name, parsing_name) {
+                items.push(AppsFolderItem {
+                    display_name,
+                    parsing_name,
+                });
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Resolve a parsing name that starts with a known folder GUID to a path.
+/// 32-bit processes, e.g., x86 ones on ARM64, cannot get the 64-bit
+/// Program Files folders, they get them from the environment.
+pub fn resolve_known_folder_path(parsing_name: &str) -> Option<PathBuf> {
+    use windows::Win32::UI::Shell::{
+        FOLDERID_ProgramFilesCommonX64, FOLDERID_ProgramFilesX64, KF_FLAG_DEFAULT,
+        SHGetKnownFolderPath,
+    };
+    use windows::core::GUID;
+
+    let rest = parsing_name.strip_prefix('{')?;
+    let (guid, relative) = rest.split_once('}')?;
+    let guid = GUID::try_from(guid).ok()?;
+    let folder = unsafe {
+        match SHGetKnownFolderPath(&guid, KF_FLAG_DEFAULT, None) {
+            Ok(folder) => take_co_task_string(folder)?,
+            Err(_) if guid == FOLDERID_ProgramFilesX64 => std::env::var("ProgramW6432").ok()?,
+            Err(_) if guid == FOLDERID_ProgramFilesCommonX64 => {
+                std::env::var("CommonProgramW6432").ok()?
+            }
+            Err(_) => return None,
+        }
+    };
+
+    Some(PathBuf::from(folder).join(relative.trim_start_matches('\\')))
+}
+
+/// Resolve a `shell:` path, e.g., `shell:Programs\App.lnk` or
+/// `shell:{A77F5D77-2E2B-44C3-A6A2-ABA601054A51}`, to the known folder by
+/// its canonical name or GUID.
+pub fn resolve_shell_path(path: &str) -> Option<PathBuf> {
+    use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance};
+    use windows::Win32::UI::Shell::{IKnownFolderManager, KF_FLAG_DEFAULT, KnownFolderManager};
+    use windows::core::HSTRING;
+
+    let prefix = path.get(..6)?;
+    if !prefix.eq_ignore_ascii_case("shell:") {
+        return None;
+    }
+    let rest = &path[6..];
+    if rest.starts_with('{') {
+        return resolve_known_folder_path(rest);
+    }
+
+    let (name, relative) = rest.split_once('\\').unwrap_or((rest, ""));
+    let folder = unsafe {
+        let _com = ComGuard::new();
+        let manager: IKnownFolderManager =
+            CoCreateInstance(&KnownFolderManager, None, CLSCTX_INPROC_SERVER).ok()?;
+        let folder = manager.GetFolderByName(&HSTRING::from(name)).ok()?;
+        take_co_task_string(folder.GetPath(KF_FLAG_DEFAULT.0 as u32).ok()?)?
+    };
+
+    let folder = PathBuf::from(folder);
+    if relative.is_empty() {
+        Some(folder)
+    } else {
+        Some(folder.join(relative))
+    }
+}
+
+/// Expand the `%VARIABLE%`s anywhere in `value`, leaving unknown ones as
+/// they are.
+pub fn expand_environment_strings(value: &str) -> String {
+    use windows::Win32::System::Environment::ExpandEnvironmentStringsW;
+    use windows::core::HSTRING;
+
+    let source = HSTRING::from(value);
+    unsafe {
+        // The size includes the terminating null
+        let len = ExpandEnvironmentStringsW(&source, None);
+        if len == 0 {
+            return value.to_string();
+        }
+        let mut buffer = vec![0u16; len as usize];
+        let written = ExpandEnvironmentStringsW(&source, Some(&mut buffer));
+        if written == 0 || written > len {
+            return value.to_string();
+        }
+        from_wide(&buffer).unwrap_or_default()
+    }
+}
+
+/// Render the icon of a `shell:AppsFolder` item with
+/// `IShellItemImageFactory`.
+#[cfg(feature = "icons")]
+pub fn load_apps_folder_icon(parsing_name: &str, size: u32) -> crate::Result<Icon> {
+    use windows::Win32::Foundation::SIZE;
+    use windows::Win32::Graphics::Gdi::DeleteObject;
+    use windows::Win32::System::Com::IBindCtx;
+    use windows::Win32::UI::Shell::{
+        IShellItemImageFactory, SHCreateItemFromParsingName, SIIGBF_ICONONLY,
+    };
+    use windows::core::HSTRING;
+
+    unsafe {
+        let _com = ComGuard::new();
+        let factory: IShellItemImageFactory = SHCreateItemFromParsingName(
+            &HSTRING::from(format!("shell:AppsFolder\\{}", parsing_name)),
+            None::<&IBindCtx>,
+        )?;
+        let bitmap = factory.GetImage(
+            SIZE {
+                cx: size as i32,
+                cy: size as i32,
+            },
+            SIIGBF_ICONONLY,
+        )?;
+        let result = bitmap_to_rgba(bitmap);
+        let _ = DeleteObject(bitmap.into());
+
+        result
+    }
+}
+
+/// Split a command line at whitespace, keeping quoted parts together.
+pub fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in arguments.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+/// Join arguments into a command line `split_arguments()` splits back,
+/// quoting those with whitespace.
+pub fn join_arguments(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg)
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Start `exe` with the `runas` verb, which asks for elevation first.
+pub fn run_as_admin(exe: &Path, args: &[String], working_dir: &Path) -> crate::Result<()> {
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+    use windows::core::{HSTRING, w};
+
+    let directory = working_dir
+        .is_dir()
+        .then(|| HSTRING::from(working_dir.as_os_str()))
+        .unwrap_or_default();
+    let result = unsafe {
+        ShellExecuteW(
+            None,
+            w!("runas"),
+            &HSTRING::from(exe.as_os_str()),
+            &HSTRING::from(join_arguments(args)),
+            &directory,
+            SW_SHOWNORMAL,
+        )
+    };
+    // Values up to 32 are errors, e.g., when the UAC prompt was declined
+    if result.0 as isize <= 32 {
+        return Err(crate::Error::Platform(
+            format!(
+                "failed to start [{}] as administrator: {}",
+                exe.display(),
+                result.0 as isize
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parse the arguments of a WSLg shortcut, `-d Ubuntu --cd "~" -- gedit`,
+/// or `~ -d Ubuntu gedit` for older WSL versions, into the distribution
+/// and the command.
+pub fn parse_wslg_arguments(arguments: &str) -> Option<(String, String)> {
+    let args = split_arguments(arguments);
+    let mut distro = None;
+    let mut command = Vec::new();
+    let mut idx = 0;
+    while idx < args.len() {
+        match args[idx].as_str() {
+            "-d" | "--distribution" => {
+                distro = args.get(idx + 1).cloned();
+                idx += 2;
+            }
+            "--cd" => idx += 2,
+            "~" => idx += 1,
+            "--" => {
+                command.extend_from_slice(&args[idx + 1..]);
+                break;
+            }
+            arg if arg.starts_with('-') => idx += 1,
+            _ => {
+                command.extend_from_slice(&args[idx..]);
+                break;
+            }
+        }
+    }
+    if command.is_empty() {
+        return None;
+    }
+
+    Some((distro?, command.join(" ")))
+}
+
+/// Whether the `Exec` of a desktop entry runs `program`, compared by file
+/// name since WSLg shortcuts do not always use absolute paths.
+pub fn desktop_entry_runs(content: &str, program: &str) -> bool {
+    let file_name = |path: &str| path.rsplit('/').next().unwrap_or(path).to_string();
+    let program = file_name(program);
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Exec="))
+        .filter_map(|exec| split_arguments(exec).into_iter().next())
+        .any(|exec_program| file_name(&exec_program) == program)
+}
+
+/// Find the desktop entry inside `distro` that WSLg created a shortcut for,
+/// through the distribution's `\\wsl.localhost` share. This needs the
+/// distribution to be running.
+pub fn find_wsl_desktop_entry(distro: &str, command: &str) -> Option<PathBuf> {
+    let program = command.split_whitespace().next()?;
+    let dir = PathBuf::from(format!(
+        "\\\\wsl.localhost\\{}\\usr\\share\\applications",
+        distro
+    ));
+
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .find(|path| {
+            std::fs::read_to_string(path).is_ok_and(|content| desktop_entry_runs(&content, program))
+        })
+}
+
+/// The icon WSLg converted from the Linux app's, which it keeps in
+/// `%LOCALAPPDATA%\Temp\WSLDVCPlugin\<distro>`.
+pub fn find_wslg_icon(distro: &str, name: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("LOCALAPPDATA")?)
+        .join("Temp")
+        .join("WSLDVCPlugin")
+        .join(distro);
+    let icon = dir.join(format!("{}.ico", name));
+
+    icon.exists().then_some(icon)
+}
+
+/// Order the shortcuts pinned to the taskbar by where their file names
+/// appear in `favorites`, the `Favorites` value of the `Taskband` key,
+/// which lists the pins in the order they are shown. Shortcuts it does not
+/// name go last, by name.
+pub fn order_taskbar_pins(mut links: Vec<PathBuf>, favorites: &[u8]) -> Vec<PathBuf> {
+    let position = |link: &PathBuf| -> Option<usize> {
+        let file_name = link.file_name()?.to_string_lossy();
+        let needle: Vec<u8> = file_name
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        favorites
+            .windows(needle.len())
+            .position(|window| window == needle.as_slice())
+    };
+    links.sort_by_cached_key(|link| (position(link).unwrap_or(usize::MAX), link.clone()));
+
+    links
+}
+
+/// The shortcuts of the apps pinned to the taskbar, in their order on the
+/// taskbar.
+pub fn read_taskbar_pins() -> Vec<PathBuf> {
+    let Some(app_data) = std::env::var_os("APPDATA") else {
+        return Vec::new();
+    };
+    let dir = PathBuf::from(app_data)
+        .join("Microsoft\\Internet Explorer\\Quick Launch\\User Pinned\\TaskBar");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let links: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"))
+        })
+        .collect();
+
+    let favorites = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Taskband")
+        .and_then(|key| key.get_raw_value("Favorites"))
+        .map(|value| value.bytes.to_vec())
+        .unwrap_or_default();
+
+    order_taskbar_pins(links, &favorites)
+}
+
+/// Package family name of the Windows Subsystem for Android.
+const WSA_PACKAGE_FAMILY: &str = "MicrosoftCorporationII.WindowsSubsystemForAndroid_8wekyb3d8bbwe";
+
+/// The Android package a shortcut of the Windows Subsystem for Android
+/// starts, `/launch wsa://com.example.app` => `com.example.app`.
+pub fn parse_wsa_arguments(arguments: &str) -> Option<String> {
+    split_arguments(arguments)
+        .iter()
+        .find_map(|arg| arg.strip_prefix("wsa://"))
+        .map(|package| package.trim_end_matches('/').to_string())
+        .filter(|package| !package.is_empty())
+}
+
+/// The icon WSA extracted from the Android app, which it keeps in the
+/// `LocalState` of its package.
+pub fn find_wsa_icon(package: &str) -> Option<PathBuf> {
+    let icon = PathBuf::from(std::env::var_os("LOCALAPPDATA")?)
+        .join("Packages")
+        .join(WSA_PACKAGE_FAMILY)
+        .join("LocalState")
+        .join(format!("{}.ico", package));
+
+    icon.exists().then_some(icon)
+}
+
+/// A process, as far as we can tell without elevated rights.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// `None` for processes of other users or protected ones.
+    pub exe: Option<PathBuf>,
+    /// Set for processes of packaged apps.
+    pub aumid: Option<String>,
+}
+
+/// Open a process just to query information about it.
+unsafe fn open_process(pid: u32) -> Option<windows::Win32::Foundation::HANDLE> {
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok() }
+}
+
+/// The full path of the executable of process `pid`.
+pub fn process_exe(pid: u32) -> Option<PathBuf> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{PROCESS_NAME_WIN32, QueryFullProcessImageNameW};
+    use windows::core::PWSTR;
+
+    let mut buffer = [0u16; SHELL_LINK_BUFFER_LEN];
+    let mut len = buffer.len() as u32;
+    unsafe {
+        let handle = open_process(pid)?;
+        let result = QueryFullProcessImageNameW(
+            handle,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(handle);
+        result.ok()?;
+    }
+
+    from_wide(&buffer[..len as usize]).map(PathBuf::from)
+}
+
+/// The AppUserModelID of process `pid` if it belongs to a packaged app.
+pub fn process_aumid(pid: u32) -> Option<String> {
+    use windows::Win32::Foundation::{CloseHandle, ERROR_SUCCESS};
+    use windows::Win32::Storage::Packaging::Appx::GetApplicationUserModelId;
+    use windows::core::PWSTR;
+
+    // APPLICATION_USER_MODEL_ID_MAX_LENGTH
+    let mut buffer = [0u16; 130];
+    let mut len = buffer.len() as u32;
+    unsafe {
+        let handle = open_process(pid)?;
+        let result = GetApplicationUserModelId(handle, &mut len, PWSTR(buffer.as_mut_ptr()));
+        let _ = CloseHandle(handle);
+        if result != ERROR_SUCCESS {
+            return None;
+        }
+    }
+
+    from_wide(&buffer)
+}
+
+/// List all processes through a Toolhelp snapshot.
+pub fn read_processes() -> crate::Result<Vec<ProcessInfo>> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut pids = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)?;
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut next = Process32FirstW(snapshot, &mut entry);
+        while next.is_ok() {
+            pids.push(entry.th32ProcessID);
+            next = Process32NextW(snapshot, &mut entry);
+        }
+        let _ = CloseHandle(snapshot);
+    }
+
+    Ok(pids
+        .into_iter()
+        // The System Idle Process
+        .filter(|&pid| pid != 0)
+        .map(|pid| ProcessInfo {
+            pid,
+            exe: process_exe(pid),
+            aumid: process_aumid(pid),
+        })
+        .collect())
+}
+
+/// A visible top-level window.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    /// The process that owns the window. For UWP apps this is the app's
+    /// process rather than `ApplicationFrameHost.exe`, which draws their
+    /// frames.
+    pub pid: u32,
+    pub title: String,
+}
+
+/// The process that owns `hwnd`, looking through the frames
+/// `ApplicationFrameHost.exe` draws around UWP apps to the app's
+/// `CoreWindow` inside.
+pub unsafe fn window_process(hwnd: windows::Win32::Foundation::HWND) -> u32 {
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{EnumChildWindows, GetWindowThreadProcessId};
+
+    unsafe extern "system" fn find_other_process(child: HWND, lparam: LPARAM) -> BOOL {
+        unsafe {
+            let (frame_pid, found) = &mut *(lparam.0 as *mut (u32, u32));
+            let mut pid = 0;
+            GetWindowThreadProcessId(child, Some(&mut pid));
+            if pid != *frame_pid {
+                *found = pid;
+                return false.into();
+            }
+            true.into()
+        }
+    }
+
+    unsafe {
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        let is_frame_host = process_exe(pid)
+            .and_then(|exe| exe.file_name().map(|name| name.to_ascii_lowercase()))
+            .is_some_and(|name| name == "applicationframehost.exe");
+        if !is_frame_host {
+            return pid;
+        }
+
+        let mut state = (pid, 0u32);
+        let _ = EnumChildWindows(
+            Some(hwnd),
+            Some(find_other_process),
+            LPARAM(&mut state as *mut (u32, u32) as isize),
+        );
+        if state.1 != 0 { state.1 } else { pid }
+    }
+}
+
+/// The title of `hwnd`.
+pub unsafe fn window_title(hwnd: windows::Win32::Foundation::HWND) -> Option<String> {
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowTextW;
+
+    let mut buffer = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+
+    from_wide(&buffer[..len.max(0) as usize])
+}
+
+/// List the visible, unowned top-level windows with a title, which are
+/// the ones the taskbar shows.
+pub fn read_windows() -> Vec<WindowInfo> {
+    use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GW_OWNER, GetWindow, IsWindowVisible,
+    };
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        unsafe {
+            let windows = &mut *(lparam.0 as *mut Vec<WindowInfo>);
+            let is_owned = GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.is_invalid());
+            if IsWindowVisible(hwnd).as_bool()
+                && !is_owned
+                && let Some(title) = window_title(hwnd)
+            {
+                windows.push(WindowInfo {
+                    pid: window_process(hwnd),
+                    title,
+                });
+            }
+            true.into()
+        }
+    }
+
+    let mut windows: Vec<WindowInfo> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(
+            Some(collect),
+            LPARAM(&mut windows as *mut Vec<WindowInfo> as isize),
+        );
+    }
+
+    windows
+}
+
+/// The process of the foreground window, `None` if no window has the
+/// focus, e.g., while the desktop is locked.
+pub fn foreground_process() -> Option<ProcessInfo> {
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    let pid = unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_invalid() {
+            return None;
+        }
+        window_process(hwnd)
+    };
+
+    Some(ProcessInfo {
+        pid,
+        exe: process_exe(pid),
+        aumid: process_aumid(pid),
+    })
+}
+
+/// What a ProgID, e.g., `ChromeHTML` or `MSEdgeHTM`, of a file or URL
+/// association opens with.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProgIdHandler {
+    /// The AppUserModelID of the packaged app that registered it.
+    pub aumid: Option<String>,
+    /// The program of its `shell\open\command`.
+    pub exe: Option<PathBuf>,
+}
+
+/// The ProgID the user picked for the URL `scheme`, e.g., `https`, in the
+/// `UserChoice` key of its association.
+pub fn read_url_user_choice(scheme: &str) -> Option<String> {
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey(format!(
+            "Software\\Microsoft\\Windows\\Shell\\Associations\\UrlAssociations\\{scheme}\\UserChoice"
+        ))
+        .ok()?;
+
+    read_string(&key, "ProgId")
+}
+
+/// What `prog_id` opens with, from its key in `HKEY_CLASSES_ROOT`.
+pub fn read_prog_id(prog_id: &str) -> Option<ProgIdHandler> {
+    use winreg::enums::HKEY_CLASSES_ROOT;
+
+    let key = RegKey::predef(HKEY_CLASSES_ROOT)
+        .open_subkey(prog_id)
+        .ok()?;
+    let aumid = key
+        .open_subkey("Application")
+        .ok()
+        .and_then(|application| read_string(&application, "AppUserModelID"));
+    let exe = key
+        .open_subkey("shell\\open\\command")
+        .ok()
+        .and_then(|command| read_string(&command, ""))
+        .and_then(|command| command_program(&command));
+
+    Some(ProgIdHandler { aumid, exe })
+}
+
+/// The program a command line of the registry runs, with its environment
+/// variables expanded, `"%ProgramFiles%\Foo\foo.exe" -url "%1"` =>
+/// `C:\Program Files\Foo\foo.exe`.
+pub fn command_program(command: &str) -> Option<PathBuf> {
+    let program = split_arguments(command).into_iter().next()?;
+
+    Some(PathBuf::from(expand_environment_strings(&program)))
+}
+
+/// The CLSIDs of `DelegationTerminal` that are not Windows Terminal: the
+/// console host, and letting Windows decide.
+const CONSOLE_HOST_TERMINALS: [&str; 2] = [
+    "{B23D10C0-E52E-411E-9D5B-C09FDF709C7D}",
+    "{00000000-0000-0000-0000-000000000000}",
+];
+
+/// Whether Windows Terminal, rather than the console host, is the
+/// default terminal application, in `HKEY_CURRENT_USER\Console\%%Startup`.
+pub fn windows_terminal_is_default() -> bool {
+    RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Console\\%%Startup")
+        .ok()
+        .and_then(|startup| read_string(&startup, "DelegationTerminal"))
+        .is_some_and(|clsid| {
+            !CONSOLE_HOST_TERMINALS
+                .iter()
+                .any(|console_host| console_host.eq_ignore_ascii_case(&clsid))
+        })
+}
+
+/// What files with `extension`, e.g., `.txt`, are registered to open with,
+/// besides the `UserChoice` of the user, in the order Explorer offers them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OpenWith {
+    /// The ProgID of the extension, and those of its `OpenWithProgids`.
+    pub prog_ids: Vec<String>,
+    /// The file names of the executables of its `OpenWithList`s, e.g.,
+    /// `notepad.exe`, lowercase.
+    pub exe_names: Vec<String>,
+}
+
+/// The `OpenWithProgids` and `OpenWithList` of `extension`, in the
+/// `FileExts` key of Explorer, where it remembers what the user opened the
+/// type with, and in `HKEY_CLASSES_ROOT\<extension>`.
+pub fn read_open_with(extension: &str) -> OpenWith {
+    use winreg::enums::HKEY_CLASSES_ROOT;
+
+    let explorer = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey(format!(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\FileExts\\{extension}"
+        ))
+        .ok();
+    let classes = RegKey::predef(HKEY_CLASSES_ROOT)
+        .open_subkey(extension)
+        .ok();
+
+    let mut open_with = OpenWith::default();
+    open_with
+        .prog_ids
+        .extend(classes.as_ref().and_then(|key| read_string(key, "")));
+    for key in explorer.iter().chain(&classes) {
+        if let Ok(prog_ids) = key.open_subkey("OpenWithProgids") {
+            open_with.prog_ids.extend(
+                prog_ids
+                    .enum_values()
+                    .filter_map(|value| Some(value.ok()?.0)),
+            );
+        }
+    }
+    // Explorer keeps the executables as values `a`, `b`, ..., the most
+    // recently used first in `MRUList`, the classes as subkeys.
+    if let Some(list) = explorer
+        .as_ref()
+        .and_then(|key| key.open_subkey("OpenWithList").ok())
+    {
+        let order = read_string(&list, "MRUList").unwrap_or_default();
+        open_with.exe_names.extend(
+            order
+                .chars()
+                .filter_map(|name| read_string(&list, &name.to_string())),
+        );
+    }
+    if let Some(list) = classes
+        .as_ref()
+        .and_then(|key| key.open_subkey("OpenWithList").ok())
+    {
+        open_with
+            .exe_names
+            .extend(list.enum_keys().filter_map(|name| name.ok()));
+    }
+
+    for exe_name in &mut open_with.exe_names {
+        *exe_name = exe_name.to_lowercase();
+    }
+    let mut seen = HashSet::new();
+    open_with
+        .prog_ids
+        .retain(|prog_id| seen.insert(prog_id.to_lowercase()));
+    let mut seen = HashSet::new();
+    open_with
+        .exe_names
+        .retain(|exe_name| seen.insert(exe_name.clone()));
+
+    open_with
+}
+
+/// The ProgID the user picked for files with `extension`, e.g., `.txt`, in
+/// the `FileExts` key of Explorer.
+pub fn read_file_user_choice(extension: &str) -> Option<String> {
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey(format!(
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\FileExts\\{extension}\\UserChoice"
+        ))
+        .ok()?;
+
+    read_string(&key, "ProgId")
+}
+
+/// The extensions the executable `exe_name`, e.g., `notepad.exe`, declares
+/// in the `SupportedTypes` of `HKEY_CLASSES_ROOT\Applications`, lowercase.
+pub fn read_supported_types(exe_name: &str) -> Vec<String> {
+    use winreg::enums::HKEY_CLASSES_ROOT;
+
+    RegKey::predef(HKEY_CLASSES_ROOT)
+        .open_subkey(format!("Applications\\{exe_name}\\SupportedTypes"))
+        .map(|types| {
+            types
+                .enum_values()
+                .filter_map(|value| Some(value.ok()?.0.to_lowercase()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The extension of `mime_type`, e.g., `.png`, in the MIME database of
+/// `HKEY_CLASSES_ROOT`.
+pub fn extension_of_mime_type(mime_type: &str) -> Option<String> {
+    use winreg::enums::HKEY_CLASSES_ROOT;
+
+    let key = RegKey::predef(HKEY_CLASSES_ROOT)
+        .open_subkey(format!("MIME\\Database\\Content Type\\{mime_type}"))
+        .ok()?;
+
+    read_string(&key, "Extension")
+}
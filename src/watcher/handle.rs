@@ -0,0 +1,306 @@
+use super::queue::{ChangeSender, SendCloser, queue};
+use super::{Backend, ChangeReceiver, Closer, QueuePolicy, Watcher, closed_error};
+use crate::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::time::{Duration, Instant};
+
+/// How long the thread waits before watching again after `recv` failed,
+/// doubled after each failure in a row, up to `MAX_RECV_RETRY_DELAY`.
+const RECV_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECV_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// What a [`WatcherHandle`] asks the watcher thread to do.
+enum Command {
+    Watch(PathBuf, Sender<Result<()>>),
+    Unwatch(PathBuf, Sender<Result<()>>),
+    WatchDefaults(Sender<Result<()>>),
+}
+
+/// Controls a [`Watcher`] that runs on a background thread, which sends the
+/// changes it sees to the [`ChangeReceiver`] returned by
+/// [`WatcherHandle::spawn`].
+///
+/// Unlike `Watcher`, whose `recv()` needs exclusive access, the handle can
+/// be shared between threads, e.g., so that one adds search paths while
+/// another waits for changes.
+pub struct WatcherHandle {
+    commands: Sender<Command>,
+    closer: Closer,
+    /// Wakes the thread up if it waits for the consumer, see
+    /// [`QueuePolicy::Block`].
+    send_closer: SendCloser,
+    shut_down: Arc<AtomicBool>,
+}
+
+impl WatcherHandle {
+    /// Start watching `search_paths` on a background thread. The thread
+    /// exits once the handle is shut down or dropped, or the receiver is
+    /// dropped.
+    pub fn spawn<P: AsRef<Path>>(
+        search_paths: &[P],
+        backend: Backend,
+    ) -> Result<(Self, ChangeReceiver)> {
+        Self::spawn_with_queue(search_paths, backend, QueuePolicy::default())
+    }
+
+    /// Like [`WatcherHandle::spawn`], with `queue_policy` for the changes the
+    /// consumer has not taken yet.
+    pub fn spawn_with_queue<P: AsRef<Path>>(
+        search_paths: &[P],
+        backend: Backend,
+        queue_policy: QueuePolicy,
+    ) -> Result<(Self, ChangeReceiver)> {
+        let search_paths: Vec<PathBuf> = search_paths
+            .iter()
+            .map(|search_path| search_path.as_ref().to_path_buf())
+            .collect();
+        let (commands_tx, commands_rx) = channel();
+        let (changes_tx, changes_rx) = queue(queue_policy);
+        let send_closer = changes_tx.closer();
+        let (ready_tx, ready_rx) = channel();
+        let shut_down = Arc::new(AtomicBool::new(false));
+        let thread_shut_down = Arc::clone(&shut_down);
+
+        // Watchers are not necessarily `Send`, so ours is created on the
+        // thread
+        std::thread::spawn(move || {
+            let mut watcher = match Watcher::with_backend(&search_paths, backend) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(watcher.closer()));
+            run(&mut watcher, &commands_rx, &changes_tx, &thread_shut_down);
+        });
+        let closer = ready_rx.recv().map_err(|_| closed_error())??;
+
+        let handle = Self {
+            commands: commands_tx,
+            closer,
+            send_closer,
+            shut_down,
+        };
+        Ok((handle, changes_rx))
+    }
+
+    pub fn watch<P: AsRef<Path>>(&self, search_path: P) -> Result<()> {
+        self.command(|reply| Command::Watch(search_path.as_ref().to_path_buf(), reply))
+    }
+
+    /// See [`Watcher::watch_defaults`].
+    pub fn watch_defaults(&self) -> Result<()> {
+        self.command(Command::WatchDefaults)
+    }
+
+    pub fn unwatch<P: AsRef<Path>>(&self, search_path: P) -> Result<()> {
+        self.command(|reply| Command::Unwatch(search_path.as_ref().to_path_buf(), reply))
+    }
+
+    /// Close the watcher. The receiver gets no more changes, and the
+    /// other methods return an error from now on.
+    pub fn shutdown(&self) {
+        self.shut_down.store(true, Ordering::Relaxed);
+        self.closer.close();
+        self.send_closer.close();
+    }
+
+    /// Hand `command` to the watcher thread, and wait for its reply.
+    fn command(&self, command: impl FnOnce(Sender<Result<()>>) -> Command) -> Result<()> {
+        if self.shut_down.load(Ordering::Relaxed) {
+            return Err(closed_error());
+        }
+        let (reply_tx, reply_rx) = channel();
+        self.commands
+            .send(command(reply_tx))
+            .map_err(|_| closed_error())?;
+        self.closer.wake();
+
+        reply_rx.recv().map_err(|_| closed_error())?
+    }
+}
+
+impl Drop for WatcherHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(
+    watcher: &mut Watcher,
+    commands: &Receiver<Command>,
+    changes: &ChangeSender,
+    shut_down: &AtomicBool,
+) {
+    let mut failures = 0;
+    loop {
+        for command in commands.try_iter() {
+            handle(watcher, command);
+        }
+
+        match watcher.recv() {
+            Ok(new_changes) => {
+                failures = 0;
+                for change in new_changes {
+                    if changes.send(change).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(_) if shut_down.load(Ordering::Relaxed) => return,
+            // Errors other than being closed are worth another try, e.g.,
+            // a search path that could not be read, but not right away
+            Err(_) => {
+                failures += 1;
+                let deadline = Instant::now() + recv_retry_delay(failures);
+                while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                    if shut_down.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    match commands.recv_timeout(left.min(RECV_RETRY_DELAY)) {
+                        Ok(command) => handle(watcher, command),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn handle(watcher: &mut Watcher, command: Command) {
+    match command {
+        Command::Watch(search_path, reply) => {
+            let _ = reply.send(watcher.watch(search_path));
+        }
+        Command::Unwatch(search_path, reply) => {
+            let _ = reply.send(watcher.unwatch(search_path));
+        }
+        Command::WatchDefaults(reply) => {
+            let _ = reply.send(watcher.watch_defaults());
+        }
+    }
+}
+
+/// How long to wait after the `failures`th failed `recv` in a row, counted
+/// from 1.
+fn recv_retry_delay(failures: u32) -> Duration {
+    RECV_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(failures - 1))
+        .min(MAX_RECV_RETRY_DELAY)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use crate::test_utils::TempDir;
+    use crate::watcher::Change;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_recv_retry_delay() {
+        assert_eq!(recv_retry_delay(1), RECV_RETRY_DELAY);
+        assert_eq!(recv_retry_delay(2), RECV_RETRY_DELAY * 2);
+        assert_eq!(recv_retry_delay(10), MAX_RECV_RETRY_DELAY);
+        assert_eq!(recv_retry_delay(u32::MAX), MAX_RECV_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_watch_from_another_thread() {
+        assert_send_sync::<WatcherHandle>();
+
+        let temp_dir = TempDir::new("handle");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("late")).unwrap();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let (handle, changes) = WatcherHandle::spawn::<PathBuf>(&[], Backend::Native).unwrap();
+        let handle = Arc::new(handle);
+
+        let watching = Arc::clone(&handle);
+        let late = root.join("late");
+        std::thread::spawn(move || watching.watch(&late))
+            .join()
+            .unwrap()
+            .unwrap();
+
+        let desktop_file = root.join("late/foo.desktop");
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        let change = changes
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap();
+        assert!(
+            matches!(change, Change::AppInstalled { app_path, .. } if app_path == desktop_file)
+        );
+
+        handle.shutdown();
+        assert!(handle.watch(&root).is_err());
+        assert!(changes.recv().is_err());
+    }
+
+    #[test]
+    fn test_unwatch_unknown_path() {
+        let (handle, _changes) = WatcherHandle::spawn::<PathBuf>(&[], Backend::Native).unwrap();
+        let unknown = Path::new("/nonexistent/applications-rs");
+        // Not the watcher thread dying, which would close the watcher
+        for _ in 0..2 {
+            assert!(matches!(
+                handle.unwatch(unknown),
+                Err(Error::NotWatched(path)) if path == unknown
+            ));
+        }
+        handle.shutdown();
+    }
+
+    #[test]
+    fn test_shutdown_while_blocked() {
+        let temp_dir = TempDir::new("handle-blocked");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let (handle, changes) = WatcherHandle::spawn_with_queue(
+            &[&root],
+            Backend::Native,
+            QueuePolicy::Block { capacity: 1 },
+        )
+        .unwrap();
+
+        for name in ["foo", "bar", "baz"] {
+            std::fs::write(
+                root.join(format!("{name}.desktop")),
+                format!(
+                    "[Desktop Entry]\nType=Application\nName={name}\nExec=sh\nIcon={}",
+                    root.join("icon.png").display()
+                ),
+            )
+            .unwrap();
+        }
+        // The first change fills the queue, the thread then waits for room
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        // It gives up on the other changes and exits, rather than sending
+        // them once there is room
+        handle.shutdown();
+        let timeout = std::time::Duration::from_secs(10);
+        let mut received = 0;
+        let disconnected = loop {
+            match changes.recv_timeout(timeout) {
+                Ok(_) => received += 1,
+                Err(e) => break e == std::sync::mpsc::RecvTimeoutError::Disconnected,
+            }
+        };
+        assert!(disconnected);
+        assert_eq!(received, 1);
+    }
+}
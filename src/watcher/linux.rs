@@ -1,10 +1,32 @@
-use super::Change;
-use crate::platforms::parse_desktop_file_content;
-use anyhow::Result;
+use super::{Change, KnownApps, closed_error, pair_moves, parse_with_retry};
+use crate::platforms::{flatpak_desktop_file, is_flatpak_app_path, parse_desktop_file_content};
+use crate::{App, AppTrait};
+use crate::{Error, Result};
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::eventfd::EventFd;
 use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
+use std::os::fd::AsFd;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use walkdir::WalkDir;
+
+/// Parse a new desktop file, which is still incomplete while it is empty.
+pub(super) fn parse_desktop_file(path: &Path) -> Result<Option<App>> {
+    let content = std::fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Err(Error::parse(path, "empty desktop file"));
+    }
+    // Entries we cannot find an icon for are not listed either
+    let Some((_app_name, _, Some(_icon_path))) = parse_desktop_file_content(&content) else {
+        return Ok(None);
+    };
+
+    Ok(App::from_path(path).ok())
+}
 
 /// The flag we use when adding new entries.
 fn watch_flag() -> AddWatchFlags {
@@ -16,111 +38,805 @@ fn watch_flag() -> AddWatchFlags {
         | AddWatchFlags::IN_ONLYDIR
 }
 
+/// A directory we watch, either a search path or one below it.
+struct WatchedDir {
+    path: PathBuf,
+    /// The search path it was found in.
+    search_path: PathBuf,
+    kind: DirKind,
+}
+
+/// What the entries of a watched directory are.
+///
+/// Snap exports its desktop files to a flat directory, which is watched like
+/// any other. Flatpak installations have a directory for each app, with the
+/// desktop file deep inside the deployed commit, so we only watch what
+/// changes when an app is deployed: the `app` directory for installs and
+/// uninstalls, and the app directories with the `current` and `active`
+/// symlinks for updates.
+#[derive(Clone, PartialEq)]
+enum DirKind {
+    /// Desktop files, and directories with more of them.
+    Tree,
+    /// A Flatpak `app` directory.
+    FlatpakRoot,
+    /// `<app_dir>` itself, or the branch directory `current` points to.
+    FlatpakApp { app_dir: PathBuf },
+}
+
+/// `IN_MASK_ADD`, which nix does not have.
+const IN_MASK_ADD: u32 = 0x2000_0000;
+
+/// The flag we use to wait for a lost search path to come back, added to
+/// the flags of a directory we may already watch.
+fn wait_flag() -> AddWatchFlags {
+    AddWatchFlags::IN_CREATE
+        | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_ONLYDIR
+        | AddWatchFlags::from_bits_retain(IN_MASK_ADD)
+}
+
 pub struct Watcher {
     inotify: Inotify,
-    search_paths: HashMap<WatchDescriptor, PathBuf>,
+    dirs: HashMap<WatchDescriptor, WatchedDir>,
+    /// Lost search paths, keyed by the closest existing ancestor they
+    /// wait to be created in.
+    lost: HashMap<WatchDescriptor, Vec<PathBuf>>,
+    /// The desktop files of the Flatpak apps we know are deployed, keyed
+    /// by app directory.
+    flatpak_apps: HashMap<PathBuf, PathBuf>,
+    known: KnownApps,
+    /// Written to by [`Closer`]s, to wake up a blocked `recv()`.
+    wake_event: Arc<EventFd>,
+    close_requested: Arc<AtomicBool>,
+    closed: bool,
+}
+
+/// Wakes up or closes a [`Watcher`] from another thread.
+#[derive(Clone)]
+pub struct Closer {
+    wake_event: Arc<EventFd>,
+    close_requested: Arc<AtomicBool>,
+}
+
+impl Closer {
+    /// Make a blocked `recv()` return, with no changes.
+    pub fn wake(&self) {
+        let _ = self.wake_event.write(1);
+    }
+
+    pub fn close(&self) {
+        self.close_requested.store(true, Ordering::Relaxed);
+        self.wake();
+    }
 }
 
 impl Watcher {
     pub fn new<P: AsRef<Path>>(search_paths: &[P]) -> Result<Self> {
         let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+        let mut watcher = Self {
+            inotify,
+            dirs: HashMap::new(),
+            lost: HashMap::new(),
+            flatpak_apps: HashMap::new(),
+            known: KnownApps::default(),
+            wake_event: Arc::new(EventFd::new()?),
+            close_requested: Arc::new(AtomicBool::new(false)),
+            closed: false,
+        };
+        for search_path in search_paths {
+            watcher.watch(search_path)?;
+        }
 
-        let mut search_paths_with_descriptor = HashMap::new();
+        Ok(watcher)
+    }
 
-        for search_path in search_paths {
-            let search_path = search_path.as_ref();
-            let watch_descriptor = inotify.add_watch(search_path, watch_flag())?;
+    /// Watch `dir` and the directories below it, which belong to
+    /// `search_path`. Returns the directories that are now watched.
+    fn watch_tree(&mut self, dir: &Path, search_path: &Path) -> Result<Vec<PathBuf>> {
+        let mut watched = Vec::new();
+        for entry in WalkDir::new(dir).into_iter().filter_map(|entry| entry.ok()) {
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            // Subdirectories may vanish before we get to them
+            let watch_descriptor = match self.inotify.add_watch(entry.path(), watch_flag()) {
+                Ok(watch_descriptor) => watch_descriptor,
+                Err(e) if entry.depth() == 0 => return Err(e.into()),
+                Err(_) => continue,
+            };
+            self.dirs.insert(
+                watch_descriptor,
+                WatchedDir {
+                    path: entry.path().to_path_buf(),
+                    search_path: search_path.to_path_buf(),
+                    kind: DirKind::Tree,
+                },
+            );
+            watched.push(entry.into_path());
+        }
+
+        Ok(watched)
+    }
+
+    fn add_watch(&mut self, path: &Path, search_path: &Path, kind: DirKind) -> Result<()> {
+        let watch_descriptor = self.inotify.add_watch(path, watch_flag())?;
+        self.dirs.insert(
+            watch_descriptor,
+            WatchedDir {
+                path: path.to_path_buf(),
+                search_path: search_path.to_path_buf(),
+                kind,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Watch the Flatpak `app` directory `root` and the apps in it. Returns
+    /// their app directories.
+    fn watch_flatpak(&mut self, root: &Path) -> Result<Vec<PathBuf>> {
+        self.add_watch(root, root, DirKind::FlatpakRoot)?;
 
-            search_paths_with_descriptor.insert(watch_descriptor, search_path.to_path_buf());
+        let app_dirs: Vec<PathBuf> = std::fs::read_dir(root)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        for app_dir in &app_dirs {
+            self.watch_flatpak_app(app_dir, root);
+            if let Some(desktop_file) = flatpak_desktop_file(app_dir)
+                && desktop_file.is_file()
+            {
+                self.flatpak_apps.insert(app_dir.clone(), desktop_file);
+            }
         }
 
-        Ok(Self {
-            inotify,
-            search_paths: search_paths_with_descriptor,
+        Ok(app_dirs)
+    }
+
+    /// Watch `app_dir` for its `current` symlink, and the branch directory
+    /// that points to for its `active` symlink.
+    fn watch_flatpak_app(&mut self, app_dir: &Path, root: &Path) {
+        self.unwatch_tree(app_dir);
+        let kind = DirKind::FlatpakApp {
+            app_dir: app_dir.to_path_buf(),
+        };
+        // The app may be uninstalled again before we get to it
+        if self.add_watch(app_dir, root, kind.clone()).is_err() {
+            return;
+        }
+        if let Ok(branch) = std::fs::read_link(app_dir.join("current")) {
+            let _ = self.add_watch(&app_dir.join(branch), root, kind);
+        }
+    }
+
+    /// Report the Flatpak app in `app_dir` as installed if it is deployed,
+    /// or as deleted if it was.
+    fn check_flatpak_app(&mut self, app_dir: &Path, root: &Path) -> Option<Change> {
+        let desktop_file = flatpak_desktop_file(app_dir)?;
+        if !desktop_file.is_file() {
+            let app_path = self.flatpak_apps.remove(app_dir)?;
+            return Some(Change::AppDeleted {
+                app_path,
+                app: None,
+            });
+        }
+
+        let app = parse_with_retry(|| parse_desktop_file(&desktop_file))?;
+        self.flatpak_apps
+            .insert(app_dir.to_path_buf(), desktop_file.clone());
+        Some(Change::AppInstalled {
+            app_path: desktop_file,
+            app: Box::new(app),
+            search_path: Some(root.to_path_buf()),
         })
     }
 
+    /// Handle an event for `name` in a directory of a Flatpak installation.
+    fn handle_flatpak_event(
+        &mut self,
+        kind: DirKind,
+        mask: AddWatchFlags,
+        name: &OsStr,
+        dir: &Path,
+        root: &Path,
+    ) -> Option<Change> {
+        match kind {
+            DirKind::FlatpakRoot if mask.contains(AddWatchFlags::IN_ISDIR) => {
+                let app_dir = dir.join(name);
+                if mask.intersects(AddWatchFlags::IN_CREATE | AddWatchFlags::IN_MOVED_TO) {
+                    self.watch_flatpak_app(&app_dir, root);
+                } else {
+                    self.unwatch_tree(&app_dir);
+                }
+                self.check_flatpak_app(&app_dir, root)
+            }
+            DirKind::FlatpakApp { app_dir } if name == "current" || name == "active" => {
+                if name == "current" {
+                    self.watch_flatpak_app(&app_dir, root);
+                }
+                self.check_flatpak_app(&app_dir, root)
+            }
+            _ => None,
+        }
+    }
+
+    /// Stop watching `dir` and the directories below it.
+    fn unwatch_tree(&mut self, dir: &Path) {
+        let watch_descriptors: Vec<WatchDescriptor> = self
+            .dirs
+            .iter()
+            .filter(|(_wd, watched)| watched.path.starts_with(dir))
+            .map(|(wd, _watched)| *wd)
+            .collect();
+        for watch_descriptor in watch_descriptors {
+            // Fails for directories that are gone, their watch went with them
+            let _ = self.inotify.rm_watch(watch_descriptor);
+            self.dirs.remove(&watch_descriptor);
+        }
+    }
+
+    /// Report `search_path` as lost and wait for it to come back.
+    fn lose(&mut self, search_path: PathBuf, changes: &mut Vec<Change>) -> Result<()> {
+        self.unwatch_tree(&search_path);
+        self.flatpak_apps
+            .retain(|app_dir, _desktop_file| !app_dir.starts_with(&search_path));
+        self.known.forget_below(&search_path);
+        changes.push(Change::WatchLost {
+            search_path: search_path.clone(),
+        });
+        self.wait_for(search_path, changes)
+    }
+
+    /// Watch the closest existing ancestor of the lost `search_path`, or
+    /// `search_path` itself if it is back.
+    fn wait_for(&mut self, search_path: PathBuf, changes: &mut Vec<Change>) -> Result<()> {
+        if search_path.is_dir() && is_flatpak_app_path(&search_path) {
+            if let Ok(app_dirs) = self.watch_flatpak(&search_path) {
+                for app_dir in app_dirs {
+                    changes.extend(self.check_flatpak_app(&app_dir, &search_path));
+                }
+                return Ok(());
+            }
+        } else if search_path.is_dir()
+            && let Ok(new_dirs) = self.watch_tree(&search_path, &search_path)
+        {
+            for new_dir in new_dirs {
+                changes.extend(installed_desktop_files(&new_dir, &search_path));
+            }
+            return Ok(());
+        }
+
+        let Some(ancestor) = search_path.ancestors().skip(1).find(|dir| dir.is_dir()) else {
+            return Err(Error::NotADirectory(search_path));
+        };
+        let watch_descriptor = self.inotify.add_watch(ancestor, wait_flag())?;
+        self.lost
+            .entry(watch_descriptor)
+            .or_default()
+            .push(search_path);
+
+        Ok(())
+    }
+
     pub fn recv(&mut self) -> Result<Vec<Change>> {
+        if self.closed {
+            return Err(closed_error());
+        }
+        loop {
+            let mut fds = [
+                PollFd::new(self.inotify.as_fd(), PollFlags::POLLIN),
+                PollFd::new(self.wake_event.as_fd(), PollFlags::POLLIN),
+            ];
+            match poll(&mut fds, PollTimeout::NONE) {
+                Ok(_) => {}
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(e.into()),
+            }
+            if fds[1].revents().is_some_and(|revents| !revents.is_empty()) {
+                let _ = self.wake_event.read();
+                if self.close_requested.load(Ordering::Relaxed) {
+                    self.close();
+                    return Err(closed_error());
+                }
+                return Ok(Vec::new());
+            }
+            if fds[0].revents().is_some_and(|revents| !revents.is_empty()) {
+                break;
+            }
+        }
+
         let events = self.inotify.read_events()?;
         let mut changes = Vec::with_capacity(events.len());
+        // The two events of a rename share a cookie
+        let mut moved_from: HashMap<u32, PathBuf> = HashMap::new();
+        let mut moves = Vec::new();
         for event in events {
-            let watch_desciptor = event.wd;
-            let search_path = self
-                .search_paths
-                .get(&watch_desciptor)
-                .expect("an event occurred on a search path that we do not watch")
-                .to_path_buf();
             let mask = event.mask;
-            let opt_file_name = event.name;
+            // Something was created next to, or on the way to, a lost
+            // search path
+            if let Some(search_paths) = self.lost.remove(&event.wd) {
+                if !mask.contains(AddWatchFlags::IN_IGNORED) && !self.dirs.contains_key(&event.wd) {
+                    let _ = self.inotify.rm_watch(event.wd);
+                }
+                for search_path in search_paths {
+                    self.wait_for(search_path, &mut changes)?;
+                }
+            }
+            // The directory was deleted, unmounted or unwatched
+            if mask.contains(AddWatchFlags::IN_IGNORED) {
+                if let Some(watched) = self.dirs.remove(&event.wd)
+                    && watched.path == watched.search_path
+                {
+                    self.lose(watched.search_path, &mut changes)?;
+                }
+                continue;
+            }
+            // Events of a directory we have just stopped watching
+            let Some(watched) = self.dirs.get(&event.wd) else {
+                continue;
+            };
+            let dir = watched.path.clone();
+            let search_path = watched.search_path.clone();
+            // Still watched, but at a path where we do not look for it
+            if mask.contains(AddWatchFlags::IN_MOVE_SELF) && dir == search_path {
+                self.lose(search_path, &mut changes)?;
+                continue;
+            }
+            let Some(file_name) = event.name else {
+                continue;
+            };
+            if watched.kind != DirKind::Tree {
+                let kind = watched.kind.clone();
+                changes.extend(self.handle_flatpak_event(
+                    kind,
+                    mask,
+                    &file_name,
+                    &dir,
+                    &search_path,
+                ));
+                continue;
+            }
+            let file_path = dir.join(file_name);
 
-            if mask.contains(AddWatchFlags::IN_CREATE) || mask.contains(AddWatchFlags::IN_MOVED_TO)
-            {
-                let file_name = opt_file_name.as_ref().unwrap();
-                let file_path = search_path.join(file_name);
-                if file_path.extension() == Some(OsStr::new("desktop"))
-                    && file_path.metadata()?.is_file()
+            if mask.contains(AddWatchFlags::IN_ISDIR) {
+                if mask.contains(AddWatchFlags::IN_CREATE)
+                    || mask.contains(AddWatchFlags::IN_MOVED_TO)
                 {
-                    let desktop_file_content = std::fs::read_to_string(&file_path)?;
-                    let Some((_app_name, _, opt_icon_path)) =
-                        parse_desktop_file_content(&desktop_file_content)
-                    else {
-                        continue;
-                    };
-
-                    if opt_icon_path.is_none() {
-                        continue;
+                    // Entries created before the watch was added have no
+                    // events of their own
+                    for new_dir in self.watch_tree(&file_path, &search_path)? {
+                        changes.extend(installed_desktop_files(&new_dir, &search_path));
                     }
-
-                    changes.push(Change::AppInstalled {
-                        app_path: file_path,
-                    });
                 }
+                if mask.contains(AddWatchFlags::IN_MOVED_TO)
+                    && let Some(from) = moved_from.remove(&event.cookie)
+                {
+                    for app_path in self.known.paths_below(&from) {
+                        if let Ok(relative) = app_path.strip_prefix(&from) {
+                            let to = file_path.join(relative);
+                            moves.push((app_path, to));
+                        }
+                    }
+                }
+                if mask.contains(AddWatchFlags::IN_MOVED_FROM) {
+                    moved_from.insert(event.cookie, file_path.clone());
+                    self.unwatch_tree(&file_path);
+                    // The desktop files in it have no events of their own
+                    changes.extend(self.known.paths_below(&file_path).into_iter().map(
+                        |app_path| Change::AppDeleted {
+                            app_path,
+                            app: None,
+                        },
+                    ));
+                }
+                continue;
+            }
+
+            if mask.contains(AddWatchFlags::IN_MOVED_FROM) {
+                moved_from.insert(event.cookie, file_path.clone());
+            }
+            if mask.contains(AddWatchFlags::IN_MOVED_TO)
+                && let Some(from) = moved_from.remove(&event.cookie)
+            {
+                moves.push((from, file_path.clone()));
+            }
+            if file_path.extension() != Some(OsStr::new("desktop")) {
+                continue;
+            }
+
+            if (mask.contains(AddWatchFlags::IN_CREATE)
+                || mask.contains(AddWatchFlags::IN_MOVED_TO))
+                && file_path.metadata()?.is_file()
+            {
+                let Some(app) = parse_with_retry(|| parse_desktop_file(&file_path)) else {
+                    continue;
+                };
+
+                changes.push(Change::AppInstalled {
+                    app_path: file_path.clone(),
+                    app: Box::new(app),
+                    search_path: Some(search_path),
+                });
             }
 
             if mask.contains(AddWatchFlags::IN_DELETE)
                 || mask.contains(AddWatchFlags::IN_MOVED_FROM)
             {
-                let file_name = opt_file_name.unwrap();
-                let file_path = search_path.join(file_name);
-                if file_path.extension() == Some(OsStr::new("desktop")) {
-                    changes.push(Change::AppDeleted {
-                        app_path: file_path,
-                    });
-                }
+                changes.push(Change::AppDeleted {
+                    app_path: file_path,
+                    app: None,
+                });
             }
         }
+        pair_moves(&mut changes, &moves);
+        self.known.update(&mut changes);
 
         Ok(changes)
     }
 
     pub fn watch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
-        let watch_descriptor = self.inotify.add_watch(search_path.as_ref(), watch_flag())?;
-        self.search_paths
-            .insert(watch_descriptor, search_path.as_ref().to_path_buf());
+        let search_path = search_path.as_ref();
+        // Walking a missing directory yields nothing to watch
+        if !search_path.is_dir() {
+            return Err(Error::NotADirectory(search_path.to_path_buf()));
+        }
+        if is_flatpak_app_path(search_path) {
+            self.watch_flatpak(search_path)?;
+        } else {
+            self.watch_tree(search_path, search_path)?;
+        }
+
+        // What the apps were, for when they are deleted
+        let desktop_files: Vec<PathBuf> = if is_flatpak_app_path(search_path) {
+            self.flatpak_apps.values().cloned().collect()
+        } else {
+            WalkDir::new(search_path)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.into_path())
+                .filter(|path| path.extension() == Some(OsStr::new("desktop")))
+                .collect()
+        };
+        for desktop_file in desktop_files {
+            if let Ok(Some(app)) = parse_desktop_file(&desktop_file) {
+                self.known.insert(desktop_file, app);
+            }
+        }
 
         Ok(())
     }
 
+    /// Watch `search_path`, which does not exist yet, as a lost search
+    /// path.
+    pub fn watch_when_created<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let mut changes = Vec::new();
+        self.wait_for(search_path.as_ref().to_path_buf(), &mut changes)
+    }
+
+    pub fn initial_state(&self, search_paths: &HashSet<PathBuf>) -> Vec<Change> {
+        self.known.installed(search_paths)
+    }
+
+    pub fn watch_list_is_empty(&self) -> bool {
+        self.dirs.is_empty() && self.lost.is_empty()
+    }
+
+    pub fn closer(&self) -> Closer {
+        Closer {
+            wake_event: Arc::clone(&self.wake_event),
+            close_requested: Arc::clone(&self.close_requested),
+        }
+    }
+
+    /// Remove all watches. `recv()` returns an error from now on.
+    pub fn close(&mut self) {
+        let watch_descriptors = self.dirs.keys().chain(self.lost.keys());
+        for watch_descriptor in watch_descriptors {
+            let _ = self.inotify.rm_watch(*watch_descriptor);
+        }
+        self.dirs.clear();
+        self.lost.clear();
+        self.flatpak_apps.clear();
+        self.known = KnownApps::default();
+        self.closed = true;
+    }
+
     pub fn unwatch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
         let search_path = search_path.as_ref();
+        let mut was_lost = false;
+        for search_paths in self.lost.values_mut() {
+            let len = search_paths.len();
+            search_paths.retain(|lost| lost != search_path);
+            was_lost |= search_paths.len() != len;
+        }
+        self.lost
+            .retain(|_wd, search_paths| !search_paths.is_empty());
+        if !was_lost
+            && !self
+                .dirs
+                .values()
+                .any(|watched| watched.search_path == search_path)
+        {
+            return Err(Error::NotWatched(search_path.to_path_buf()));
+        }
 
-        let watch_descriptor = *self
-            .search_paths
+        let watch_descriptors: Vec<WatchDescriptor> = self
+            .dirs
             .iter()
-            .find(|(_wd, path)| *path == search_path)
-            .unwrap_or_else(|| {
-                panic!(
-                    "search path [{}] has not been watched",
-                    search_path.display()
-                )
+            .filter(|(_wd, watched)| watched.search_path == search_path)
+            .map(|(wd, _watched)| *wd)
+            .collect();
+        for watch_descriptor in watch_descriptors {
+            self.inotify.rm_watch(watch_descriptor)?;
+            self.dirs.remove(&watch_descriptor);
+        }
+        self.flatpak_apps
+            .retain(|app_dir, _desktop_file| !app_dir.starts_with(search_path));
+        self.known.forget_below(search_path);
+
+        Ok(())
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// `AppInstalled` for the desktop files directly in `dir`.
+fn installed_desktop_files(dir: &Path, search_path: &Path) -> Vec<Change> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("desktop")) && path.is_file())
+        .filter_map(|path| {
+            let app = parse_with_retry(|| parse_desktop_file(&path))?;
+            Some(Change::AppInstalled {
+                app_path: path,
+                app: Box::new(app),
+                search_path: Some(search_path.to_path_buf()),
             })
-            .0;
+        })
+        .collect()
+}
 
-        self.inotify.rm_watch(watch_descriptor)?;
-        self.search_paths
-            .remove(&watch_descriptor)
-            .expect("just checked it is Some");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
 
-        Ok(())
+    #[test]
+    fn test_watch_nested_directories() {
+        let temp_dir = TempDir::new("watcher");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("vendor")).unwrap();
+        let mut watcher = Watcher::new(&[&root]).unwrap();
+        assert_eq!(watcher.dirs.len(), 2);
+
+        // Created along with its parent, possibly before the parent is
+        // watched
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        std::fs::create_dir_all(root.join("new/nested")).unwrap();
+        let desktop_file = root.join("new/nested/foo.desktop");
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        let installed = loop {
+            let changes = watcher.recv().unwrap();
+            if let Some(Change::AppInstalled {
+                app, search_path, ..
+            }) = changes.into_iter().find(|change| {
+                matches!(change, Change::AppInstalled { app_path, .. } if *app_path == desktop_file)
+            }) {
+                break (app, search_path);
+            }
+        };
+        assert_eq!(installed.0.name, "Foo");
+        assert_eq!(installed.1.as_deref(), Some(root.as_path()));
+        assert!(
+            watcher
+                .dirs
+                .values()
+                .any(|dir| dir.path == root.join("new/nested"))
+        );
+
+        watcher.unwatch(&root).unwrap();
+        assert!(watcher.dirs.is_empty());
+    }
+
+    #[test]
+    fn test_watch_missing_directory() {
+        let missing = Path::new("/nonexistent/applications-rs");
+        assert!(matches!(
+            Watcher::new(&[missing]),
+            Err(Error::NotADirectory(path)) if path == missing
+        ));
+    }
+
+    #[test]
+    fn test_watch_moves() {
+        let temp_dir = TempDir::new("moves");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        std::fs::write(
+            root.join("a/foo.desktop"),
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        let mut watcher = Watcher::new(&[root.join("a"), root.join("b")]).unwrap();
+        let next_changes = |watcher: &mut Watcher| loop {
+            let changes = watcher.recv().unwrap();
+            if !changes.is_empty() {
+                break changes;
+            }
+        };
+
+        // Between two search paths
+        std::fs::rename(root.join("a/foo.desktop"), root.join("b/foo.desktop")).unwrap();
+        let changes = next_changes(&mut watcher);
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppMoved { from, to, app, search_path: Some(search_path) }]
+                if *from == root.join("a/foo.desktop")
+                    && *to == root.join("b/foo.desktop")
+                    && app.name == "Foo"
+                    && *search_path == root.join("b")
+        ));
+
+        // Along with its directory
+        std::fs::create_dir_all(root.join("b/vendor")).unwrap();
+        std::fs::rename(
+            root.join("b/foo.desktop"),
+            root.join("b/vendor/foo.desktop"),
+        )
+        .unwrap();
+        let _ = next_changes(&mut watcher);
+        std::fs::rename(root.join("b/vendor"), root.join("a/vendor")).unwrap();
+        let changes = next_changes(&mut watcher);
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppMoved { from, to, .. }]
+                if *from == root.join("b/vendor/foo.desktop") && *to == root.join("a/vendor/foo.desktop")
+        ));
+    }
+
+    #[test]
+    fn test_watch_flatpak_installation() {
+        let temp_dir = TempDir::new("flatpak");
+        let tmp = temp_dir.path().to_path_buf();
+        let root = tmp.join("flatpak/app");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(tmp.join("icon.png"), b"").unwrap();
+        let deploy = |app_dir: &Path, commit: &str, name: &str| {
+            let applications = app_dir
+                .join("x86_64/stable")
+                .join(commit)
+                .join("files/share/applications");
+            std::fs::create_dir_all(&applications).unwrap();
+            std::fs::write(
+                applications.join("org.example.Foo.desktop"),
+                format!(
+                    "[Desktop Entry]\nType=Application\nName={name}\nExec=foo\nIcon={}",
+                    tmp.join("icon.png").display()
+                ),
+            )
+            .unwrap();
+            let active = app_dir.join("x86_64/stable/active.tmp");
+            std::os::unix::fs::symlink(commit, &active).unwrap();
+            std::fs::rename(&active, app_dir.join("x86_64/stable/active")).unwrap();
+        };
+        let installed_name = |watcher: &mut Watcher| loop {
+            let changes = watcher.recv().unwrap();
+            if let Some(Change::AppInstalled { app_path, app, .. }) = changes.into_iter().next() {
+                assert_eq!(
+                    app_path,
+                    root.join("org.example.Foo/current/active/files/share/applications/org.example.Foo.desktop")
+                );
+                break app.name;
+            }
+        };
+        let mut watcher = Watcher::new(&[&root]).unwrap();
+
+        // Installed in one go
+        let staging = tmp.join("org.example.Foo");
+        deploy(&staging, "aaa", "Foo");
+        std::os::unix::fs::symlink("x86_64/stable", staging.join("current")).unwrap();
+        std::fs::rename(&staging, root.join("org.example.Foo")).unwrap();
+        assert_eq!(installed_name(&mut watcher), "Foo");
+
+        // Updated by deploying another commit
+        deploy(&root.join("org.example.Foo"), "bbb", "Foo 2");
+        assert_eq!(installed_name(&mut watcher), "Foo 2");
+        // The root, the app directory and its branch directory
+        assert_eq!(watcher.dirs.len(), 3);
+
+        std::fs::remove_dir_all(root.join("org.example.Foo")).unwrap();
+        loop {
+            let changes = watcher.recv().unwrap();
+            if changes
+                .iter()
+                .any(|change| matches!(change, Change::AppDeleted { app: Some(app), .. } if app.name == "Foo 2"))
+            {
+                break;
+            }
+        }
+
+        watcher.unwatch(&root).unwrap();
+    }
+
+    #[test]
+    fn test_close_wakes_up_recv() {
+        let temp_dir = TempDir::new("watcher-close");
+        let root = temp_dir.path().to_path_buf();
+        let mut watcher = Watcher::new(&[&root]).unwrap();
+        let closer = watcher.closer();
+
+        let recv = std::thread::spawn(move || {
+            let result = watcher.recv();
+            (result.is_err(), watcher.dirs.is_empty())
+        });
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        closer.close();
+        assert_eq!(recv.join().unwrap(), (true, true));
+    }
+
+    #[test]
+    fn test_rearm_lost_search_path() {
+        let temp_dir = TempDir::new("watcher-lost");
+        let root = temp_dir.path().to_path_buf();
+        let search_path = root.join("applications");
+        std::fs::create_dir_all(&search_path).unwrap();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let mut watcher = Watcher::new(&[&search_path]).unwrap();
+
+        std::fs::remove_dir(&search_path).unwrap();
+        loop {
+            let changes = watcher.recv().unwrap();
+            if changes.iter().any(|change| {
+                matches!(change, Change::WatchLost { search_path: lost } if *lost == search_path)
+            }) {
+                break;
+            }
+        }
+        assert!(watcher.dirs.is_empty());
+
+        std::fs::create_dir(&search_path).unwrap();
+        let desktop_file = search_path.join("foo.desktop");
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        loop {
+            let changes = watcher.recv().unwrap();
+            if changes.iter().any(|change| {
+                matches!(change, Change::AppInstalled { app_path, .. } if *app_path == desktop_file)
+            }) {
+                break;
+            }
+        }
+        assert!(watcher.lost.is_empty());
+
+        watcher.unwatch(&search_path).unwrap();
     }
 }
@@ -1,201 +1,599 @@
-use super::Change;
-use anyhow::Result;
-use nix::fcntl::open;
-use nix::{
-    fcntl::OFlag,
-    sys::{
-        event::{EvFlags, EventFilter, FilterFlag, KEvent, Kqueue},
-        stat::Mode,
-    },
-};
+//! An FSEvents backend, which watches each search path recursively with a
+//! single stream, instead of a file descriptor per directory.
+
+use super::{Change, KnownApps, closed_error, pair_moves, parse_with_retry};
+use crate::utils::mac::MacAppPath;
+use crate::{App, AppTrait};
+use crate::{Error, Result};
+use fsevent_sys as fs;
+use fsevent_sys::core_foundation as cf;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, c_void};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::{
-    collections::{HashMap, HashSet},
-    fs,
-    os::fd::IntoRawFd,
-};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread::JoinHandle;
+use walkdir::WalkDir;
+
+/// How long FSEvents collects events before it hands them to us, in seconds.
+const LATENCY: f64 = 0.3;
+
+/// How deep below a search path we look for app bundles, e.g.,
+/// `/Applications/Utilities/Terminal.app` is 2.
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// An event reported by FSEvents.
+struct Event {
+    path: PathBuf,
+    flags: fs::FSEventStreamEventFlags,
+    id: fs::FSEventStreamEventId,
+}
+
+/// A run loop, which is safe to stop from other threads.
+struct RunLoop(cf::CFRunLoopRef);
+
+unsafe impl Send for RunLoop {}
+
+/// An FSEvents stream that runs on its own thread.
+struct Stream {
+    run_loop: RunLoop,
+    thread: JoinHandle<()>,
+}
+
+impl Stream {
+    fn start(
+        paths: &[PathBuf],
+        since: fs::FSEventStreamEventId,
+        tx: Sender<Vec<Event>>,
+    ) -> Result<Self> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        let (run_loop_tx, run_loop_rx) = channel();
+
+        let thread = std::thread::spawn(move || unsafe {
+            let cf_paths =
+                cf::CFArrayCreateMutable(cf::kCFAllocatorDefault, 0, &cf::kCFTypeArrayCallBacks);
+            for path in &paths {
+                let mut err = std::ptr::null_mut();
+                let cf_path = cf::str_path_to_cfstring_ref(path, &mut err);
+                if !err.is_null() {
+                    cf::CFRelease(err as cf::CFRef);
+                    continue;
+                }
+                cf::CFArrayAppendValue(cf_paths, cf_path);
+                cf::CFRelease(cf_path);
+            }
+
+            let info = Box::into_raw(Box::new(tx));
+            let context = fs::FSEventStreamContext {
+                version: 0,
+                info: info as *mut c_void,
+                retain: None,
+                release: None,
+                copy_description: None,
+            };
+            let stream = fs::FSEventStreamCreate(
+                cf::kCFAllocatorDefault,
+                callback,
+                &context,
+                cf_paths,
+                since,
+                LATENCY,
+                fs::kFSEventStreamCreateFlagFileEvents
+                    | fs::kFSEventStreamCreateFlagNoDefer
+                    | fs::kFSEventStreamCreateFlagWatchRoot,
+            );
+            cf::CFRelease(cf_paths as cf::CFRef);
+
+            let run_loop = cf::CFRunLoopGetCurrent();
+            fs::FSEventStreamScheduleWithRunLoop(stream, run_loop, cf::kCFRunLoopDefaultMode);
+            let started = fs::FSEventStreamStart(stream) != 0;
+            let _ = run_loop_tx.send(started.then_some(RunLoop(run_loop)));
+
+            if started {
+                cf::CFRunLoopRun();
+                fs::FSEventStreamStop(stream);
+            }
+            fs::FSEventStreamInvalidate(stream);
+            fs::FSEventStreamRelease(stream);
+            drop(Box::from_raw(info));
+        });
+
+        match run_loop_rx.recv().ok().flatten() {
+            Some(run_loop) => Ok(Self { run_loop, thread }),
+            None => {
+                let _ = thread.join();
+                Err(Error::Platform(
+                    "failed to start the FSEvents stream".into(),
+                ))
+            }
+        }
+    }
+
+    fn stop(self) {
+        unsafe { cf::CFRunLoopStop(self.run_loop.0) };
+        let _ = self.thread.join();
+    }
+}
 
-fn watch_flag() -> FilterFlag {
-    FilterFlag::NOTE_WRITE | FilterFlag::NOTE_DELETE | FilterFlag::NOTE_RENAME
+extern "C" fn callback(
+    _stream: fs::FSEventStreamRef,
+    info: *mut c_void,
+    num_events: usize,
+    event_paths: *mut c_void,
+    event_flags: *const fs::FSEventStreamEventFlags,
+    event_ids: *const fs::FSEventStreamEventId,
+) {
+    let tx = unsafe { &*(info as *const Sender<Vec<Event>>) };
+    let event_paths = event_paths as *const *const std::ffi::c_char;
+
+    let events = (0..num_events)
+        .map(|i| unsafe {
+            Event {
+                path: PathBuf::from(
+                    CStr::from_ptr(*event_paths.add(i))
+                        .to_string_lossy()
+                        .as_ref(),
+                ),
+                flags: *event_flags.add(i),
+                id: *event_ids.add(i),
+            }
+        })
+        .collect();
+    let _ = tx.send(events);
 }
 
 pub struct Watcher {
-    search_paths: HashMap<i32, PathBuf>,
-    kqueue: Kqueue,
+    /// The search paths and the app bundles in them, with their inodes.
+    search_paths: HashMap<PathBuf, HashMap<PathBuf, u64>>,
+    stream: Option<Stream>,
+    tx: Sender<Vec<Event>>,
+    rx: Receiver<Vec<Event>>,
+    last_event_id: fs::FSEventStreamEventId,
+    /// Search paths that were deleted, moved away or unmounted. FSEvents
+    /// keeps watching their paths, so they are back once they exist again.
+    lost: HashSet<PathBuf>,
+    known: KnownApps,
+    /// Whether FSEvents is still replaying the changes since the event ID
+    /// we resumed from.
+    replaying: bool,
+    /// Set by [`Closer`]s, which then wake up a blocked `recv()`.
+    closed: Arc<AtomicBool>,
+    /// The paths a bundle, by its inode, was deleted from and installed at
+    /// in the current batch, which is a move if it has both.
+    renames: HashMap<u64, (Option<PathBuf>, Option<PathBuf>)>,
+}
+
+/// Wakes up or closes a [`Watcher`] from another thread.
+#[derive(Clone)]
+pub struct Closer {
+    tx: Sender<Vec<Event>>,
+    closed: Arc<AtomicBool>,
+}
 
-    prev_app_list: HashMap<i32, HashSet<PathBuf>>,
+impl Closer {
+    /// Make a blocked `recv()` return, with no changes.
+    pub fn wake(&self) {
+        // FSEvents never hands us an empty batch
+        let _ = self.tx.send(Vec::new());
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.wake();
+    }
 }
 
 impl Watcher {
     pub fn new<P: AsRef<Path>>(search_paths: &[P]) -> Result<Self> {
-        let kqueue = Kqueue::new()?;
+        Self::with_event_id(search_paths, None)
+    }
 
-        let mut search_paths_with_fd_info = HashMap::new();
-        let mut kevent_to_register = Vec::with_capacity(search_paths.len());
-        let mut prev_app_list = HashMap::new();
+    /// Like [`Watcher::new`], but first reports the changes made since
+    /// `event_id`, a value of [`Watcher::last_event_id`] saved from an
+    /// earlier run.
+    pub fn resume<P: AsRef<Path>>(search_paths: &[P], event_id: u64) -> Result<Self> {
+        Self::with_event_id(search_paths, Some(event_id))
+    }
 
+    fn with_event_id<P: AsRef<Path>>(search_paths: &[P], event_id: Option<u64>) -> Result<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = Self {
+            search_paths: HashMap::new(),
+            stream: None,
+            tx,
+            rx,
+            lost: HashSet::new(),
+            known: KnownApps::default(),
+            last_event_id: event_id.unwrap_or_else(|| unsafe { fs::FSEventsGetCurrentEventId() }),
+            replaying: event_id.is_some(),
+            closed: Arc::new(AtomicBool::new(false)),
+            renames: HashMap::new(),
+        };
         for search_path in search_paths {
             let search_path = search_path.as_ref();
             if !search_path.is_dir() {
-                return Err(anyhow::anyhow!("search_path is not a directory"));
+                return Err(Error::NotADirectory(search_path.to_path_buf()));
             }
+            watcher.add_search_path(search_path);
+        }
+        watcher.restart()?;
 
-            let owned_fd = open(search_path, OFlag::O_RDONLY, Mode::empty())?;
-            let raw_fd = owned_fd.into_raw_fd();
-            search_paths_with_fd_info.insert(raw_fd, search_path.to_path_buf());
-            let kevent = KEvent::new(
-                raw_fd as usize,
-                EventFilter::EVFILT_VNODE,
-                EvFlags::EV_ADD | EvFlags::EV_CLEAR,
-                watch_flag(),
-                0,
-                0,
-            );
-
-            kevent_to_register.push(kevent);
+        Ok(watcher)
+    }
 
-            let apps = get_current_apps(&search_path)?;
-            prev_app_list.insert(raw_fd, apps);
+    fn add_search_path(&mut self, search_path: &Path) {
+        let bundles = find_bundles(search_path);
+        // What the apps were, for when they are deleted
+        for bundle in bundles.keys() {
+            if let Ok(app) = App::from_path(bundle) {
+                self.known.insert(bundle.clone(), app);
+            }
         }
-        kqueue.kevent(&kevent_to_register, &mut [], None)?;
+        self.search_paths.insert(search_path.to_path_buf(), bundles);
+    }
 
-        Ok(Self {
-            search_paths: search_paths_with_fd_info,
-            kqueue,
-            prev_app_list,
-        })
+    /// The ID of the last event we have handled, to [`Watcher::resume`]
+    /// from later.
+    pub fn last_event_id(&self) -> u64 {
+        self.last_event_id
     }
 
-    pub fn recv(&mut self) -> Result<Vec<Change>> {
+    /// Replace the stream with one for the current search paths, which
+    /// continues from the last event we have handled.
+    fn restart(&mut self) -> Result<()> {
+        if let Some(stream) = self.stream.take() {
+            stream.stop();
+        }
         if self.search_paths.is_empty() {
-            return Ok(Vec::new());
+            return Ok(());
         }
+        let paths: Vec<PathBuf> = self.search_paths.keys().cloned().collect();
+        self.stream = Some(Stream::start(&paths, self.last_event_id, self.tx.clone())?);
 
-        let kevent = unsafe { std::mem::MaybeUninit::<KEvent>::zeroed().assume_init() };
-        let mut buffer = vec![kevent; self.search_paths.len()];
+        Ok(())
+    }
 
-        let n_events = self.kqueue.kevent(&[], buffer.as_mut(), None)?;
+    pub fn recv(&mut self) -> Result<Vec<Change>> {
+        if self.closed.load(Ordering::Relaxed) {
+            self.close();
+            return Err(closed_error());
+        }
+        let events = self.rx.recv().map_err(|_| closed_error())?;
+        if self.closed.load(Ordering::Relaxed) {
+            self.close();
+            return Err(closed_error());
+        }
+        let mut changes = Vec::new();
+        for event in events {
+            self.last_event_id = self.last_event_id.max(event.id);
+            if event.flags & fs::kFSEventStreamEventFlagHistoryDone != 0 {
+                self.replaying = false;
+                continue;
+            }
+            // A search path, or the volume it is on, came or went
+            let root_flags = fs::kFSEventStreamEventFlagRootChanged
+                | fs::kFSEventStreamEventFlagMount
+                | fs::kFSEventStreamEventFlagUnmount;
+            if event.flags & root_flags != 0 {
+                self.check_roots(&mut changes);
+                continue;
+            }
+            let Some(search_path) = self
+                .search_paths
+                .keys()
+                .find(|search_path| event.path.starts_with(search_path))
+                .cloned()
+            else {
+                continue;
+            };
+
+            let rescan = fs::kFSEventStreamEventFlagMustScanSubDirs
+                | fs::kFSEventStreamEventFlagUserDropped
+                | fs::kFSEventStreamEventFlagKernelDropped;
+            if event.flags & rescan != 0 {
+                self.rescan(&search_path, &search_path, &mut changes);
+            } else if let Some(bundle) = bundle_of(&event.path, &search_path) {
+                self.update_bundle(&bundle, &search_path, &mut changes);
+            } else if event.flags & fs::kFSEventStreamEventFlagItemIsDir != 0 {
+                self.rescan(&event.path, &search_path, &mut changes);
+            }
+        }
+        let moves: Vec<(PathBuf, PathBuf)> = self
+            .renames
+            .drain()
+            .filter_map(|(_inode, paths)| match paths {
+                (Some(from), Some(to)) => Some((from, to)),
+                _ => None,
+            })
+            .collect();
+        pair_moves(&mut changes, &moves);
+        self.known.update(&mut changes);
 
-        let mut changes = Vec::with_capacity(n_events);
+        Ok(changes)
+    }
 
-        for kevent in buffer.iter().take(n_events) {
-            let raw_fd = kevent.ident() as i32;
-            let fflag = kevent.fflags();
-            let search_path_name = self
-                .search_paths
-                .get(&raw_fd)
-                .expect("an event occurred on a search path that we do not watch")
-                .clone();
-
-            if fflag.contains(FilterFlag::NOTE_WRITE) {
-                let prev_app_list = self
-                    .prev_app_list
-                    .get(&raw_fd)
-                    .expect("an event occurred on a search path that we do not watch");
-                let current_app_list = get_current_apps(&search_path_name)?;
-
-                let apps_deleted = prev_app_list.difference(&current_app_list);
-                let apps_added = current_app_list.difference(prev_app_list);
-
-                for app_deleted in apps_deleted {
-                    changes.push(Change::AppDeleted {
-                        app_path: app_deleted.clone(),
-                    });
+    /// Report the search paths that are gone as lost, and the apps in the
+    /// ones that are back as installed.
+    fn check_roots(&mut self, changes: &mut Vec<Change>) {
+        let search_paths: Vec<PathBuf> = self.search_paths.keys().cloned().collect();
+        for search_path in search_paths {
+            let exists = search_path.is_dir();
+            if !exists && self.lost.insert(search_path.clone()) {
+                // Nothing is known about it until it is back
+                if let Some(known) = self.search_paths.get_mut(&search_path) {
+                    known.clear();
                 }
+                self.known.forget_below(&search_path);
+                changes.push(Change::WatchLost { search_path });
+            } else if exists && self.lost.remove(&search_path) {
+                self.rescan(&search_path, &search_path, changes);
+            }
+        }
+    }
 
-                for app_added in apps_added {
-                    changes.push(Change::AppInstalled {
-                        app_path: app_added.clone(),
-                    });
-                }
+    /// Report `bundle` as installed or deleted if that is news to us.
+    ///
+    /// Installers often update an app by moving the old bundle away and a
+    /// new one in its place, which we may only see once both are done. The
+    /// new bundle is another inode, and it is reported as deleted and then
+    /// installed again. A bundle that is only moved keeps its inode.
+    fn update_bundle(&mut self, bundle: &Path, search_path: &Path, changes: &mut Vec<Change>) {
+        let known_inode = self.search_paths[search_path].get(bundle).copied();
+        let inode = bundle_inode(bundle);
+        let replaced = known_inode.is_some() && inode.is_some() && known_inode != inode;
+        // While replaying, `known` is already up to date, but the changes
+        // it was updated with have not been reported yet
+        let report = if self.replaying {
+            !changes.iter().any(|change| change_path(change) == bundle)
+        } else {
+            inode.is_some() != known_inode.is_some() || replaced
+        };
+        if !report {
+            return;
+        }
 
-                *self
-                    .prev_app_list
-                    .get_mut(&raw_fd)
-                    .expect("an event occurred on a search path that do not watch") =
-                    current_app_list;
+        let known = self
+            .search_paths
+            .get_mut(search_path)
+            .expect("events are only handled for watched search paths");
+        if replaced {
+            known.remove(bundle);
+            changes.push(Change::AppDeleted {
+                app_path: bundle.to_path_buf(),
+                app: None,
+            });
+        }
+        if let Some(inode) = inode {
+            if let Some(change) = installed_bundle(bundle, search_path) {
+                known.insert(bundle.to_path_buf(), inode);
+                changes.push(change);
+                if !replaced {
+                    self.renames.entry(inode).or_default().1 = Some(bundle.to_path_buf());
+                }
+            }
+        } else {
+            if let Some(inode) = known_inode {
+                self.renames.entry(inode).or_default().0 = Some(bundle.to_path_buf());
             }
+            known.remove(bundle);
+            changes.push(Change::AppDeleted {
+                app_path: bundle.to_path_buf(),
+                app: None,
+            });
         }
+    }
 
-        Ok(changes)
+    /// Compare the app bundles below `dir` with the ones we know about.
+    fn rescan(&mut self, dir: &Path, search_path: &Path, changes: &mut Vec<Change>) {
+        let current = if dir.is_dir() {
+            find_bundles(dir)
+        } else {
+            HashMap::new()
+        };
+        let mut bundles: HashSet<PathBuf> = self.search_paths[search_path]
+            .keys()
+            .filter(|bundle| bundle.starts_with(dir))
+            .cloned()
+            .collect();
+        bundles.extend(current.into_keys());
+
+        for bundle in bundles {
+            self.update_bundle(&bundle, search_path, changes);
+        }
     }
 
     pub fn unwatch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
         let search_path = search_path.as_ref();
 
-        let fd = *self
-            .search_paths
-            .iter()
-            .find(|(_fd, path)| *path == search_path)
-            .unwrap_or_else(|| {
-                panic!(
-                    "search path [{}] has not been watched",
-                    search_path.display()
-                )
-            })
-            .0;
-
-        self.search_paths
-            .remove(&fd)
-            .expect("it has just been checked");
-        self.prev_app_list.remove(&fd).unwrap_or_else(|| {
-            panic!(
-                "search path [{}] has not been watched",
-                search_path.display()
-            )
-        });
-
-        let kevent = KEvent::new(
-            fd as usize,
-            EventFilter::EVFILT_VNODE,
-            EvFlags::EV_DELETE,
-            FilterFlag::empty(),
-            0,
-            0,
-        );
-        self.kqueue.kevent(&[kevent], &mut [], None)?;
+        self.lost.remove(search_path);
+        self.known.forget_below(search_path);
+        if self.search_paths.remove(search_path).is_none() {
+            return Err(Error::NotWatched(search_path.to_path_buf()));
+        }
 
-        Ok(())
+        self.restart()
     }
 
     pub fn watch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
         let search_path = search_path.as_ref();
 
         if !search_path.is_dir() {
-            return Err(anyhow::anyhow!("search_path is not a directory"));
-        }
-        let owned_fd = open(search_path, OFlag::O_RDONLY, Mode::empty())?;
-        let raw_fd = owned_fd.into_raw_fd();
-        self.search_paths.insert(raw_fd, search_path.to_path_buf());
-        let kevent = KEvent::new(
-            raw_fd as usize,
-            EventFilter::EVFILT_VNODE,
-            EvFlags::EV_ADD | EvFlags::EV_CLEAR,
-            watch_flag(),
-            0,
-            0,
-        );
+            return Err(Error::NotADirectory(search_path.to_path_buf()));
+        }
+        self.add_search_path(search_path);
+
+        self.restart()
+    }
 
-        let apps = get_current_apps(&search_path)?;
-        self.prev_app_list.insert(raw_fd, apps);
+    /// Watch `search_path`, which does not exist yet, as a lost search
+    /// path.
+    pub fn watch_when_created<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+        self.search_paths
+            .insert(search_path.to_path_buf(), HashMap::new());
+        self.lost.insert(search_path.to_path_buf());
 
-        self.kqueue.kevent(&[kevent], &mut [], None)?;
+        self.restart()
+    }
 
-        Ok(())
+    pub fn initial_state(&self, search_paths: &HashSet<PathBuf>) -> Vec<Change> {
+        self.known.installed(search_paths)
     }
 
     pub fn watch_list_is_empty(&self) -> bool {
         self.search_paths.is_empty()
     }
+
+    pub fn closer(&self) -> Closer {
+        Closer {
+            tx: self.tx.clone(),
+            closed: Arc::clone(&self.closed),
+        }
+    }
+
+    /// Stop the FSEvents stream. `recv()` returns an error from now on.
+    pub fn close(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        if let Some(stream) = self.stream.take() {
+            stream.stop();
+        }
+        self.search_paths.clear();
+        self.lost.clear();
+        self.known = KnownApps::default();
+    }
 }
 
-fn get_current_apps<P: AsRef<Path> + ?Sized>(path: &P) -> Result<HashSet<PathBuf>> {
-    let list = fs::read_dir(path)?
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            (path.is_dir() && path.extension()? == "app").then_some(path)
-        })
-        .collect();
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn change_path(change: &Change) -> &Path {
+    match change {
+        Change::AppInstalled { app_path, .. } | Change::AppDeleted { app_path, .. } => app_path,
+        Change::AppMoved { to, .. } => to,
+        Change::WatchLost { search_path } => search_path,
+    }
+}
+
+/// The outermost app bundle `path` is in, or is, below `search_path`.
+fn bundle_of(path: &Path, search_path: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(search_path).ok()?;
+    let mut bundle = search_path.to_path_buf();
+    for component in relative.components() {
+        bundle.push(component);
+        if bundle.extension().is_some_and(|ext| ext == "app") {
+            return Some(bundle);
+        }
+    }
+
+    None
+}
+
+/// The inode of `bundle`, `None` if it does not exist.
+fn bundle_inode(bundle: &Path) -> Option<u64> {
+    let metadata = bundle.metadata().ok()?;
+    metadata.is_dir().then(|| metadata.ino())
+}
+
+/// The app bundles below `dir`, without the ones nested in other bundles,
+/// and their inodes.
+fn find_bundles(dir: &Path) -> HashMap<PathBuf, u64> {
+    let mut bundles = HashMap::new();
+    let mut walker = WalkDir::new(dir).max_depth(MAX_SCAN_DEPTH).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        let hidden = entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.');
+        if path.extension().is_some_and(|ext| ext == "app") {
+            if let Ok(metadata) = entry.metadata() {
+                bundles.insert(path.to_path_buf(), metadata.ino());
+            }
+            walker.skip_current_dir();
+        } else if hidden {
+            walker.skip_current_dir();
+        }
+    }
 
-    Ok(list)
+    bundles
+}
+
+/// Parse a new app bundle, which is still being copied until it is
+/// complete, see [`MacAppPath::check_complete`].
+pub(super) fn parse_bundle(path: &Path) -> Result<Option<App>> {
+    MacAppPath::new(path.to_path_buf()).check_complete()?;
+
+    Ok(App::from_path(path).ok())
+}
+
+fn installed_bundle(app_path: &Path, search_path: &Path) -> Option<Change> {
+    let app = parse_with_retry(|| parse_bundle(app_path))?;
+    Some(Change::AppInstalled {
+        app_path: app_path.to_path_buf(),
+        app: Box::new(app),
+        search_path: Some(search_path.to_path_buf()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_bundle_of() {
+        let search_path = Path::new("/Applications");
+        assert_eq!(
+            bundle_of(
+                Path::new("/Applications/Utilities/Terminal.app/Contents/Info.plist"),
+                search_path
+            ),
+            Some(PathBuf::from("/Applications/Utilities/Terminal.app"))
+        );
+        assert_eq!(
+            bundle_of(Path::new("/Applications/Utilities"), search_path),
+            None
+        );
+        assert_eq!(bundle_of(Path::new("/Library/Foo.app"), search_path), None);
+    }
+
+    #[test]
+    fn test_find_bundles() {
+        let temp_dir = TempDir::new("fsevents-bundles");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("Foo.app/Contents/Helpers/Bar.app")).unwrap();
+        std::fs::create_dir_all(dir.join("Vendor/Baz.app")).unwrap();
+        std::fs::create_dir_all(dir.join(".hidden/Qux.app")).unwrap();
+
+        let bundles: HashSet<PathBuf> = find_bundles(&dir).into_keys().collect();
+        assert_eq!(
+            bundles,
+            HashSet::from([dir.join("Foo.app"), dir.join("Vendor/Baz.app")])
+        );
+    }
+
+    #[test]
+    fn test_bundle_inode_of_replaced_bundle() {
+        let temp_dir = TempDir::new("fsevents-replaced");
+        let dir = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(dir.join("Foo.app")).unwrap();
+        std::fs::create_dir_all(dir.join("staging/Foo.app")).unwrap();
+
+        let old_inode = bundle_inode(&dir.join("Foo.app")).unwrap();
+        std::fs::rename(dir.join("Foo.app"), dir.join("Foo.app.old")).unwrap();
+        std::fs::rename(dir.join("staging/Foo.app"), dir.join("Foo.app")).unwrap();
+        assert_ne!(bundle_inode(&dir.join("Foo.app")), Some(old_inode));
+        assert_eq!(bundle_inode(&dir.join("Bar.app")), None);
+    }
 }
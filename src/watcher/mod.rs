@@ -1,28 +1,753 @@
-use std::path::PathBuf;
+use crate::trace::debug;
+use crate::{App, AppIdentity, Error, Result, get_default_search_paths};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[cfg(target_os = "linux")]
 mod linux;
 #[cfg(target_os = "linux")]
-pub use linux::*;
+use linux as native;
 
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
-pub use macos::*;
+use macos as native;
 
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use windows::*;
+use windows as native;
 
+mod handle;
+mod polling;
+mod queue;
+
+pub use handle::WatcherHandle;
+pub use queue::{ChangeReceiver, QueuePolicy};
+
+/// How a [`Watcher`] learns about changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// inotify on Linux, FSEvents on macOS, and `ReadDirectoryChangesW`,
+    /// the registry and the package catalog on Windows.
+    #[default]
+    Native,
+    /// Scan the search paths every `interval` and compare them with the
+    /// last scan, for file systems that do not report changes, e.g., NFS
+    /// or FUSE mounts.
+    Polling { interval: Duration },
+}
+
+enum Inner {
+    Native(native::Watcher),
+    Polling(polling::Watcher),
+}
+
+pub struct Watcher {
+    inner: Inner,
+    /// What we have been asked to watch, to not watch it twice.
+    search_paths: HashSet<PathBuf>,
+    /// Returned by the next `recv()`, see [`Watcher::with_initial_state`].
+    pending: Vec<Change>,
+}
+
+/// Closes a [`Watcher`], possibly blocked in `recv()` on another thread.
+#[derive(Clone)]
+pub struct Closer {
+    inner: CloserInner,
+}
+
+#[derive(Clone)]
+enum CloserInner {
+    Native(native::Closer),
+    Polling(polling::Closer),
+}
+
+impl Closer {
+    /// Make a `recv()` blocked on another thread return, with no changes.
+    pub fn wake(&self) {
+        match &self.inner {
+            CloserInner::Native(closer) => closer.wake(),
+            CloserInner::Polling(closer) => closer.wake(),
+        }
+    }
+
+    /// Close the watcher, whose `recv()` then returns an error. Does
+    /// nothing if it is already closed or dropped.
+    pub fn close(&self) {
+        match &self.inner {
+            CloserInner::Native(closer) => closer.close(),
+            CloserInner::Polling(closer) => closer.close(),
+        }
+    }
+}
+
+impl Watcher {
+    pub fn new<P: AsRef<Path>>(search_paths: &[P]) -> Result<Self> {
+        Self::with_backend(search_paths, Backend::Native)
+    }
+
+    pub fn with_backend<P: AsRef<Path>>(search_paths: &[P], backend: Backend) -> Result<Self> {
+        let inner = match backend {
+            Backend::Native => Inner::Native(native::Watcher::new(search_paths)?),
+            Backend::Polling { interval } => {
+                Inner::Polling(polling::Watcher::new(search_paths, interval)?)
+            }
+        };
+
+        Ok(Self {
+            inner,
+            search_paths: to_path_bufs(search_paths),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Watch `search_paths` with FSEvents, and first report the changes
+    /// made since `event_id`, a value of [`Watcher::last_event_id`] saved
+    /// from an earlier run.
+    #[cfg(target_os = "macos")]
+    pub fn resume<P: AsRef<Path>>(search_paths: &[P], event_id: u64) -> Result<Self> {
+        Ok(Self {
+            inner: Inner::Native(native::Watcher::resume(search_paths, event_id)?),
+            search_paths: to_path_bufs(search_paths),
+            pending: Vec::new(),
+        })
+    }
+
+    /// The ID of the last FSEvents event we have handled, `None` for the
+    /// polling backend.
+    #[cfg(target_os = "macos")]
+    pub fn last_event_id(&self) -> Option<u64> {
+        match &self.inner {
+            Inner::Native(watcher) => Some(watcher.last_event_id()),
+            Inner::Polling(_) => None,
+        }
+    }
+
+    /// Report the apps that are already installed, as `AppInstalled`
+    /// changes returned by the next `recv()`, before any change made since
+    /// they were found. A state built from the changes alone is then
+    /// complete, with no window between a [`get_all_apps`](crate::get_all_apps)
+    /// and the watcher starting.
+    ///
+    /// ```no_run
+    /// # use applications::watcher::{Change, Watcher};
+    /// let mut watcher = Watcher::new(&["/usr/share/applications"])?.with_initial_state();
+    /// while let Ok(changes) = watcher.recv() {
+    ///     for change in changes {
+    ///         if let Change::AppInstalled { app, .. } = change {
+    ///             println!("{}", app.name);
+    ///         }
+    ///     }
+    /// }
+    /// # Ok::<(), applications::Error>(())
+    /// ```
+    pub fn with_initial_state(mut self) -> Self {
+        let mut installed = match &self.inner {
+            Inner::Native(watcher) => watcher.initial_state(&self.search_paths),
+            Inner::Polling(watcher) => watcher.initial_state(&self.search_paths),
+        };
+        installed.append(&mut self.pending);
+        self.pending = installed;
+
+        self
+    }
+
+    /// Wait for changes, which may come back empty. Returns an error once
+    /// the watcher is closed.
+    pub fn recv(&mut self) -> Result<Vec<Change>> {
+        if !self.pending.is_empty() {
+            return Ok(std::mem::take(&mut self.pending));
+        }
+        let changes = match &mut self.inner {
+            Inner::Native(watcher) => watcher.recv()?,
+            Inner::Polling(watcher) => watcher.recv()?,
+        };
+        debug!(?changes, "changed");
+
+        Ok(changes)
+    }
+
+    pub fn watch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+        match &mut self.inner {
+            Inner::Native(watcher) => watcher.watch(search_path)?,
+            Inner::Polling(watcher) => watcher.watch(search_path)?,
+        }
+        debug!(path = %search_path.display(), "watching");
+        self.search_paths.insert(search_path.to_path_buf());
+
+        Ok(())
+    }
+
+    /// Watch `search_path`, which does not exist yet, once it is created.
+    /// Its apps are then reported as installed.
+    pub fn watch_when_created<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+        match &mut self.inner {
+            Inner::Native(watcher) => watcher.watch_when_created(search_path)?,
+            Inner::Polling(watcher) => watcher.watch_when_created(search_path)?,
+        }
+        debug!(path = %search_path.display(), "watching once created");
+        self.search_paths.insert(search_path.to_path_buf());
+
+        Ok(())
+    }
+
+    /// Watch the default search paths we do not watch yet. The ones that
+    /// do not exist, e.g., `~/Applications` before the first Safari web
+    /// app, or `~/.local/share/flatpak/app` before the first per-user
+    /// Flatpak, are watched once they are created.
+    pub fn watch_defaults(&mut self) -> Result<()> {
+        for search_path in get_default_search_paths() {
+            if self.search_paths.contains(&search_path) {
+                continue;
+            }
+            if search_path.is_dir() {
+                self.watch(&search_path)?;
+            } else {
+                self.watch_when_created(&search_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn unwatch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+        match &mut self.inner {
+            Inner::Native(watcher) => watcher.unwatch(search_path)?,
+            Inner::Polling(watcher) => watcher.unwatch(search_path)?,
+        }
+        debug!(path = %search_path.display(), "unwatched");
+        self.search_paths.remove(search_path);
+
+        Ok(())
+    }
+
+    pub fn watch_list_is_empty(&self) -> bool {
+        match &self.inner {
+            Inner::Native(watcher) => watcher.watch_list_is_empty(),
+            Inner::Polling(watcher) => watcher.watch_list_is_empty(),
+        }
+    }
+
+    pub fn closer(&self) -> Closer {
+        let inner = match &self.inner {
+            Inner::Native(watcher) => CloserInner::Native(watcher.closer()),
+            Inner::Polling(watcher) => CloserInner::Polling(watcher.closer()),
+        };
+        Closer { inner }
+    }
+
+    /// Stop watching and release the kernel resources, which dropping the
+    /// watcher does as well. `recv()` returns an error from now on.
+    pub fn close(&mut self) {
+        match &mut self.inner {
+            Inner::Native(watcher) => watcher.close(),
+            Inner::Polling(watcher) => watcher.close(),
+        }
+        self.search_paths.clear();
+        self.pending.clear();
+    }
+}
+
+fn to_path_bufs<P: AsRef<Path>>(paths: &[P]) -> HashSet<PathBuf> {
+    paths
+        .iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect()
+}
+
+/// A change to the apps in the watched search paths.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Change {
     AppInstalled {
         app_path: PathBuf,
+        /// The new app, parsed from `app_path`.
+        app: Box<App>,
+        /// The watched search path `app_path` is in, `None` for apps that
+        /// are not found through one, e.g., Windows apps only registered
+        /// in the registry.
+        search_path: Option<PathBuf>,
     },
     /// NOTE: Since `app_path` has been deleted, so there is no way we can check it, which
     /// means there are cases where `app_path` is not a an application.
     AppDeleted {
         app_path: PathBuf,
+        /// The app as we last parsed it, `None` if we never did, e.g., for
+        /// files that were not apps.
+        app: Option<Box<App>>,
+    },
+    /// The app at `from` was renamed, or moved to another directory, e.g.,
+    /// between two watched search paths, and is now at `to`. The polling
+    /// backend cannot tell, and reports a deletion and an installation.
+    AppMoved {
+        from: PathBuf,
+        to: PathBuf,
+        /// The app, parsed from `to`.
+        app: Box<App>,
+        /// The watched search path `to` is in.
+        search_path: Option<PathBuf>,
     },
+    /// `search_path` was deleted, moved away or unmounted, so changes to it
+    /// are no longer seen. It is watched again once it is back, and the
+    /// apps in it are then reported as installed.
+    WatchLost { search_path: PathBuf },
+}
+
+/// The changes from the apps of an earlier scan, `old`, to the ones of a
+/// later one, `new`, matched by their [`App::identity`]: an `AppDeleted`
+/// for each app that is gone, then, in the order of `new`, an `AppMoved`
+/// for each app whose [`App::app_desktop_path`] has changed, and an
+/// `AppInstalled` for each app that is new or has changed otherwise, e.g.,
+/// upgraded. Apps that share a path, e.g., a working directory, are told
+/// apart. `search_path` is always `None`, and only the first of the apps
+/// with the same identity is compared.
+///
+/// ```no_run
+/// use applications::watcher::diff;
+/// use applications::{get_all_apps, get_default_search_paths};
+///
+/// let search_paths = get_default_search_paths();
+/// let old = get_all_apps(&search_paths).unwrap();
+/// std::thread::sleep(std::time::Duration::from_secs(60));
+/// let new = get_all_apps(&search_paths).unwrap();
+/// for change in diff(&old, &new) {
+///     println!("{change:?}");
+/// }
+/// ```
+pub fn diff(old: &[App], new: &[App]) -> Vec<Change> {
+    diff_by(&by_identity(old), &by_identity(new))
+}
+
+fn by_identity(apps: &[App]) -> Vec<(AppIdentity, &Path, &App)> {
+    apps.iter()
+        .map(|app| (app.identity(), app.app_desktop_path.as_path(), app))
+        .collect()
+}
+
+/// [`diff`] of apps keyed by the path they were parsed from, which is not
+/// their `app_desktop_path` for Windows shortcuts.
+pub(crate) fn diff_by_path(old: &[(&Path, &App)], new: &[(&Path, &App)]) -> Vec<Change> {
+    fn with_key<'a>(apps: &[(&'a Path, &'a App)]) -> Vec<(&'a Path, &'a Path, &'a App)> {
+        apps.iter()
+            .map(|(app_path, app)| (*app_path, *app_path, *app))
+            .collect()
+    }
+
+    diff_by(&with_key(old), &with_key(new))
+}
+
+/// The changes from the `(key, app_path, app)`s of `old` to the ones of
+/// `new`, the first of the apps with the same key wins.
+fn diff_by<K: Eq + Hash>(old: &[(K, &Path, &App)], new: &[(K, &Path, &App)]) -> Vec<Change> {
+    let mut old_apps: HashMap<&K, (&Path, &App)> = HashMap::new();
+    for (key, app_path, app) in old {
+        old_apps.entry(key).or_insert((app_path, app));
+    }
+    let new_keys: HashSet<&K> = new.iter().map(|(key, ..)| key).collect();
+
+    let mut seen = HashSet::new();
+    let mut changes: Vec<Change> = old
+        .iter()
+        .filter(|(key, ..)| !new_keys.contains(key) && seen.insert(key))
+        .map(|(_, app_path, app)| Change::AppDeleted {
+            app_path: app_path.to_path_buf(),
+            app: Some(Box::new((*app).clone())),
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    for (key, app_path, app) in new {
+        if !seen.insert(key) {
+            continue;
+        }
+        changes.push(match old_apps.get(key) {
+            Some((_, old_app)) if old_app == app => continue,
+            Some((from, _)) if from != app_path => Change::AppMoved {
+                from: from.to_path_buf(),
+                to: app_path.to_path_buf(),
+                app: Box::new((*app).clone()),
+                search_path: None,
+            },
+            _ => Change::AppInstalled {
+                app_path: app_path.to_path_buf(),
+                app: Box::new((*app).clone()),
+                search_path: None,
+            },
+        });
+    }
+
+    changes
+}
+
+/// What `recv` returns once a watcher is closed.
+pub(crate) fn closed_error() -> Error {
+    Error::WatcherClosed
+}
+
+/// The apps a watcher has last parsed, keyed by their `app_path`, to tell
+/// what an `AppDeleted` was.
+#[derive(Default)]
+pub(crate) struct KnownApps(HashMap<PathBuf, App>);
+
+impl KnownApps {
+    pub(crate) fn insert(&mut self, app_path: PathBuf, app: App) {
+        self.0.insert(app_path, app);
+    }
+
+    pub(crate) fn get(&self, app_path: &Path) -> Option<&App> {
+        self.0.get(app_path)
+    }
+
+    /// Remember the apps `changes` install, and fill in the ones they
+    /// delete.
+    pub(crate) fn update(&mut self, changes: &mut [Change]) {
+        for change in changes {
+            match change {
+                Change::AppInstalled { app_path, app, .. } => {
+                    self.0.insert(app_path.clone(), (**app).clone());
+                }
+                Change::AppDeleted { app_path, app } => {
+                    if let Some(known) = self.0.remove(app_path)
+                        && app.is_none()
+                    {
+                        *app = Some(Box::new(known));
+                    }
+                }
+                Change::AppMoved { from, to, app, .. } => {
+                    self.0.remove(from);
+                    self.0.insert(to.clone(), (**app).clone());
+                }
+                Change::WatchLost { .. } => {}
+            }
+        }
+    }
+
+    /// An `AppInstalled` for each app, in the order of their paths, with
+    /// the innermost of `search_paths` it is in.
+    pub(crate) fn installed(&self, search_paths: &HashSet<PathBuf>) -> Vec<Change> {
+        let mut apps: Vec<(&PathBuf, &App)> = self.0.iter().collect();
+        apps.sort_by_key(|(app_path, _)| *app_path);
+        apps.into_iter()
+            .map(|(app_path, app)| Change::AppInstalled {
+                app_path: app_path.clone(),
+                app: Box::new(app.clone()),
+                search_path: search_paths
+                    .iter()
+                    .filter(|search_path| app_path.starts_with(search_path))
+                    .max_by_key(|search_path| search_path.components().count())
+                    .cloned(),
+            })
+            .collect()
+    }
+
+    /// The paths of the apps below `dir`.
+    pub(crate) fn paths_below(&self, dir: &Path) -> Vec<PathBuf> {
+        self.0
+            .keys()
+            .filter(|app_path| app_path.starts_with(dir))
+            .cloned()
+            .collect()
+    }
+
+    /// Forget the apps below `dir`, e.g., of a search path that is lost.
+    pub(crate) fn forget_below(&mut self, dir: &Path) {
+        self.0.retain(|app_path, _app| !app_path.starts_with(dir));
+    }
+}
+
+/// Turn the `AppDeleted` and the `AppInstalled` of each `(from, to)` of
+/// `moves` into one `AppMoved`, where the installation was. Moves whose
+/// `from` or `to` is not an app are left as they are, e.g., a desktop file
+/// renamed from a temporary name once it is written.
+pub(crate) fn pair_moves(changes: &mut Vec<Change>, moves: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moves {
+        let deleted = changes.iter().position(
+            |change| matches!(change, Change::AppDeleted { app_path, .. } if app_path == from),
+        );
+        let installed = changes.iter().position(
+            |change| matches!(change, Change::AppInstalled { app_path, .. } if app_path == to),
+        );
+        let (Some(deleted), Some(installed)) = (deleted, installed) else {
+            continue;
+        };
+        if let Change::AppInstalled {
+            app, search_path, ..
+        } = &mut changes[installed]
+        {
+            changes[installed] = Change::AppMoved {
+                from: from.clone(),
+                to: to.clone(),
+                app: std::mem::take(app),
+                search_path: search_path.take(),
+            };
+        }
+        changes.remove(deleted);
+    }
+}
+
+/// How many times, and how far apart, we try to parse a new app that is
+/// still being copied: twice as long after each attempt, up to
+/// `PARSE_MAX_RETRY_DELAY`, for large bundles, about half a minute in all.
+const PARSE_ATTEMPTS: u32 = 10;
+const PARSE_RETRY_DELAY: Duration = Duration::from_millis(300);
+const PARSE_MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How long to wait after the failed `attempt`, counted from 1.
+fn parse_retry_delay(attempt: u32) -> Duration {
+    PARSE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(PARSE_MAX_RETRY_DELAY)
+}
+
+/// Parse a newly installed app with `parse`, which returns an error while
+/// the app is incomplete and `Ok(None)` if it is not an app at all.
+pub(crate) fn parse_with_retry(mut parse: impl FnMut() -> Result<Option<App>>) -> Option<App> {
+    for attempt in 1..=PARSE_ATTEMPTS {
+        match parse() {
+            Ok(app) => return app,
+            Err(_) if attempt < PARSE_ATTEMPTS => std::thread::sleep(parse_retry_delay(attempt)),
+            Err(_) => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    /// Close `watcher` unless the returned sender is dropped within
+    /// `timeout`, so that waiting for a change that never comes fails the
+    /// test instead of hanging it.
+    #[cfg(target_os = "linux")]
+    fn deadline(watcher: &Watcher, timeout: Duration) -> std::sync::mpsc::Sender<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let closer = watcher.closer();
+        std::thread::spawn(move || {
+            if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(timeout)
+            {
+                closer.close();
+            }
+        });
+
+        sender
+    }
+
+    #[test]
+    fn test_diff() {
+        let app = |name: &str, version: &str| App {
+            name: name.to_string(),
+            app_desktop_path: PathBuf::from(format!("/apps/{name}.desktop")),
+            version: Some(version.to_string()),
+            ..Default::default()
+        };
+        let old = [app("foo", "1"), app("bar", "1"), app("baz", "1")];
+        let new = [app("baz", "2"), app("foo", "1"), app("qux", "1")];
+
+        let changes = diff(&old, &new);
+        assert!(matches!(
+            changes.as_slice(),
+            [
+                Change::AppDeleted { app: Some(deleted), .. },
+                Change::AppInstalled { app: upgraded, .. },
+                Change::AppInstalled { app: installed, .. },
+            ] if deleted.name == "bar"
+                && upgraded.name == "baz"
+                && upgraded.version.as_deref() == Some("2")
+                && installed.name == "qux"
+        ));
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    // Bundles are identified by their path on macOS
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_diff_apps_sharing_a_working_directory() {
+        let app = |dir: &str, name: &str| App {
+            name: name.to_string(),
+            app_desktop_path: PathBuf::from(dir),
+            app_path_exe: Some(PathBuf::from(format!("/windows/system32/{name}.exe"))),
+            ..Default::default()
+        };
+        let old = [
+            app("/windows/system32", "notepad"),
+            app("/windows/system32", "calc"),
+        ];
+
+        let changes = diff(&old, &old[..1]);
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppDeleted { app: Some(deleted), .. }] if deleted.name == "calc"
+        ));
+
+        let new = [app("/windows", "notepad"), app("/windows/system32", "calc")];
+        let changes = diff(&old, &new);
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppMoved { from, to, app, .. }]
+                if from == Path::new("/windows/system32")
+                    && to == Path::new("/windows")
+                    && app.name == "notepad"
+        ));
+    }
+
+    #[test]
+    fn test_parse_with_retry() {
+        let mut attempts = 0;
+        let app = parse_with_retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(Error::parse("/apps/foo.desktop", "still being copied"))
+            } else {
+                Ok(Some(App::default()))
+            }
+        });
+        assert!(app.is_some());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let app = parse_with_retry(|| {
+            attempts += 1;
+            Ok(None)
+        });
+        assert!(app.is_none());
+        assert_eq!(attempts, 1);
+
+        assert_eq!(parse_retry_delay(1), PARSE_RETRY_DELAY);
+        assert_eq!(parse_retry_delay(2), PARSE_RETRY_DELAY * 2);
+        assert_eq!(parse_retry_delay(PARSE_ATTEMPTS), PARSE_MAX_RETRY_DELAY);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_watch_when_created() {
+        let temp_dir = TempDir::new("when-created");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let search_path = root.join("not/yet");
+        let mut watcher = Watcher::new::<PathBuf>(&[]).unwrap();
+        watcher.watch_when_created(&search_path).unwrap();
+
+        std::fs::create_dir_all(&search_path).unwrap();
+        let desktop_file = search_path.join("foo.desktop");
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        let deadline = deadline(&watcher, Duration::from_secs(10));
+        loop {
+            let changes = watcher.recv().expect("installed within the deadline");
+            if changes.iter().any(|change| {
+                matches!(change, Change::AppInstalled { app_path, .. } if *app_path == desktop_file)
+            }) {
+                break;
+            }
+        }
+        drop(deadline);
+
+        watcher.unwatch(&search_path).unwrap();
+        assert!(watcher.watch_list_is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_initial_state() {
+        let temp_dir = TempDir::new("initial-state");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let desktop_file = root.join("foo.desktop");
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+
+        let mut watcher = Watcher::new(&[&root]).unwrap().with_initial_state();
+        let changes = watcher.recv().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            &changes[0],
+            Change::AppInstalled { app_path, search_path: Some(search_path), .. }
+                if *app_path == desktop_file && *search_path == root
+        ));
+    }
+
+    #[test]
+    fn test_pair_moves() {
+        let installed = |path: &str| Change::AppInstalled {
+            app_path: PathBuf::from(path),
+            app: Box::default(),
+            search_path: None,
+        };
+        let deleted = |path: &str| Change::AppDeleted {
+            app_path: PathBuf::from(path),
+            app: None,
+        };
+        let mut changes = vec![
+            deleted("/apps/foo.desktop"),
+            installed("/apps/bar.desktop"),
+            installed("/apps/baz.desktop"),
+        ];
+        pair_moves(
+            &mut changes,
+            &[
+                (
+                    PathBuf::from("/apps/foo.desktop"),
+                    PathBuf::from("/apps/bar.desktop"),
+                ),
+                (
+                    PathBuf::from("/apps/baz.desktop.tmp"),
+                    PathBuf::from("/apps/baz.desktop"),
+                ),
+            ],
+        );
+
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(
+            &changes[0],
+            Change::AppMoved { from, to, .. }
+                if *from == Path::new("/apps/foo.desktop") && *to == Path::new("/apps/bar.desktop")
+        ));
+        assert!(matches!(&changes[1], Change::AppInstalled { .. }));
+    }
+
+    #[test]
+    fn test_known_apps() {
+        let mut known = KnownApps::default();
+        let app = App {
+            name: "Foo".to_string(),
+            ..Default::default()
+        };
+        known.insert(PathBuf::from("/apps/foo.desktop"), app.clone());
+
+        let mut changes = vec![
+            Change::AppDeleted {
+                app_path: PathBuf::from("/apps/foo.desktop"),
+                app: None,
+            },
+            Change::AppDeleted {
+                app_path: PathBuf::from("/apps/foo.desktop"),
+                app: None,
+            },
+        ];
+        known.update(&mut changes);
+        assert!(
+            matches!(&changes[0], Change::AppDeleted { app: Some(deleted), .. } if **deleted == app)
+        );
+        assert!(matches!(&changes[1], Change::AppDeleted { app: None, .. }));
+    }
 }
@@ -0,0 +1,351 @@
+use super::{Change, KnownApps, closed_error, diff_by_path};
+use crate::{App, Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+#[cfg(target_os = "linux")]
+use super::linux::parse_desktop_file as parse_app;
+#[cfg(target_os = "macos")]
+use super::macos::parse_bundle as parse_app;
+#[cfg(target_os = "windows")]
+use super::windows::parse_shortcut as parse_app;
+
+/// The files, or bundles, apps are parsed from, and when they were last
+/// modified.
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+/// Whether the entry is a desktop file, an app bundle or a shortcut.
+fn is_app(entry: &walkdir::DirEntry) -> bool {
+    let extension = if cfg!(target_os = "macos") {
+        "app"
+    } else if cfg!(target_os = "windows") {
+        "lnk"
+    } else {
+        "desktop"
+    };
+    let file_type = entry.file_type();
+    (file_type.is_file() || (cfg!(target_os = "macos") && file_type.is_dir()))
+        && entry.path().extension().is_some_and(|ext| ext == extension)
+}
+
+/// When `path` was last modified. For bundles, this is their `Info.plist`,
+/// since the modification time of a directory only changes with its
+/// entries.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let path = if cfg!(target_os = "macos") {
+        path.join("Contents/Info.plist")
+    } else {
+        path.to_path_buf()
+    };
+    path.metadata().ok()?.modified().ok()
+}
+
+fn scan(search_path: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    let mut walker = WalkDir::new(search_path).into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !is_app(&entry) {
+            continue;
+        }
+        if entry.file_type().is_dir() {
+            walker.skip_current_dir();
+        }
+        snapshot.insert(entry.path().to_path_buf(), modified(entry.path()));
+    }
+
+    snapshot
+}
+
+/// Compare a scan of `search_path` with the `previous` one, whose apps are
+/// `known`. Only the modified apps are parsed again, the ones that are
+/// still incomplete keep their previous modification time in `current`,
+/// so that they are tried again on the next scan.
+fn diff(
+    previous: &Snapshot,
+    current: &mut Snapshot,
+    search_path: &Path,
+    known: &KnownApps,
+) -> Vec<Change> {
+    let old: Vec<(&Path, &App)> = previous
+        .keys()
+        .filter_map(|path| Some((path.as_path(), known.get(path)?)))
+        .collect();
+
+    let mut new: Vec<(&Path, &App)> = Vec::new();
+    let mut parsed: Vec<(&Path, App)> = Vec::new();
+    for (path, modified) in current.iter_mut() {
+        let previous_modified = previous.get(path);
+        if previous_modified != Some(modified) {
+            match parse_app(path) {
+                Ok(Some(app)) => {
+                    parsed.push((path, app));
+                    continue;
+                }
+                Ok(None) => continue,
+                Err(_) => *modified = previous_modified.copied().flatten(),
+            }
+        }
+        new.extend(known.get(path).map(|app| (path.as_path(), app)));
+    }
+    new.extend(parsed.iter().map(|(path, app)| (*path, app)));
+
+    // Modified apps are installed again, e.g., upgraded
+    let mut changes = diff_by_path(&old, &new);
+    for change in changes.iter_mut() {
+        if let Change::AppInstalled {
+            search_path: change_search_path,
+            ..
+        } = change
+        {
+            *change_search_path = Some(search_path.to_path_buf());
+        }
+    }
+
+    changes
+}
+
+/// A search path and the result of its last scan, `None` while it is lost.
+struct Watched {
+    search_path: PathBuf,
+    snapshot: Option<Snapshot>,
+}
+
+/// What a [`Closer`] asked for.
+#[derive(Default)]
+struct Requests {
+    wake: bool,
+    close: bool,
+}
+
+/// The requests of [`Closer`]s, and the condition variable that wakes up
+/// a `recv()` waiting for the next scan.
+type CloseState = Arc<(Mutex<Requests>, Condvar)>;
+
+/// Why a `recv()` stopped waiting.
+enum Wakeup {
+    Scan,
+    Woken,
+    Closed,
+}
+
+/// Wakes up or closes a polling [`Watcher`] from another thread.
+#[derive(Clone)]
+pub(super) struct Closer(CloseState);
+
+impl Closer {
+    pub(super) fn wake(&self) {
+        self.request(|requests| requests.wake = true);
+    }
+
+    pub(super) fn close(&self) {
+        self.request(|requests| requests.close = true);
+    }
+
+    fn request(&self, request: impl FnOnce(&mut Requests)) {
+        let (requests, condvar) = &*self.0;
+        request(&mut requests.lock().unwrap());
+        condvar.notify_all();
+    }
+}
+
+/// Rescans the search paths periodically.
+pub(super) struct Watcher {
+    watched: Vec<Watched>,
+    interval: Duration,
+    next_scan: Instant,
+    known: KnownApps,
+    close_state: CloseState,
+}
+
+impl Watcher {
+    pub(super) fn new<P: AsRef<Path>>(search_paths: &[P], interval: Duration) -> Result<Self> {
+        let mut watcher = Self {
+            watched: Vec::new(),
+            interval,
+            next_scan: Instant::now() + interval,
+            known: KnownApps::default(),
+            close_state: CloseState::default(),
+        };
+        for search_path in search_paths {
+            watcher.watch(search_path)?;
+        }
+
+        Ok(watcher)
+    }
+
+    pub(super) fn recv(&mut self) -> Result<Vec<Change>> {
+        loop {
+            match self.wait_for_next_scan() {
+                Wakeup::Scan => {}
+                Wakeup::Woken => return Ok(Vec::new()),
+                Wakeup::Closed => {
+                    self.close();
+                    return Err(closed_error());
+                }
+            }
+            self.next_scan = Instant::now() + self.interval;
+
+            let mut changes = Vec::new();
+            for watched in self.watched.iter_mut() {
+                let search_path = &watched.search_path;
+                if !search_path.is_dir() {
+                    if watched.snapshot.take().is_some() {
+                        self.known.forget_below(search_path);
+                        changes.push(Change::WatchLost {
+                            search_path: search_path.clone(),
+                        });
+                    }
+                    continue;
+                }
+                let mut current = scan(search_path);
+                let previous = watched.snapshot.take().unwrap_or_default();
+                changes.extend(diff(&previous, &mut current, search_path, &self.known));
+                watched.snapshot = Some(current);
+            }
+            self.known.update(&mut changes);
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+        }
+    }
+
+    /// Wait until it is time for the next scan, or a [`Closer`] asks for
+    /// something else.
+    fn wait_for_next_scan(&self) -> Wakeup {
+        let (requests, condvar) = &*self.close_state;
+        let mut requests = requests.lock().unwrap();
+        loop {
+            if requests.close {
+                return Wakeup::Closed;
+            }
+            if requests.wake {
+                requests.wake = false;
+                return Wakeup::Woken;
+            }
+            let now = Instant::now();
+            if now >= self.next_scan {
+                return Wakeup::Scan;
+            }
+            requests = condvar
+                .wait_timeout(requests, self.next_scan - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    pub(super) fn watch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+
+        if !search_path.is_dir() {
+            return Err(Error::NotADirectory(search_path.to_path_buf()));
+        }
+        let snapshot = scan(search_path);
+        // What the apps were, for when they are deleted
+        for app_path in snapshot.keys() {
+            if let Ok(Some(app)) = parse_app(app_path) {
+                self.known.insert(app_path.clone(), app);
+            }
+        }
+        self.watched.push(Watched {
+            search_path: search_path.to_path_buf(),
+            snapshot: Some(snapshot),
+        });
+
+        Ok(())
+    }
+
+    /// Watch `search_path`, which does not exist yet, as a lost search
+    /// path.
+    pub(super) fn watch_when_created<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        self.watched.push(Watched {
+            search_path: search_path.as_ref().to_path_buf(),
+            snapshot: None,
+        });
+
+        Ok(())
+    }
+
+    pub(super) fn unwatch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref();
+
+        let len = self.watched.len();
+        self.watched
+            .retain(|watched| watched.search_path != search_path);
+        if self.watched.len() == len {
+            return Err(Error::NotWatched(search_path.to_path_buf()));
+        }
+        self.known.forget_below(search_path);
+
+        Ok(())
+    }
+
+    pub(super) fn initial_state(&self, search_paths: &HashSet<PathBuf>) -> Vec<Change> {
+        self.known.installed(search_paths)
+    }
+
+    pub(super) fn watch_list_is_empty(&self) -> bool {
+        self.watched.is_empty()
+    }
+
+    pub(super) fn closer(&self) -> Closer {
+        Closer(Arc::clone(&self.close_state))
+    }
+
+    pub(super) fn close(&mut self) {
+        Closer(Arc::clone(&self.close_state)).close();
+        self.watched.clear();
+        self.known = KnownApps::default();
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    #[test]
+    fn test_polling_watcher() {
+        let temp_dir = TempDir::new("polling");
+        let root = temp_dir.path().to_path_buf();
+        std::fs::create_dir_all(root.join("vendor")).unwrap();
+        std::fs::write(root.join("icon.png"), b"").unwrap();
+        let mut watcher = Watcher::new(&[&root], Duration::from_millis(50)).unwrap();
+
+        let desktop_file = root.join("vendor/foo.desktop");
+        std::fs::write(&desktop_file, "").unwrap();
+        std::fs::write(
+            &desktop_file,
+            format!(
+                "[Desktop Entry]\nType=Application\nName=Foo\nExec=sh\nIcon={}",
+                root.join("icon.png").display()
+            ),
+        )
+        .unwrap();
+        let changes = watcher.recv().unwrap();
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppInstalled { app_path, app, .. }] if *app_path == desktop_file && app.name == "Foo"
+        ));
+
+        std::fs::remove_file(&desktop_file).unwrap();
+        let changes = watcher.recv().unwrap();
+        assert!(matches!(
+            changes.as_slice(),
+            [Change::AppDeleted { app_path, app: Some(app) }] if *app_path == desktop_file && app.name == "Foo"
+        ));
+
+        std::fs::remove_dir_all(&root).unwrap();
+        let changes = watcher.recv().unwrap();
+        assert!(matches!(changes.as_slice(), [Change::WatchLost { .. }]));
+
+        watcher.closer().close();
+        assert!(watcher.recv().is_err());
+        assert!(watcher.watch_list_is_empty());
+    }
+}
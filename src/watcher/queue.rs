@@ -0,0 +1,242 @@
+use super::Change;
+use std::collections::VecDeque;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How many changes a [`WatcherHandle`](super::WatcherHandle) queues for a
+/// consumer that falls behind, e.g., while an OS upgrade rewrites hundreds
+/// of desktop files, and what happens once that many are queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueuePolicy {
+    #[default]
+    Unbounded,
+    /// The watcher waits for the consumer, and does not handle changes or
+    /// `watch()` calls in the meantime. Shutting the handle down stops the
+    /// wait, and the changes not queued yet are dropped.
+    Block { capacity: usize },
+    /// The oldest changes are dropped, see [`ChangeReceiver::dropped`].
+    DropOldest { capacity: usize },
+}
+
+#[derive(Default)]
+struct State {
+    changes: VecDeque<Change>,
+    dropped: usize,
+    sender_gone: bool,
+    receiver_gone: bool,
+    /// Set by [`SendCloser::close`], nothing is sent from then on.
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<State>,
+    /// Notified when a change is queued or taken, either side is gone, or
+    /// the queue is closed.
+    changed: Condvar,
+}
+
+pub(super) fn queue(policy: QueuePolicy) -> (ChangeSender, ChangeReceiver) {
+    let queue = Arc::new(Queue::default());
+    let sender = ChangeSender {
+        queue: Arc::clone(&queue),
+        policy,
+    };
+    (sender, ChangeReceiver { queue })
+}
+
+pub(super) struct ChangeSender {
+    queue: Arc<Queue>,
+    policy: QueuePolicy,
+}
+
+impl ChangeSender {
+    /// Queue `change`. Fails once the receiver is gone or the queue is
+    /// closed, also while it waits for room.
+    pub(super) fn send(&self, change: Change) -> Result<(), Change> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if state.receiver_gone || state.closed {
+                return Err(change);
+            }
+            match self.policy {
+                QueuePolicy::Block { capacity } if state.changes.len() >= capacity.max(1) => {
+                    state = self.queue.changed.wait(state).unwrap();
+                    continue;
+                }
+                QueuePolicy::DropOldest { capacity } if state.changes.len() >= capacity.max(1) => {
+                    state.changes.pop_front();
+                    state.dropped += 1;
+                }
+                _ => {}
+            }
+            state.changes.push_back(change);
+            self.queue.changed.notify_all();
+            return Ok(());
+        }
+    }
+
+    pub(super) fn closer(&self) -> SendCloser {
+        SendCloser {
+            queue: Arc::clone(&self.queue),
+        }
+    }
+}
+
+/// Closes the queue of a [`ChangeSender`] from another thread, which wakes
+/// it up if it waits for room.
+pub(super) struct SendCloser {
+    queue: Arc<Queue>,
+}
+
+impl SendCloser {
+    pub(super) fn close(&self) {
+        self.queue.state.lock().unwrap().closed = true;
+        self.queue.changed.notify_all();
+    }
+}
+
+impl Drop for ChangeSender {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().sender_gone = true;
+        self.queue.changed.notify_all();
+    }
+}
+
+/// The changes seen by a [`WatcherHandle`](super::WatcherHandle), with the
+/// methods of a [`Receiver`](std::sync::mpsc::Receiver).
+pub struct ChangeReceiver {
+    queue: Arc<Queue>,
+}
+
+impl ChangeReceiver {
+    /// Wait for the next change. Fails once the watcher is closed and the
+    /// queued changes are taken.
+    pub fn recv(&self) -> Result<Change, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Change, RecvTimeoutError> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    pub fn try_recv(&self) -> Result<Change, TryRecvError> {
+        let mut state = self.queue.state.lock().unwrap();
+        match state.changes.pop_front() {
+            Some(change) => {
+                self.queue.changed.notify_all();
+                Ok(change)
+            }
+            None if state.sender_gone => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// The changes, until the watcher is closed.
+    pub fn iter(&self) -> impl Iterator<Item = Change> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    /// How many changes were dropped so far, with
+    /// [`QueuePolicy::DropOldest`].
+    pub fn dropped(&self) -> usize {
+        self.queue.state.lock().unwrap().dropped
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<Change, RecvTimeoutError> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if let Some(change) = state.changes.pop_front() {
+                self.queue.changed.notify_all();
+                return Ok(change);
+            }
+            if state.sender_gone {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            state = match deadline {
+                None => self.queue.changed.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.queue
+                        .changed
+                        .wait_timeout(state, deadline - now)
+                        .unwrap()
+                        .0
+                }
+            };
+        }
+    }
+}
+
+impl Drop for ChangeReceiver {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().receiver_gone = true;
+        self.queue.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn deleted(path: &str) -> Change {
+        Change::AppDeleted {
+            app_path: PathBuf::from(path),
+            app: None,
+        }
+    }
+
+    fn app_path(change: Change) -> PathBuf {
+        match change {
+            Change::AppDeleted { app_path, .. } => app_path,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (sender, receiver) = queue(QueuePolicy::DropOldest { capacity: 2 });
+        for path in ["/a", "/b", "/c"] {
+            sender.send(deleted(path)).unwrap();
+        }
+        drop(sender);
+
+        assert_eq!(receiver.dropped(), 1);
+        let paths: Vec<PathBuf> = receiver.iter().map(app_path).collect();
+        assert_eq!(paths, [PathBuf::from("/b"), PathBuf::from("/c")]);
+    }
+
+    #[test]
+    fn test_block() {
+        let (sender, receiver) = queue(QueuePolicy::Block { capacity: 1 });
+        sender.send(deleted("/a")).unwrap();
+        let blocked = std::thread::spawn(move || sender.send(deleted("/b")).is_ok());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!blocked.is_finished());
+
+        assert_eq!(app_path(receiver.recv().unwrap()), PathBuf::from("/a"));
+        assert!(blocked.join().unwrap());
+        assert_eq!(app_path(receiver.recv().unwrap()), PathBuf::from("/b"));
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Disconnected);
+    }
+
+    #[test]
+    fn test_close_while_blocked() {
+        let (sender, receiver) = queue(QueuePolicy::Block { capacity: 1 });
+        let closer = sender.closer();
+        sender.send(deleted("/a")).unwrap();
+        let blocked = std::thread::spawn(move || sender.send(deleted("/b")).is_err());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!blocked.is_finished());
+
+        closer.close();
+        assert!(blocked.join().unwrap());
+        assert_eq!(app_path(receiver.recv().unwrap()), PathBuf::from("/a"));
+        assert_eq!(receiver.try_recv().unwrap_err(), TryRecvError::Disconnected);
+    }
+}
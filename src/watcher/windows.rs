@@ -1,48 +1,187 @@
-use super::Change;
+use super::{Change, KnownApps, closed_error, pair_moves, parse_with_retry};
 use crate::platforms::parse_lnk2;
-use anyhow::Result;
+use crate::utils::windows::{read_packaged_apps, read_registry_apps, uninstall_keys};
+use crate::{App, AppTrait};
+use crate::{Error, Result};
 use notify::Result as NotifyResult;
 use notify::event::CreateKind;
-use notify::event::RemoveKind;
+use notify::event::{ModifyKind, RemoveKind, RenameMode};
 use notify::windows::ReadDirectoryChangesWatcher;
 use notify::{Event, EventKind, RecursiveMode, Watcher as WatcherTrait, recommended_watcher};
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+use walkdir::WalkDir;
+use windows::ApplicationModel::{Package, PackageCatalog};
+use windows::Foundation::TypedEventHandler;
+
+/// What the file system watcher, the registry thread and the package
+/// catalog report.
+enum Message {
+    Fs(NotifyResult<Event>),
+    Changes(Vec<Change>),
+    /// A lost search path exists again.
+    Restored(PathBuf),
+    /// Sent by a [`Closer`].
+    Close,
+}
+
+/// Wakes up or closes a [`Watcher`] from another thread.
+#[derive(Clone)]
+pub struct Closer {
+    tx: Sender<Message>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Closer {
+    /// Make a blocked `recv()` return, with no changes.
+    pub fn wake(&self) {
+        let _ = self.tx.send(Message::Changes(Vec::new()));
+    }
+
+    pub fn close(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.tx.send(Message::Close);
+    }
+}
+
+/// How often we check whether a lost search path is back.
+const RESTORE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long we wait for the new name of a renamed file, which
+/// `ReadDirectoryChangesW` reports right after the old one.
+const RENAME_TIMEOUT: Duration = Duration::from_millis(100);
 
 pub struct Watcher {
     notify_watcher: ReadDirectoryChangesWatcher,
-    rx: Receiver<NotifyResult<Event>>,
+    search_paths: Vec<PathBuf>,
+    rx: Receiver<Message>,
+    /// For the threads waiting for lost search paths.
+    tx: Sender<Message>,
+    /// Tells the registry thread, and the threads waiting for lost search
+    /// paths, to exit. Set once the watcher is closed.
+    stop: Arc<AtomicBool>,
+    known: KnownApps,
+    package_watch: Option<PackageWatch>,
+    /// Read while waiting for the new name of a renamed file, and handled
+    /// by the next `recv()`.
+    unread: Option<Message>,
+}
+
+/// The package catalog, kept alive so that its install and uninstall
+/// events keep coming, and the tokens of our handlers for them.
+struct PackageWatch {
+    catalog: PackageCatalog,
+    installing: i64,
+    uninstalling: i64,
+}
+
+impl PackageWatch {
+    fn stop(self) {
+        let _ = self.catalog.RemovePackageInstalling(self.installing);
+        let _ = self.catalog.RemovePackageUninstalling(self.uninstalling);
+    }
 }
 
 impl Watcher {
     pub fn new<P: AsRef<Path>>(search_paths: &[P]) -> Result<Self> {
-        let (tx, rx) = mpsc::channel::<NotifyResult<Event>>();
-        let mut watcher = recommended_watcher(tx)?;
+        let (tx, rx) = mpsc::channel::<Message>();
+        let fs_tx = tx.clone();
+        let mut watcher = recommended_watcher(move |event| {
+            let _ = fs_tx.send(Message::Fs(event));
+        })?;
         for search_path in search_paths.iter() {
             let search_path = search_path.as_ref();
             watcher.watch(search_path, RecursiveMode::Recursive)?;
         }
 
+        // MSI and other installers that create no shortcut only show up in
+        // the registry, Store apps only in the package catalog
+        let stop = Arc::new(AtomicBool::new(false));
+        let registry_tx = tx.clone();
+        let registry_stop = Arc::clone(&stop);
+        std::thread::spawn(move || watch_uninstall_keys(registry_tx, registry_stop));
+        let package_watch = watch_packages(tx.clone()).ok();
+
+        let mut known = KnownApps::default();
+        for search_path in search_paths {
+            remember_shortcuts(&mut known, search_path.as_ref());
+        }
+
         Ok(Self {
             notify_watcher: watcher,
+            search_paths: search_paths
+                .iter()
+                .map(|search_path| search_path.as_ref().to_path_buf())
+                .collect(),
             rx,
+            tx,
+            stop,
+            known,
+            package_watch,
+            unread: None,
         })
     }
 
     pub fn recv(&mut self) -> Result<Vec<Change>> {
+        let mut changes = self.recv_changes()?;
+        self.known.update(&mut changes);
+
+        Ok(changes)
+    }
+
+    fn recv_changes(&mut self) -> Result<Vec<Change>> {
         let mut changes = Vec::new();
 
-        let event = self.rx.recv()??;
+        if self.stop.load(Ordering::Relaxed) {
+            self.close();
+            return Err(closed_error());
+        }
+        let message = match self.unread.take() {
+            Some(message) => message,
+            None => self.rx.recv().map_err(|_| closed_error())?,
+        };
+        let event = match message {
+            Message::Close => {
+                self.close();
+                return Err(closed_error());
+            }
+            Message::Fs(event) => event?,
+            Message::Changes(changes) => return Ok(changes),
+            Message::Restored(search_path) => return self.restore(search_path),
+        };
         let event_kind = event.kind;
 
+        // ReadDirectoryChangesW stops when the directory itself goes away
+        if let EventKind::Remove(_) = event_kind {
+            for path in event.paths.iter() {
+                if self.search_paths.contains(path) {
+                    let _ = self.notify_watcher.unwatch(path);
+                    self.known.forget_below(path);
+                    changes.push(Change::WatchLost {
+                        search_path: path.clone(),
+                    });
+                    let tx = self.tx.clone();
+                    let stop = Arc::clone(&self.stop);
+                    let search_path = path.clone();
+                    std::thread::spawn(move || wait_for_search_path(search_path, tx, stop));
+                }
+            }
+        }
+
         if EventKind::Create(CreateKind::File) == event_kind {
             for path in event.paths.iter() {
                 if path.extension() == Some(OsStr::new("lnk")) && path.metadata()?.is_file() {
-                    if parse_lnk2(path.clone()).is_some() {
+                    if let Some(app) = parse_with_retry(|| parse_shortcut(path)) {
                         changes.push(Change::AppInstalled {
                             app_path: path.clone(),
+                            app: Box::new(app),
+                            search_path: self.search_path_of(path),
                         });
                     }
                 }
@@ -52,22 +191,504 @@ impl Watcher {
         if EventKind::Remove(RemoveKind::File) == event_kind {
             for path in event.paths {
                 if path.extension() == Some(OsStr::new("lnk")) {
-                    changes.push(Change::AppDeleted { app_path: path });
+                    changes.push(Change::AppDeleted {
+                        app_path: path,
+                        app: None,
+                    });
                 }
             }
         }
 
+        if let EventKind::Modify(ModifyKind::Name(mode)) = event_kind {
+            let mut paths = event.paths.into_iter();
+            let (from, to) = match mode {
+                RenameMode::Both => (paths.next(), paths.next()),
+                RenameMode::From => (paths.next(), self.renamed_to()),
+                RenameMode::To => (None, paths.next()),
+                _ => (None, None),
+            };
+            changes.extend(self.renamed(from, to));
+        }
+
         Ok(changes)
     }
 
+    /// The new name of the file whose old name we were just told, if it
+    /// was renamed within the search paths.
+    fn renamed_to(&mut self) -> Option<PathBuf> {
+        match self.rx.recv_timeout(RENAME_TIMEOUT) {
+            Ok(Message::Fs(Ok(event)))
+                if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::To)) =>
+            {
+                event.paths.into_iter().next()
+            }
+            Ok(message) => {
+                self.unread = Some(message);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// The changes of a file or directory renamed, or moved, from `from`
+    /// to `to`. Either is `None` if it is outside of the search paths.
+    fn renamed(&self, from: Option<PathBuf>, to: Option<PathBuf>) -> Vec<Change> {
+        let is_shortcut = |path: &Path| path.extension() == Some(OsStr::new("lnk"));
+        let mut changes = Vec::new();
+        let mut moves = Vec::new();
+        if let Some(to) = &to
+            && to.is_dir()
+        {
+            // The shortcuts in it have no events of their own
+            if let Some(from) = &from {
+                for app_path in self.known.paths_below(from) {
+                    let Ok(relative) = app_path.strip_prefix(from) else {
+                        continue;
+                    };
+                    moves.push((app_path.clone(), to.join(relative)));
+                    changes.push(Change::AppDeleted {
+                        app_path,
+                        app: None,
+                    });
+                }
+            }
+            changes.extend(installed_shortcuts(to, self.search_path_of(to)));
+        } else {
+            if let Some(from) = &from {
+                if is_shortcut(from) {
+                    changes.push(Change::AppDeleted {
+                        app_path: from.clone(),
+                        app: None,
+                    });
+                } else {
+                    // A directory moved out of the search paths
+                    changes.extend(self.known.paths_below(from).into_iter().map(|app_path| {
+                        Change::AppDeleted {
+                            app_path,
+                            app: None,
+                        }
+                    }));
+                }
+            }
+            if let Some(to) = &to
+                && is_shortcut(to)
+                && let Some(app) = parse_with_retry(|| parse_shortcut(to))
+            {
+                changes.push(Change::AppInstalled {
+                    app_path: to.clone(),
+                    app: Box::new(app),
+                    search_path: self.search_path_of(to),
+                });
+            }
+            if let (Some(from), Some(to)) = (from, to) {
+                moves.push((from, to));
+            }
+        }
+        pair_moves(&mut changes, &moves);
+
+        changes
+    }
+
+    /// The watched search path `path` is in.
+    fn search_path_of(&self, path: &Path) -> Option<PathBuf> {
+        self.search_paths
+            .iter()
+            .find(|search_path| path.starts_with(search_path))
+            .cloned()
+    }
+
+    /// Watch the lost `search_path` again, and report the shortcuts in it.
+    fn restore(&mut self, search_path: PathBuf) -> Result<Vec<Change>> {
+        // Unwatched while it was lost
+        if !self.search_paths.contains(&search_path) {
+            return Ok(Vec::new());
+        }
+        self.notify_watcher
+            .watch(&search_path, RecursiveMode::Recursive)?;
+
+        Ok(installed_shortcuts(&search_path, Some(search_path.clone())))
+    }
+
     pub fn unwatch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
-        self.notify_watcher.unwatch(search_path.as_ref())?;
+        // Lost search paths are no longer watched by notify
+        if let Err(e) = self.notify_watcher.unwatch(search_path.as_ref())
+            && search_path.as_ref().is_dir()
+        {
+            return Err(e.into());
+        }
+        self.search_paths
+            .retain(|watched| watched != search_path.as_ref());
+        self.known.forget_below(search_path.as_ref());
         Ok(())
     }
 
     pub fn watch<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
         self.notify_watcher
             .watch(search_path.as_ref(), RecursiveMode::Recursive)?;
+        self.search_paths.push(search_path.as_ref().to_path_buf());
+        remember_shortcuts(&mut self.known, search_path.as_ref());
+        Ok(())
+    }
+
+    /// Watch `search_path`, which does not exist yet, as a lost search
+    /// path.
+    pub fn watch_when_created<P: AsRef<Path>>(&mut self, search_path: P) -> Result<()> {
+        let search_path = search_path.as_ref().to_path_buf();
+        self.search_paths.push(search_path.clone());
+        let tx = self.tx.clone();
+        let stop = Arc::clone(&self.stop);
+        std::thread::spawn(move || wait_for_search_path(search_path, tx, stop));
+
+        Ok(())
+    }
+
+    /// The shortcuts we know about, and the registry entries and packages
+    /// as they are now, which the registry thread and the package catalog
+    /// report changes to.
+    pub fn initial_state(&self, search_paths: &HashSet<PathBuf>) -> Vec<Change> {
+        let mut changes = self.known.installed(search_paths);
+        changes.extend(diff_registry_apps(&HashMap::new(), &registry_apps()));
+        changes.extend(
+            read_packaged_apps()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|app| Change::AppInstalled {
+                    app_path: app.app_desktop_path.clone(),
+                    app: Box::new(app),
+                    search_path: None,
+                }),
+        );
+
+        changes
+    }
+
+    pub fn watch_list_is_empty(&self) -> bool {
+        self.search_paths.is_empty()
+    }
+
+    pub fn closer(&self) -> Closer {
+        Closer {
+            tx: self.tx.clone(),
+            stop: Arc::clone(&self.stop),
+        }
+    }
+
+    /// Stop watching the search paths, the registry and the package
+    /// catalog. `recv()` returns an error from now on.
+    pub fn close(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for search_path in self.search_paths.drain(..) {
+            let _ = self.notify_watcher.unwatch(&search_path);
+        }
+        if let Some(package_watch) = self.package_watch.take() {
+            package_watch.stop();
+        }
+        self.known = KnownApps::default();
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// An `AppInstalled` for each shortcut below `dir`.
+fn installed_shortcuts(dir: &Path, search_path: Option<PathBuf>) -> Vec<Change> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_file() && entry.path().extension() == Some(OsStr::new("lnk"))
+        })
+        .filter_map(|entry| {
+            let app = parse_with_retry(|| parse_shortcut(entry.path()))?;
+            Some(Change::AppInstalled {
+                app_path: entry.into_path(),
+                app: Box::new(app),
+                search_path: search_path.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Parse the shortcuts in `search_path`, for when they are deleted.
+fn remember_shortcuts(known: &mut KnownApps, search_path: &Path) {
+    for entry in WalkDir::new(search_path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension() == Some(OsStr::new("lnk")))
+    {
+        if let Ok(Some(app)) = parse_shortcut(entry.path()) {
+            known.insert(entry.into_path(), app);
+        }
+    }
+}
+
+/// Wait for the lost `search_path` to exist again.
+fn wait_for_search_path(search_path: PathBuf, tx: Sender<Message>, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        if search_path.is_dir() {
+            let _ = tx.send(Message::Restored(search_path));
+            return;
+        }
+        std::thread::sleep(RESTORE_POLL_INTERVAL);
+    }
+}
+
+/// Parse a new shortcut, which is still being written while it is empty.
+pub(super) fn parse_shortcut(path: &Path) -> Result<Option<App>> {
+    if path.metadata()?.len() == 0 {
+        return Err(Error::parse(path, "empty shortcut"));
+    }
+    if parse_lnk2(path.to_path_buf()).is_none() {
+        return Ok(None);
+    }
+
+    Ok(App::from_path(path).ok())
+}
+
+/// The path an app is reported with: its executable, or its installation
+/// directory when it has none we know of.
+fn app_path(app: &App) -> PathBuf {
+    app.app_path_exe
+        .clone()
+        .unwrap_or_else(|| app.app_desktop_path.clone())
+}
+
+/// An entry of an `Uninstall` key: the key, named with its view, and the
+/// subkey.
+type RegistryKey = (String, String);
+
+/// Apps of the entries of the `Uninstall` keys, by their [`RegistryKey`].
+fn registry_apps() -> HashMap<RegistryKey, App> {
+    read_registry_apps()
+        .into_iter()
+        .filter(|registry_app| !registry_app.system_component)
+        .filter_map(|registry_app| {
+            let app = registry_app.to_app()?;
+            Some(((registry_app.uninstall_key, registry_app.key_name), app))
+        })
+        .collect()
+}
+
+/// The apps installed and removed between two [`registry_apps`].
+fn diff_registry_apps(
+    old: &HashMap<RegistryKey, App>,
+    new: &HashMap<RegistryKey, App>,
+) -> Vec<Change> {
+    let installed = new
+        .iter()
+        .filter(|(key, _)| !old.contains_key(*key))
+        .map(|(_, app)| Change::AppInstalled {
+            app_path: app_path(app),
+            app: Box::new(app.clone()),
+            search_path: None,
+        });
+    let deleted = old
+        .iter()
+        .filter(|(key, _)| !new.contains_key(*key))
+        .map(|(_, app)| Change::AppDeleted {
+            app_path: app_path(app),
+            app: Some(Box::new(app.clone())),
+        });
+
+    installed.chain(deleted).collect()
+}
+
+/// Report the entries added to and removed from the `Uninstall` keys,
+/// woken up by `RegNotifyChangeKeyValue()`, until `stop` is set.
+fn watch_uninstall_keys(tx: Sender<Message>, stop: Arc<AtomicBool>) {
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_FAILED, WAIT_OBJECT_0};
+    use windows::Win32::System::Registry::{
+        HKEY, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME, RegNotifyChangeKeyValue,
+    };
+    use windows::Win32::System::Threading::{CreateEventW, WaitForMultipleObjects};
+    use winreg::RegKey;
+    use winreg::enums::KEY_NOTIFY;
+
+    let keys: Vec<RegKey> = uninstall_keys(KEY_NOTIFY)
+        .into_iter()
+        .filter_map(|(hive, path, flags)| {
+            RegKey::predef(hive)
+                .open_subkey_with_flags(path, flags)
+                .ok()
+        })
+        .collect();
+    let events: Vec<HANDLE> = keys
+        .iter()
+        .filter_map(|_| unsafe { CreateEventW(None, false, false, None).ok() })
+        .collect();
+    if keys.is_empty() || events.len() != keys.len() {
+        return;
+    }
+
+    // A notification fires once, it has to be requested again after that
+    let arm = |key: &RegKey, event: HANDLE| unsafe {
+        RegNotifyChangeKeyValue(
+            HKEY(key.raw_handle() as *mut _),
+            true,
+            REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+            Some(event),
+            true,
+        )
+    };
+    for (key, event) in keys.iter().zip(&events) {
+        let _ = arm(key, *event);
+    }
+
+    let mut known = registry_apps();
+    while !stop.load(Ordering::Relaxed) {
+        // Wake up now and then to see whether the watcher is gone
+        let result = unsafe { WaitForMultipleObjects(&events, false, 500) };
+        if result == WAIT_FAILED {
+            break;
+        }
+        let index = result.0.wrapping_sub(WAIT_OBJECT_0.0) as usize;
+        let (Some(key), Some(event)) = (keys.get(index), events.get(index)) else {
+            continue;
+        };
+        let _ = arm(key, *event);
+
+        // Installers write the values of their entry one after another
+        std::thread::sleep(Duration::from_millis(500));
+        let current = registry_apps();
+        let changes = diff_registry_apps(&known, &current);
+        known = current;
+        if !changes.is_empty() && tx.send(Message::Changes(changes)).is_err() {
+            break;
+        }
+    }
+
+    for event in events {
+        unsafe {
+            let _ = CloseHandle(event);
+        }
+    }
+}
+
+fn package_path(package: &Package) -> Option<PathBuf> {
+    let path = package.InstalledPath().ok()?.to_string_lossy();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// What we still know about a package that is being uninstalled, whose
+/// app list entries are already gone.
+fn package_app(package: &Package, app_path: &Path) -> App {
+    App {
+        name: package
+            .DisplayName()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+        publisher: package
+            .PublisherDisplayName()
+            .ok()
+            .map(|publisher| publisher.to_string_lossy())
+            .filter(|publisher| !publisher.is_empty()),
+        app_desktop_path: app_path.to_path_buf(),
+        ..Default::default()
+    }
+}
+
+/// Report the packaged apps installed and removed for the current user,
+/// until the returned watch is stopped.
+fn watch_packages(tx: Sender<Message>) -> windows::core::Result<PackageWatch> {
+    let catalog = PackageCatalog::OpenForCurrentUser()?;
+
+    let installing_tx = tx.clone();
+    let installing = catalog.PackageInstalling(&TypedEventHandler::new(move |_, args| {
+        if let Some(args) = &*args
+            && args.IsComplete()?
+            && let Some(app_path) = package_path(&args.Package()?)
+        {
+            // A package has an app list entry per app it contains
+            let changes: Vec<Change> = read_packaged_apps()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|app| app.app_desktop_path == app_path)
+                .map(|app| Change::AppInstalled {
+                    app_path: app_path.clone(),
+                    app: Box::new(app),
+                    search_path: None,
+                })
+                .collect();
+            if !changes.is_empty() {
+                let _ = installing_tx.send(Message::Changes(changes));
+            }
+        }
         Ok(())
+    }))?;
+    let uninstalling = catalog.PackageUninstalling(&TypedEventHandler::new(move |_, args| {
+        if let Some(args) = &*args
+            && args.IsComplete()?
+            && let Some(app_path) = package_path(&args.Package()?)
+        {
+            let app = package_app(&args.Package()?, &app_path);
+            let _ = tx.send(Message::Changes(vec![Change::AppDeleted {
+                app_path,
+                app: Some(Box::new(app)),
+            }]));
+        }
+        Ok(())
+    }))?;
+
+    Ok(PackageWatch {
+        catalog,
+        installing,
+        uninstalling,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_registry_apps() {
+        let app = |exe: &str| App {
+            app_path_exe: Some(PathBuf::from(exe)),
+            ..Default::default()
+        };
+        let key =
+            |uninstall_key: &str, key_name: &str| (uninstall_key.to_string(), key_name.to_string());
+        let (machine, machine_32, user) = (
+            "HKEY_LOCAL_MACHINE\\Uninstall",
+            "HKEY_LOCAL_MACHINE\\Uninstall (32-bit)",
+            "HKEY_CURRENT_USER\\Uninstall",
+        );
+        let old = HashMap::from([
+            (key(machine, "Foo"), app("C:\\Foo\\foo.exe")),
+            (key(user, "Bar"), app("C:\\Bar\\bar.exe")),
+        ]);
+        let new = HashMap::from([
+            (key(machine, "Foo"), app("C:\\Foo\\foo.exe")),
+            (key(machine, "Baz"), app("C:\\Baz\\baz.exe")),
+        ]);
+
+        let changes = diff_registry_apps(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(
+            &changes[0],
+            Change::AppInstalled { app_path, search_path: None, .. }
+                if app_path == Path::new("C:\\Baz\\baz.exe")
+        ));
+        assert!(matches!(
+            &changes[1],
+            Change::AppDeleted { app_path, app: Some(_) } if app_path == Path::new("C:\\Bar\\bar.exe")
+        ));
+
+        // The 32-bit build of Foo, in a subkey of the same name
+        let mut both = new.clone();
+        both.insert(key(machine_32, "Foo"), app("C:\\Foo (x86)\\foo.exe"));
+        let changes = diff_registry_apps(&new, &both);
+        assert!(matches!(
+            &changes[..],
+            [Change::AppInstalled { app_path, .. }] if app_path == Path::new("C:\\Foo (x86)\\foo.exe")
+        ));
+        let changes = diff_registry_apps(&both, &new);
+        assert!(matches!(
+            &changes[..],
+            [Change::AppDeleted { app_path, .. }] if app_path == Path::new("C:\\Foo (x86)\\foo.exe")
+        ));
     }
 }